@@ -0,0 +1,80 @@
+//! Counts allocations of converting a flat map of 100k string entries,
+//! compared with only deserializing the document. Writing the entries allocates
+//! at most a small constant number of times per entry, strings are written
+//! straight into the output
+//!
+//! Run with `cargo bench --bench flat_map`
+
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	hint::black_box,
+	sync::atomic::{AtomicUsize, Ordering},
+	time::Instant,
+};
+
+const ENTRIES: usize = 100_000;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() {
+	let yaml = (0..ENTRIES)
+		.map(|i| format!("key_{}: Value number {}\n", i, i))
+		.collect::<String>();
+
+	let (deserialize, deserialize_ms) = measure(|| {
+		black_box(serde_yaml::from_str::<serde_yaml::Mapping>(&yaml).unwrap());
+	});
+
+	let (parse, parse_ms) = measure(|| {
+		black_box(yaml2lua::parse(&yaml).unwrap());
+	});
+
+	println!("{} entries", ENTRIES);
+	println!(
+		"deserialize: {:>8.2} ms, {:>6.2} allocations per entry",
+		deserialize_ms,
+		deserialize as f64 / ENTRIES as f64
+	);
+	println!(
+		"parse:       {:>8.2} ms, {:>6.2} allocations per entry",
+		parse_ms,
+		parse as f64 / ENTRIES as f64
+	);
+
+	assert!(
+		parse.saturating_sub(deserialize) <= ENTRIES * 2,
+		"converting allocates more than twice per entry"
+	);
+}
+
+/// Get the allocations and milliseconds of running the function once
+fn measure(f: impl Fn()) -> (usize, f64) {
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+	let start = Instant::now();
+
+	f();
+
+	let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+	(ALLOCATIONS.load(Ordering::Relaxed) - allocations, elapsed)
+}
@@ -0,0 +1,81 @@
+//! Compares string escaping against the previous implementation, which scanned
+//! the string, re-scanned it with `escape_default` and formatted the result again
+//!
+//! Run with `cargo bench --bench strings`
+
+use std::{hint::black_box, time::Instant};
+use yaml2lua::fmt::{escape_lua_string, QuoteStyle};
+
+const ITERATIONS: usize = 200;
+
+fn main() {
+	let strings = fixture();
+	let yaml = strings
+		.iter()
+		.enumerate()
+		.map(|(i, s)| format!("key{}: {:?}\n", i, s))
+		.collect::<String>();
+
+	let previous = measure(|| {
+		for string in &strings {
+			black_box(format!("\"{}\"", previous_escape(string)));
+		}
+	});
+
+	let current = measure(|| {
+		for string in &strings {
+			black_box(escape_lua_string(string, QuoteStyle::Double));
+		}
+	});
+
+	let parse = measure(|| {
+		black_box(yaml2lua::parse(&yaml).unwrap());
+	});
+
+	println!(
+		"escaping {} strings, {} iterations",
+		strings.len(),
+		ITERATIONS
+	);
+	println!("previous: {:>8.2} ms", previous);
+	println!("current:  {:>8.2} ms", current);
+	println!("parse:    {:>8.2} ms", parse);
+}
+
+/// Mostly plain strings with some needing escapes, like typical localization files
+fn fixture() -> Vec<String> {
+	(0..5_000)
+		.map(|i| match i % 4 {
+			0 => format!("Plain text line number {} without anything special", i),
+			1 => format!("Line {} with \"quotes\" and a\ttab", i),
+			2 => format!("Multi\nline\ntext {}", i),
+			_ => format!("Unicode zażółć gęślą jaźń {}", i),
+		})
+		.collect()
+}
+
+fn measure(f: impl Fn()) -> f64 {
+	let start = Instant::now();
+
+	for _ in 0..ITERATIONS {
+		f();
+	}
+
+	start.elapsed().as_secs_f64() * 1000.0 / ITERATIONS as f64
+}
+
+fn previous_escape(string: &str) -> String {
+	let mut chars = string.chars();
+
+	while let Some(char) = chars.next() {
+		if char == '\\' {
+			if !matches!(chars.next(), Some('n' | 't' | 'r' | '\\' | '"')) {
+				return string.escape_default().to_string();
+			}
+		} else if matches!(char, '\n' | '\t' | '\r' | '"') {
+			return string.escape_default().to_string();
+		}
+	}
+
+	string.to_owned()
+}
@@ -0,0 +1,37 @@
+//! Run with `cargo +nightly fuzz run parse` from the repository root
+//!
+//! Every entry point has to return an error for invalid input instead of panicking,
+//! inputs that made it panic belong in the regression corpus in `src/lib.rs`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use yaml2lua::{FidelityMode, KeyStyle, OutputMode, ParseOptions, UnknownTags};
+
+fuzz_target!(|data: &[u8]| {
+	let _ = yaml2lua::parse_bytes(data);
+
+	let Ok(yaml) = std::str::from_utf8(data) else {
+		return;
+	};
+
+	let options = [
+		ParseOptions::new(),
+		ParseOptions::new()
+			.key_style(KeyStyle::MirrorSource)
+			.fidelity(FidelityMode::Wrapped(Default::default()))
+			.on_unknown_tag(UnknownTags::Error),
+		ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.dedupe_strings(Default::default())
+			.dedupe_subtrees(Default::default()),
+	];
+
+	for options in &options {
+		let _ = yaml2lua::parse_with_options(yaml, options);
+		let _ = yaml2lua::parse_partial(yaml, options);
+		let _ = yaml2lua::parse_documents(yaml, options);
+	}
+
+	let _ = yaml2lua::digest(yaml);
+});
@@ -0,0 +1,299 @@
+use crate::{
+	emit_document, encoding,
+	fmt::{escape_lua_string, is_lua_identifier, QuoteStyle},
+	parse_split, Error, KeyStyle, ModuleStyle, ParseOptions, Result, SourceComments,
+};
+use std::{
+	collections::{BTreeMap, HashMap},
+	fs,
+	path::{Path, PathBuf},
+};
+
+/// Summary of converting a directory of YAML files
+#[derive(Debug, Default)]
+pub struct BatchReport {
+	/// Files that were converted successfully
+	pub converted: Vec<ConvertedFile>,
+	/// Files that couldn't be converted, other files are still converted
+	pub failed: Vec<FailedFile>,
+	/// Index modules written with [`ParseOptions::index_modules`]
+	pub indexes: Vec<PathBuf>,
+}
+
+/// YAML file converted into a Lua file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertedFile {
+	pub input: PathBuf,
+	pub output: PathBuf,
+}
+
+/// YAML file that failed to convert
+#[derive(Debug)]
+pub struct FailedFile {
+	pub input: PathBuf,
+	pub error: Error,
+}
+
+/// Convert every `.yaml` and `.yml` file inside the input directory and its
+/// subdirectories into a `.lua` file at the same relative path in the output directory
+///
+/// Files are processed in a sorted order. Errors of individual files are
+/// collected in the report, only failing to read a directory stops the conversion
+pub fn convert_dir(
+	input: impl AsRef<Path>,
+	output: impl AsRef<Path>,
+	options: &ParseOptions,
+) -> Result<BatchReport> {
+	let (input, output) = (input.as_ref(), output.as_ref());
+	let mut report = BatchReport::default();
+
+	for file in find_yaml_files(input)? {
+		let target = output_path(input, output, &file);
+		let result = convert_file(&file, &target, options);
+
+		report.push(file, target, result);
+	}
+
+	if let Some(style) = &options.index_modules {
+		write_indexes(input, output, &mut report, style, options)?;
+	}
+
+	Ok(report)
+}
+
+/// Split YAML string with [`parse_split`] and write every module into
+/// `<key>.lua` inside the directory, creating it if needed. Returns paths of written files
+pub fn split_to_dir(
+	yaml: &str,
+	dir: impl AsRef<Path>,
+	options: &ParseOptions,
+) -> Result<Vec<PathBuf>> {
+	let dir = dir.as_ref();
+	let modules = parse_split(yaml, options)?;
+
+	fs::create_dir_all(dir)?;
+
+	modules
+		.into_iter()
+		.map(|(name, lua)| {
+			let path = dir.join(name + ".lua");
+			fs::write(&path, lua)?;
+			Ok(path)
+		})
+		.collect()
+}
+
+impl BatchReport {
+	pub(crate) fn push(&mut self, input: PathBuf, output: PathBuf, result: Result<()>) {
+		match result {
+			Ok(()) => self.converted.push(ConvertedFile { input, output }),
+			Err(error) => self.failed.push(FailedFile { input, error }),
+		}
+	}
+}
+
+/// Convert a single YAML file, creating parent directories of the target
+pub(crate) fn convert_file(file: &Path, target: &Path, options: &ParseOptions) -> Result<()> {
+	let lua = convert_bytes(&fs::read(file)?, options, Some(file))?;
+
+	if let Some(parent) = target.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	Ok(fs::write(target, lua)?)
+}
+
+/// Convert YAML file contents, post-processing hooks receive the path of the file
+pub(crate) fn convert_bytes(
+	bytes: &[u8],
+	options: &ParseOptions,
+	path: Option<&Path>,
+) -> Result<String> {
+	let (lua, _) = match (&options.source_comments, path) {
+		(Some(comments), Some(path)) if comments.file.is_none() => {
+			let options = options.clone().source_comments(SourceComments {
+				file: Some(path.display().to_string()),
+				..comments.clone()
+			});
+
+			emit_document(&encoding::decode(bytes)?, &options)?
+		}
+		_ => emit_document(&encoding::decode(bytes)?, options)?,
+	};
+
+	options.finish(lua, path)
+}
+
+/// Module that is listed in an index
+struct IndexEntry {
+	/// File stem or directory name used to require the module
+	name: String,
+	/// Source of the module, which is a YAML file or a directory
+	input: PathBuf,
+}
+
+/// Write `init.lua` into every output directory with converted files, requiring
+/// its files and the indexes of its subdirectories
+pub(crate) fn write_indexes(
+	input: &Path,
+	output: &Path,
+	report: &mut BatchReport,
+	style: &ModuleStyle,
+	options: &ParseOptions,
+) -> Result<()> {
+	let mut dirs: BTreeMap<PathBuf, Vec<IndexEntry>> = BTreeMap::new();
+	dirs.entry(PathBuf::new()).or_default();
+
+	for file in &report.converted {
+		let Ok(relative) = file.output.strip_prefix(output) else {
+			continue;
+		};
+
+		let mut module = relative.with_extension("");
+		let mut source = file.input.clone();
+
+		while let Some(parent) = module.parent() {
+			let name = module.file_name().unwrap_or_default().to_string_lossy();
+			let entries = dirs.entry(parent.to_owned()).or_default();
+
+			// Directories are listed once, by the first file inside of them
+			if entries.iter().any(|entry| entry.input == source) {
+				break;
+			}
+
+			entries.push(IndexEntry {
+				name: name.into_owned(),
+				input: source,
+			});
+
+			source = input.join(parent);
+			module = parent.to_owned();
+		}
+	}
+
+	for (dir, mut entries) in dirs {
+		entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+		let mut lua = String::new();
+		options.begin_output(&mut lua);
+		lua.push_str("return {\n");
+		let mut names: HashMap<String, String> = HashMap::new();
+
+		for entry in entries {
+			let ident = KeyStyle::SanitizedIdentifiers
+				.apply(&entry.name)
+				.into_owned();
+
+			let conflict = if entry.name == "init" {
+				Some(String::from("it's reserved for the index module"))
+			} else {
+				names
+					.get(&ident)
+					.map(|other| format!("it resolves to the same name as \"{}\"", other))
+			};
+
+			if let Some(message) = conflict {
+				// Converted `init.yaml` is overwritten by the index
+				if entry.name == "init" {
+					report.converted.retain(|file| file.input != entry.input);
+				}
+
+				report.failed.push(FailedFile {
+					input: entry.input,
+					error: Error::ModuleName {
+						key: format!("\"{}\"", entry.name),
+						message,
+					},
+				});
+
+				continue;
+			}
+
+			options.indent.write(&mut lua, 1);
+			lua.push_str(&format!(
+				"{} = require({}),\n",
+				ident,
+				style.reference(&dir, &entry.name, options.quote_style)
+			));
+
+			names.insert(ident, entry.name);
+		}
+
+		if options.omit_trailing_commas && lua.ends_with(",\n") {
+			lua.truncate(lua.len() - 2);
+			lua.push('\n');
+		}
+
+		lua.push('}');
+		options.end_output(&mut lua);
+
+		let path = output.join(&dir).join("init.lua");
+
+		fs::create_dir_all(output.join(&dir))?;
+		fs::write(&path, lua)?;
+
+		report.indexes.push(path);
+	}
+
+	Ok(())
+}
+
+impl ModuleStyle {
+	/// Get the argument of `require` for the module in the directory relative to the output,
+	/// with strings delimited by the given quote
+	fn reference(&self, dir: &Path, name: &str, quote_style: QuoteStyle) -> String {
+		let quote = |s: &str| {
+			let quote = quote_style.quote();
+			format!("{}{}{}", quote, escape_lua_string(s, quote_style), quote)
+		};
+
+		match self {
+			ModuleStyle::Roblox if is_lua_identifier(name) => format!("script.{}", name),
+			ModuleStyle::Roblox => format!("script[{}]", quote(name)),
+			ModuleStyle::Path(root) => {
+				let path: Vec<String> = (!root.is_empty())
+					.then(|| root.clone())
+					.into_iter()
+					.chain(dir.iter().map(|s| s.to_string_lossy().into_owned()))
+					.chain([name.to_owned()])
+					.collect();
+
+				quote(&path.join("."))
+			}
+		}
+	}
+}
+
+pub(crate) fn is_yaml(path: &Path) -> bool {
+	path.extension()
+		.is_some_and(|extension| extension == "yaml" || extension == "yml")
+}
+
+/// Get path of the Lua file for the YAML file found in the input directory
+pub(crate) fn output_path(input: &Path, output: &Path, file: &Path) -> PathBuf {
+	output
+		.join(file.strip_prefix(input).unwrap_or(file))
+		.with_extension("lua")
+}
+
+fn find_yaml_files(dir: &Path) -> Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+	let mut dirs = vec![dir.to_owned()];
+
+	while let Some(dir) = dirs.pop() {
+		for entry in fs::read_dir(&dir)? {
+			let entry = entry?;
+			let path = entry.path();
+
+			if entry.file_type()?.is_dir() {
+				dirs.push(path);
+			} else if is_yaml(&path) {
+				files.push(path);
+			}
+		}
+	}
+
+	files.sort();
+
+	Ok(files)
+}
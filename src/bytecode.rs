@@ -0,0 +1,71 @@
+use crate::{parse_with_options, Dialect, Error, OutputMode, ParseOptions, Result};
+use mlua::Lua;
+
+/// Chunk name used in compiler messages, `=` keeps Lua from decorating it
+const CHUNK_NAME: &str = "=yaml2lua";
+
+/// Parse YAML string and compile the generated Lua into a precompiled Lua 5.4 chunk
+///
+/// [`OutputMode::Table`] is emitted as [`OutputMode::Module`] so loading the chunk
+/// returns the table. Only the [`Dialect::Lua54`] dialect matches the compiler,
+/// bytecode for other dialects isn't supported and returns [`Error::Options`]
+///
+/// Requires the `bytecode` feature
+///
+/// ```rust
+/// let bytecode = yaml2lua::parse_to_bytecode("a: 1", &Default::default()).unwrap();
+///
+/// assert!(bytecode.starts_with(b"\x1bLua"));
+/// ```
+pub fn parse_to_bytecode(yaml: &str, options: &ParseOptions) -> Result<Vec<u8>> {
+	// Chunks are only loaded by the VM version that compiled them
+	if options.dialect != Dialect::Lua54 {
+		return Err(Error::Options(format!(
+			"bytecode can't be compiled for the {} dialect",
+			options.dialect.name()
+		)));
+	}
+
+	let lua = if options.output_mode == OutputMode::Table {
+		let options = ParseOptions {
+			output_mode: OutputMode::Module,
+			..options.clone()
+		};
+
+		parse_with_options(yaml, &options)?
+	} else {
+		parse_with_options(yaml, options)?
+	};
+
+	compile(&lua)
+}
+
+fn compile(source: &str) -> Result<Vec<u8>> {
+	let lua = Lua::new();
+	let function = lua
+		.load(source)
+		.set_name(CHUNK_NAME)
+		.into_function()
+		.map_err(|error| {
+			let message = match error {
+				mlua::Error::SyntaxError { message, .. } => message,
+				error => error.to_string(),
+			};
+
+			// Messages look like `yaml2lua:3: unexpected symbol near '}'`
+			let line = message
+				.strip_prefix(&CHUNK_NAME[1..])
+				.and_then(|rest| rest.strip_prefix(':'))
+				.and_then(|rest| rest.split(':').next())
+				.and_then(|line| line.parse::<usize>().ok());
+
+			Error::Bytecode {
+				source_line: line
+					.and_then(|line| source.lines().nth(line.checked_sub(1)?).map(str::to_owned)),
+				line,
+				message,
+			}
+		})?;
+
+	Ok(function.dump(false))
+}
@@ -0,0 +1,53 @@
+/// Default target size of chunks returned by [`Chunks`] in bytes
+const DEFAULT_TARGET_SIZE: usize = 8 * 1024;
+
+/// Iterator over pieces of the generated Lua returned by [`parse_chunks`](crate::parse_chunks)
+///
+/// Chunks are split at line ends only, so they never end inside a string, escape
+/// sequence or comment. Each chunk is at least as long as the target size unless
+/// it's the last one and only longer by the rest of its last line
+#[derive(Debug, Clone)]
+pub struct Chunks {
+	output: String,
+	position: usize,
+	target_size: usize,
+}
+
+impl Chunks {
+	pub(crate) fn new(output: String) -> Self {
+		Self {
+			output,
+			position: 0,
+			target_size: DEFAULT_TARGET_SIZE,
+		}
+	}
+
+	/// Set the size in bytes chunks are split at, 8 KiB by default
+	pub fn target_size(mut self, target_size: usize) -> Self {
+		self.target_size = target_size.max(1);
+		self
+	}
+}
+
+impl Iterator for Chunks {
+	type Item = String;
+
+	fn next(&mut self) -> Option<String> {
+		let rest = &self.output[self.position..];
+
+		if rest.is_empty() {
+			return None;
+		}
+
+		// Newline bytes never appear inside multi-byte characters
+		let start = self.target_size.saturating_sub(1).min(rest.len());
+		let len = rest.as_bytes()[start..]
+			.iter()
+			.position(|byte| *byte == b'\n')
+			.map_or(rest.len(), |end| start + end + 1);
+
+		self.position += len;
+
+		Some(rest[..len].to_owned())
+	}
+}
@@ -0,0 +1,189 @@
+use crate::{
+	emit::{Emitter, EntryCache},
+	transform, KeyStyle, ParseOptions, Result,
+};
+
+/// Options that only change how entries are written from their own key and value,
+/// so cached entries can be reused with them. Any other option changed from its
+/// default makes [`Converter::convert_incremental`] convert everything every time
+const INCREMENTAL_OPTIONS: &[&str] = &[
+	"numeric_keys",
+	"float_precision",
+	"warnings_as_errors",
+	"schema",
+	"key_style",
+	"rename_keys",
+	"bare_keys",
+	"duplicate_keys",
+	"key_type_collisions",
+	"sort_keys",
+	"force_string_paths",
+	"constant_references",
+	"original_keys_table",
+	"fold_single_key_maps",
+	"sequence_length_field",
+	"table_layout",
+	"scalars_per_line",
+	"flatten",
+	"expand_dotted_keys",
+	"merge_sequences",
+	"merge_conflicts",
+	"output_mode",
+	"header",
+	"base_depth",
+	"comments",
+	"dialect",
+	"freeze",
+	"validator",
+	"annotations",
+	"luau_types",
+	"post_process",
+	"max_string_len",
+	"error_placeholder",
+	"columnar_records",
+	"non_finite_floats",
+	"large_integers",
+	"nulls",
+	"index_modules",
+	"embedded_yaml",
+	"max_depth",
+	"on_unknown_tag",
+	"with_tag",
+	"tag_wrapper",
+	"format_version",
+	"root_key",
+	"final_newline",
+	"trailing_semicolon",
+	"indent",
+	"trailing_commas",
+	"compact",
+	"quote_style",
+	"long_strings",
+];
+
+/// Reusable converter for parsing many YAML documents with the same options
+///
+/// Keeps the output buffer between conversions so converting lots of small
+/// documents doesn't allocate a new string every time. Output is the same as
+/// returned by [`parse_with_options`](crate::parse_with_options)
+///
+/// Converters only own their buffer besides the options, so creating one per
+/// thread is cheap, cloning [`ParseOptions`] only clones `Arc`s of their hooks
+///
+/// ```rust
+/// use yaml2lua::{Converter, ParseOptions};
+///
+/// let mut converter = Converter::new(ParseOptions::new());
+///
+/// assert_eq!(converter.convert("a: 1").unwrap(), "{\n\t[\"a\"] = 1,\n}");
+/// assert_eq!(converter.convert("b: 2").unwrap(), "{\n\t[\"b\"] = 2,\n}");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Converter {
+	options: ParseOptions,
+	output: String,
+	/// Top-level entries written by the last incremental conversion
+	cache: EntryCache,
+}
+
+impl Converter {
+	/// Create converter using the provided options
+	pub fn new(options: ParseOptions) -> Self {
+		Self {
+			options,
+			output: String::new(),
+			cache: EntryCache::new(),
+		}
+	}
+
+	/// Get options used by this converter
+	pub fn options(&self) -> &ParseOptions {
+		&self.options
+	}
+
+	/// Parse YAML string into a Lua table, returned output is valid until the next conversion
+	pub fn convert(&mut self, yaml: &str) -> Result<&str> {
+		self.convert_with(yaml, None)
+	}
+
+	/// Parse YAML string like [`convert`](Self::convert), reusing the output of top-level
+	/// entries that didn't change since the last incremental conversion
+	///
+	/// Output is always the same as converting the document from scratch. Entries are
+	/// compared by a digest of their key and value, so only changed entries are written
+	/// again. The whole document is still parsed, only writing unchanged entries
+	/// is skipped. Tag handlers have to return the same replacement for the same value
+	///
+	/// Options that make entries depend on the rest of the document or on how it's
+	/// written, beyond the values it's loaded as, convert everything every time. These are
+	/// string and subtree deduplication, preserved anchors, chunked output, progress callbacks,
+	/// previews, source comments, [`KeyStyle::MirrorSource`], [`FidelityMode::Wrapped`](crate::FidelityMode::Wrapped),
+	/// [`binary`](ParseOptions::binary), [`timestamps`](ParseOptions::timestamps) and options added later
+	///
+	/// ```rust
+	/// use yaml2lua::{Converter, ParseOptions};
+	///
+	/// let mut converter = Converter::new(ParseOptions::new());
+	///
+	/// converter.convert_incremental("a: [1, 2]\nb: 1").unwrap();
+	///
+	/// assert_eq!(
+	/// 	converter.convert_incremental("a: [1, 2]\nb: 2").unwrap(),
+	/// 	"{\n\t[\"a\"] = {\n\t\t1,\n\t\t2,\n\t},\n\t[\"b\"] = 2,\n}"
+	/// );
+	/// ```
+	pub fn convert_incremental(&mut self, yaml: &str) -> Result<&str> {
+		let options = &self.options;
+
+		// Tags and scalar styles read from the source aren't part of the digests of entries
+		let incremental = options.key_style != KeyStyle::MirrorSource
+			&& options
+				.changed()
+				.iter()
+				.all(|option| INCREMENTAL_OPTIONS.contains(option));
+
+		if !incremental {
+			return self.convert(yaml);
+		}
+
+		let cache = std::mem::take(&mut self.cache);
+		self.convert_with(yaml, Some(cache))
+	}
+
+	fn convert_with(&mut self, yaml: &str, cache: Option<EntryCache>) -> Result<&str> {
+		let root =
+			transform::prepare_root(transform::load_root(yaml, &self.options)?, &self.options)?;
+		let mut emitter = Emitter::new(&self.options)
+			.with_source(yaml)
+			.with_buffer(std::mem::take(&mut self.output));
+
+		if let Some(cache) = cache {
+			emitter = emitter.with_entry_cache(cache);
+		}
+
+		let result = emitter.emit(&root);
+		let (output, cache) = emitter.into_cached();
+		self.output = output;
+
+		// Failed conversions may have only cached some of the entries
+		if result.is_ok() {
+			self.cache = cache;
+		}
+
+		if let Err(err) = result {
+			self.output.clear();
+			return Err(err);
+		}
+
+		self.output = self
+			.options
+			.finish(std::mem::take(&mut self.output), None)?;
+
+		Ok(&self.output)
+	}
+
+	/// Parse YAML string into a Lua table, returning an owned string
+	pub fn convert_to_string(&mut self, yaml: &str) -> Result<String> {
+		self.convert(yaml).map(str::to_owned)
+	}
+}
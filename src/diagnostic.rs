@@ -0,0 +1,58 @@
+use crate::KeyPath;
+use std::fmt;
+
+/// Non-fatal issue found during conversion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub kind: DiagnosticKind,
+	pub path: KeyPath,
+	pub message: String,
+}
+
+/// How serious a [`Diagnostic`] is, warnings become errors in strict mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+	Info,
+	Warning,
+}
+
+/// Machine-readable kind of a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+	/// Entry was skipped because its key can't be a Lua table key
+	UnsupportedKey,
+	/// Integer doesn't fit into a signed 64-bit Lua integer
+	LargeInteger,
+	/// Tagged value was converted into a wrapper table or stripped, see [`UnknownTags`](crate::UnknownTags)
+	UnknownTag,
+	/// Value couldn't be coerced into the type declared by the schema
+	Coercion,
+	/// Two keys of the same table resolve to the same Lua key
+	KeyCollision,
+	/// Keys of different types in one table are equal when compared as configured
+	KeyTypeCollision,
+	/// Key was changed to make it a valid Lua identifier
+	SanitizedKey,
+	/// String was cut to the maximum length set in the options
+	TruncatedString,
+	/// Entries were left out of a preview
+	Elided,
+	/// Sequence mixes single-key mappings with other values, so it wasn't folded
+	UnfoldedSequence,
+	/// Scalar without an exact Lua value was written as a wrapper table
+	WrappedScalar,
+	/// Element of a sequence written as columnar records doesn't have the keys of the header
+	IrregularRecord,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.path.is_root() {
+			write!(f, "{}", self.message)
+		} else {
+			write!(f, "{} at `{}`", self.message, self.path)
+		}
+	}
+}
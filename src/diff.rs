@@ -0,0 +1,61 @@
+use crate::{KeyPath, PathSegment};
+use serde_yaml::{Mapping, Value};
+
+/// Sections of the patch table, see [`crate::diff`]
+pub(crate) struct Patch {
+	set: Mapping,
+	removed: Vec<Value>,
+	added: Mapping,
+}
+
+impl Patch {
+	/// Compare the documents, collecting changed paths in document order
+	pub(crate) fn between(old: &Mapping, new: &Mapping) -> Self {
+		let mut patch = Patch {
+			set: Mapping::new(),
+			removed: Vec::new(),
+			added: Mapping::new(),
+		};
+
+		patch.compare(old, new, &mut KeyPath::new());
+		patch
+	}
+
+	fn compare(&mut self, old: &Mapping, new: &Mapping, path: &mut KeyPath) {
+		for (key, old) in old {
+			path.push(PathSegment::from_key(key));
+
+			match (old, new.get(key)) {
+				(_, None) => self.removed.push(Value::String(path.to_string())),
+				(Value::Mapping(old), Some(Value::Mapping(new))) => self.compare(old, new, path),
+				(old, Some(new)) if old != new => {
+					self.set
+						.insert(Value::String(path.to_string()), new.clone());
+				}
+				_ => {}
+			}
+
+			path.pop();
+		}
+
+		for (key, new) in new {
+			if !old.contains_key(key) {
+				path.push(PathSegment::from_key(key));
+				self.added
+					.insert(Value::String(path.to_string()), new.clone());
+				path.pop();
+			}
+		}
+	}
+
+	/// Turn the patch into the root mapping that gets emitted
+	pub(crate) fn into_mapping(self) -> Mapping {
+		let mut mapping = Mapping::new();
+
+		mapping.insert(Value::from("set"), Value::Mapping(self.set));
+		mapping.insert(Value::from("removed"), Value::Sequence(self.removed));
+		mapping.insert(Value::from("added"), Value::Mapping(self.added));
+
+		mapping
+	}
+}
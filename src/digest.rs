@@ -0,0 +1,85 @@
+use crate::transform::Root;
+use serde_yaml::{Mapping, Value};
+use sha2::{Digest, Sha256};
+
+/// Version of the canonical encoding, changing it changes every digest
+const VERSION: &[u8] = b"yaml2lua-digest-v1\0";
+
+/// Hash the canonical encoding of the document, see [`crate::digest`] for its description
+pub(crate) fn digest(root: &Root) -> [u8; 32] {
+	let mut hasher = Sha256::new();
+
+	hasher.update(VERSION);
+
+	match root {
+		Root::Mapping(mapping) => encode_mapping(&mut hasher, mapping),
+		Root::Tagged(value) => encode(&mut hasher, value),
+	}
+
+	hasher.finalize().into()
+}
+
+/// Hash a single entry of a mapping, using the same encoding as [`digest`]
+pub(crate) fn digest_entry(key: &Value, value: &Value) -> [u8; 32] {
+	let mut hasher = Sha256::new();
+
+	hasher.update(VERSION);
+	encode(&mut hasher, key);
+	encode(&mut hasher, value);
+
+	hasher.finalize().into()
+}
+
+fn encode(hasher: &mut Sha256, value: &Value) {
+	match value {
+		Value::Null => hasher.update(b"n"),
+		Value::Bool(true) => hasher.update(b"t"),
+		Value::Bool(false) => hasher.update(b"f"),
+		Value::Number(n) => match n.as_f64() {
+			Some(float) if n.is_f64() => {
+				hasher.update(b"d");
+				hasher.update(float.to_bits().to_be_bytes());
+			}
+			_ => {
+				hasher.update(b"i");
+				hasher.update(n.to_string());
+				hasher.update(b";");
+			}
+		},
+		Value::String(s) => encode_string(hasher, s),
+		Value::Sequence(sequence) => {
+			hasher.update(b"[");
+			encode_len(hasher, sequence.len());
+
+			for value in sequence {
+				encode(hasher, value);
+			}
+		}
+		Value::Mapping(mapping) => encode_mapping(hasher, mapping),
+		Value::Tagged(tagged) => {
+			hasher.update(b"!");
+			encode_string(hasher, &tagged.tag.to_string());
+			encode(hasher, &tagged.value);
+		}
+	}
+}
+
+fn encode_mapping(hasher: &mut Sha256, mapping: &Mapping) {
+	hasher.update(b"{");
+	encode_len(hasher, mapping.len());
+
+	for (key, value) in mapping {
+		encode(hasher, key);
+		encode(hasher, value);
+	}
+}
+
+fn encode_string(hasher: &mut Sha256, string: &str) {
+	hasher.update(b"s");
+	encode_len(hasher, string.len());
+	hasher.update(string);
+}
+
+fn encode_len(hasher: &mut Sha256, len: usize) {
+	hasher.update((len as u64).to_be_bytes());
+}
@@ -0,0 +1,2653 @@
+use crate::{
+	digest,
+	fmt::{
+		format_lua_key, format_number_key, is_lua_identifier, write_lua_key, write_lua_string,
+		LuaKey as Key,
+	},
+	hoist,
+	options::RenameKeys,
+	source,
+	transform::{Failures, Root},
+	Binary, Comments, Diagnostic, DiagnosticKind, Dialect, DuplicateKeys, Error, FidelityMode,
+	Indent, KeyComparison, KeyPath, KeyStyle, LargeIntegers, NonFiniteFloats, Nulls, OutputMode,
+	ParseOptions, PathSegment, Progress, Result, Severity, Stats, StringOverflow, TableLayout,
+	TagHandler, TagReplacement, TagWrapper, Timestamps, UnknownTags, FORMAT_VERSIONS,
+};
+use serde_yaml::{value::TaggedValue, Mapping, Number, Value};
+use std::{
+	borrow::Cow,
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	io::Write,
+	ops::ControlFlow,
+	rc::Rc,
+};
+
+mod anchors;
+mod annotations;
+mod binary;
+mod long_strings;
+mod shape;
+mod shared;
+mod timestamps;
+mod types;
+mod validate;
+
+/// Table value that can be split into assignments
+#[derive(Clone, Copy)]
+enum Table<'a> {
+	Mapping(&'a Mapping),
+	Sequence(&'a [Value]),
+}
+
+impl<'a> Table<'a> {
+	fn from_value(value: &'a Value) -> Option<Self> {
+		match value {
+			Value::Mapping(mapping) => Some(Table::Mapping(mapping)),
+			Value::Sequence(sequence) => Some(Table::Sequence(sequence)),
+			_ => None,
+		}
+	}
+
+	fn values(&self) -> Box<dyn Iterator<Item = &'a Value> + 'a> {
+		match *self {
+			Table::Mapping(mapping) => Box::new(mapping.values()),
+			Table::Sequence(sequence) => Box::new(sequence.iter()),
+		}
+	}
+
+	fn len(&self) -> usize {
+		match self {
+			Table::Mapping(mapping) => mapping.len(),
+			Table::Sequence(sequence) => sequence.len(),
+		}
+	}
+
+	/// Whether this table or any of its descendants has more entries than the threshold
+	fn needs_chunking(&self, threshold: usize) -> bool {
+		self.len() > threshold
+			|| self
+				.values()
+				.filter_map(Table::from_value)
+				.any(|table| table.needs_chunking(threshold))
+	}
+}
+
+/// Identity of a key once loaded into Lua, where `1` and `1.0` are the same key
+#[derive(PartialEq, Eq, Hash)]
+enum KeyId {
+	String(String),
+	Number(u64),
+	Bool(bool),
+}
+
+impl KeyId {
+	fn of(key: &Key) -> Self {
+		match key {
+			Key::String(s) | Key::Identifier(s) => KeyId::String(s.to_string()),
+			Key::Number(n) => {
+				let float = n.as_f64().unwrap_or_default();
+				KeyId::Number(if float == 0.0 { 0 } else { float.to_bits() })
+			}
+			Key::Bool(b) => KeyId::Bool(*b),
+		}
+	}
+}
+
+/// Entry of a table being written, see [`TableLayout`] for how entries are ordered
+enum Entry<'v> {
+	/// Element of a sequence, placed at the next array index
+	Positional { index: usize, value: &'v Value },
+	/// Entry of a mapping with its resolved key
+	Keyed {
+		source: &'v Value,
+		key: Key<'v>,
+		value: &'v Value,
+	},
+	/// Entry added by the options rather than coming from the document
+	Synthetic { key: &'v str, value: Synthetic<'v> },
+}
+
+enum Synthetic<'v> {
+	/// Number of elements of a sequence
+	Length(usize),
+	/// Identifiers of sanitized keys mapped to the original keys
+	OriginalKeys(Vec<(String, &'v str)>),
+	/// Name of the tag of an inlined tag wrapper
+	Tag(&'v str),
+}
+
+impl Entry<'_> {
+	fn is_positional(&self) -> bool {
+		matches!(self, Entry::Positional { .. })
+	}
+
+	fn is_synthetic(&self) -> bool {
+		matches!(self, Entry::Synthetic { .. })
+	}
+}
+
+/// Largest integer that every integer below can be represented exactly as a double
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+/// Name of the companion table mapping sanitized keys to the original ones
+const ORIGINAL_KEYS: &str = "__original_keys";
+
+/// Buffered output is written into the sink once it's longer than this
+const FLUSH_SIZE: usize = 64 * 1024;
+
+/// Options changing the formatting that [`FORMAT_VERSIONS`] specify, which can't be
+/// combined with a pinned version
+const VERSIONED_FORMATTING: &[&str] = &[
+	"bare_keys",
+	"table_layout",
+	"scalars_per_line",
+	"indent",
+	"trailing_commas",
+	"compact",
+	"quote_style",
+	"long_strings",
+];
+
+/// Written text of top-level entries and the position of their separator in it,
+/// by the digest of their key and value
+pub(crate) type EntryCache = HashMap<[u8; 32], (String, Option<usize>)>;
+
+/// Walks parsed YAML and writes the Lua table, collecting diagnostics on the way
+pub(crate) struct Emitter<'a> {
+	options: &'a ParseOptions,
+	lua: String,
+	path: KeyPath,
+	diagnostics: Vec<Diagnostic>,
+	stats: Option<Stats>,
+	/// Strings hoisted into locals and names of the locals
+	hoisted_strings: HashMap<String, String>,
+	/// Paths of tables hoisted into locals and names of the locals
+	shared_tables: HashMap<KeyPath, String>,
+	/// Whether a shared table is being declared, references inside it aren't copied
+	in_shared_table: bool,
+	/// Anchored values of the source, for [`ParseOptions::preserve_anchors`]
+	anchors: Vec<source::Anchor>,
+	/// Paths of entries with plain keys in the source, for [`KeyStyle::MirrorSource`]
+	plain_keys: Rc<HashSet<KeyPath>>,
+	/// Types of strings tagged with core schema tags, for [`FidelityMode::Wrapped`] and [`ParseOptions::binary`]
+	core_tags: HashMap<KeyPath, &'static str>,
+	/// Paths of timestamps in the source, for [`ParseOptions::timestamps`]
+	timestamps: HashSet<KeyPath>,
+	/// Lines of entries in the source, for [`ParseOptions::source_comments`]
+	source_lines: HashMap<KeyPath, usize>,
+	/// Errors of values replaced with the placeholder in a partial conversion
+	errors: Option<Vec<Error>>,
+	/// Values that failed to transform before being emitted, by path
+	/// Errors of values that already failed to transform, with their position in the failures
+	failures: HashMap<KeyPath, (usize, Error)>,
+	/// Tags found with [`UnknownTags::Error`], reported together once the document is written
+	unknown_tags: Vec<(KeyPath, String)>,
+	/// Top-level entries written by the previous conversion and by this one
+	entry_cache: Option<(EntryCache, EntryCache)>,
+	/// Number of values written, only counted with a progress callback
+	nodes: usize,
+	/// Node count or output length at which progress is reported next
+	next_progress: usize,
+	/// Position of the comma after the last entry written into the innermost open table
+	last_separator: Option<usize>,
+	/// Writer the output is written into piece by piece instead of keeping all of it
+	sink: Option<&'a mut dyn Write>,
+	/// Number of bytes already written into the sink
+	flushed: usize,
+	/// Whether the output written into the sink ends at the start of a line
+	sink_line_start: bool,
+	/// Long strings the output written into the sink is in, for [`ParseOptions::long_strings`]
+	sink_long_strings: long_strings::LongStrings,
+}
+
+impl<'a> Emitter<'a> {
+	pub fn new(options: &'a ParseOptions) -> Self {
+		Self {
+			options,
+			lua: String::new(),
+			path: KeyPath::new(),
+			diagnostics: Vec::new(),
+			stats: None,
+			hoisted_strings: HashMap::new(),
+			shared_tables: HashMap::new(),
+			in_shared_table: false,
+			anchors: Vec::new(),
+			plain_keys: Rc::default(),
+			core_tags: HashMap::new(),
+			timestamps: HashSet::new(),
+			source_lines: HashMap::new(),
+			errors: None,
+			failures: HashMap::new(),
+			unknown_tags: Vec::new(),
+			entry_cache: None,
+			last_separator: None,
+			sink: None,
+			flushed: 0,
+			sink_line_start: true,
+			sink_long_strings: long_strings::LongStrings::default(),
+			nodes: 0,
+			next_progress: options
+				.progress
+				.as_ref()
+				.map_or(0, |hook| hook.interval.next(0, 0)),
+		}
+	}
+
+	/// Write the output into an existing buffer, reusing its allocation
+	pub fn with_buffer(mut self, mut buffer: String) -> Self {
+		buffer.clear();
+		self.lua = buffer;
+		self
+	}
+
+	/// Use the YAML source the emitted value was loaded from, only needed with
+	/// [`KeyStyle::MirrorSource`], [`ParseOptions::source_comments`],
+	/// [`ParseOptions::preserve_anchors`], [`ParseOptions::binary`], [`ParseOptions::timestamps`]
+	/// and [`FidelityMode::Wrapped`]
+	pub fn with_source(mut self, yaml: &str) -> Self {
+		if self.options.preserve_anchors {
+			self.anchors = source::anchors(yaml);
+		}
+
+		if matches!(self.options.fidelity, FidelityMode::Wrapped(_))
+			|| self.options.binary != Binary::Base64
+		{
+			self.core_tags = source::core_tags(yaml);
+		}
+
+		if self.options.timestamps != Timestamps::String
+			|| matches!(self.options.fidelity, FidelityMode::Wrapped(_))
+		{
+			self.timestamps = source::timestamps(yaml);
+		}
+
+		if self.options.key_style == KeyStyle::MirrorSource {
+			self.plain_keys = Rc::new(source::plain_keys(yaml));
+		}
+
+		if let Some(comments) = &self.options.source_comments {
+			self.source_lines = source::lines(yaml, comments.max_depth);
+		}
+
+		self
+	}
+
+	/// Replace values that fail to convert with the error placeholder instead of failing,
+	/// along with the values that already failed to transform
+	pub fn partial(mut self, failures: Failures) -> Self {
+		self.errors = Some(Vec::new());
+		self.failures = failures
+			.into_iter()
+			.enumerate()
+			.map(|(i, (path, error))| (path, (i, error)))
+			.collect();
+		self
+	}
+
+	/// Reuse top-level entries written by the previous conversion when their key and value
+	/// didn't change, the options must be the same and not depend on the rest of the document
+	pub fn with_entry_cache(mut self, previous: EntryCache) -> Self {
+		self.entry_cache = Some((previous, EntryCache::new()));
+		self
+	}
+
+	/// Write the output into the sink as it's generated, only keeping the part that can still change
+	pub fn with_sink(mut self, sink: &'a mut dyn Write) -> Self {
+		self.sink = Some(sink);
+		self
+	}
+
+	/// Start at the given path instead of the document root
+	pub fn at(mut self, path: KeyPath) -> Self {
+		self.path = path;
+		self
+	}
+
+	/// Collect conversion statistics, left disabled to avoid the bookkeeping
+	pub fn with_stats(mut self) -> Self {
+		self.stats = Some(Stats::default());
+		self
+	}
+
+	/// Write the rest of the output into the sink, ended as set in the options
+	pub fn finish_sink(&mut self) -> Result<()> {
+		self.options.end_output(&mut self.lua);
+		self.write_sink(self.lua.len())?;
+
+		if let Some(sink) = &mut self.sink {
+			sink.flush()?;
+		}
+
+		Ok(())
+	}
+
+	pub fn into_output(self) -> String {
+		self.lua
+	}
+
+	/// Get the output with the top-level entries written by this conversion
+	pub fn into_cached(self) -> (String, EntryCache) {
+		let cache = self.entry_cache.map(|(_, current)| current);
+		(self.lua, cache.unwrap_or_default())
+	}
+
+	pub fn into_parts(self) -> (String, Vec<Diagnostic>, Stats) {
+		(self.lua, self.diagnostics, self.stats.unwrap_or_default())
+	}
+
+	/// Get the output with diagnostics and errors of a partial conversion
+	pub fn into_partial(self) -> (String, Vec<Diagnostic>, Vec<Error>) {
+		let mut errors = self.errors.unwrap_or_default();
+
+		// Values left out of the output, like elided ones, still failed
+		let mut failures: Vec<_> = self.failures.into_values().collect();
+		failures.sort_by_key(|(i, _)| *i);
+		errors.extend(failures.into_iter().map(|(_, error)| error));
+
+		(self.lua, self.diagnostics, errors)
+	}
+
+	pub fn emit_root(&mut self, root: &Mapping) -> Result<()> {
+		self.emit_mapping_root(root, None)
+	}
+
+	/// Emit a document root, tagged roots are written like tagged values at any other depth
+	pub(crate) fn emit(&mut self, root: &Root) -> Result<()> {
+		match root {
+			Root::Mapping(mapping) => self.emit_root(mapping),
+			Root::Tagged(tagged @ Value::Tagged(inner)) => match &inner.value {
+				Value::Mapping(mapping) => self.emit_mapping_root(mapping, Some(tagged)),
+				_ => self.emit_value(tagged),
+			},
+			Root::Tagged(value) => self.emit_value(value),
+		}
+	}
+
+	/// Emit the root mapping, written inside of its tag if the root is tagged
+	fn emit_mapping_root(&mut self, root: &Mapping, tagged: Option<&Value>) -> Result<()> {
+		self.check_format_version()?;
+		self.check_freeze()?;
+		self.check_max_string_len()?;
+		self.check_validator()?;
+		self.check_luau_types()?;
+		self.check_columnar()?;
+		self.check_trailing_semicolon()?;
+		self.check_compact()?;
+		self.check_binary()?;
+		self.check_timestamps()?;
+		self.options.begin_output(&mut self.lua);
+
+		// Tables of tagged roots are counted when they're written
+		if tagged.is_none() {
+			self.count(|stats| {
+				stats.tables += 1;
+				stats.max_depth = 1;
+			});
+		}
+
+		if let Some(dedup) = &self.options.dedupe_strings {
+			self.require_statements("string deduplication")?;
+
+			let mut strings = hoist::repeated_strings(root, dedup);
+
+			// Long strings are limited where they're used so diagnostics have their path
+			strings.retain(|string| !self.is_too_long(string));
+
+			for (i, string) in strings.iter().enumerate() {
+				let name = format!("__s{}", i + 1);
+
+				self.lua.push_str("local ");
+				self.lua.push_str(&name);
+				self.assign();
+				self.write_string_value(string);
+				self.end_statement();
+				self.hoisted_strings.insert(string.to_string(), name);
+			}
+
+			if !strings.is_empty() {
+				self.end_line();
+			}
+		}
+
+		if let Some(dedup) = &self.options.dedupe_subtrees {
+			self.write_shared_tables(root, dedup)?;
+		}
+
+		if self.options.preserve_anchors {
+			self.write_anchored_tables(root)?;
+		}
+
+		let untagged = |feature: &str| match tagged {
+			Some(_) => Err(Error::Options(format!(
+				"{} requires an untagged root",
+				feature
+			))),
+			None => Ok(()),
+		};
+
+		let shape = if self.options.validator
+			|| self.options.annotations.is_some()
+			|| self.options.luau_types.is_some()
+		{
+			untagged("validators, annotations and Luau types")?;
+			Some(self.shape_root(root)?)
+		} else {
+			None
+		};
+
+		if let (Some(class), Some(shape)) = (&self.options.annotations, &shape) {
+			self.require_statements("annotations")?;
+			self.lua.push_str(&annotations::generate(shape, class)?);
+		}
+
+		if let (Some(types), Some(shape)) = (&self.options.luau_types, &shape) {
+			self.write_code(&types::generate(shape, types)?);
+		}
+
+		match self.options.chunk_threshold {
+			Some(threshold) => {
+				untagged("chunked output")?;
+				self.emit_chunked(root, threshold)?
+			}
+			// Annotated and validated modules need a local to attach the type or metatable to
+			None if shape.is_some() && self.options.output_mode == OutputMode::Module => {
+				self.lua.push_str("local data");
+
+				if let Some(types) = &self.options.luau_types {
+					self.lua.push_str(": ");
+					self.lua.push_str(&types.name);
+				}
+
+				self.assign();
+				self.walk_mapping(root, 0)?;
+				self.end_statement();
+				self.end_line();
+
+				match shape.filter(|_| self.options.validator) {
+					Some(shape) => {
+						self.write_code(&validate::generate(&shape));
+						self.end_statement();
+						self.end_line();
+						self.lua.push_str(
+							"return setmetatable(data, { __index = { validate = validate } })",
+						);
+					}
+					None => self.lua.push_str("return data"),
+				}
+			}
+			None => {
+				self.write_prefix();
+
+				match tagged {
+					Some(tagged) => self.write_value(tagged, 0)?,
+					None => self.walk_mapping(root, 0)?,
+				}
+			}
+		}
+
+		self.check_unknown_tags()?;
+		self.indent_output();
+
+		let len = self.flushed + self.lua.len();
+		self.count(|stats| stats.output_bytes = len);
+
+		Ok(())
+	}
+
+	/// Emit any value as the root, not only mappings
+	pub fn emit_value(&mut self, value: &Value) -> Result<()> {
+		self.check_format_version()?;
+		self.check_freeze()?;
+		self.check_max_string_len()?;
+		self.check_columnar()?;
+		self.check_trailing_semicolon()?;
+		self.check_compact()?;
+		self.check_binary()?;
+		self.check_timestamps()?;
+		self.options.begin_output(&mut self.lua);
+		self.write_prefix();
+		self.write_value(value, 0)?;
+
+		self.check_unknown_tags()?;
+		self.indent_output();
+
+		let len = self.flushed + self.lua.len();
+		self.count(|stats| stats.output_bytes = len);
+
+		Ok(())
+	}
+
+	/// Write code preceding the root table according to the output mode
+	fn write_prefix(&mut self) {
+		match &self.options.output_mode {
+			OutputMode::Table => {}
+			OutputMode::Module => self.lua.push_str("return "),
+			OutputMode::Assignment(name) => {
+				self.lua.push_str(name);
+				self.assign();
+			}
+		}
+	}
+
+	fn check_format_version(&self) -> Result<()> {
+		let Some(version) = self.options.format_version else {
+			return Ok(());
+		};
+
+		if !FORMAT_VERSIONS.contains(&version) {
+			return Err(Error::Options(format!(
+				"format version {} isn't supported, supported versions are {:?}",
+				version, FORMAT_VERSIONS
+			)));
+		}
+
+		match self
+			.options
+			.changed()
+			.into_iter()
+			.find(|option| VERSIONED_FORMATTING.contains(option))
+		{
+			Some(option) => Err(Error::Options(format!(
+				"{} changes the formatting pinned by format version {}",
+				option, version
+			))),
+			None => Ok(()),
+		}
+	}
+
+	fn check_max_string_len(&self) -> Result<()> {
+		match &self.options.max_string_len {
+			Some((max, StringOverflow::Truncate(suffix))) if suffix.chars().count() > *max => {
+				Err(Error::Options(format!(
+					"truncation suffix \"{}\" is longer than the maximum string length {}",
+					suffix, max
+				)))
+			}
+			_ => Ok(()),
+		}
+	}
+
+	fn check_freeze(&self) -> Result<()> {
+		if !self.options.freeze {
+			return Ok(());
+		}
+
+		if self.options.dialect != Dialect::Luau {
+			return Err(Error::Options(String::from(
+				"table freezing requires the Luau dialect",
+			)));
+		}
+
+		if self.options.chunk_threshold.is_some() {
+			return Err(Error::Options(String::from(
+				"table freezing can't be used with chunked output",
+			)));
+		}
+
+		Ok(())
+	}
+
+	fn check_validator(&self) -> Result<()> {
+		if !self.options.validator {
+			return Ok(());
+		}
+
+		let conflict = if self.options.output_mode != OutputMode::Module {
+			"requires module output mode"
+		} else if self.options.chunk_threshold.is_some() {
+			"can't be used with chunked output"
+		} else if self.options.freeze {
+			"can't be used with table freezing"
+		} else {
+			return Ok(());
+		};
+
+		Err(Error::Options(format!("validate function {}", conflict)))
+	}
+
+	fn check_luau_types(&self) -> Result<()> {
+		if self.options.luau_types.is_none() {
+			return Ok(());
+		}
+
+		let conflict = if self.options.dialect != Dialect::Luau {
+			"require the Luau dialect"
+		} else if self.options.output_mode != OutputMode::Module {
+			"require module output mode"
+		} else if self.options.chunk_threshold.is_some() {
+			"can't be used with chunked output"
+		} else {
+			return Ok(());
+		};
+
+		Err(Error::Options(format!("Luau types {}", conflict)))
+	}
+
+	fn check_columnar(&self) -> Result<()> {
+		if self.options.columnar_records.is_none() {
+			return Ok(());
+		}
+
+		let conflict = if self.options.chunk_threshold.is_some() {
+			"chunked output"
+		} else if self.options.dedupe_subtrees.is_some() {
+			"subtree deduplication"
+		} else if self.options.preview.is_some() {
+			"previews"
+		} else if self.options.validator
+			|| self.options.annotations.is_some()
+			|| self.options.luau_types.is_some()
+		{
+			"validators, annotations and Luau types"
+		} else {
+			return Ok(());
+		};
+
+		Err(Error::Options(format!(
+			"columnar records can't be used with {}",
+			conflict
+		)))
+	}
+
+	/// Fail if the output mode can't contain statements like local declarations
+	fn check_trailing_semicolon(&self) -> Result<()> {
+		match self.options.trailing_semicolon {
+			true => self.require_statements("trailing semicolon"),
+			false => Ok(()),
+		}
+	}
+
+	fn check_compact(&self) -> Result<()> {
+		if !self.options.compact {
+			return Ok(());
+		}
+
+		let conflict = if self.options.annotations.is_some() {
+			"annotations"
+		} else if self.options.chunk_threshold.is_some() {
+			"chunked output"
+		} else if self.options.long_strings {
+			"long strings"
+		} else if self.options.header.is_some() {
+			"a header"
+		} else {
+			return Ok(());
+		};
+
+		Err(Error::Options(format!(
+			"compact output can't be used with {}",
+			conflict
+		)))
+	}
+
+	fn require_statements(&self, feature: &str) -> Result<()> {
+		if self.options.output_mode == OutputMode::Table {
+			return Err(Error::Options(format!(
+				"{} requires module or assignment output mode",
+				feature
+			)));
+		}
+
+		Ok(())
+	}
+
+	/// Emit the root as a series of assignments, splitting tables with more
+	/// entries than the threshold (and their ancestors) into one statement per entry
+	fn emit_chunked(&mut self, root: &Mapping, threshold: usize) -> Result<()> {
+		self.require_statements("chunked output")?;
+
+		if self.options.preview.is_some() {
+			return Err(Error::Options(String::from(
+				"preview can't be used with chunked output",
+			)));
+		}
+
+		let (declaration, name, end) = match &self.options.output_mode {
+			OutputMode::Assignment(name) => (name.clone(), name.clone(), ""),
+			_ => (String::from("local t"), String::from("t"), "\nreturn t"),
+		};
+
+		self.lua.push_str(&format!("{} = {{}}\n", declaration));
+		self.walk_chunked(&name, Table::Mapping(root), threshold, 1)?;
+
+		self.lua.pop();
+		self.lua.push_str(end);
+
+		Ok(())
+	}
+
+	fn walk_chunked(
+		&mut self,
+		target: &str,
+		table: Table,
+		threshold: usize,
+		depth: usize,
+	) -> Result<()> {
+		let entries: Vec<(Option<&Value>, Key, &Value)> = match table {
+			Table::Mapping(mapping) => self
+				.resolve_entries(self.written_entries(mapping))?
+				.into_iter()
+				.map(|(source, key, value)| (Some(source), key, value))
+				.collect(),
+			Table::Sequence(sequence) => sequence
+				.iter()
+				.enumerate()
+				.map(|(i, value)| (None, Key::Number((i + 1).into()), value))
+				.collect(),
+		};
+
+		for (i, (source, key, value)) in entries.into_iter().enumerate() {
+			self.path.push(match source {
+				Some(source) => PathSegment::from_key(source),
+				None => PathSegment::Index(i),
+			});
+
+			let target = match &key {
+				key if self.bare_key(key).is_some() => {
+					format!("{}.{}", target, self.format_key(key))
+				}
+				Key::Number(n) => format!("{}[{}]", target, self.format_number_key(n)?),
+				key => format!("{}{}", target, self.format_key(key)),
+			};
+
+			let table = Table::from_value(value).filter(|table| {
+				// Folded sequences are written as one table
+				let folded = match table {
+					Table::Sequence(sequence) => {
+						self.options.fold_single_key_maps && single_key_entries(sequence).is_some()
+					}
+					Table::Mapping(_) => false,
+				};
+
+				!folded && table.needs_chunking(threshold)
+			});
+
+			match table {
+				Some(table) => {
+					self.count(|stats| {
+						stats.tables += 1;
+						stats.max_depth = stats.max_depth.max(depth + 1);
+					});
+
+					self.lua.push_str(&format!("{} = {{}}\n", target));
+
+					let length = match (table, &self.options.sequence_length_field) {
+						(Table::Sequence(sequence), Some(field)) => Some(format!(
+							"{}{} = {}\n",
+							target,
+							self.format_key(&Key::from(field.as_str())),
+							sequence.len()
+						)),
+						_ => None,
+					};
+
+					if let Some(length) = length {
+						self.lua.push_str(&length);
+					}
+
+					self.walk_chunked(&target, table, threshold, depth + 1)?;
+				}
+				None => {
+					self.lua.push_str(&target);
+					self.lua.push_str(" = ");
+					self.write_entry_value(value, 0, false)?;
+				}
+			}
+
+			self.path.pop();
+		}
+
+		Ok(())
+	}
+
+	/// Get entries of the sequence folded into one table when it's enabled in the options,
+	/// reporting sequences that only partially consist of single-key mappings
+	fn fold_entries<'v>(
+		&mut self,
+		sequence: &'v [Value],
+	) -> Result<Option<Vec<(&'v Value, &'v Value)>>> {
+		if !self.options.fold_single_key_maps {
+			return Ok(None);
+		}
+
+		let entries = single_key_entries(sequence);
+
+		if entries.is_none()
+			&& sequence
+				.iter()
+				.any(|v| v.as_mapping().is_some_and(|m| m.len() == 1))
+		{
+			self.report(
+				Severity::Info,
+				DiagnosticKind::UnfoldedSequence,
+				String::from(
+					"sequence mixes single-key mappings with other values and wasn't folded",
+				),
+			)?;
+		}
+
+		Ok(entries)
+	}
+
+	/// Count the value about to be written and call the progress callback if it's due
+	fn progress(&mut self) -> Result<()> {
+		let Some(hook) = &self.options.progress else {
+			return Ok(());
+		};
+
+		self.nodes += 1;
+
+		let bytes = self.flushed + self.lua.len();
+
+		if !hook.interval.is_due(self.next_progress, self.nodes, bytes) {
+			return Ok(());
+		}
+
+		self.next_progress = hook.interval.next(self.nodes, bytes);
+
+		let progress = Progress {
+			nodes: self.nodes,
+			bytes,
+			path: &self.path,
+		};
+
+		match (hook.callback)(&progress) {
+			ControlFlow::Continue(()) => Ok(()),
+			ControlFlow::Break(()) => Err(Error::Cancelled),
+		}
+	}
+
+	fn count(&mut self, f: impl FnOnce(&mut Stats)) {
+		if let Some(stats) = &mut self.stats {
+			f(stats);
+		}
+	}
+
+	/// Record a diagnostic, failing instead if it's a warning in strict mode
+	fn report(&mut self, severity: Severity, kind: DiagnosticKind, message: String) -> Result<()> {
+		let diagnostic = Diagnostic {
+			severity,
+			kind,
+			path: self.path.clone(),
+			message,
+		};
+
+		if self.options.strict && severity >= Severity::Warning {
+			return Err(Error::Strict(diagnostic));
+		}
+
+		self.diagnostics.push(diagnostic);
+
+		Ok(())
+	}
+
+	/// Skip an entry whose key can't be a Lua table key, failing in strict mode
+	fn skip_key(&mut self, source: &Value, key: String, message: String) -> Result<()> {
+		self.path.push(PathSegment::from_key(source));
+
+		if self.options.strict {
+			let path = self.path.clone();
+			self.path.pop();
+
+			return Err(Error::UnsupportedKey { path, key });
+		}
+
+		self.report(Severity::Warning, DiagnosticKind::UnsupportedKey, message)?;
+		self.path.pop();
+
+		Ok(())
+	}
+
+	/// Rename the string key with [`ParseOptions::rename_keys`], if it's set
+	fn rename_key<'k>(&self, key: &'k str) -> Cow<'k, str> {
+		match &self.options.rename_keys {
+			Some(RenameKeys(rename)) => Cow::Owned(rename(key)),
+			None => Cow::Borrowed(key),
+		}
+	}
+
+	/// Rename the string key and rewrite it using the key style
+	fn style_key<'k>(&self, key: &'k str) -> Cow<'k, str> {
+		match self.rename_key(key) {
+			Cow::Borrowed(key) => self.options.key_style.apply(key),
+			Cow::Owned(key) => Cow::Owned(self.options.key_style.apply(&key).into_owned()),
+		}
+	}
+
+	/// Leave out entries with null values if they're skipped by [`ParseOptions::nulls`]
+	pub(super) fn written_entries<'v>(
+		&self,
+		entries: impl IntoIterator<Item = (&'v Value, &'v Value)>,
+	) -> impl Iterator<Item = (&'v Value, &'v Value)> {
+		let skip = self.options.nulls == Nulls::Skip;
+
+		entries
+			.into_iter()
+			.filter(move |(_, value)| !(skip && value.is_null()))
+	}
+
+	/// Resolve keys of a single table, skipping entries whose keys can't be emitted
+	fn resolve_entries<'v>(
+		&mut self,
+		entries: impl IntoIterator<Item = (&'v Value, &'v Value)>,
+	) -> Result<Vec<(&'v Value, Key<'v>, &'v Value)>> {
+		let mut resolved = Vec::new();
+		let mut seen: HashMap<KeyId, &Value> = HashMap::new();
+		let mut compared: HashMap<String, (&Value, &str)> = HashMap::new();
+
+		let companion = Value::String(String::from(ORIGINAL_KEYS));
+
+		if self.options.original_keys_table
+			&& self.options.key_style == KeyStyle::SanitizedIdentifiers
+		{
+			seen.insert(KeyId::String(String::from(ORIGINAL_KEYS)), &companion);
+		}
+
+		let rewrites = self.options.numeric_keys.is_some()
+			|| self.options.key_style != KeyStyle::Preserve
+			|| self.options.rename_keys.is_some()
+			|| self.options.max_string_len.is_some()
+			// Elements of folded sequences can repeat keys
+			|| self.options.fold_single_key_maps;
+
+		for (source, value) in entries {
+			let key = match source {
+				Value::String(s) => match self.options.numeric_keys.and_then(|n| n.parse(s)) {
+					Some(n) => Key::Number(n),
+					None if self.options.key_style == KeyStyle::MirrorSource => {
+						self.path.push(PathSegment::from_key(source));
+						let plain = self.plain_keys.contains(&self.path);
+						self.path.pop();
+
+						if plain {
+							Key::Identifier(self.rename_key(s))
+						} else {
+							Key::String(self.rename_key(s))
+						}
+					}
+					None if self.options.key_style == KeyStyle::SanitizedIdentifiers => {
+						let ident = self.style_key(s);
+
+						if ident != *s {
+							self.path.push(PathSegment::from_key(source));
+							self.report(
+								Severity::Info,
+								DiagnosticKind::SanitizedKey,
+								format!("key \"{}\" was emitted as `{}`", s, ident),
+							)?;
+							self.path.pop();
+						}
+
+						Key::Identifier(ident)
+					}
+					None => Key::String(self.style_key(s)),
+				},
+				Value::Number(n) if format_number_key(n).is_none() => {
+					self.skip_key(
+						source,
+						n.to_string(),
+						format!("skipped entry with {} key, which can't be a table key", n),
+					)?;
+
+					continue;
+				}
+				Value::Number(n) => Key::Number(n.clone()),
+				Value::Bool(b) => Key::Bool(*b),
+				_ => {
+					self.skip_key(
+						source,
+						String::from(type_name(source)),
+						format!(
+							"skipped entry with unsupported key of type {}",
+							type_name(source)
+						),
+					)?;
+
+					continue;
+				}
+			};
+
+			let key = match key {
+				Key::String(s) if self.is_too_long(&s) => Key::String(self.limit_key(source, s)?),
+				Key::Identifier(s) if self.is_too_long(&s) => {
+					Key::Identifier(self.limit_key(source, s)?)
+				}
+				key => key,
+			};
+
+			// Lua stores whole float keys as integers, so `1` and `1.0` are always the same key
+			if rewrites || matches!(key, Key::Number(_)) {
+				if let Some(other) = seen.insert(KeyId::of(&key), source) {
+					self.collision(other, source, &key)?;
+				}
+			}
+
+			if let Some(text) = self.compared_text(&key) {
+				let kind = key_kind(&key);
+
+				match compared.get(&text) {
+					Some((other, other_kind)) if *other_kind != kind => {
+						let message = format!(
+							"keys {} and {} are equal when compared {}",
+							describe_key(other),
+							describe_key(source),
+							match self.options.key_type_collisions {
+								KeyComparison::Tostring => "with tostring",
+								_ => "as strings",
+							}
+						);
+
+						self.report(Severity::Warning, DiagnosticKind::KeyTypeCollision, message)?;
+					}
+					Some(_) => {}
+					None => {
+						compared.insert(text, (source, kind));
+					}
+				}
+			}
+
+			resolved.push((source, key, value));
+		}
+
+		if self.options.sort_keys {
+			resolved.sort_by(|(_, a, _), (_, b, _)| compare_keys(a, b));
+		}
+
+		Ok(resolved)
+	}
+
+	/// Get the text of the key compared to keys of other types, see [`KeyComparison`]
+	fn compared_text(&self, key: &Key) -> Option<String> {
+		let text = match (self.options.key_type_collisions, key) {
+			(KeyComparison::None, _) => return None,
+			(_, Key::String(s) | Key::Identifier(s)) => s.to_string(),
+			(_, Key::Bool(b)) => b.to_string(),
+			(KeyComparison::String, Key::Number(n)) => n.to_string(),
+			(KeyComparison::Tostring, Key::Number(n)) => lua_tostring(n, self.options.dialect),
+		};
+
+		Some(text)
+	}
+
+	/// Whether the string is longer than the maximum length, in characters
+	fn is_too_long(&self, string: &str) -> bool {
+		self.options
+			.max_string_len
+			.as_ref()
+			.is_some_and(|(max, _)| string.len() > *max && string.chars().count() > *max)
+	}
+
+	/// Apply the maximum length to a string at the current path
+	fn limit_string<'s>(&mut self, string: Cow<'s, str>) -> Result<Cow<'s, str>> {
+		let Some((max, overflow)) = &self.options.max_string_len else {
+			return Ok(string);
+		};
+
+		let length = string.chars().count();
+
+		if length <= *max {
+			return Ok(string);
+		}
+
+		let suffix = match overflow {
+			StringOverflow::Error => {
+				return Err(Error::StringTooLong {
+					path: self.path.clone(),
+					length,
+					max: *max,
+				})
+			}
+			StringOverflow::Truncate(suffix) => suffix,
+		};
+
+		let kept = max.saturating_sub(suffix.chars().count());
+		let end = string
+			.char_indices()
+			.nth(kept)
+			.map_or(string.len(), |(i, _)| i);
+
+		let truncated = format!("{}{}", &string[..end], suffix);
+
+		self.report(
+			Severity::Warning,
+			DiagnosticKind::TruncatedString,
+			format!("string of {} characters was truncated to {}", length, max),
+		)?;
+
+		Ok(Cow::Owned(truncated))
+	}
+
+	fn limit_key<'s>(&mut self, source: &Value, key: Cow<'s, str>) -> Result<Cow<'s, str>> {
+		self.path.push(PathSegment::from_key(source));
+		let key = self.limit_string(key);
+		self.path.pop();
+
+		key
+	}
+
+	/// Handle two keys of the same table resolving into the same Lua key
+	fn collision(&mut self, first: &Value, second: &Value, key: &Key) -> Result<()> {
+		let key = match key {
+			// Lua stores whole float keys as integers, so that's the key both resolve to
+			Key::Number(n) => match n.as_f64() {
+				Some(float)
+					if n.is_f64()
+						&& float.fract() == 0.0
+						&& (i64::MIN as f64..i64::MAX as f64).contains(&float) =>
+				{
+					format_lua_key(&Key::Number(Number::from(float as i64)))
+				}
+				_ => format_lua_key(key),
+			},
+			key => format_lua_key(key),
+		};
+		let (first, second) = (describe_key(first), describe_key(second));
+
+		match self.options.duplicate_keys {
+			DuplicateKeys::Error => Err(Error::KeyCollision {
+				path: self.path.clone(),
+				key: second,
+				other: first,
+				resolved: key,
+			}),
+			DuplicateKeys::Warn => self.report(
+				Severity::Warning,
+				DiagnosticKind::KeyCollision,
+				format!("keys {} and {} both resolve to {}", first, second, key),
+			),
+		}
+	}
+
+	/// Write a single table entry including its indentation and trailing comma
+	fn walk(&mut self, key: Option<&Key>, value: &Value, depth: usize) -> Result<()> {
+		self.indent(depth);
+
+		if let Some(key) = key {
+			match key {
+				Key::Number(n) => {
+					let n = self.format_number_key(n)?;
+					self.lua.push('[');
+					self.lua.push_str(&n);
+					self.lua.push(']');
+				}
+				key => self.write_key(key),
+			};
+
+			self.assign();
+		}
+
+		self.write_entry_value(value, depth, true)
+	}
+
+	/// Write the value of an entry followed by the separator of table entries if
+	/// it's in a table and the comment enabled in the options, the comment is
+	/// always placed after the separator
+	fn write_entry_value(&mut self, value: &Value, depth: usize, separated: bool) -> Result<()> {
+		if self.errors.is_none() {
+			return self.write_entry_contents(value, depth, separated);
+		}
+
+		if let Some((_, error)) = self.failures.remove(&self.path) {
+			return self.write_failed(error, separated);
+		}
+
+		let (len, path) = (self.lua.len(), self.path.segments().len());
+
+		match self.write_entry_contents(value, depth, separated) {
+			Err(
+				error @ (Error::KeyCollision { .. }
+				| Error::Strict(_)
+				| Error::UnsupportedKey { .. }
+				| Error::Transform { .. }
+				| Error::Number { .. }
+				| Error::StringTooLong { .. }
+				| Error::UnknownTags(_)
+				| Error::TagHandler { .. }),
+			) => {
+				self.lua.truncate(len);
+				self.path.truncate(path);
+				self.write_failed(error, separated)
+			}
+			result => result,
+		}
+	}
+
+	/// Write the error placeholder in place of a value that failed to convert
+	fn write_failed(&mut self, error: Error, separated: bool) -> Result<()> {
+		let placeholder = self.options.error_placeholder.as_deref().unwrap_or("nil");
+
+		self.lua.push_str(placeholder);
+
+		if separated {
+			self.separate();
+		}
+
+		self.write_entry_comment(&format!(
+			"ERROR: {}",
+			error.to_string().replace(['\r', '\n'], " ")
+		));
+		self.end_line();
+
+		if let Some(errors) = &mut self.errors {
+			errors.push(error);
+		}
+
+		Ok(())
+	}
+
+	fn write_entry_contents(&mut self, value: &Value, depth: usize, separated: bool) -> Result<()> {
+		let coerced = self.coerce(value)?;
+		let value = coerced.as_ref().unwrap_or(value);
+
+		self.write_coerced(value, depth)?;
+
+		if separated {
+			self.separate();
+		}
+
+		let comment = self.comment(value);
+		let location = self.location();
+		let comments: Vec<&str> = comment
+			.iter()
+			.chain(location.iter())
+			.map(|c| c.as_ref())
+			.collect();
+
+		if !comments.is_empty() {
+			self.write_entry_comment(&comments.join(" "));
+		}
+
+		self.end_line();
+
+		Ok(())
+	}
+
+	/// Get the location of the current entry in the source, if it's annotated
+	fn location(&self) -> Option<Cow<'static, str>> {
+		let line = self.source_lines.get(&self.path)?;
+
+		Some(Cow::Owned(
+			match &self.options.source_comments.as_ref()?.file {
+				Some(file) => format!("{}:{}", file, line),
+				None => format!("line {}", line),
+			},
+		))
+	}
+
+	fn comment(&self, value: &Value) -> Option<Cow<'static, str>> {
+		let name = match (self.options.comments, value) {
+			(Comments::None, _) => return None,
+			(_, Value::Tagged(t)) => return Some(Cow::Owned(t.tag.to_string())),
+			(Comments::Tags, _) => return None,
+			(Comments::Types, Value::String(_)) => "string",
+			(Comments::Types, Value::Number(n)) if n.is_f64() => "float",
+			(Comments::Types, Value::Number(_)) => "integer",
+			(Comments::Types, Value::Bool(_)) => "boolean",
+			(Comments::Types, Value::Null) => "nil",
+			(Comments::Types, Value::Sequence(_) | Value::Mapping(_)) => return None,
+		};
+
+		Some(Cow::Borrowed(name))
+	}
+
+	/// Write a value, nested tables are indented relative to the given depth
+	fn write_value(&mut self, value: &Value, depth: usize) -> Result<()> {
+		let coerced = self.coerce(value)?;
+		self.write_coerced(coerced.as_ref().unwrap_or(value), depth)
+	}
+
+	fn write_coerced(&mut self, value: &Value, depth: usize) -> Result<()> {
+		self.progress()?;
+
+		self.count(|stats| match value {
+			Value::String(s) => {
+				stats.strings += 1;
+				stats.string_bytes += s.len();
+			}
+			Value::Number(n) if n.is_f64() => stats.floats += 1,
+			Value::Number(_) => stats.integers += 1,
+			Value::Bool(_) => stats.bools += 1,
+			Value::Null => stats.nils += 1,
+			Value::Sequence(_) | Value::Mapping(_) => {
+				stats.tables += 1;
+				stats.max_depth = stats.max_depth.max(depth + 1);
+			}
+			Value::Tagged(_) => stats.tagged_values += 1,
+		});
+
+		if matches!(value, Value::Sequence(_) | Value::Mapping(_)) && self.write_shared_reference()
+		{
+			return Ok(());
+		}
+
+		if self.write_elided_table(value, depth)? {
+			return Ok(());
+		}
+
+		if self.write_binary(value)? || self.write_timestamp(value)? {
+			return Ok(());
+		}
+
+		if self.write_wrapped(value, depth)? {
+			return Ok(());
+		}
+
+		if self.write_constant(value)? {
+			return Ok(());
+		}
+
+		match value {
+			Value::String(s) => match self.hoisted_strings.get(s) {
+				Some(name) => self.lua.push_str(name),
+				None if self.is_too_long(s) => {
+					let s = self.limit_string(Cow::Borrowed(s))?;
+					self.write_string_value(&s);
+				}
+				None => self.write_string_value(s),
+			},
+			Value::Number(n) => {
+				let n = self.format_number(n)?;
+				self.lua.push_str(&n);
+			}
+			Value::Bool(b) => self.lua.push_str(if *b { "true" } else { "false" }),
+			Value::Null => match &self.options.nulls {
+				Nulls::Sentinel(sentinel) => self.lua.push_str(sentinel),
+				_ => self.lua.push_str("nil"),
+			},
+			Value::Sequence(s) => match self.fold_entries(s)? {
+				Some(entries) => self.walk_mapping(entries, depth)?,
+				None if self.write_records(s, depth)? => {}
+				None => {
+					let mut entries = Vec::with_capacity(s.len() + 1);
+
+					if let Some(field) = &self.options.sequence_length_field {
+						entries.push(Entry::Synthetic {
+							key: field,
+							value: Synthetic::Length(s.len()),
+						});
+					}
+
+					entries.extend(
+						s.iter()
+							.enumerate()
+							.map(|(index, value)| Entry::Positional { index, value }),
+					);
+
+					self.write_table(entries, depth)?;
+				}
+			},
+			Value::Mapping(m) => {
+				self.walk_mapping(m, depth)?;
+			}
+			Value::Tagged(t) => self.write_tagged(t, depth)?,
+		}
+
+		Ok(())
+	}
+
+	/// Write a tagged value according to [`ParseOptions::on_unknown_tag`]
+	fn write_tagged(&mut self, tagged: &TaggedValue, depth: usize) -> Result<()> {
+		let tag = tagged.tag.to_string();
+
+		if let Some(handler) = self.tag_handler(&tag) {
+			return self.write_replaced(handler, tagged, depth);
+		}
+
+		match &self.options.unknown_tags {
+			UnknownTags::Error if self.errors.is_some() => {
+				Err(Error::UnknownTags(vec![(self.path.clone(), tag)]))
+			}
+			UnknownTags::Error => {
+				self.unknown_tags.push((self.path.clone(), tag));
+
+				// Values are still written to find the unknown tags nested in them
+				self.write_value(&tagged.value, depth)
+			}
+			UnknownTags::Strip => {
+				self.report(
+					Severity::Info,
+					DiagnosticKind::UnknownTag,
+					format!("tag `{}` was stripped", tag),
+				)?;
+
+				self.write_value(&tagged.value, depth)
+			}
+			UnknownTags::Wrapper => {
+				self.report(
+					Severity::Warning,
+					DiagnosticKind::UnknownTag,
+					format!("tag `{}` was converted into a wrapper table", tag),
+				)?;
+
+				let name = tag_name(&tag);
+
+				let (key, tag_field) = match &self.options.tag_wrapper {
+					TagWrapper::Key(template) => (template.replace("$tag", name), None),
+					TagWrapper::Fields {
+						tag_field,
+						value_field,
+						inline_mappings,
+					} => {
+						if let (Value::Mapping(mapping), true) = (&tagged.value, inline_mappings) {
+							let mut entries = self.mapping_entries(mapping)?;
+							entries.insert(
+								0,
+								Entry::Synthetic {
+									key: tag_field,
+									value: Synthetic::Tag(name),
+								},
+							);
+
+							return self.write_table(entries, depth);
+						}
+
+						if tag_field == value_field {
+							return Err(Error::Options(format!(
+								"tag and value fields of tag wrappers are both named \"{}\"",
+								tag_field
+							)));
+						}
+
+						(value_field.clone(), Some(tag_field))
+					}
+				};
+
+				self.open_table();
+
+				if let Some(tag_field) = tag_field {
+					self.indent(depth + 1);
+					self.write_key(&Key::from(tag_field.as_str()));
+					self.assign();
+					self.write_string(name);
+					self.separate();
+					self.end_line();
+				}
+
+				self.indent(depth + 1);
+				self.write_key(&Key::from(key.as_str()));
+				self.assign();
+				self.write_value(&tagged.value, depth + 1)?;
+				self.separate();
+				self.end_line();
+
+				self.indent(depth);
+				self.close_table();
+
+				Ok(())
+			}
+			UnknownTags::Custom(handler) => self.write_replaced(handler, tagged, depth),
+		}
+	}
+
+	/// Get the handler registered for the tag with [`ParseOptions::with_tag`]
+	pub(super) fn tag_handler(&self, tag: &str) -> Option<&'a TagHandler> {
+		let name = tag_name(tag);
+
+		self.options
+			.tag_handlers
+			.iter()
+			.find(|(tag, _)| tag == name)
+			.map(|(_, handler)| handler)
+	}
+
+	/// Write the replacement of the tagged value returned by the handler
+	fn write_replaced(
+		&mut self,
+		handler: &TagHandler,
+		tagged: &TaggedValue,
+		depth: usize,
+	) -> Result<()> {
+		match self.replace_tagged(handler, tagged)? {
+			TagReplacement::Value(value) => self.write_value(&value, depth),
+			TagReplacement::Lua(lua) => {
+				self.lua.push_str(&lua);
+				Ok(())
+			}
+		}
+	}
+
+	/// Call the handler of a tagged value, replacements can't be tagged again
+	/// so handlers can't make the conversion recurse forever
+	pub(super) fn replace_tagged(
+		&self,
+		handler: &TagHandler,
+		tagged: &TaggedValue,
+	) -> Result<TagReplacement> {
+		let tag = tagged.tag.to_string();
+
+		let error = |source| Error::TagHandler {
+			path: self.path.clone(),
+			tag: tag.clone(),
+			source,
+		};
+
+		match handler.call(tag_name(&tag), &tagged.value) {
+			Ok(TagReplacement::Value(Value::Tagged(_))) => {
+				Err(error("handler returned a tagged value".into()))
+			}
+			Ok(replacement) => Ok(replacement),
+			Err(source) => Err(error(source)),
+		}
+	}
+
+	/// Fail if unknown tags were found with [`UnknownTags::Error`]
+	fn check_unknown_tags(&mut self) -> Result<()> {
+		if self.unknown_tags.is_empty() {
+			return Ok(());
+		}
+
+		Err(Error::UnknownTags(std::mem::take(&mut self.unknown_tags)))
+	}
+
+	/// Write the sequence as a header with the keys of its first mapping followed by the
+	/// values of every mapping with the same keys, if columnar records are enabled
+	fn write_records(&mut self, sequence: &[Value], depth: usize) -> Result<bool> {
+		let Some(columnar) = &self.options.columnar_records else {
+			return Ok(false);
+		};
+
+		let Some(first) = sequence.iter().find_map(Value::as_mapping) else {
+			return Ok(false);
+		};
+
+		let is_record = |value: &Value| {
+			value.as_mapping().is_some_and(|mapping| {
+				mapping.len() == first.len() && first.keys().all(|key| mapping.contains_key(key))
+			})
+		};
+
+		let rows = sequence.iter().filter(|value| is_record(value)).count();
+
+		if first.is_empty() || rows < columnar.min_rows.max(1) {
+			return Ok(false);
+		}
+
+		// Keys are resolved once, at the first mapping, so they're only reported once
+		let index = sequence.iter().position(is_record).unwrap_or_default();
+
+		self.path.push(PathSegment::Index(index));
+		let header = self.resolve_entries(first);
+		self.path.pop();
+
+		let header: Vec<(&Value, Key)> = header?
+			.into_iter()
+			.map(|(source, key, _)| (source, key))
+			.collect();
+
+		let (open, close, comma) = match (self.options.freeze, self.options.compact) {
+			(true, false) => ("table.freeze({ ", " })", ", "),
+			(false, false) => ("{ ", " }", ", "),
+			(true, true) => ("table.freeze({", "})", ","),
+			(false, true) => ("{", "}", ","),
+		};
+
+		self.open_table();
+		self.indent(depth + 1);
+		self.write_key(&Key::from(columnar.fields_key.as_str()));
+		self.assign();
+		self.lua.push_str(open);
+
+		for (i, (_, key)) in header.iter().enumerate() {
+			if i > 0 {
+				self.lua.push_str(comma);
+			}
+
+			match key {
+				Key::String(s) | Key::Identifier(s) => self.write_string(s),
+				Key::Number(n) => {
+					let n = self.format_number_key(n)?;
+					self.lua.push_str(&n);
+				}
+				Key::Bool(b) => self.lua.push_str(&b.to_string()),
+			}
+		}
+
+		self.lua.push_str(close);
+		self.separate();
+		self.end_line();
+
+		for (index, value) in sequence.iter().enumerate() {
+			self.path.push(PathSegment::Index(index));
+
+			match value.as_mapping().filter(|_| is_record(value)) {
+				Some(row) => {
+					self.count(|stats| stats.tables += 1);
+
+					self.indent(depth + 1);
+					self.lua.push_str(open);
+
+					for (i, (source, _)) in header.iter().enumerate() {
+						if i > 0 {
+							self.lua.push_str(comma);
+						}
+
+						if let Some(value) = row.get(*source) {
+							self.path.push(PathSegment::from_key(source));
+							self.write_value(value, depth + 1)?;
+							self.path.pop();
+						}
+					}
+
+					self.lua.push_str(close);
+					self.separate();
+					self.end_line();
+				}
+				None => {
+					self.report(
+						Severity::Info,
+						DiagnosticKind::IrregularRecord,
+						String::from("element doesn't have the keys of the other records"),
+					)?;
+
+					self.walk(None, value, depth + 1)?;
+				}
+			}
+
+			self.path.pop();
+		}
+
+		self.indent(depth);
+		self.close_table();
+
+		Ok(true)
+	}
+
+	/// Write a table nested deeper than the preview allows as an empty table with a comment
+	fn write_elided_table(&mut self, value: &Value, depth: usize) -> Result<bool> {
+		let Some(preview) = self.options.preview else {
+			return Ok(false);
+		};
+
+		let len = match value {
+			Value::Sequence(sequence) => sequence.len(),
+			Value::Mapping(mapping) => mapping.len(),
+			_ => return Ok(false),
+		};
+
+		if depth < preview.max_render_depth || len == 0 {
+			return Ok(false);
+		}
+
+		self.lua
+			.push_str(&format!("{{ --[[ … {} ]] }}", describe_entries(len, "")));
+		self.elided(len)?;
+
+		Ok(true)
+	}
+
+	/// Number of entries of a table written in the preview
+	fn entry_limit(&self) -> usize {
+		self.options
+			.preview
+			.map_or(usize::MAX, |preview| preview.max_entries_per_table)
+	}
+
+	/// Write a comment in place of entries over the preview limit
+	fn write_elided_entries(&mut self, elided: usize, depth: usize) -> Result<()> {
+		if elided == 0 {
+			return Ok(());
+		}
+
+		self.indent(depth + 1);
+		self.write_comment(&format!("… {}", describe_entries(elided, "more")));
+		self.end_line();
+		self.elided(elided)
+	}
+
+	fn elided(&mut self, count: usize) -> Result<()> {
+		self.count(|stats| stats.elided_entries += count);
+		self.report(
+			Severity::Info,
+			DiagnosticKind::Elided,
+			format!("{} left out of the preview", describe_entries(count, "")),
+		)
+	}
+
+	fn write_string(&mut self, string: &str) {
+		let quote = self.options.quote_style;
+
+		self.lua.push(quote.quote());
+		write_lua_string(&mut self.lua, string, quote);
+		self.lua.push(quote.quote());
+	}
+
+	/// Get the identifier a key is written as, if it's written bare
+	fn bare_key<'k>(&self, key: &'k Key) -> Option<&'k str> {
+		match key {
+			Key::Identifier(ident) => Some(ident.as_ref()),
+			Key::String(s) if self.options.bare_keys => Some(s.as_ref()),
+			_ => None,
+		}
+		.filter(|ident| is_lua_identifier(ident))
+	}
+
+	fn format_key(&self, key: &Key) -> String {
+		let mut output = String::new();
+
+		match self.bare_key(key) {
+			Some(ident) => output.push_str(ident),
+			None => write_lua_key(&mut output, key, self.options.quote_style),
+		}
+
+		output
+	}
+
+	fn write_key(&mut self, key: &Key) {
+		match self.bare_key(key) {
+			Some(ident) => self.lua.push_str(ident),
+			None => write_lua_key(&mut self.lua, key, self.options.quote_style),
+		}
+	}
+
+	fn open_table(&mut self) {
+		if self.options.freeze {
+			self.lua.push_str("table.freeze(");
+		}
+
+		self.lua.push('{');
+		self.end_line();
+		self.last_separator = None;
+	}
+
+	/// Write the comma after a table entry, the last one is removed when the table
+	/// is closed if trailing commas are disabled
+	fn separate(&mut self) {
+		self.last_separator = Some(self.lua.len());
+		self.lua.push(',');
+	}
+
+	/// Write the ` = ` of an assignment or keyed entry, without spaces in compact output
+	pub(super) fn assign(&mut self) {
+		self.lua
+			.push_str(if self.options.compact { "=" } else { " = " });
+	}
+
+	/// End the line of an entry, nothing ends it in compact output
+	pub(super) fn end_line(&mut self) {
+		if !self.options.compact {
+			self.lua.push('\n');
+		}
+	}
+
+	/// End a statement, which is followed by a space instead of a newline in compact output
+	pub(super) fn end_statement(&mut self) {
+		self.lua.push(if self.options.compact { ' ' } else { '\n' });
+	}
+
+	/// Write a comment, written as a block comment in compact output
+	/// so it doesn't comment out what follows
+	fn write_comment(&mut self, comment: &str) {
+		if !self.options.compact {
+			self.lua.push_str("-- ");
+			self.lua.push_str(comment);
+			return;
+		}
+
+		let level = (0..)
+			.map(|level| "=".repeat(level))
+			.find(|equals| !comment.contains(&format!("]{}]", equals)))
+			.unwrap_or_default();
+
+		self.lua
+			.push_str(&format!("--[{0}[ {1} ]{0}]", level, comment));
+	}
+
+	/// Write a comment following an entry on its line
+	fn write_entry_comment(&mut self, comment: &str) {
+		if !self.options.compact {
+			self.lua.push(' ');
+		}
+
+		self.write_comment(comment);
+	}
+
+	fn close_table(&mut self) {
+		if let Some(separator) = self.last_separator.take() {
+			// Compact output leaves out every separator it can
+			if self.options.omit_trailing_commas || self.options.compact {
+				self.lua.remove(separator);
+			}
+		}
+
+		self.lua.push('}');
+
+		if self.options.freeze {
+			self.lua.push(')');
+		}
+	}
+
+	/// Indent all lines by the base depth, written last so nothing else has to account for it
+	fn indent_output(&mut self) {
+		// Output written into the sink is indented as it's written
+		if self.options.base_depth == 0 || self.options.compact || self.sink.is_some() {
+			return;
+		}
+
+		let mut indent = String::new();
+		self.options
+			.indent
+			.write(&mut indent, self.options.base_depth);
+
+		let mut output = String::with_capacity(self.lua.len());
+		let mut long_strings = long_strings::LongStrings::default();
+
+		for line in self.lua.split_inclusive('\n') {
+			let in_string = self.options.long_strings && long_strings.line(line);
+
+			if line != "\n" && !in_string {
+				output.push_str(&indent);
+			}
+
+			output.push_str(line);
+		}
+
+		self.lua = output;
+	}
+
+	/// Write the buffered output into the sink if there's enough of it, keeping
+	/// the comma of the last entry and the last character which can still be removed
+	fn flush_sink(&mut self) -> Result<()> {
+		if self.sink.is_none()
+			|| self.lua.len() < FLUSH_SIZE
+			|| self.errors.is_some()
+			|| self.entry_cache.is_some()
+		{
+			return Ok(());
+		}
+
+		let mut end = self
+			.last_separator
+			.unwrap_or(self.lua.len())
+			.min(self.lua.len() - 1);
+
+		while !self.lua.is_char_boundary(end) {
+			end -= 1;
+		}
+
+		// Long strings are found line by line, so only whole lines are indented
+		if self.options.long_strings && self.options.base_depth > 0 {
+			end = self.lua[..end].rfind('\n').map_or(0, |i| i + 1);
+
+			if end == 0 {
+				return Ok(());
+			}
+		}
+
+		self.write_sink(end)?;
+
+		if let Some(separator) = &mut self.last_separator {
+			*separator -= end;
+		}
+
+		Ok(())
+	}
+
+	/// Write the buffered output up to the end into the sink, indented by the base depth
+	fn write_sink(&mut self, end: usize) -> Result<()> {
+		let Some(sink) = &mut self.sink else {
+			return Ok(());
+		};
+
+		let text = &self.lua[..end];
+
+		if self.options.base_depth == 0 || self.options.compact {
+			sink.write_all(text.as_bytes())?;
+		} else {
+			let mut indent = String::new();
+			self.options
+				.indent
+				.write(&mut indent, self.options.base_depth);
+
+			for line in text.split_inclusive('\n') {
+				let in_string = self.options.long_strings && self.sink_long_strings.line(line);
+
+				if self.sink_line_start && line != "\n" && !in_string {
+					sink.write_all(indent.as_bytes())?;
+				}
+
+				sink.write_all(line.as_bytes())?;
+				self.sink_line_start = line.ends_with('\n');
+			}
+		}
+
+		self.flushed += end;
+		self.lua.drain(..end);
+
+		Ok(())
+	}
+
+	fn indent(&mut self, depth: usize) {
+		if !self.options.compact {
+			self.options.indent.write(&mut self.lua, depth);
+		}
+	}
+
+	/// Write generated code indented with tabs, indenting it as set in the options.
+	/// Compact output has the lines joined with spaces and blank lines removed
+	pub(super) fn write_code(&mut self, code: &str) {
+		if self.options.compact {
+			let lines: Vec<&str> = code
+				.lines()
+				.map(str::trim)
+				.filter(|line| !line.is_empty())
+				.collect();
+
+			self.lua.push_str(&lines.join(" "));
+
+			if code.ends_with('\n') {
+				self.lua.push(' ');
+			}
+
+			return;
+		}
+
+		if self.options.indent == Indent::Tabs {
+			self.lua.push_str(code);
+			return;
+		}
+
+		for line in code.split_inclusive('\n') {
+			let code = line.trim_start_matches('\t');
+
+			self.indent(line.len() - code.len());
+			self.lua.push_str(code);
+		}
+	}
+
+	/// Coerce the value according to the schema, if there's a rule for the current path
+	fn coerce(&mut self, value: &Value) -> Result<Option<Value>> {
+		let Some(ty) = self.options.schema.as_ref().and_then(|s| s.get(&self.path)) else {
+			return Ok(None);
+		};
+
+		match ty.coerce(value) {
+			Ok(coerced) => Ok(coerced),
+			Err(()) => {
+				let message = match value {
+					Value::String(s) => format!("can't coerce \"{}\" into {}", s, ty.name()),
+					_ => format!("can't coerce {} into {}", type_name(value), ty.name()),
+				};
+
+				self.report(Severity::Warning, DiagnosticKind::Coercion, message)?;
+
+				Ok(None)
+			}
+		}
+	}
+
+	fn walk_mapping<'v>(
+		&mut self,
+		entries: impl IntoIterator<Item = (&'v Value, &'v Value)>,
+		depth: usize,
+	) -> Result<()> {
+		let table = self.mapping_entries(entries)?;
+		self.write_table(table, depth)
+	}
+
+	/// Resolve entries of a mapping into table entries, adding the synthetic entries enabled in the options
+	fn mapping_entries<'v>(
+		&mut self,
+		entries: impl IntoIterator<Item = (&'v Value, &'v Value)>,
+	) -> Result<Vec<Entry<'v>>> {
+		let mut original_keys = Vec::new();
+		let mut table = Vec::new();
+
+		for (source, key, value) in self.resolve_entries(self.written_entries(entries))? {
+			if let (Key::Identifier(ident), Value::String(original)) = (&key, source) {
+				if ident != original {
+					original_keys.push((ident.to_string(), original.as_str()));
+				}
+			}
+
+			table.push(Entry::Keyed { source, key, value });
+		}
+
+		if self.options.original_keys_table && !original_keys.is_empty() {
+			table.push(Entry::Synthetic {
+				key: ORIGINAL_KEYS,
+				value: Synthetic::OriginalKeys(original_keys),
+			});
+		}
+
+		Ok(table)
+	}
+
+	/// Write a table constructor with its entries ordered according to the [`TableLayout`]
+	///
+	/// Positional entries take array indices starting at 1, which keyed entries can't
+	/// use without shadowing them. Synthetic entries never count towards the preview limit
+	fn write_table(&mut self, mut entries: Vec<Entry>, depth: usize) -> Result<()> {
+		if self.options.table_layout == TableLayout::PositionalFirst {
+			// Lengths come before the elements they count, like `{ n = 3, "a", nil, "c" }`
+			entries.sort_by_key(|entry| match entry {
+				Entry::Synthetic {
+					value: Synthetic::Length(_),
+					..
+				} => 0,
+				entry if entry.is_positional() => 1,
+				_ => 2,
+			});
+		}
+
+		let positional = entries.iter().filter(|entry| entry.is_positional()).count();
+		let synthetic: Vec<&str> = entries
+			.iter()
+			.filter_map(|entry| match entry {
+				Entry::Synthetic { key, .. } => Some(*key),
+				_ => None,
+			})
+			.collect();
+
+		for entry in &entries {
+			let Entry::Keyed { source, key, .. } = entry else {
+				continue;
+			};
+
+			let shadowed = match key {
+				Key::Number(n) => n
+					.as_f64()
+					.filter(|i| i.fract() == 0.0 && *i >= 1.0 && *i <= positional as f64)
+					.map(|i| format!("element {}", i)),
+				_ => None,
+			};
+
+			let shadowed = shadowed.or_else(|| {
+				let (Key::String(text) | Key::Identifier(text)) = key else {
+					return None;
+				};
+
+				synthetic
+					.iter()
+					.find(|synthetic| **synthetic == text.as_ref())
+					.map(|synthetic| format!("added key \"{}\"", synthetic))
+			});
+
+			if let Some(other) = shadowed {
+				return Err(Error::KeyCollision {
+					path: self.path.clone(),
+					key: describe_key(source),
+					other,
+					resolved: format_lua_key(key),
+				});
+			}
+		}
+
+		let limit = self.entry_limit();
+		let elided = entries
+			.iter()
+			.filter(|entry| !entry.is_synthetic())
+			.count()
+			.saturating_sub(limit);
+		let mut written = 0;
+
+		let per_line = self.scalars_per_line(&entries);
+		let mut column = 0;
+
+		self.open_table();
+
+		for entry in entries {
+			// Grouped lines end before entries written on their own lines
+			if column > 0 && (!entry.is_positional() || written == limit) {
+				self.lua.push('\n');
+				column = 0;
+			}
+
+			if !entry.is_synthetic() {
+				if written == limit {
+					self.write_elided_entries(elided, depth)?;
+				}
+
+				written += 1;
+
+				if written > limit {
+					continue;
+				}
+			}
+
+			match entry {
+				Entry::Positional { index, value } => {
+					self.path.push(PathSegment::Index(index));
+
+					match per_line {
+						Some(per_line) => {
+							if column == 0 {
+								self.indent(depth + 1);
+							} else {
+								self.lua.push(' ');
+							}
+
+							self.write_value(value, depth + 1)?;
+							self.separate();
+
+							column = (column + 1) % per_line;
+
+							if column == 0 {
+								self.lua.push('\n');
+							}
+						}
+						None => self.walk(None, value, depth + 1)?,
+					}
+
+					self.path.pop();
+				}
+				Entry::Keyed { source, key, value } => {
+					let cached = self.entry_cache.is_some()
+						&& depth == 0 && self.path.is_root()
+						&& !self.in_shared_table;
+
+					self.path.push(PathSegment::from_key(source));
+
+					if cached {
+						self.write_cached_entry(digest::digest_entry(source, value), |emitter| {
+							emitter.walk(Some(&key), value, depth + 1)
+						})?;
+					} else {
+						self.walk(Some(&key), value, depth + 1)?;
+					}
+
+					self.path.pop();
+				}
+				Entry::Synthetic { key, value } => {
+					self.indent(depth + 1);
+
+					match value {
+						Synthetic::Length(len) => {
+							self.write_key(&Key::from(key));
+							self.assign();
+							self.lua.push_str(&len.to_string());
+						}
+						Synthetic::Tag(name) => {
+							self.write_key(&Key::from(key));
+							self.assign();
+							self.write_string(name);
+						}
+						Synthetic::OriginalKeys(keys) => {
+							self.lua.push_str(key);
+							self.assign();
+							self.open_table();
+
+							for (ident, original) in keys {
+								self.indent(depth + 2);
+								self.lua.push_str(&ident);
+								self.assign();
+								self.write_string(original);
+								self.separate();
+								self.end_line();
+							}
+
+							self.indent(depth + 1);
+							self.close_table();
+						}
+					}
+
+					self.separate();
+					self.end_line();
+				}
+			}
+
+			self.flush_sink()?;
+		}
+
+		if column > 0 {
+			self.lua.push('\n');
+		}
+
+		self.indent(depth);
+		self.close_table();
+
+		Ok(())
+	}
+
+	/// Write the entry from the cache, or write it and add it to the cache
+	fn write_cached_entry(
+		&mut self,
+		digest: [u8; 32],
+		write: impl FnOnce(&mut Self) -> Result<()>,
+	) -> Result<()> {
+		if let Some((previous, current)) = &mut self.entry_cache {
+			if let Some((text, separator)) = previous.remove(&digest) {
+				let start = self.lua.len();
+
+				self.lua.push_str(&text);
+				self.last_separator = separator.map(|separator| start + separator);
+				current.insert(digest, (text, separator));
+
+				return Ok(());
+			}
+		}
+
+		let start = self.lua.len();
+		write(self)?;
+
+		let text = self.lua[start..].to_owned();
+		let separator = self
+			.last_separator
+			.and_then(|separator| separator.checked_sub(start));
+
+		if let Some((_, current)) = &mut self.entry_cache {
+			current.insert(digest, (text, separator));
+		}
+
+		Ok(())
+	}
+
+	/// Write the scalar as a wrapper table if it has no exact Lua value and wrapping
+	/// is enabled, returning whether it was written
+	fn write_wrapped(&mut self, value: &Value, depth: usize) -> Result<bool> {
+		let FidelityMode::Wrapped(fields) = &self.options.fidelity else {
+			return Ok(false);
+		};
+
+		let Some((ty, text)) = self.wrapped_scalar(value) else {
+			return Ok(false);
+		};
+
+		self.report(
+			Severity::Info,
+			DiagnosticKind::WrappedScalar,
+			format!("{} value was written as a wrapper table", ty),
+		)?;
+
+		self.open_table();
+
+		for (field, value) in [(&fields.type_field, ty), (&fields.value_field, &text)] {
+			self.indent(depth + 1);
+			self.write_key(&Key::Identifier(Cow::Borrowed(field)));
+			self.assign();
+			self.write_string(value);
+			self.separate();
+			self.end_line();
+		}
+
+		self.indent(depth);
+		self.close_table();
+
+		Ok(true)
+	}
+
+	/// Get the prefix of constant references at the current path, if its strings reference constants
+	pub(super) fn constant_prefix(&self) -> Option<&'a str> {
+		self.options
+			.constant_references
+			.iter()
+			.find(|(pattern, _)| pattern.matches(&self.path))
+			.map(|(_, prefix)| prefix.as_str())
+	}
+
+	/// Write the string as a reference to a constant if its path is matched by
+	/// [`ParseOptions::constant_references`], returning whether it was written
+	fn write_constant(&mut self, value: &Value) -> Result<bool> {
+		let (Value::String(s), Some(prefix)) = (value, self.constant_prefix()) else {
+			return Ok(false);
+		};
+
+		if !is_lua_identifier(s) {
+			return Err(Error::Transform {
+				path: self.path.clone(),
+				message: format!("\"{}\" isn't a valid identifier to reference a constant", s),
+			});
+		}
+
+		self.lua.push_str(prefix);
+		self.lua.push_str(s);
+
+		Ok(true)
+	}
+
+	/// Get the type and text of the wrapper table the scalar at the current path is written as
+	fn wrapped_scalar(&self, value: &Value) -> Option<(&'static str, String)> {
+		let FidelityMode::Wrapped(_) = &self.options.fidelity else {
+			return None;
+		};
+
+		match value {
+			Value::Number(n) if n.as_i64().is_none() && n.is_u64() => Some(("u64", n.to_string())),
+			Value::Number(n) if !self.options.dialect.has_integers() && !n.is_f64() => {
+				match n.as_i64() {
+					Some(int) if int.unsigned_abs() > MAX_SAFE_INTEGER => {
+						Some(("i64", n.to_string()))
+					}
+					_ => None,
+				}
+			}
+			Value::String(s) => match self.core_tags.get(&self.path) {
+				Some(&"binary") if self.options.binary == Binary::Base64 => {
+					Some(("binary", s.split_whitespace().collect()))
+				}
+				Some(&"binary") => None,
+				Some(&"timestamp") if self.options.timestamps != Timestamps::String => None,
+				Some(&ty) => Some((ty, s.clone())),
+				// Plain scalars written like timestamps are timestamps without a tag too
+				None if self.options.timestamps == Timestamps::String
+					&& self.timestamps.contains(&self.path) =>
+				{
+					Some(("timestamp", s.clone()))
+				}
+				None => None,
+			},
+			_ => None,
+		}
+	}
+
+	/// Get how many elements of the table are written per line, only tables whose positional
+	/// entries are all scalars written without comments or wrapper tables are grouped
+	fn scalars_per_line(&mut self, entries: &[Entry]) -> Option<usize> {
+		let per_line = self
+			.options
+			.scalars_per_line
+			.filter(|&n| n > 1 && !self.options.compact)?;
+
+		// Comments and error placeholders have to end their lines
+		if self.options.comments != Comments::None || self.errors.is_some() {
+			return None;
+		}
+
+		let inline = entries.iter().all(|entry| {
+			let Entry::Positional { index, value } = entry else {
+				return true;
+			};
+
+			self.path.push(PathSegment::Index(*index));
+
+			let inline = matches!(
+				value,
+				Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_)
+			) && self.wrapped_scalar(value).is_none()
+				&& !value.as_str().is_some_and(|s| self.is_long_string(s))
+				&& !self.source_lines.contains_key(&self.path);
+
+			self.path.pop();
+			inline
+		});
+
+		inline.then_some(per_line)
+	}
+
+	/// Format a number key, integers are reported like values and floats use
+	/// [`format_number_key`] after being rounded to the float precision
+	fn format_number_key(&mut self, number: &Number) -> Result<String> {
+		if !number.is_f64() {
+			return self.format_number(number);
+		}
+
+		let rounded = match (self.options.float_precision, number.as_f64()) {
+			(Some(precision), Some(float)) => round_float(float, precision)
+				.and_then(|rounded| rounded.parse::<f64>().ok())
+				.map(Number::from),
+			_ => None,
+		};
+
+		// Non-finite keys are skipped when the entries are resolved
+		let number = rounded.as_ref().unwrap_or(number);
+		Ok(format_number_key(number).unwrap_or_else(|| number.to_string()))
+	}
+
+	fn format_number(&mut self, number: &Number) -> Result<String> {
+		if let Some(float) = number.as_f64().filter(|float| !float.is_finite()) {
+			return self.format_non_finite(number, float);
+		}
+
+		let message = if number.as_i64().is_none() && number.is_u64() {
+			Some(format!(
+				"integer {} doesn't fit into a signed 64-bit integer",
+				number
+			))
+		} else if !self.options.dialect.has_integers() && !number.is_f64() {
+			let exact = number
+				.as_i64()
+				.is_some_and(|int| int.unsigned_abs() <= MAX_SAFE_INTEGER);
+
+			(!exact).then(|| {
+				format!(
+					"integer {} can't be represented exactly by a {} number",
+					number,
+					self.options.dialect.name()
+				)
+			})
+		} else {
+			None
+		};
+
+		if let Some(message) = message {
+			match self.options.large_integers {
+				LargeIntegers::Native => {
+					self.report(Severity::Warning, DiagnosticKind::LargeInteger, message)?
+				}
+				LargeIntegers::String => {
+					let quote = self.options.quote_style.quote();
+					return Ok(format!("{}{}{}", quote, number, quote));
+				}
+				LargeIntegers::Float => {
+					return Ok(Number::from(number.as_f64().unwrap_or_default()).to_string())
+				}
+				LargeIntegers::Error => {
+					return Err(Error::Number {
+						path: self.path.clone(),
+						message,
+					})
+				}
+			}
+		}
+
+		if let (Some(precision), Some(float)) = (self.options.float_precision, number.as_f64()) {
+			if number.is_f64() && float.is_finite() {
+				if let Some(rounded) = round_float(float, precision) {
+					return Ok(rounded);
+				}
+			}
+		}
+
+		Ok(number.to_string())
+	}
+
+	/// Format an infinite or NaN float as an expression, since Lua has no literals for them
+	fn format_non_finite(&self, number: &Number, float: f64) -> Result<String> {
+		if self.options.non_finite_floats == NonFiniteFloats::Error {
+			return Err(Error::Number {
+				path: self.path.clone(),
+				message: format!("{} isn't a finite number", number),
+			});
+		}
+
+		Ok(String::from(match float {
+			float if float.is_nan() => "0/0",
+			float if float > 0.0 => "math.huge",
+			_ => "-math.huge",
+		}))
+	}
+}
+
+/// Get entries of a non-empty sequence whose elements are all single-key mappings
+fn single_key_entries(sequence: &[Value]) -> Option<Vec<(&Value, &Value)>> {
+	if sequence.is_empty() {
+		return None;
+	}
+
+	sequence
+		.iter()
+		.map(|value| match value.as_mapping() {
+			Some(mapping) if mapping.len() == 1 => mapping.iter().next(),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Order of keys sorted by [`ParseOptions::sort_keys`]
+fn compare_keys(a: &Key, b: &Key) -> Ordering {
+	let rank = |key: &Key| match key {
+		Key::Number(_) => 0,
+		Key::String(_) | Key::Identifier(_) => 1,
+		Key::Bool(_) => 2,
+	};
+
+	match (a, b) {
+		(Key::Number(a), Key::Number(b)) => match (a.as_i64(), b.as_i64()) {
+			(Some(a), Some(b)) => a.cmp(&b),
+			_ => {
+				let float = |n: &Number| n.as_f64().unwrap_or_default();
+				float(a).total_cmp(&float(b))
+			}
+		},
+		(Key::String(a) | Key::Identifier(a), Key::String(b) | Key::Identifier(b)) => a.cmp(b),
+		(Key::Bool(a), Key::Bool(b)) => a.cmp(b),
+		_ => rank(a).cmp(&rank(b)),
+	}
+}
+
+fn key_kind(key: &Key) -> &'static str {
+	match key {
+		Key::String(_) | Key::Identifier(_) => "string",
+		Key::Number(_) => "number",
+		Key::Bool(_) => "boolean",
+	}
+}
+
+/// Format a number key the way Lua's `tostring` does once it's stored in a table
+fn lua_tostring(number: &Number, dialect: Dialect) -> String {
+	if let Some(int) = number.as_i64() {
+		if dialect.has_integers() || int.unsigned_abs() < 10u64.pow(14) {
+			return int.to_string();
+		}
+	}
+
+	let float = number.as_f64().unwrap_or_default();
+
+	// Lua 5.4 converts integral float keys into integers
+	if dialect.has_integers() && float.fract() == 0.0 && float.abs() < i64::MAX as f64 {
+		return (float as i64).to_string();
+	}
+
+	if float.is_nan() {
+		return String::from("nan");
+	}
+
+	if float.is_infinite() {
+		return String::from(if float > 0.0 { "inf" } else { "-inf" });
+	}
+
+	format_g14(float)
+}
+
+/// Format a float like C's `%.14g`, used by Lua to convert numbers into strings
+fn format_g14(float: f64) -> String {
+	if float == 0.0 {
+		return String::from("0");
+	}
+
+	let scientific = format!("{:.13e}", float);
+	let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+	let exponent: i32 = exponent.parse().unwrap_or_default();
+
+	let trim = |digits: &str| -> String {
+		match digits.contains('.') {
+			true => digits
+				.trim_end_matches('0')
+				.trim_end_matches('.')
+				.to_owned(),
+			false => digits.to_owned(),
+		}
+	};
+
+	if !(-4..14).contains(&exponent) {
+		let sign = if exponent < 0 { '-' } else { '+' };
+		return format!("{}e{}{:02}", trim(mantissa), sign, exponent.abs());
+	}
+
+	trim(&format!("{:.*}", (13 - exponent) as usize, float))
+}
+
+/// Describe a number of entries like `3 entries`, with an optional word before the noun
+fn describe_entries(count: usize, word: &str) -> String {
+	let noun = if count == 1 { "entry" } else { "entries" };
+
+	match word {
+		"" => format!("{} {}", count, noun),
+		word => format!("{} {} {}", count, word, noun),
+	}
+}
+
+/// Describe a YAML key in messages, quoting strings so they aren't confused with numbers
+fn describe_key(key: &Value) -> String {
+	match key {
+		Value::String(s) => format!("\"{}\"", s),
+		Value::Number(n) => n.to_string(),
+		Value::Bool(b) => b.to_string(),
+		_ => String::from("?"),
+	}
+}
+
+/// Get the short name of a tag used as the key of its wrapper table, `!Tag` becomes `Tag`
+/// and resolved tags like `!<tag:example.com,2024:vector>` become `vector`
+pub(crate) fn tag_name(tag: &str) -> &str {
+	let tag = tag.strip_prefix('!').unwrap_or(tag);
+
+	match tag.strip_prefix('<').and_then(|tag| tag.strip_suffix('>')) {
+		Some(uri) => uri
+			.rsplit([':', '/', '#', '!'])
+			.find(|name| !name.is_empty())
+			.unwrap_or(uri),
+		None => tag,
+	}
+}
+
+pub(crate) fn type_name(value: &Value) -> &'static str {
+	match value {
+		Value::Null => "null",
+		Value::Bool(_) => "bool",
+		Value::Number(_) => "number",
+		Value::String(_) => "string",
+		Value::Sequence(_) => "sequence",
+		Value::Mapping(_) => "mapping",
+		Value::Tagged(_) => "tagged value",
+	}
+}
+
+/// Round float to the given number of decimal places using round-half-even
+/// on its shortest decimal representation, returns `None` if no rounding is needed
+fn round_float(float: f64, places: u8) -> Option<String> {
+	let repr = format!("{}", float.abs());
+	let (int, fract) = repr.split_once('.')?;
+	let places = places as usize;
+
+	if fract.len() <= places {
+		return None;
+	}
+
+	let mut digits: Vec<u8> = int.bytes().chain(fract.bytes().take(places)).collect();
+
+	let next = fract.as_bytes()[places];
+	let rest = &fract[places + 1..];
+	let odd = digits.last().is_some_and(|d| (d - b'0') % 2 == 1);
+
+	if next > b'5' || (next == b'5' && (rest.bytes().any(|d| d != b'0') || odd)) {
+		let mut i = digits.len();
+
+		loop {
+			if i == 0 {
+				digits.insert(0, b'1');
+				break;
+			}
+
+			i -= 1;
+
+			if digits[i] == b'9' {
+				digits[i] = b'0';
+			} else {
+				digits[i] += 1;
+				break;
+			}
+		}
+	}
+
+	let (int, fract) = digits.split_at(digits.len() - places);
+	let fract = std::str::from_utf8(fract)
+		.unwrap_or_default()
+		.trim_end_matches('0');
+	let int = std::str::from_utf8(int).unwrap_or_default();
+
+	let sign = if float.is_sign_negative() { "-" } else { "" };
+	let fract = if fract.is_empty() { "0" } else { fract };
+
+	Some(format!("{}{}.{}", sign, int, fract))
+}
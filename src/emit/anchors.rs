@@ -0,0 +1,148 @@
+use super::Emitter;
+use crate::{hoist::MAX_LOCALS, Error, KeyPath, ParseOptions, PathSegment, Result};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+
+impl Emitter<'_> {
+	/// Declare locals for anchored tables that aliases refer to and remember the
+	/// paths of the anchors and aliases so the tables are referenced where they occur
+	pub(super) fn write_anchored_tables(&mut self, root: &Mapping) -> Result<()> {
+		self.require_statements("anchor preservation")?;
+
+		if self.options.dedupe_subtrees.is_some() {
+			return Err(Error::Options(String::from(
+				"anchors can't be preserved with subtree deduplication",
+			)));
+		}
+
+		// Occurrences are written separately to compare them, without reporting progress twice
+		let quiet = ParseOptions {
+			progress: None,
+			..self.options.clone()
+		};
+
+		let anchors = std::mem::take(&mut self.anchors);
+		let available = MAX_LOCALS.saturating_sub(self.hoisted_strings.len());
+
+		let path = std::mem::take(&mut self.path);
+		let mut names = HashSet::new();
+		self.in_shared_table = true;
+
+		for anchor in &anchors {
+			if names.len() == available {
+				break;
+			}
+
+			// Merged aliases are gone, and schema rules depend on the path,
+			// so only tables still written the same way are shared
+			let mut shared: Vec<(KeyPath, &Value)> = Vec::new();
+			let mut written = None;
+
+			for path in std::iter::once(&anchor.path).chain(&anchor.aliases) {
+				let Some((path, value)) = self.find_occurrence(root, path) else {
+					continue;
+				};
+
+				let mut emitter = Emitter::new(&quiet).at(path.clone());
+				emitter.plain_keys = self.plain_keys.clone();
+				emitter.write_value(value, 0)?;
+
+				match &written {
+					None => written = Some(emitter.lua),
+					Some(written) if *written != emitter.lua => continue,
+					Some(_) => {}
+				}
+
+				shared.push((path, value));
+			}
+
+			if shared.len() < 2 {
+				continue;
+			}
+
+			let name = local_name(&anchor.name, &names);
+
+			self.path = shared[0].0.clone();
+			self.lua.push_str("local ");
+			self.lua.push_str(&name);
+			self.assign();
+			self.write_value(shared[0].1, 0)?;
+			self.end_statement();
+
+			for (path, _) in shared {
+				self.shared_tables.insert(path, name.clone());
+			}
+
+			names.insert(name);
+		}
+
+		self.path = path;
+		self.in_shared_table = false;
+
+		if !names.is_empty() {
+			self.end_line();
+		}
+
+		Ok(())
+	}
+
+	/// Find the table at the path of an anchor or alias, paths of roots
+	/// put under the root key are found under the key
+	fn find_occurrence<'v>(
+		&self,
+		root: &'v Mapping,
+		path: &KeyPath,
+	) -> Option<(KeyPath, &'v Value)> {
+		let value = find(root, path.segments()).map(|value| (path.clone(), value));
+
+		let value = value.or_else(|| {
+			let mut segments = vec![PathSegment::Key(self.options.root_key.clone()?)];
+			segments.extend_from_slice(path.segments());
+
+			find(root, &segments).map(|value| (KeyPath::from(segments), value))
+		});
+
+		value.filter(|(_, value)| matches!(value, Value::Sequence(_) | Value::Mapping(_)))
+	}
+}
+
+fn find<'v>(root: &'v Mapping, segments: &[PathSegment]) -> Option<&'v Value> {
+	let (first, rest) = segments.split_first()?;
+	let entry = |mapping: &'v Mapping, segment: &PathSegment| {
+		mapping
+			.iter()
+			.find(|(key, _)| PathSegment::from_key(key) == *segment)
+			.map(|(_, value)| value)
+	};
+
+	let mut value = entry(root, first)?;
+
+	for segment in rest {
+		value = match (segment, value) {
+			(PathSegment::Index(i), Value::Sequence(sequence)) => sequence.get(*i)?,
+			(segment, Value::Mapping(mapping)) => entry(mapping, segment)?,
+			_ => return None,
+		};
+	}
+
+	Some(value)
+}
+
+/// Name the local of an anchor like `__anchor_name`, numbered
+/// if another anchor with the same name was declared
+fn local_name(anchor: &str, names: &HashSet<String>) -> String {
+	let sanitized: String = anchor
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect();
+
+	let name = format!("__anchor_{}", sanitized);
+
+	(1..)
+		.map(|i| match i {
+			1 => name.clone(),
+			i => format!("{}{}", name, i),
+		})
+		.find(|name| !names.contains(name))
+		.unwrap_or(name)
+}
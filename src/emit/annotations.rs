@@ -0,0 +1,123 @@
+use super::shape::{Shape, Structure};
+use crate::{Error, KeyStyle, Result};
+use std::collections::HashSet;
+
+/// Generate LuaLS `---@class` blocks describing the shape followed by
+/// a `---@type` line annotating the statement that comes after it
+///
+/// Nested mappings become classes named after their path like `Config.weapons`,
+/// sequences of mappings use the name of the sequence for their elements
+pub(super) fn generate(shape: &Shape, class: &str) -> Result<String> {
+	let valid = class.split('.').all(|segment| {
+		segment.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+			&& segment
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || c == '_')
+	});
+
+	if !valid {
+		return Err(Error::Options(format!(
+			"\"{}\" can't be used as an annotation class name",
+			class
+		)));
+	}
+
+	let mut generator = Generator {
+		classes: Vec::new(),
+		names: HashSet::new(),
+	};
+
+	let root = generator.type_of(shape, class);
+
+	let mut lua = String::new();
+
+	// Blank lines keep the blocks from being attached to the statement
+	for class in generator.classes {
+		lua.push_str(&class);
+		lua.push('\n');
+	}
+
+	lua.push_str("---@type ");
+	lua.push_str(&root);
+	lua.push('\n');
+
+	Ok(lua)
+}
+
+struct Generator {
+	/// Generated class blocks, parents before their children
+	classes: Vec<String>,
+	names: HashSet<String>,
+}
+
+impl Generator {
+	/// Get the annotation type of the shape, generating classes for its mappings
+	fn type_of(&mut self, shape: &Shape, name: &str) -> String {
+		let mut types = Vec::new();
+
+		for ty in &shape.types {
+			match (*ty, &shape.structure) {
+				("table", Structure::Fields(fields)) => {
+					let class = self.class_name(name);
+					let position = self.classes.len();
+					let mut block = format!("---@class {}\n", class);
+
+					self.classes.push(String::new());
+
+					for field in fields {
+						let child = format!(
+							"{}.{}",
+							class,
+							KeyStyle::SanitizedIdentifiers.apply(&field.name)
+						);
+						let mut ty = self.type_of(&field.shape, &child);
+
+						if !field.required && !ty.ends_with("|nil") && ty != "any" {
+							ty.push_str("|nil");
+						}
+
+						let key = field.ident.as_deref().unwrap_or(&field.index);
+						block.push_str(&format!("---@field {} {}\n", key, ty));
+					}
+
+					self.classes[position] = block;
+					types.push(class);
+				}
+				("table", Structure::Elements(elements)) => {
+					let element = self.type_of(elements, name);
+
+					if element.contains('|') {
+						types.push(format!("({})[]", element));
+					} else {
+						types.push(format!("{}[]", element));
+					}
+				}
+				(ty, _) => types.push(String::from(ty)),
+			}
+		}
+
+		if types.is_empty() {
+			return String::from("any");
+		}
+
+		if shape.nullable {
+			types.push(String::from("nil"));
+		}
+
+		types.join("|")
+	}
+
+	/// Claim a class name, adding a number to names that are already taken
+	fn class_name(&mut self, name: &str) -> String {
+		let mut class = String::from(name);
+		let mut n = 1;
+
+		while self.names.contains(&class) {
+			n += 1;
+			class = format!("{}{}", name, n);
+		}
+
+		self.names.insert(class.clone());
+		class
+	}
+}
@@ -0,0 +1,124 @@
+use super::Emitter;
+use crate::{fmt::write_lua_bytes, Binary, Dialect, Error, Result};
+use serde_yaml::Value;
+
+impl Emitter<'_> {
+	pub(super) fn check_binary(&self) -> Result<()> {
+		if self.options.binary == Binary::Buffer && self.options.dialect != Dialect::Luau {
+			return Err(Error::Options(String::from(
+				"binary buffers require the Luau dialect",
+			)));
+		}
+
+		Ok(())
+	}
+
+	/// Write the string as its decoded bytes if it's tagged with `!!binary` and
+	/// [`ParseOptions::binary`](crate::ParseOptions::binary) decodes binary strings,
+	/// returning whether it was written
+	pub(super) fn write_binary(&mut self, value: &Value) -> Result<bool> {
+		let Value::String(s) = value else {
+			return Ok(false);
+		};
+
+		if self.options.binary == Binary::Base64 || !self.is_binary() {
+			return Ok(false);
+		}
+
+		let Some(bytes) = decode_base64(s) else {
+			return Err(Error::Transform {
+				path: self.path.clone(),
+				message: String::from("`!!binary` value isn't valid base64"),
+			});
+		};
+
+		match self.options.binary {
+			Binary::Bytes => {
+				let separator = if self.options.compact { "," } else { ", " };
+
+				self.lua.push('{');
+
+				for (i, byte) in bytes.iter().enumerate() {
+					if i > 0 {
+						self.lua.push_str(separator);
+					}
+
+					self.lua.push_str(&byte.to_string());
+				}
+
+				self.lua.push('}');
+			}
+			Binary::Buffer => {
+				self.lua.push_str("buffer.fromstring(");
+				self.write_bytes(&bytes);
+				self.lua.push(')');
+			}
+			_ => self.write_bytes(&bytes),
+		}
+
+		Ok(true)
+	}
+
+	fn write_bytes(&mut self, bytes: &[u8]) {
+		let quote = self.options.quote_style;
+
+		self.lua.push(quote.quote());
+		write_lua_bytes(&mut self.lua, bytes, quote);
+		self.lua.push(quote.quote());
+	}
+
+	/// Whether the string at the current path is tagged with `!!binary` in the source
+	pub(super) fn is_binary(&self) -> bool {
+		self.core_tags.get(&self.path) == Some(&"binary")
+	}
+}
+
+/// Decode standard base64 ignoring whitespace, padding is optional
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+	let digits: Vec<u8> = text
+		.bytes()
+		.filter(|byte| !byte.is_ascii_whitespace())
+		.collect();
+
+	let digits = match digits.iter().position(|&byte| byte == b'=') {
+		Some(padding) if digits[padding..].iter().all(|&byte| byte == b'=') => {
+			if !digits.len().is_multiple_of(4) || digits.len() - padding > 2 {
+				return None;
+			}
+
+			&digits[..padding]
+		}
+		Some(_) => return None,
+		None => &digits[..],
+	};
+
+	if digits.len() % 4 == 1 {
+		return None;
+	}
+
+	let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+	let mut buffer = 0u32;
+	let mut bits = 0;
+
+	for &digit in digits {
+		let value = match digit {
+			b'A'..=b'Z' => digit - b'A',
+			b'a'..=b'z' => digit - b'a' + 26,
+			b'0'..=b'9' => digit - b'0' + 52,
+			b'+' => 62,
+			b'/' => 63,
+			_ => return None,
+		};
+
+		buffer = buffer << 6 | u32::from(value);
+		bits += 6;
+
+		if bits >= 8 {
+			bits -= 8;
+			bytes.push((buffer >> bits) as u8);
+			buffer &= (1 << bits) - 1;
+		}
+	}
+
+	Some(bytes)
+}
@@ -0,0 +1,116 @@
+use super::Emitter;
+
+impl Emitter<'_> {
+	/// Whether the string is written as a long string by [`ParseOptions::long_strings`](crate::ParseOptions::long_strings),
+	/// strings with control characters other than newlines and tabs keep their escapes
+	pub(super) fn is_long_string(&self, string: &str) -> bool {
+		self.options.long_strings
+			&& string.contains('\n')
+			&& !string
+				.bytes()
+				.any(|byte| matches!(byte, 0x00..=0x08 | 0x0B..=0x1F | 0x7F))
+	}
+
+	/// Write a string value, as a long string like `[[...]]` if it spans lines
+	pub(super) fn write_string_value(&mut self, string: &str) {
+		if !self.is_long_string(string) {
+			self.write_string(string);
+			return;
+		}
+
+		let equals = "=".repeat(level(string));
+
+		// Lua skips a newline right after the opening bracket, so the first line starts on its own
+		self.lua.push('[');
+		self.lua.push_str(&equals);
+		self.lua.push_str("[\n");
+		self.lua.push_str(string);
+		self.lua.push(']');
+		self.lua.push_str(&equals);
+		self.lua.push(']');
+	}
+}
+
+/// Get the lowest level of the long brackets that the string can be written in,
+/// the closing bracket can't occur in the string or be completed by its end.
+/// Level 0 strings also can't contain `[[`, which Lua 5.1 reads as nested brackets
+fn level(string: &str) -> usize {
+	(0..)
+		.find(|&level| {
+			let equals = "=".repeat(level);
+
+			!string.contains(&format!("]{}]", equals))
+				&& !string.ends_with(&format!("]{}", equals))
+				&& (level > 0 || !string.contains("[["))
+		})
+		.unwrap_or_default()
+}
+
+/// Tracks whether lines of the output start inside of long strings or long comments,
+/// which can't be indented without changing them. Lines are scanned in order
+#[derive(Debug, Default)]
+pub(super) struct LongStrings {
+	/// Level of the long bracket the scanned lines ended in
+	open: Option<usize>,
+}
+
+impl LongStrings {
+	/// Scan the line, returning whether it starts inside of a long bracket
+	pub fn line(&mut self, line: &str) -> bool {
+		let starts_inside = self.open.is_some();
+		let bytes = line.as_bytes();
+		let mut i = 0;
+
+		while i < bytes.len() {
+			if let Some(level) = self.open {
+				let close = format!("]{}]", "=".repeat(level));
+
+				match line[i..].find(&close) {
+					Some(end) => {
+						i += end + close.len();
+						self.open = None;
+						continue;
+					}
+					None => break,
+				}
+			}
+
+			match bytes[i] {
+				quote @ (b'"' | b'\'') => {
+					i += 1;
+
+					while i < bytes.len() && bytes[i] != quote {
+						i += if bytes[i] == b'\\' { 2 } else { 1 };
+					}
+
+					i += 1;
+				}
+				b'-' if bytes.get(i + 1) == Some(&b'-') => match opening(&bytes[i + 2..]) {
+					Some((level, len)) => {
+						self.open = Some(level);
+						i += 2 + len;
+					}
+					None => break,
+				},
+				b'[' => match opening(&bytes[i..]) {
+					Some((level, len)) => {
+						self.open = Some(level);
+						i += len;
+					}
+					None => i += 1,
+				},
+				_ => i += 1,
+			}
+		}
+
+		starts_inside
+	}
+}
+
+/// Get the level and length of the opening long bracket the bytes start with
+fn opening(bytes: &[u8]) -> Option<(usize, usize)> {
+	let rest = bytes.strip_prefix(b"[")?;
+	let level = rest.iter().take_while(|&&byte| byte == b'=').count();
+
+	(rest.get(level) == Some(&b'[')).then_some((level, level + 2))
+}
@@ -0,0 +1,214 @@
+use super::{Emitter, Key, KeyId};
+use crate::{
+	fmt::{is_lua_identifier, write_escaped, QuoteStyle},
+	PathSegment, Result, TagReplacement, UnknownTags,
+};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeSet;
+
+/// Shape of the values observed at one place of the document,
+/// sequence elements are merged into a single shape
+#[derive(Default)]
+pub(super) struct Shape {
+	/// Lua types of the values, empty if only `null` was observed
+	pub types: BTreeSet<&'static str>,
+	pub nullable: bool,
+	pub structure: Structure,
+}
+
+#[derive(Default)]
+pub(super) enum Structure {
+	#[default]
+	None,
+	Fields(Vec<Field>),
+	Elements(Box<Shape>),
+	/// Mappings mixed with sequences or tag wrappers, only the type is known
+	Opaque,
+}
+
+pub(super) struct Field {
+	id: KeyId,
+	/// Lua code indexing the field, like `["key"]`
+	pub index: String,
+	/// Key if it's a valid identifier
+	pub ident: Option<String>,
+	/// Lua type of the key
+	pub key_type: &'static str,
+	/// Key as it appeared in the source, used for naming
+	pub name: String,
+	pub shape: Shape,
+	pub required: bool,
+}
+
+impl Emitter<'_> {
+	/// Get the shape of the root as it's emitted, with the schema and key options applied
+	///
+	/// Shapes are resolved by a separate emitter so diagnostics aren't reported twice
+	pub(super) fn shape_root(&self, root: &Mapping) -> Result<Shape> {
+		let mut emitter = Emitter::new(self.options).at(self.path.clone());
+
+		Ok(Shape {
+			types: BTreeSet::from(["table"]),
+			nullable: false,
+			structure: emitter.shape_fields(root)?,
+		})
+	}
+
+	fn shape(&mut self, value: &Value) -> Result<Shape> {
+		let coerced = self.coerce(value)?;
+
+		let (ty, structure) = match coerced.as_ref().unwrap_or(value) {
+			Value::Null => {
+				return Ok(Shape {
+					nullable: true,
+					..Shape::default()
+				})
+			}
+			Value::Bool(_) => ("boolean", Structure::None),
+			Value::Number(_) => ("number", Structure::None),
+			// Constants can have any value
+			Value::String(_) if self.constant_prefix().is_some() => {
+				return Ok(Shape {
+					nullable: true,
+					..Shape::default()
+				})
+			}
+			Value::String(_) => ("string", Structure::None),
+			Value::Sequence(sequence) => match self.fold_entries(sequence)? {
+				Some(entries) => ("table", self.shape_fields(entries)?),
+				None => {
+					let mut elements = Shape::default();
+
+					for (i, value) in sequence.iter().enumerate() {
+						self.path.push(PathSegment::Index(i));
+						elements.merge(self.shape(value)?);
+						self.path.pop();
+					}
+
+					("table", Structure::Elements(Box::new(elements)))
+				}
+			},
+			Value::Mapping(mapping) => ("table", self.shape_fields(mapping)?),
+			Value::Tagged(tagged) => match (
+				self.tag_handler(&tagged.tag.to_string()),
+				&self.options.unknown_tags,
+			) {
+				(None, UnknownTags::Wrapper) => ("table", Structure::Opaque),
+				(None, UnknownTags::Error | UnknownTags::Strip) => {
+					return self.shape(&tagged.value)
+				}
+				(Some(handler), _) | (None, UnknownTags::Custom(handler)) => {
+					match self.replace_tagged(handler, tagged)? {
+						TagReplacement::Value(value) => return self.shape(&value),
+						// Lua expressions can evaluate to anything
+						TagReplacement::Lua(_) => {
+							return Ok(Shape {
+								nullable: true,
+								..Shape::default()
+							})
+						}
+					}
+				}
+			},
+		};
+
+		Ok(Shape {
+			types: BTreeSet::from([ty]),
+			nullable: false,
+			structure,
+		})
+	}
+
+	fn shape_fields<'v>(
+		&mut self,
+		entries: impl IntoIterator<Item = (&'v Value, &'v Value)>,
+	) -> Result<Structure> {
+		let mut fields = Vec::new();
+
+		for (source, key, value) in self.resolve_entries(self.written_entries(entries))? {
+			let segment = PathSegment::from_key(source);
+
+			let (index, ident, key_type) = match &key {
+				Key::String(s) | Key::Identifier(s) => (
+					format!("[{}]", literal(s)),
+					is_lua_identifier(s).then(|| s.to_string()),
+					"string",
+				),
+				Key::Number(n) => (format!("[{}]", self.format_number_key(n)?), None, "number"),
+				Key::Bool(b) => (format!("[{}]", b), None, "boolean"),
+			};
+
+			let name = match &segment {
+				PathSegment::Key(key) => key.clone(),
+				PathSegment::Index(index) => index.to_string(),
+			};
+
+			self.path.push(segment);
+
+			fields.push(Field {
+				id: KeyId::of(&key),
+				index,
+				ident,
+				key_type,
+				name,
+				shape: self.shape(value)?,
+				required: true,
+			});
+
+			self.path.pop();
+		}
+
+		Ok(Structure::Fields(fields))
+	}
+}
+
+impl Shape {
+	/// Merge shape of another value observed at the same place, fields
+	/// missing from either of the values are no longer required
+	fn merge(&mut self, other: Shape) {
+		self.types.extend(other.types);
+		self.nullable |= other.nullable;
+
+		self.structure = match (std::mem::take(&mut self.structure), other.structure) {
+			(Structure::None, structure) | (structure, Structure::None) => structure,
+			(Structure::Fields(mut fields), Structure::Fields(other)) => {
+				let mut other: Vec<Option<Field>> = other.into_iter().map(Some).collect();
+
+				for field in &mut fields {
+					let found = other
+						.iter_mut()
+						.find(|f| f.as_ref().is_some_and(|f| f.id == field.id))
+						.and_then(Option::take);
+
+					match found {
+						Some(found) => {
+							field.required &= found.required;
+							field.shape.merge(found.shape);
+						}
+						None => field.required = false,
+					}
+				}
+
+				fields.extend(other.into_iter().flatten().map(|field| Field {
+					required: false,
+					..field
+				}));
+
+				Structure::Fields(fields)
+			}
+			(Structure::Elements(mut elements), Structure::Elements(other)) => {
+				elements.merge(*other);
+				Structure::Elements(elements)
+			}
+			_ => Structure::Opaque,
+		};
+	}
+}
+
+/// Double quoted string literal with every special character escaped
+pub(super) fn literal(string: &str) -> String {
+	let mut literal = String::from("\"");
+	write_escaped(&mut literal, string, QuoteStyle::Double);
+	literal.push('"');
+	literal
+}
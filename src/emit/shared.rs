@@ -0,0 +1,143 @@
+use super::Emitter;
+use crate::{
+	hoist::{self, MAX_LOCALS},
+	Error, KeyPath, ParseOptions, Result, SubtreeDedup,
+};
+use serde_yaml::Mapping;
+use std::collections::{HashMap, HashSet};
+
+/// Function deep copying shared tables where they're used
+const COPY: &str = "__copy";
+
+impl Emitter<'_> {
+	/// Declare locals for tables repeated in the document and remember
+	/// their paths so the tables are referenced instead of written again
+	pub(super) fn write_shared_tables(
+		&mut self,
+		root: &Mapping,
+		dedup: &SubtreeDedup,
+	) -> Result<()> {
+		self.require_statements("subtree deduplication")?;
+
+		if dedup.copy && self.options.freeze {
+			return Err(Error::Options(String::from(
+				"copied shared tables can't be frozen",
+			)));
+		}
+
+		// Occurrences are written separately to compare them, without reporting progress twice
+		let quiet = ParseOptions {
+			progress: None,
+			..self.options.clone()
+		};
+
+		let mut groups = Vec::new();
+
+		for table in hoist::repeated_tables(root, dedup) {
+			// Schema rules depend on the path, so only tables written the same way are shared
+			let mut written: Vec<Vec<KeyPath>> = Vec::new();
+			let mut indices: HashMap<String, usize> = HashMap::new();
+
+			for path in table.paths {
+				let mut emitter = Emitter::new(&quiet).at(path.clone());
+				emitter.plain_keys = self.plain_keys.clone();
+				emitter.write_value(table.value, 0)?;
+
+				let index = *indices.entry(emitter.lua).or_insert_with(|| {
+					written.push(Vec::new());
+					written.len() - 1
+				});
+
+				written[index].push(path);
+			}
+
+			groups.extend(
+				written
+					.into_iter()
+					.filter(|paths| paths.len() >= 2)
+					.map(|paths| (table.value, table.size, paths)),
+			);
+		}
+
+		let available =
+			MAX_LOCALS.saturating_sub(self.hoisted_strings.len() + usize::from(dedup.copy));
+
+		groups.sort_by_key(|(_, size, paths)| std::cmp::Reverse(size * (paths.len() - 1)));
+		groups.truncate(dedup.max_locals.min(available));
+
+		// Tables only found inside other shared tables are written once anyway
+		let shared: HashSet<&KeyPath> = groups.iter().flat_map(|(_, _, paths)| paths).collect();
+		let nested = |path: &KeyPath| {
+			(0..path.segments().len())
+				.any(|len| shared.contains(&KeyPath::from(path.segments()[..len].to_vec())))
+		};
+
+		let mut groups: Vec<_> = groups
+			.iter()
+			.filter(|(_, _, paths)| !paths.iter().all(nested))
+			.collect();
+
+		// Nested tables are declared before the tables containing them
+		groups.sort_by_key(|(_, size, paths)| (*size, paths[0].segments().len()));
+
+		let path = std::mem::take(&mut self.path);
+		self.in_shared_table = true;
+
+		for (i, (value, _, paths)) in groups.iter().enumerate() {
+			let name = format!("__shared{}", i + 1);
+
+			self.path = paths[0].clone();
+			self.lua.push_str("local ");
+			self.lua.push_str(&name);
+			self.assign();
+			self.write_value(value, 0)?;
+			self.end_statement();
+
+			for path in paths {
+				self.shared_tables.insert(path.clone(), name.clone());
+			}
+		}
+
+		self.path = path;
+		self.in_shared_table = false;
+
+		if groups.is_empty() {
+			return Ok(());
+		}
+
+		if dedup.copy {
+			self.write_code(&format!(
+				"local function {copy}(value)\n\
+				\tlocal copy = {{}}\n\
+				\tfor k, v in pairs(value) do\n\
+				\t\tcopy[k] = type(v) == \"table\" and {copy}(v) or v\n\
+				\tend\n\
+				\treturn copy\n\
+				end\n",
+				copy = COPY
+			));
+		}
+
+		self.end_line();
+
+		Ok(())
+	}
+
+	/// Write a reference to the shared table at the current path, if there's one
+	pub(super) fn write_shared_reference(&mut self) -> bool {
+		let Some(name) = self.shared_tables.get(&self.path) else {
+			return false;
+		};
+
+		let copy =
+			!self.in_shared_table && self.options.dedupe_subtrees.is_some_and(|dedup| dedup.copy);
+
+		if copy {
+			self.lua.push_str(&format!("{}({})", COPY, name));
+		} else {
+			self.lua.push_str(name);
+		}
+
+		true
+	}
+}
@@ -0,0 +1,90 @@
+use super::Emitter;
+use crate::{fmt::LuaKey as Key, timestamp::Timestamp, Dialect, Error, Result, Timestamps};
+use serde_yaml::Value;
+use std::borrow::Cow;
+
+impl Emitter<'_> {
+	pub(super) fn check_timestamps(&self) -> Result<()> {
+		if self.options.timestamps == Timestamps::DateTime && self.options.dialect != Dialect::Luau
+		{
+			return Err(Error::Options(String::from(
+				"DateTime timestamps require the Luau dialect",
+			)));
+		}
+
+		Ok(())
+	}
+
+	/// Write the string as set by [`ParseOptions::timestamps`](crate::ParseOptions::timestamps)
+	/// if it's a timestamp in the source, returning whether it was written
+	pub(super) fn write_timestamp(&mut self, value: &Value) -> Result<bool> {
+		let Value::String(s) = value else {
+			return Ok(false);
+		};
+
+		if self.options.timestamps == Timestamps::String || !self.timestamps.contains(&self.path) {
+			return Ok(false);
+		}
+
+		let Some(timestamp) = Timestamp::parse(s.trim()) else {
+			return Err(Error::Transform {
+				path: self.path.clone(),
+				message: format!("\"{}\" isn't a valid timestamp", s),
+			});
+		};
+
+		match self.options.timestamps {
+			Timestamps::Epoch if timestamp.fraction.is_empty() => {
+				self.lua.push_str(&timestamp.epoch().to_string());
+			}
+			Timestamps::Epoch => {
+				let fraction: f64 = format!("0.{}", timestamp.fraction).parse().unwrap_or(0.0);
+				self.lua
+					.push_str(&(timestamp.epoch() as f64 + fraction).to_string());
+			}
+			Timestamps::OsTime => {
+				let utc = timestamp.utc();
+				let fields = [
+					("year", utc.year),
+					("month", i64::from(utc.month)),
+					("day", i64::from(utc.day)),
+					("hour", i64::from(utc.hour)),
+					("min", i64::from(utc.minute)),
+					("sec", i64::from(utc.second)),
+				];
+
+				self.lua.push_str("os.time({");
+
+				for (i, (field, value)) in fields.into_iter().enumerate() {
+					self.lua.push_str(match (i, self.options.compact) {
+						(0, true) => "",
+						(0, false) => " ",
+						(_, true) => ",",
+						(_, false) => ", ",
+					});
+
+					self.write_key(&Key::Identifier(Cow::Borrowed(field)));
+					self.assign();
+					self.lua.push_str(&value.to_string());
+				}
+
+				self.lua
+					.push_str(if self.options.compact { "})" } else { " })" });
+			}
+			_ => {
+				let utc = timestamp.utc();
+				let fraction = match utc.fraction.is_empty() {
+					true => String::new(),
+					false => format!(".{}", utc.fraction),
+				};
+
+				self.lua.push_str(&format!(
+					"DateTime.fromIsoDate(\"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z\")",
+					utc.year, utc.month, utc.day, utc.hour, utc.minute, utc.second, fraction
+				));
+			}
+		}
+
+		Ok(true)
+	}
+}
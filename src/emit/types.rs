@@ -0,0 +1,98 @@
+use super::shape::{Field, Shape, Structure};
+use crate::{fmt::is_lua_identifier, Error, LuauTypes, Result};
+
+/// Generate a Luau type declaration describing the shape, like `export type Config = { ... }`
+///
+/// Mappings become table types with a property for every string key, other keys
+/// are described by one indexer. Fields missing from some elements of a sequence
+/// and values that can be `nil` are optional
+pub(super) fn generate(shape: &Shape, types: &LuauTypes) -> Result<String> {
+	if !is_lua_identifier(&types.name) {
+		return Err(Error::Options(format!(
+			"\"{}\" can't be used as a Luau type name",
+			types.name
+		)));
+	}
+
+	let mut lua = String::new();
+
+	if types.export {
+		lua.push_str("export ");
+	}
+
+	lua.push_str("type ");
+	lua.push_str(&types.name);
+	lua.push_str(" = ");
+	lua.push_str(&type_of(shape, false, 0));
+	lua.push_str("\n\n");
+
+	Ok(lua)
+}
+
+/// Get the type of the shape, with nested table types indented for the depth
+fn type_of(shape: &Shape, optional: bool, depth: usize) -> String {
+	let types: Vec<String> = shape
+		.types
+		.iter()
+		.map(|ty| match (*ty, &shape.structure) {
+			("table", Structure::Fields(fields)) => table(fields, depth),
+			("table", Structure::Elements(elements)) => {
+				format!("{{ {} }}", type_of(elements, false, depth))
+			}
+			("table", _) => String::from("{ [any]: any }"),
+			(ty, _) => String::from(ty),
+		})
+		.collect();
+
+	match types.len() {
+		// Only `nil` was observed, or values that can be anything
+		0 => String::from("any"),
+		1 if optional || shape.nullable => format!("{}?", types[0]),
+		_ if optional || shape.nullable => format!("({})?", types.join(" | ")),
+		_ => types.join(" | "),
+	}
+}
+
+fn table(fields: &[Field], depth: usize) -> String {
+	if fields.is_empty() {
+		return String::from("{}");
+	}
+
+	let indent = "\t".repeat(depth + 1);
+	let mut lua = String::from("{\n");
+
+	let mut key_types = Vec::new();
+	let mut value_types = Vec::new();
+
+	for field in fields {
+		let ty = type_of(&field.shape, !field.required, depth + 1);
+
+		if field.key_type != "string" {
+			if !key_types.contains(&field.key_type) {
+				key_types.push(field.key_type);
+			}
+
+			if !value_types.contains(&ty) {
+				value_types.push(ty);
+			}
+
+			continue;
+		}
+
+		let key = field.ident.as_deref().unwrap_or(&field.index);
+		lua.push_str(&format!("{}{}: {},\n", indent, key, ty));
+	}
+
+	if !key_types.is_empty() {
+		lua.push_str(&format!(
+			"{}[{}]: {},\n",
+			indent,
+			key_types.join(" | "),
+			value_types.join(" | ")
+		));
+	}
+
+	lua.push_str(&"\t".repeat(depth));
+	lua.push('}');
+	lua
+}
@@ -0,0 +1,122 @@
+use super::shape::{literal, Shape, Structure};
+use std::fmt::Write;
+
+/// Generate `validate(value)` function checking values against the shape
+///
+/// Checks are stored in a table instead of locals so large documents
+/// don't hit the limit of locals in the chunk
+pub(super) fn generate(shape: &Shape) -> String {
+	let mut checks = Vec::new();
+	let root = check(shape, false, &mut checks);
+
+	let mut lua = String::from(
+		"local checks = {}
+
+local function join(path, key)
+	if path == \"\" then
+		return key
+	end
+
+	return path .. \".\" .. key
+end
+",
+	);
+
+	for check in checks {
+		lua.push('\n');
+		lua.push_str(&check);
+	}
+
+	let _ = write!(
+		lua,
+		"\nlocal function validate(value)\n\treturn checks[{}](value, \"\")\nend",
+		root
+	);
+
+	lua
+}
+
+/// Generate the check of the shape after the checks of its children, returning its index
+fn check(shape: &Shape, optional: bool, checks: &mut Vec<String>) -> usize {
+	let mut body = String::new();
+
+	if shape.nullable || optional {
+		body.push_str("\tif value == nil then\n\t\treturn true\n\tend\n");
+	} else {
+		body.push_str(
+			"\tif value == nil then\n\t\treturn false, path, \"missing required value\"\n\tend\n",
+		);
+	}
+
+	if !shape.types.is_empty() {
+		let condition: Vec<String> = shape
+			.types
+			.iter()
+			.map(|ty| format!("kind ~= \"{}\"", ty))
+			.collect();
+
+		let expected: Vec<&str> = shape.types.iter().copied().collect();
+
+		let _ = write!(
+			body,
+			"\n\tlocal kind = type(value)\n\n\tif {} then\n\t\treturn false, path, \"expected {}, got \" .. kind\n\tend\n",
+			condition.join(" and "),
+			expected.join("|"),
+		);
+	}
+
+	let mut children = String::new();
+
+	match &shape.structure {
+		Structure::Fields(fields) if !fields.is_empty() => {
+			// Declared once, every local counts towards the limit of the function
+			children.push_str("\n\t\tlocal ok, at, message\n");
+
+			for field in fields {
+				let check = check(&field.shape, !field.required, checks);
+
+				// Identifiers are joined with dots, other keys keep their indexing syntax
+				let path = match &field.ident {
+					Some(ident) => format!("join(path, {})", literal(ident)),
+					None => format!("path .. {}", literal(&field.index)),
+				};
+
+				let _ = write!(
+					children,
+					"\n\t\tok, at, message = checks[{}](value{}, {})\n\n\t\tif not ok then\n\t\t\treturn false, at, message\n\t\tend\n",
+					check, field.index, path,
+				);
+			}
+		}
+		Structure::Elements(elements) => {
+			let check = check(elements, false, checks);
+
+			let _ = write!(
+				children,
+				"\n\t\tfor i = 1, #value do\n\t\t\tlocal ok, at, message = checks[{}](value[i], path .. \"[\" .. i .. \"]\")\n\n\t\t\tif not ok then\n\t\t\t\treturn false, at, message\n\t\t\tend\n\t\tend\n",
+				check,
+			);
+		}
+		Structure::Fields(_) | Structure::None | Structure::Opaque => {}
+	}
+
+	if !children.is_empty() {
+		// Children are only checked in tables, other types already failed unless the
+		// shape was also observed as a scalar or only as `null`
+		let _ = write!(
+			body,
+			"\n\tif type(value) == \"table\" then{}\tend\n",
+			children
+		);
+	}
+
+	body.push_str("\n\treturn true\n");
+
+	checks.push(format!(
+		"checks[{}] = function(value, path)\n{}end\n",
+		checks.len() + 1,
+		body
+	));
+
+	checks.len()
+}
@@ -0,0 +1,51 @@
+use crate::{Error, Result};
+use std::borrow::Cow;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Decode text using its byte order mark, falling back to UTF-8 without it
+pub(crate) fn decode(bytes: &[u8]) -> Result<Cow<'_, str>> {
+	if let Some(bytes) = bytes.strip_prefix(UTF8_BOM) {
+		return decode_utf8(bytes);
+	}
+
+	if let Some(bytes) = bytes.strip_prefix(UTF16_LE_BOM) {
+		return decode_utf16(bytes, u16::from_le_bytes, "UTF-16 LE");
+	}
+
+	if let Some(bytes) = bytes.strip_prefix(UTF16_BE_BOM) {
+		return decode_utf16(bytes, u16::from_be_bytes, "UTF-16 BE");
+	}
+
+	decode_utf8(bytes)
+}
+
+fn decode_utf8(bytes: &[u8]) -> Result<Cow<'_, str>> {
+	std::str::from_utf8(bytes)
+		.map(Cow::Borrowed)
+		.map_err(|err| Error::Encoding(format!("invalid UTF-8 at byte {}", err.valid_up_to())))
+}
+
+fn decode_utf16(
+	bytes: &[u8],
+	from_bytes: fn([u8; 2]) -> u16,
+	encoding: &str,
+) -> Result<Cow<'static, str>> {
+	if !bytes.len().is_multiple_of(2) {
+		return Err(Error::Encoding(format!(
+			"{} input has an odd number of bytes",
+			encoding
+		)));
+	}
+
+	let units: Vec<u16> = bytes
+		.chunks_exact(2)
+		.map(|chunk| from_bytes([chunk[0], chunk[1]]))
+		.collect();
+
+	String::from_utf16(&units)
+		.map(Cow::Owned)
+		.map_err(|_| Error::Encoding(format!("{} input contains an unpaired surrogate", encoding)))
+}
@@ -0,0 +1,493 @@
+use crate::{source, Diagnostic, KeyPath};
+use std::{error, fmt, io, path::PathBuf, result};
+
+/// Result type used across the crate
+pub type Result<T> = result::Result<T, Error>;
+
+/// Error returned when YAML can't be converted into a Lua table
+#[derive(Debug)]
+pub enum Error {
+	/// Input is not valid YAML
+	Yaml(serde_yaml::Error),
+	/// Input is a stream of multiple documents, which are converted with
+	/// [`parse_documents`](crate::parse_documents) or [`parse_stream`](crate::parse_stream)
+	MultipleDocuments { documents: usize },
+	/// Input couldn't be read or output couldn't be written
+	Io(io::Error),
+	/// Input bytes aren't valid UTF-8 or UTF-16 text
+	Encoding(String),
+	/// Two keys of the same table resolve to the same Lua key
+	KeyCollision {
+		path: KeyPath,
+		key: String,
+		other: String,
+		resolved: String,
+	},
+	/// Entry has a key that can't be a Lua table key, which is only an error in strict mode
+	/// and skips the entry otherwise
+	UnsupportedKey {
+		/// Path of the entry, ending with its key
+		path: KeyPath,
+		/// Type of the key, or the number for numbers like NaN
+		key: String,
+	},
+	/// Warning diagnostic reported in strict mode
+	Strict(Diagnostic),
+	/// Options can't be used together
+	Options(String),
+	/// Structural transformation enabled in the options failed
+	Transform { path: KeyPath, message: String },
+	/// Number can't be written as set in the options, like a NaN with
+	/// [`NonFiniteFloats::Error`](crate::NonFiniteFloats::Error)
+	Number { path: KeyPath, message: String },
+	/// Key path doesn't exist in the document
+	PathNotFound {
+		/// Part of the path that was found
+		path: KeyPath,
+		/// Segment that wasn't found
+		segment: String,
+		/// Keys or indices available where the segment was expected
+		available: Vec<String>,
+	},
+	/// Key path or path pattern has invalid syntax
+	InvalidPath { path: String, message: String },
+	/// Top-level key can't be used as the name of a module file
+	ModuleName { key: String, message: String },
+	/// String tagged as embedded YAML is not valid YAML
+	EmbeddedYaml {
+		/// Path of the tagged string
+		path: KeyPath,
+		error: serde_yaml::Error,
+	},
+	/// Tables are nested deeper than the maximum depth set in the options
+	TooDeep {
+		/// Path of the first table over the maximum depth
+		path: KeyPath,
+		max: usize,
+	},
+	/// String is longer than the maximum length set in the options
+	StringTooLong {
+		path: KeyPath,
+		/// Length of the string in characters
+		length: usize,
+		max: usize,
+	},
+	/// Generated Lua couldn't be compiled into bytecode
+	Bytecode {
+		message: String,
+		/// One-based line of the generated Lua the compiler failed at
+		line: Option<usize>,
+		/// Text of the line the compiler failed at
+		source_line: Option<String>,
+	},
+	/// Document visitor stopped visiting the document
+	Visit {
+		/// Path of the node the visitor failed at
+		path: KeyPath,
+		source: Box<dyn error::Error + Send + Sync>,
+	},
+	/// Document has tags that aren't handled, with [`UnknownTags::Error`](crate::UnknownTags::Error)
+	UnknownTags(Vec<(KeyPath, String)>),
+	/// Tag handler failed to replace a tagged value
+	TagHandler {
+		path: KeyPath,
+		tag: String,
+		source: Box<dyn error::Error + Send + Sync>,
+	},
+	/// Progress callback stopped the conversion
+	Cancelled,
+	/// Lua passed to [`unparse`](crate::unparse) isn't a supported literal
+	Lua(String),
+	/// Post-processing hook failed
+	PostProcess {
+		/// YAML file the output was generated from
+		path: Option<PathBuf>,
+		source: Box<dyn error::Error + Send + Sync>,
+	},
+}
+
+/// Kind of an [`Error`] without its data, one for every variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+	Yaml,
+	MultipleDocuments,
+	Io,
+	Encoding,
+	KeyCollision,
+	UnsupportedKey,
+	Strict,
+	Options,
+	Transform,
+	Number,
+	PathNotFound,
+	InvalidPath,
+	ModuleName,
+	EmbeddedYaml,
+	TooDeep,
+	StringTooLong,
+	Bytecode,
+	Visit,
+	UnknownTags,
+	TagHandler,
+	Cancelled,
+	Lua,
+	PostProcess,
+}
+
+/// Position in the YAML source, lines and columns are one-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+	pub line: usize,
+	/// Column in characters
+	pub column: usize,
+	/// Byte offset from the start of the source
+	pub index: usize,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Yaml(err) => err.fmt(f),
+			Error::MultipleDocuments { documents } => write!(
+				f,
+				"input is a stream of {} documents, convert it with `parse_documents` or `parse_stream`",
+				documents
+			),
+			Error::Io(err) => err.fmt(f),
+			Error::Encoding(message) => write!(f, "input can't be decoded: {}", message),
+			Error::KeyCollision {
+				path,
+				key,
+				other,
+				resolved,
+			} => {
+				write!(f, "keys {} and {} both resolve to {}", other, key, resolved)?;
+
+				if !path.is_root() {
+					write!(f, " at `{}`", path)?;
+				}
+
+				Ok(())
+			}
+			Error::UnsupportedKey { path, key } => {
+				write!(f, "{} key can't be a Lua table key", key)?;
+
+				if !path.is_root() {
+					write!(f, " at `{}`", path)?;
+				}
+
+				Ok(())
+			}
+			Error::Strict(diagnostic) => diagnostic.fmt(f),
+			Error::Options(message) => write!(f, "invalid options: {}", message),
+			Error::Transform { path, message } | Error::Number { path, message } => {
+				write!(f, "{}", message)?;
+
+				if !path.is_root() {
+					write!(f, " at `{}`", path)?;
+				}
+
+				Ok(())
+			}
+			Error::PathNotFound {
+				path,
+				segment,
+				available,
+			} => {
+				if path.is_root() {
+					write!(f, "`{}` not found at the root", segment)?;
+				} else {
+					write!(f, "`{}` not found at `{}`", segment, path)?;
+				}
+
+				if available.is_empty() {
+					write!(f, ", no keys are available")
+				} else {
+					write!(f, ", available: {}", available.join(", "))
+				}
+			}
+			Error::InvalidPath { path, message } => {
+				write!(f, "invalid path `{}`: {}", path, message)
+			}
+			Error::ModuleName { key, message } => {
+				write!(f, "key {} can't be used as a module name: {}", key, message)
+			}
+			Error::EmbeddedYaml { path, error } => {
+				write!(f, "embedded YAML at `{}` is invalid: {}", path, error)
+			}
+			Error::TooDeep { path, max } => write!(
+				f,
+				"tables are nested deeper than the maximum of {} at `{}`",
+				max, path
+			),
+			Error::StringTooLong { path, length, max } => {
+				write!(
+					f,
+					"string of {} characters is longer than the maximum of {}",
+					length, max
+				)?;
+
+				if !path.is_root() {
+					write!(f, " at `{}`", path)?;
+				}
+
+				Ok(())
+			}
+			Error::Bytecode {
+				message,
+				source_line,
+				..
+			} => {
+				write!(f, "generated Lua can't be compiled: {}", message)?;
+
+				if let Some(source_line) = source_line {
+					write!(f, " in `{}`", source_line.trim())?;
+				}
+
+				Ok(())
+			}
+			Error::Visit { path, source } => {
+				write!(f, "visitor failed: {}", source)?;
+
+				if !path.is_root() {
+					write!(f, " at `{}`", path)?;
+				}
+
+				Ok(())
+			}
+			Error::UnknownTags(tags) => {
+				write!(f, "unknown tags:")?;
+
+				for (i, (path, tag)) in tags.iter().enumerate() {
+					let separator = if i == 0 { " " } else { ", " };
+
+					if path.is_root() {
+						write!(f, "{}`{}` at the root", separator, tag)?;
+					} else {
+						write!(f, "{}`{}` at `{}`", separator, tag, path)?;
+					}
+				}
+
+				Ok(())
+			}
+			Error::TagHandler { path, tag, source } => {
+				write!(f, "handler of tag `{}` failed: {}", tag, source)?;
+
+				if !path.is_root() {
+					write!(f, " at `{}`", path)?;
+				}
+
+				Ok(())
+			}
+			Error::Cancelled => write!(f, "conversion was cancelled"),
+			Error::Lua(message) => write!(f, "Lua can't be converted: {}", message),
+			Error::PostProcess { path, source } => {
+				write!(f, "post-processing failed: {}", source)?;
+
+				if let Some(path) = path {
+					write!(f, " for {}", path.display())?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
+
+impl Error {
+	/// Render the error with its location and a snippet of the source for printing to users
+	///
+	/// Source must be the YAML the error was returned for. Location of YAML syntax
+	/// errors comes from the parser, errors about a key path point at the key.
+	/// Errors without a location are rendered like their `Display` output
+	///
+	/// ```rust
+	/// let yaml = "a: 1\nb: [1, 2\nc: 3";
+	/// let err = yaml2lua::parse(yaml).unwrap_err();
+	///
+	/// assert!(err.render(yaml).contains("3 | c: 3"));
+	/// ```
+	pub fn render(&self, source: &str) -> String {
+		let mut output = format!("error: {}", self);
+
+		if let Some(location) = self.locate(source) {
+			output.push('\n');
+			output.push_str(&render_snippet(source, location.line, location.column));
+		}
+
+		output
+	}
+
+	/// Get the kind of the error
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Error::Yaml(_) => ErrorKind::Yaml,
+			Error::MultipleDocuments { .. } => ErrorKind::MultipleDocuments,
+			Error::Io(_) => ErrorKind::Io,
+			Error::Encoding(_) => ErrorKind::Encoding,
+			Error::KeyCollision { .. } => ErrorKind::KeyCollision,
+			Error::UnsupportedKey { .. } => ErrorKind::UnsupportedKey,
+			Error::Strict(_) => ErrorKind::Strict,
+			Error::Options(_) => ErrorKind::Options,
+			Error::Transform { .. } => ErrorKind::Transform,
+			Error::Number { .. } => ErrorKind::Number,
+			Error::PathNotFound { .. } => ErrorKind::PathNotFound,
+			Error::InvalidPath { .. } => ErrorKind::InvalidPath,
+			Error::ModuleName { .. } => ErrorKind::ModuleName,
+			Error::EmbeddedYaml { .. } => ErrorKind::EmbeddedYaml,
+			Error::TooDeep { .. } => ErrorKind::TooDeep,
+			Error::StringTooLong { .. } => ErrorKind::StringTooLong,
+			Error::Bytecode { .. } => ErrorKind::Bytecode,
+			Error::Visit { .. } => ErrorKind::Visit,
+			Error::UnknownTags(_) => ErrorKind::UnknownTags,
+			Error::TagHandler { .. } => ErrorKind::TagHandler,
+			Error::Cancelled => ErrorKind::Cancelled,
+			Error::Lua(_) => ErrorKind::Lua,
+			Error::PostProcess { .. } => ErrorKind::PostProcess,
+		}
+	}
+
+	/// Get the location of a YAML syntax error as reported by the parser
+	///
+	/// Errors about a value don't know the source, their location is found with [`locate`](Self::locate)
+	pub fn location(&self) -> Option<Location> {
+		let location = match self {
+			Error::Yaml(err) => err.location()?,
+			_ => return None,
+		};
+
+		Some(Location {
+			line: location.line(),
+			column: location.column(),
+			index: location.index(),
+		})
+	}
+
+	/// Find the location of the error in the source it was returned for, which is
+	/// the [`location`](Self::location) of syntax errors and the key of the value
+	/// at the [`path`](Self::path) of errors about a value
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, KeyStyle, Location, ParseOptions};
+	///
+	/// let yaml = "a: 1\nb:\n  c d: 2\n  c_d: 3";
+	/// let options = ParseOptions::new().key_style(KeyStyle::SanitizedIdentifiers);
+	/// let err = parse_with_options(yaml, &options).unwrap_err();
+	///
+	/// assert_eq!(err.path().unwrap().to_string(), "b");
+	/// assert_eq!(err.locate(yaml), Some(Location { line: 2, column: 1, index: 5 }));
+	/// ```
+	pub fn locate(&self, source: &str) -> Option<Location> {
+		if let Some(location) = self.location() {
+			return Some(location);
+		}
+
+		let path = self.path().filter(|path| !path.is_root())?;
+		let (line, column) = source::locate(source, path)?;
+
+		let line_start: usize = source
+			.split_inclusive('\n')
+			.take(line.saturating_sub(1))
+			.map(str::len)
+			.sum();
+		let offset: usize = source[line_start..]
+			.chars()
+			.take(column.saturating_sub(1))
+			.map(char::len_utf8)
+			.sum();
+
+		Some(Location {
+			line,
+			column,
+			index: line_start + offset,
+		})
+	}
+
+	/// Get the key path of the value the error is about, if it's about a specific value
+	pub fn path(&self) -> Option<&KeyPath> {
+		match self {
+			Error::KeyCollision { path, .. }
+			| Error::UnsupportedKey { path, .. }
+			| Error::Transform { path, .. }
+			| Error::Number { path, .. }
+			| Error::PathNotFound { path, .. }
+			| Error::TooDeep { path, .. }
+			| Error::StringTooLong { path, .. }
+			| Error::EmbeddedYaml { path, .. }
+			| Error::Visit { path, .. }
+			| Error::TagHandler { path, .. } => Some(path),
+			Error::UnknownTags(tags) => tags.first().map(|(path, _)| path),
+			Error::Strict(diagnostic) => Some(&diagnostic.path),
+			_ => None,
+		}
+	}
+}
+
+/// Lines of context shown before the line with the error
+const CONTEXT_BEFORE: usize = 2;
+/// Lines of context shown after the line with the error
+const CONTEXT_AFTER: usize = 1;
+
+/// Render source lines around the one-based line and column with a caret under the column
+fn render_snippet(source: &str, line: usize, column: usize) -> String {
+	let lines: Vec<&str> = source.lines().collect();
+
+	// Errors at the end of the input can be on the line after the last one
+	let line = line.clamp(1, lines.len() + 1);
+	let first = line.saturating_sub(CONTEXT_BEFORE).max(1);
+	let last = (line + CONTEXT_AFTER).min(lines.len());
+
+	let width = last.max(line).to_string().len();
+	let gutter = " ".repeat(width);
+
+	let mut output = format!(
+		"{} --> line {}, column {}\n{} |\n",
+		gutter, line, column, gutter
+	);
+
+	for number in first..=last.max(line) {
+		let text = lines.get(number - 1).copied().unwrap_or_default();
+		output.push_str(&format!("{:>width$} | {}\n", number, text, width = width));
+
+		if number == line {
+			// Keep tabs so the caret lines up with the source however tabs are displayed
+			let padding: String = text
+				.chars()
+				.take(column.saturating_sub(1))
+				.map(|c| if c == '\t' { '\t' } else { ' ' })
+				.collect();
+
+			output.push_str(&format!("{} | {}^\n", gutter, padding));
+		}
+	}
+
+	output.pop();
+	output
+}
+
+impl error::Error for Error {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Error::Yaml(err) => Some(err),
+			Error::Io(err) => Some(err),
+			Error::EmbeddedYaml { error, .. } => Some(error),
+			Error::PostProcess { source, .. }
+			| Error::Visit { source, .. }
+			| Error::TagHandler { source, .. } => Some(source.as_ref()),
+			_ => None,
+		}
+	}
+}
+
+impl From<serde_yaml::Error> for Error {
+	fn from(err: serde_yaml::Error) -> Self {
+		Error::Yaml(err)
+	}
+}
+
+impl From<io::Error> for Error {
+	fn from(err: io::Error) -> Self {
+		Error::Io(err)
+	}
+}
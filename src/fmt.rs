@@ -0,0 +1,274 @@
+//! Low-level helpers for writing Lua code, used by the converter itself
+//!
+//! ```rust
+//! use yaml2lua::fmt::{escape_lua_string, format_lua_key, is_lua_identifier, LuaKey, QuoteStyle};
+//!
+//! assert_eq!(escape_lua_string("a\tb", QuoteStyle::Double), "a\\tb");
+//! assert_eq!(format_lua_key(&LuaKey::from("end")), "[\"end\"]");
+//! assert!(is_lua_identifier("max_retries"));
+//! ```
+
+use serde_yaml::Number;
+use std::{borrow::Cow, fmt::Write};
+
+/// Reserved words that can't be used as Lua identifiers
+pub(crate) const LUA_KEYWORDS: [&str; 22] = [
+	"and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+	"local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Quote character used to delimit a Lua string literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+	/// `"string"`
+	#[default]
+	Double,
+	/// `'string'`
+	Single,
+}
+
+/// Key of a Lua table entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum LuaKey<'a> {
+	/// String key written as `["key"]`
+	String(Cow<'a, str>),
+	/// String key written as a bare `key`, falls back to `["key"]` if it isn't a valid identifier
+	Identifier(Cow<'a, str>),
+	/// Number key written as `[1]`
+	Number(Number),
+	/// Boolean key written as `[true]`
+	Bool(bool),
+}
+
+impl QuoteStyle {
+	/// Get the quote character
+	pub fn quote(&self) -> char {
+		match self {
+			QuoteStyle::Double => '"',
+			QuoteStyle::Single => '\'',
+		}
+	}
+}
+
+impl<'a> From<&'a str> for LuaKey<'a> {
+	fn from(key: &'a str) -> Self {
+		LuaKey::String(Cow::Borrowed(key))
+	}
+}
+
+/// Escape the contents of a Lua string literal delimited by the given quote, without the quotes
+///
+/// Backslashes, the quote, `\n`, `\r` and `\t` use their short escapes and any other
+/// control characters use decimal escapes like `\027`, which work in every Lua version.
+/// Other unicode characters are kept as they are.
+///
+/// Strings that only contain valid short escapes (like the two characters `\n`)
+/// and no characters that need escaping are treated as already escaped and
+/// returned unchanged, this is how YAML strings are converted
+pub fn escape_lua_string(string: &str, quote: QuoteStyle) -> Cow<'_, str> {
+	match escape_start(string, quote) {
+		None => Cow::Borrowed(string),
+		Some(start) => {
+			let mut output = String::with_capacity(string.len() + 8);
+			output.push_str(&string[..start]);
+			write_escaped(&mut output, &string[start..], quote);
+			Cow::Owned(output)
+		}
+	}
+}
+
+/// Write the escaped string into the output, same as [`escape_lua_string`]
+/// but without allocating
+pub(crate) fn write_lua_string(output: &mut String, string: &str, quote: QuoteStyle) {
+	match escape_start(string, quote) {
+		None => output.push_str(string),
+		Some(start) => {
+			output.push_str(&string[..start]);
+			write_escaped(output, &string[start..], quote);
+		}
+	}
+}
+
+/// Find where escaping has to start, which is the first backslash or the first
+/// character that needs escaping, or `None` if the string can be kept as it is
+fn escape_start(string: &str, quote: QuoteStyle) -> Option<usize> {
+	let quote = quote.quote() as u8;
+	let bytes = string.as_bytes();
+
+	let mut first_backslash = None;
+	let mut i = 0;
+
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => {
+				first_backslash.get_or_insert(i);
+
+				match bytes.get(i + 1) {
+					Some(b'n' | b't' | b'r' | b'\\') => i += 2,
+					Some(byte) if *byte == quote => i += 2,
+					_ => return first_backslash,
+				}
+			}
+			_ if needs_escape(bytes, i, quote) => return Some(first_backslash.unwrap_or(i)),
+			_ => i += 1,
+		}
+	}
+
+	None
+}
+
+/// Whether the byte at the index starts a character that has to be escaped,
+/// which are the quote and control characters including the two-byte C1 ones
+fn needs_escape(bytes: &[u8], i: usize, quote: u8) -> bool {
+	match bytes[i] {
+		byte if byte == quote => true,
+		0x00..=0x1F | 0x7F => true,
+		0xC2 => bytes
+			.get(i + 1)
+			.is_some_and(|next| (0x80..=0x9F).contains(next)),
+		_ => false,
+	}
+}
+
+/// Escape every character of the string, copying runs that need no escaping at once
+pub(crate) fn write_escaped(output: &mut String, string: &str, quote: QuoteStyle) {
+	let quote = quote.quote() as u8;
+	let bytes = string.as_bytes();
+
+	let mut run = 0;
+	let mut i = 0;
+
+	while i < bytes.len() {
+		let byte = bytes[i];
+
+		let escape = match byte {
+			b'\\' => Some("\\\\"),
+			b'\n' => Some("\\n"),
+			b'\r' => Some("\\r"),
+			b'\t' => Some("\\t"),
+			b'"' if byte == quote => Some("\\\""),
+			b'\'' if byte == quote => Some("\\'"),
+			_ if needs_escape(bytes, i, quote) => None,
+			_ => {
+				i += 1;
+				continue;
+			}
+		};
+
+		output.push_str(&string[run..i]);
+
+		match escape {
+			Some(escape) => {
+				output.push_str(escape);
+				i += 1;
+			}
+			None => {
+				let len = if byte == 0xC2 { 2 } else { 1 };
+
+				// Pad to three digits so a following digit isn't read as part of the escape
+				for byte in &bytes[i..i + len] {
+					output.push('\\');
+					output.push(char::from(b'0' + byte / 100));
+					output.push(char::from(b'0' + byte / 10 % 10));
+					output.push(char::from(b'0' + byte % 10));
+				}
+
+				i += len;
+			}
+		}
+
+		run = i;
+	}
+
+	output.push_str(&string[run..]);
+}
+
+/// Write bytes that aren't necessarily UTF-8 as the contents of a string literal,
+/// keeping printable ASCII and writing other bytes as decimal escapes like `\000`
+pub(crate) fn write_lua_bytes(output: &mut String, bytes: &[u8], quote: QuoteStyle) {
+	for &byte in bytes {
+		match byte {
+			b'\\' => output.push_str("\\\\"),
+			_ if byte == quote.quote() as u8 => {
+				output.push('\\');
+				output.push(quote.quote());
+			}
+			0x20..=0x7E => output.push(char::from(byte)),
+			_ => {
+				let _ = write!(output, "\\{:03}", byte);
+			}
+		}
+	}
+}
+
+/// Format a number used as a table key, without the brackets
+///
+/// Integers are plain decimals and floats never use scientific notation, whole
+/// floats keep a trailing `.0` like `1000.0` for `1e3`. NaN and infinities
+/// can't be table keys, so they return `None`
+///
+/// ```rust
+/// use yaml2lua::fmt::format_number_key;
+///
+/// assert_eq!(format_number_key(&1e3.into()).as_deref(), Some("1000.0"));
+/// assert_eq!(format_number_key(&f64::NAN.into()), None);
+/// ```
+pub fn format_number_key(number: &Number) -> Option<String> {
+	if !number.is_f64() {
+		return Some(number.to_string());
+	}
+
+	let float = number.as_f64()?;
+
+	if !float.is_finite() {
+		return None;
+	}
+
+	let mut output = float.to_string();
+
+	if !output.contains('.') {
+		output.push_str(".0");
+	}
+
+	Some(output)
+}
+
+/// Format a table key as it appears before `=` in a table constructor
+pub fn format_lua_key(key: &LuaKey) -> String {
+	let mut output = String::new();
+	write_lua_key(&mut output, key, QuoteStyle::Double);
+	output
+}
+
+/// Write the key into the output, same as [`format_lua_key`] but without allocating
+/// and with string keys delimited by the given quote
+pub(crate) fn write_lua_key(output: &mut String, key: &LuaKey, quote: QuoteStyle) {
+	match key {
+		LuaKey::Identifier(ident) if is_lua_identifier(ident) => output.push_str(ident),
+		LuaKey::String(s) | LuaKey::Identifier(s) => {
+			output.push('[');
+			output.push(quote.quote());
+			write_lua_string(output, s, quote);
+			output.push(quote.quote());
+			output.push(']');
+		}
+		LuaKey::Number(n) => {
+			// Non-finite numbers aren't valid keys, but still show what the key was
+			let n = format_number_key(n).unwrap_or_else(|| n.to_string());
+			let _ = write!(output, "[{}]", n);
+		}
+		LuaKey::Bool(b) => output.push_str(if *b { "[true]" } else { "[false]" }),
+	}
+}
+
+/// Check whether the string can be used as a bare Lua identifier,
+/// which means ASCII letters, digits and `_` not starting with a digit and not a reserved word
+pub fn is_lua_identifier(string: &str) -> bool {
+	let mut chars = string.chars();
+
+	chars
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+		&& !LUA_KEYWORDS.contains(&string)
+}
@@ -0,0 +1,197 @@
+use crate::{KeyPath, PathSegment};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+
+/// Lua limits each function to 200 local variables, one is kept
+/// for the table itself when it's built with assignments
+pub(crate) const MAX_LOCALS: usize = 199;
+
+/// Options for hoisting repeated string values into locals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StringDedup {
+	/// Minimum number of occurrences for a string to be hoisted
+	pub min_count: usize,
+	/// Minimum length of a string in bytes for it to be hoisted
+	pub min_len: usize,
+	/// Maximum number of hoisted strings, capped to stay within Lua's local limit
+	pub max_locals: usize,
+}
+
+impl Default for StringDedup {
+	fn default() -> Self {
+		Self {
+			min_count: 2,
+			min_len: 8,
+			max_locals: 100,
+		}
+	}
+}
+
+/// Find string values worth hoisting, most bytes saved first
+/// and then sorted by their first occurrence in the document
+pub(crate) fn repeated_strings<'a>(root: &'a Mapping, dedup: &StringDedup) -> Vec<&'a str> {
+	/// Strings in order of their first occurrence with their counts
+	#[derive(Default)]
+	struct Counts<'a> {
+		counts: Vec<(&'a str, usize)>,
+		indices: HashMap<&'a str, usize>,
+	}
+
+	fn count<'a>(value: &'a Value, counts: &mut Counts<'a>) {
+		match value {
+			Value::String(s) => {
+				let index = *counts.indices.entry(s).or_insert_with(|| {
+					counts.counts.push((s, 0));
+					counts.counts.len() - 1
+				});
+
+				counts.counts[index].1 += 1;
+			}
+			Value::Sequence(sequence) => sequence.iter().for_each(|v| count(v, counts)),
+			Value::Mapping(mapping) => mapping.values().for_each(|v| count(v, counts)),
+			Value::Tagged(tagged) => count(&tagged.value, counts),
+			_ => {}
+		}
+	}
+
+	let mut counts = Counts::default();
+	root.values().for_each(|v| count(v, &mut counts));
+
+	let mut candidates: Vec<(usize, &str, usize)> = counts
+		.counts
+		.into_iter()
+		.enumerate()
+		.filter(|(_, (s, n))| *n >= dedup.min_count.max(2) && s.len() >= dedup.min_len)
+		.map(|(i, (s, n))| (i, s, n))
+		.collect();
+
+	candidates.sort_by_key(|(i, s, n)| (std::cmp::Reverse(s.len() * n), *i));
+	candidates.truncate(dedup.max_locals.min(MAX_LOCALS));
+	candidates.sort_by_key(|(i, _, _)| *i);
+
+	candidates.into_iter().map(|(_, s, _)| s).collect()
+}
+
+/// Options for hoisting identical tables into locals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubtreeDedup {
+	/// Minimum number of values inside a table, counting nested ones, for it to be hoisted
+	pub min_size: usize,
+	/// Maximum number of hoisted tables, capped to stay within Lua's local limit
+	pub max_locals: usize,
+	/// Deep copy hoisted tables where they're used instead of sharing one table,
+	/// so modifying one of them at runtime doesn't change the others
+	pub copy: bool,
+}
+
+impl Default for SubtreeDedup {
+	fn default() -> Self {
+		Self {
+			min_size: 4,
+			max_locals: 100,
+			copy: false,
+		}
+	}
+}
+
+/// Table that occurs more than once in the document
+pub(crate) struct RepeatedTable<'a> {
+	pub value: &'a Value,
+	/// Paths of the occurrences in document order
+	pub paths: Vec<KeyPath>,
+	/// Number of values inside the table
+	pub size: usize,
+}
+
+/// Find tables occurring more than once, in document order of their first
+/// occurrence. Tables nested in a repeated table are found too
+pub(crate) fn repeated_tables<'a>(
+	root: &'a Mapping,
+	dedup: &SubtreeDedup,
+) -> Vec<RepeatedTable<'a>> {
+	/// Table found in the document, with its position in document order
+	struct Found<'a> {
+		position: usize,
+		value: &'a Value,
+		path: KeyPath,
+		size: usize,
+	}
+
+	/// Collect tables and return the number of values inside the value
+	fn collect<'a>(
+		value: &'a Value,
+		path: &mut KeyPath,
+		dedup: &SubtreeDedup,
+		found: &mut Vec<Found<'a>>,
+		position: &mut usize,
+	) -> usize {
+		let start = *position;
+		*position += 1;
+
+		let mut visit = |segment, value, found: &mut Vec<Found<'a>>| {
+			path.push(segment);
+			let size = collect(value, path, dedup, found, position);
+			path.pop();
+			size
+		};
+
+		let size = match value {
+			Value::Sequence(sequence) => sequence
+				.iter()
+				.enumerate()
+				.map(|(i, v)| visit(PathSegment::Index(i), v, found))
+				.sum(),
+			Value::Mapping(mapping) => mapping
+				.iter()
+				.map(|(k, v)| visit(PathSegment::from_key(k), v, found))
+				.sum(),
+			// Tagged tables are written as wrappers, their values aren't shared
+			_ => return 1,
+		};
+
+		if size >= dedup.min_size.max(1) {
+			found.push(Found {
+				position: start,
+				value,
+				path: path.clone(),
+				size,
+			});
+		}
+
+		size + 1
+	}
+
+	let mut found = Vec::new();
+	let mut path = KeyPath::new();
+	let mut position = 0;
+
+	for (key, value) in root {
+		path.push(PathSegment::from_key(key));
+		collect(value, &mut path, dedup, &mut found, &mut position);
+		path.pop();
+	}
+
+	found.sort_by_key(|table| table.position);
+
+	let mut tables: Vec<RepeatedTable> = Vec::new();
+	let mut indices: HashMap<&Value, usize> = HashMap::new();
+
+	for table in found {
+		let index = *indices.entry(table.value).or_insert_with(|| {
+			tables.push(RepeatedTable {
+				value: table.value,
+				paths: Vec::new(),
+				size: table.size,
+			});
+
+			tables.len() - 1
+		});
+
+		tables[index].paths.push(table.path);
+	}
+
+	tables
+		.into_iter()
+		.filter(|table| table.paths.len() >= 2)
+		.collect()
+}
@@ -0,0 +1,133 @@
+use crate::fmt::{is_lua_identifier, LUA_KEYWORDS};
+use std::borrow::Cow;
+
+/// How string keys are rewritten before being emitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyStyle {
+	/// Keep keys as they are
+	#[default]
+	Preserve,
+	/// `max-retries` becomes `max_retries`
+	SnakeCase,
+	/// `max_retries` becomes `max-retries`
+	KebabCase,
+	/// `max-retries` becomes `maxRetries`
+	CamelCase,
+	/// `max-retries` becomes `MaxRetries`
+	PascalCase,
+	/// Every string key is emitted as a bare Lua identifier like `max_retries = 1`
+	///
+	/// Invalid characters are replaced with `_`, keys starting with a digit
+	/// are prefixed with `_` and reserved words get a `_` suffix (`end_`).
+	/// Number and boolean keys are left untouched
+	SanitizedIdentifiers,
+	/// Keys written as plain scalars become bare identifiers like `max_retries = 1`
+	/// when they're valid identifiers, quoted keys stay bracketed like `["max_retries"] = 1`
+	///
+	/// Keys reached through an alias are written like the keys of the anchored value.
+	/// Keys without a source, like the ones added by transformations or merged from
+	/// other documents, are written like with [`KeyStyle::Preserve`]
+	MirrorSource,
+}
+
+impl KeyStyle {
+	pub(crate) fn apply<'a>(&self, key: &'a str) -> Cow<'a, str> {
+		let words = || split_words(key);
+
+		match self {
+			KeyStyle::Preserve | KeyStyle::MirrorSource => Cow::Borrowed(key),
+			KeyStyle::SnakeCase => Cow::Owned(words().join("_")),
+			KeyStyle::KebabCase => Cow::Owned(words().join("-")),
+			KeyStyle::CamelCase => Cow::Owned(
+				words()
+					.iter()
+					.enumerate()
+					.map(|(i, word)| {
+						if i == 0 {
+							word.clone()
+						} else {
+							capitalize(word)
+						}
+					})
+					.collect(),
+			),
+			KeyStyle::PascalCase => {
+				Cow::Owned(words().iter().map(|word| capitalize(word)).collect())
+			}
+			KeyStyle::SanitizedIdentifiers => sanitize(key),
+		}
+	}
+}
+
+fn sanitize(key: &str) -> Cow<'_, str> {
+	let is_valid = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+	let starts_with_digit = key.starts_with(|c: char| c.is_ascii_digit());
+	let is_keyword = LUA_KEYWORDS.contains(&key);
+
+	if is_lua_identifier(key) {
+		return Cow::Borrowed(key);
+	}
+
+	let mut ident: String = key
+		.chars()
+		.map(|c| if is_valid(c) { c } else { '_' })
+		.collect();
+
+	if ident.is_empty() || starts_with_digit {
+		ident.insert(0, '_');
+	}
+
+	if is_keyword {
+		ident.push('_');
+	}
+
+	Cow::Owned(ident)
+}
+
+/// Split key into lowercase words on `-`, `_`, whitespace and case changes,
+/// keeping acronyms together so `HTTPServer` becomes `http` and `server`
+fn split_words(key: &str) -> Vec<String> {
+	let mut words = Vec::new();
+	let mut word = String::new();
+
+	let chars: Vec<char> = key.chars().collect();
+
+	for (i, &char) in chars.iter().enumerate() {
+		if char == '-' || char == '_' || char.is_whitespace() {
+			if !word.is_empty() {
+				words.push(word.to_lowercase());
+				word.clear();
+			}
+
+			continue;
+		}
+
+		if char.is_uppercase() && !word.is_empty() {
+			let prev = chars[i - 1];
+			let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+
+			if !prev.is_uppercase() || next_lower {
+				words.push(word.to_lowercase());
+				word.clear();
+			}
+		}
+
+		word.push(char);
+	}
+
+	if !word.is_empty() {
+		words.push(word.to_lowercase());
+	}
+
+	words
+}
+
+fn capitalize(word: &str) -> String {
+	let mut chars = word.chars();
+
+	match chars.next() {
+		Some(first) => first.to_uppercase().chain(chars).collect(),
+		None => String::new(),
+	}
+}
@@ -33,11 +33,109 @@
 
 #![allow(clippy::tabs_in_doc_comments)]
 
-use indexmap::IndexMap;
-use serde_yaml::{from_str, Result, Value};
+mod batch;
+#[cfg(feature = "bytecode")]
+mod bytecode;
+mod chunks;
+mod converter;
+mod diagnostic;
+mod diff;
+mod digest;
+mod emit;
+mod encoding;
+mod error;
+mod hoist;
+mod keys;
+mod lua;
+#[cfg(feature = "mlua")]
+mod lua_table;
+mod options;
+mod partial;
+mod path;
+mod progress;
+#[cfg(feature = "roblox")]
+mod roblox;
+mod schema;
+mod self_test;
+mod source;
+mod stats;
+mod tags;
+mod timestamp;
+mod transform;
+mod unparse;
+mod value;
+mod visit;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub mod fmt;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+use emit::Emitter;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::{
+	fs,
+	io::{Read, Write},
+	path::Path,
+};
+
+pub use batch::{convert_dir, split_to_dir, BatchReport, ConvertedFile, FailedFile};
+#[cfg(feature = "bytecode")]
+pub use bytecode::parse_to_bytecode;
+pub use chunks::Chunks;
+pub use converter::Converter;
+pub use diagnostic::{Diagnostic, DiagnosticKind, Severity};
+pub use error::{Error, ErrorKind, Location, Result};
+pub use hoist::{StringDedup, SubtreeDedup};
+pub use keys::KeyStyle;
+#[cfg(feature = "mlua")]
+pub use lua_table::to_lua_table;
+pub use options::{
+	Binary, ColumnarRecords, Comments, Dialect, DuplicateKeys, EmbeddedYaml, FidelityMode, Indent,
+	KeyComparison, LargeIntegers, LuauTypes, MergeConflicts, ModuleStyle, NonFiniteFloats, Nulls,
+	NumericKeys, OutputMode, ParseOptions, Preview, SequenceMerge, SourceComments, StreamStyle,
+	StringOverflow, TableLayout, Timestamps, WrapperFields,
+};
+pub use partial::PartialResult;
+pub use path::{KeyPath, PathPattern, PathSegment};
+pub use progress::{Progress, ProgressInterval};
+pub use schema::{ScalarType, Schema};
+pub use self_test::{self_test, SelfTestCase, SelfTestReport};
+pub use stats::Stats;
+pub use tags::{TagHandler, TagReplacement, TagWrapper, UnknownTags};
+pub use value::LuaValue;
+pub use visit::{visit, visit_value, DocumentVisitor, VisitError};
+#[cfg(feature = "watch")]
+pub use watch::{watch_dir, WatchEvent};
+
+/// Output format versions supported by [`ParseOptions::format_version`], oldest first
+///
+/// Output of a pinned version stays byte-identical between releases of the crate,
+/// formatting changes only go into new versions. Version 1 is specified by the
+/// golden files in `tests/golden`:
+///
+/// - tables are indented with one tab per level, every entry ends with `,` and a newline
+/// - keys are written as `["key"]`, numbers and booleans as `[1]` and `[true]`
+/// - strings are double-quoted, `"` and `\` are escaped with a backslash, `\n`, `\r`
+///   and `\t` use their short escapes and other control characters use decimal `\ddd`
+///   escapes, everything else is written as UTF-8
+/// - integers are written in decimal, floats in their shortest form that reads back
+///   the same, whole floats keep `.0` and exponents are written like `1e20`
+/// - empty tables are written as `{` and `}` on separate lines
+///
+/// Infinite and NaN floats aren't covered, since they have no literal in Lua
+pub const FORMAT_VERSIONS: &[u32] = &[1];
 
 /// Parse YAML string into a Lua table
 ///
+/// Input must be a single document, streams of `---` separated documents fail with
+/// [`Error::MultipleDocuments`] and are converted by [`parse_documents`] or [`parse_stream`]
+///
 /// ```rust
 /// use yaml2lua::parse;
 ///
@@ -64,130 +162,709 @@ use serde_yaml::{from_str, Result, Value};
 /// assert_eq!(parse(yaml).unwrap(), lua);
 /// ```
 pub fn parse(yaml: &str) -> Result<String> {
-	let yaml: IndexMap<Value, Value> = from_str(yaml)?;
-	let mut lua = String::from("{\n");
+	parse_with_options(yaml, &ParseOptions::default())
+}
 
-	for (key, value) in yaml {
-		lua.push_str(&walk(Some(&key), &value, 1));
-	}
+/// Parse YAML string into a Lua table using the provided options
+///
+/// Entries are written in the order they have in the source unless
+/// [`ParseOptions::sort_keys`] is enabled. Output only depends on the input
+/// and the options, converting the same document always gives the same output
+pub fn parse_with_options(yaml: &str, options: &ParseOptions) -> Result<String> {
+	parse_with_diagnostics(yaml, options).map(|(lua, _)| lua)
+}
+
+/// Parse YAML string into a Lua table, also returning diagnostics about
+/// lossy or surprising conversions like skipped entries or unknown tags
+///
+/// ```rust
+/// use yaml2lua::{parse_with_diagnostics, DiagnosticKind, ParseOptions};
+///
+/// let (lua, diagnostics) = parse_with_diagnostics("a: !Tag 1", &ParseOptions::new()).unwrap();
+///
+/// assert_eq!(diagnostics[0].kind, DiagnosticKind::UnknownTag);
+/// assert_eq!(diagnostics[0].path.to_string(), "a");
+/// ```
+pub fn parse_with_diagnostics(
+	yaml: &str,
+	options: &ParseOptions,
+) -> Result<(String, Vec<Diagnostic>)> {
+	let (lua, diagnostics) = emit_document(yaml, options)?;
+	Ok((options.finish(lua, None)?, diagnostics))
+}
+
+/// Parse YAML string into a Lua table, replacing values that fail to convert
+/// instead of failing the whole document
+///
+/// Failed values are written as the [`error_placeholder`](ParseOptions::error_placeholder)
+/// followed by a `-- ERROR: ...` comment and their errors are returned in the result.
+/// Recovered errors are the ones about a single value, like key collisions, strings over
+/// the length limit, invalid embedded YAML and warnings in strict mode. YAML syntax
+/// errors and errors about the whole document still fail the conversion
+///
+/// ```rust
+/// use yaml2lua::{parse_partial, ParseOptions, StringOverflow};
+///
+/// let options = ParseOptions::new().max_string_len(3, StringOverflow::Error);
+/// let result = parse_partial("a: abc\nb: abcd", &options).unwrap();
+///
+/// assert_eq!(result.errors.len(), 1);
+/// assert!(result.lua.contains("[\"b\"] = nil, -- ERROR: string of 4 characters"));
+/// ```
+pub fn parse_partial(yaml: &str, options: &ParseOptions) -> Result<PartialResult> {
+	let (root, failures) =
+		transform::prepare_partial(transform::load_root(yaml, options)?, options)?;
+	let mut emitter = Emitter::new(options).with_source(yaml).partial(failures);
+
+	emitter.emit(&root)?;
+
+	let (lua, diagnostics, errors) = emitter.into_partial();
+
+	Ok(PartialResult {
+		lua: options.finish(lua, None)?,
+		errors,
+		diagnostics,
+	})
+}
+
+/// Convert YAML string without running post-processing hooks
+pub(crate) fn emit_document(
+	yaml: &str,
+	options: &ParseOptions,
+) -> Result<(String, Vec<Diagnostic>)> {
+	let root = transform::prepare_root(transform::load_root(yaml, options)?, options)?;
+	let mut emitter = Emitter::new(options).with_source(yaml);
 
-	lua.push('}');
+	emitter.emit(&root)?;
 
-	Ok(lua)
+	let (lua, diagnostics, _) = emitter.into_parts();
+
+	Ok((lua, diagnostics))
+}
+
+/// Parse YAML string into a Lua table returned in chunks, for writing into sinks
+/// that take output piece by piece
+///
+/// Chunks joined together are the same as the output of [`parse_with_options`]
+///
+/// ```rust
+/// use yaml2lua::{parse, parse_chunks, ParseOptions};
+///
+/// let chunks = parse_chunks("a: 1\nb: 2", &ParseOptions::new()).unwrap().target_size(4);
+/// let chunks: Vec<String> = chunks.collect();
+///
+/// assert_eq!(chunks, ["{\n\t[\"a\"] = 1,\n", "\t[\"b\"] = 2,\n", "}"]);
+/// assert_eq!(chunks.concat(), parse("a: 1\nb: 2").unwrap());
+/// ```
+pub fn parse_chunks(yaml: &str, options: &ParseOptions) -> Result<Chunks> {
+	parse_with_options(yaml, options).map(Chunks::new)
+}
+
+/// Most documents returned as multiple values by [`parse_stream`], Lua functions
+/// have at most 255 registers and each returned value needs one
+const MAX_RETURN_VALUES: usize = 200;
+
+/// Parse every document of a `---` separated YAML stream into a separate Lua table
+///
+/// ```rust
+/// use yaml2lua::{parse_documents, ParseOptions};
+///
+/// let docs = parse_documents("a: 1\n---\nb: 2", &ParseOptions::new()).unwrap();
+///
+/// assert_eq!(docs, ["{\n\t[\"a\"] = 1,\n}", "{\n\t[\"b\"] = 2,\n}"]);
+/// ```
+pub fn parse_documents(yaml: &str, options: &ParseOptions) -> Result<Vec<String>> {
+	split_documents(yaml)?
+		.into_iter()
+		.map(|doc| parse_with_options(doc, options))
+		.collect()
 }
 
-fn walk(key: Option<&Value>, value: &Value, depth: usize) -> String {
+/// Parse every document of a `---` separated YAML stream into one module returning all of them
+///
+/// Documents are emitted like in [`OutputMode::Table`] whatever the output mode of the
+/// options is, so options writing statements can't be used. Post-processing hooks run
+/// and the final newline and semicolon are added once for the whole module. [`StreamStyle::MultipleValues`] supports at most 200
+/// documents because of the limits of Lua functions, larger streams need [`StreamStyle::Table`]
+///
+/// ```rust
+/// use yaml2lua::{parse_stream, ParseOptions, StreamStyle};
+///
+/// let yaml = "a: 1\n---\nb: 2";
+///
+/// assert_eq!(
+/// 	parse_stream(yaml, &ParseOptions::new(), StreamStyle::MultipleValues).unwrap(),
+/// 	"return {\n\t[\"a\"] = 1,\n}, {\n\t[\"b\"] = 2,\n}"
+/// );
+/// assert_eq!(
+/// 	parse_stream(yaml, &ParseOptions::new(), StreamStyle::Table).unwrap(),
+/// 	"return {\n\t{\n\t\t[\"a\"] = 1,\n\t},\n\t{\n\t\t[\"b\"] = 2,\n\t},\n}"
+/// );
+/// ```
+pub fn parse_stream(yaml: &str, options: &ParseOptions, style: StreamStyle) -> Result<String> {
+	let documents = split_documents(yaml)?;
+
+	if style == StreamStyle::MultipleValues && documents.len() > MAX_RETURN_VALUES {
+		return Err(Error::Options(format!(
+			"stream of {} documents can't be returned as multiple values, the limit is {}",
+			documents.len(),
+			MAX_RETURN_VALUES
+		)));
+	}
+
+	let document_options = ParseOptions {
+		output_mode: OutputMode::Table,
+		base_depth: options.base_depth + usize::from(style == StreamStyle::Table),
+		// The stream is started and ended as a whole
+		header: None,
+		final_newline: false,
+		trailing_semicolon: false,
+		..options.clone()
+	};
+
 	let mut lua = String::new();
+	options.begin_output(&mut lua);
+	lua.push_str("return ");
+
+	if style == StreamStyle::Table {
+		lua.push_str("{\n");
+	}
 
-	lua.push_str(&get_indent(depth));
+	for (i, doc) in documents.into_iter().enumerate() {
+		let (doc, _) = emit_document(doc, &document_options)?;
 
-	if let Some(key) = key {
-		match key {
-			Value::String(s) => {
-				lua.push_str(&format!("[\"{}\"] = ", escape_string(s)));
-			}
-			Value::Number(n) => {
-				lua.push_str(&format!("[{}] = ", n));
+		match style {
+			StreamStyle::MultipleValues => {
+				if i > 0 {
+					lua.push_str(", ");
+				}
+
+				lua.push_str(&doc);
 			}
-			Value::Bool(b) => {
-				lua.push_str(&format!("[{}] = ", b));
+			StreamStyle::Table => {
+				lua.push_str(&doc);
+				lua.push_str(",\n");
 			}
-			_ => return String::new(),
-		};
+		}
 	}
 
-	match value {
-		Value::String(s) => lua.push_str(&format!("\"{}\"", &escape_string(s))),
-		Value::Number(n) => lua.push_str(&n.to_string()),
-		Value::Bool(b) => lua.push_str(&b.to_string()),
-		Value::Null => lua.push_str("nil"),
-		Value::Sequence(s) => {
-			lua.push_str("{\n");
-
-			for v in s {
-				lua.push_str(&walk(None, v, depth + 1));
-			}
+	if style == StreamStyle::Table {
+		lua.push('}');
+	}
 
-			lua.push_str(&get_indent(depth));
-			lua.push('}');
-		}
-		Value::Mapping(m) => {
-			lua.push_str("{\n");
+	options.finish(lua, None)
+}
 
-			for (k, v) in m {
-				lua.push_str(&walk(Some(k), v, depth + 1));
-			}
+/// Split the stream into documents, reporting syntax errors of the documents
+fn split_documents(yaml: &str) -> Result<Vec<&str>> {
+	if let Some(documents) = source::split_documents(yaml) {
+		return Ok(documents);
+	}
 
-			lua.push_str(&get_indent(depth));
-			lua.push('}');
-		}
-		Value::Tagged(t) => {
-			lua.push_str("{\n");
+	source::check_depth(yaml)?;
 
-			lua.push_str(&get_indent(depth + 1));
-			lua.push_str(&format!(
-				"[\"{}\"] = {}",
-				t.tag.to_string().strip_prefix('!').unwrap(),
-				&walk(None, &t.value, depth + 1)
-					.strip_prefix(&"\t".repeat(depth + 1))
-					.unwrap()
-			));
+	for document in serde_yaml::Deserializer::from_str(yaml) {
+		Value::deserialize(document)?;
+	}
+
+	Err(Error::Options(String::from(
+		"YAML stream can't be split into documents",
+	)))
+}
+
+/// Parse YAML bytes into a Lua table, detecting the text encoding
+///
+/// UTF-8 byte order mark is stripped and input starting with a UTF-16 LE or BE
+/// byte order mark is transcoded. Input without any is expected to be UTF-8
+///
+/// ```rust
+/// use yaml2lua::{parse, parse_bytes};
+///
+/// assert_eq!(parse_bytes(b"\xEF\xBB\xBFa: 1").unwrap(), parse("a: 1").unwrap());
+/// ```
+pub fn parse_bytes(bytes: &[u8]) -> Result<String> {
+	parse(&encoding::decode(bytes)?)
+}
+
+/// Read YAML file and parse it into a Lua table, see [`parse_bytes`] for supported encodings
+pub fn parse_file(path: impl AsRef<Path>) -> Result<String> {
+	parse_bytes(&fs::read(path)?)
+}
+
+/// Parse YAML read from any reader into a Lua table
+///
+/// Output is the same as returned by [`parse_bytes`] for the bytes read. The input is
+/// buffered, it's read to the end before anything is parsed, and encodings are detected
+/// like in [`parse_bytes`] with invalid text reported as [`Error::Encoding`]
+///
+/// ```rust
+/// use std::io::Cursor;
+/// use yaml2lua::parse_reader;
+///
+/// let lua = parse_reader(Cursor::new("a: 1")).unwrap();
+///
+/// assert_eq!(lua, "{\n\t[\"a\"] = 1,\n}");
+/// ```
+pub fn parse_reader(reader: impl Read) -> Result<String> {
+	let options = ParseOptions::default();
+	let root = transform::prepare_root(transform::load_reader(reader, &options)?, &options)?;
+
+	let mut emitter = Emitter::new(&options);
+	emitter.emit(&root)?;
+
+	Ok(emitter.into_output())
+}
+
+/// Parse YAML read from the reader and write the Lua table into the writer
+/// as it's generated, like [`parse_to_writer`]
+pub fn parse_reader_to(reader: impl Read, mut writer: impl Write) -> Result<()> {
+	let options = ParseOptions::default();
+	let root = transform::prepare_root(transform::load_reader(reader, &options)?, &options)?;
+
+	let mut emitter = Emitter::new(&options).with_sink(&mut writer);
+	emitter.emit(&root)?;
+	emitter.finish_sink()
+}
+
+/// Parse YAML string into a Lua table written into the writer as it's generated
+///
+/// Output is the same as returned by [`parse`], but it's written piece by piece
+/// instead of being built first, so converting large documents only keeps the parsed
+/// document in memory. Output written before a conversion fails stays in the writer
+///
+/// ```rust
+/// use yaml2lua::parse_to_writer;
+///
+/// let mut lua = Vec::new();
+/// parse_to_writer("a: 1", &mut lua).unwrap();
+///
+/// assert_eq!(lua, b"{\n\t[\"a\"] = 1,\n}");
+/// ```
+pub fn parse_to_writer(yaml: &str, writer: impl Write) -> Result<()> {
+	parse_to_writer_with_options(yaml, &ParseOptions::default(), writer)
+}
+
+/// Parse YAML string like [`parse_with_options`] into a Lua table written into
+/// the writer as it's generated, see [`parse_to_writer`]
+///
+/// Post-processing hooks need the whole output, so it's built first when there are any
+pub fn parse_to_writer_with_options(
+	yaml: &str,
+	options: &ParseOptions,
+	mut writer: impl Write,
+) -> Result<()> {
+	if !options.post_process.is_empty() {
+		writer.write_all(parse_with_options(yaml, options)?.as_bytes())?;
+		return Ok(());
+	}
+
+	let root = transform::prepare_root(transform::load_root(yaml, options)?, options)?;
+	let mut emitter = Emitter::new(options)
+		.with_source(yaml)
+		.with_sink(&mut writer);
 
-			lua.push_str(&get_indent(depth));
-			lua.push('}');
+	emitter.emit(&root)?;
+	emitter.finish_sink()
+}
+
+/// Deep merge multiple YAML documents and parse the result into a Lua table
+///
+/// Documents are merged from left to right: later scalars override earlier ones,
+/// mappings are merged recursively and sequences are replaced unless
+/// [`SequenceMerge::Concat`] is used. See [`MergeConflicts`] for what happens
+/// when a key is a table in one document and something else in another
+///
+/// Merged keys keep the position they have in the first document with them,
+/// keys only found in later documents come after all keys defined before them
+///
+/// Mappings under a tag at the root are merged keeping the tag of the latest document
+/// with one, other tagged roots replace the documents before them like scalars
+///
+/// ```rust
+/// use yaml2lua::{parse_merged, ParseOptions};
+///
+/// let base = "server: { host: localhost, port: 80 }";
+/// let production = "server: { host: example.com }";
+///
+/// let lua = parse_merged(&[base, production], &ParseOptions::new()).unwrap();
+///
+/// assert!(lua.contains(r#"["host"] = "example.com","#));
+/// assert!(lua.contains(r#"["port"] = 80,"#));
+/// ```
+pub fn parse_merged(docs: &[&str], options: &ParseOptions) -> Result<String> {
+	let mut merged: Option<transform::Root> = None;
+
+	for doc in docs {
+		let root = transform::load_root(doc, options)?;
+
+		match &mut merged {
+			Some(merged) => transform::merge_root(merged, root, options)?,
+			None => merged = Some(root),
 		}
 	}
 
-	lua.push_str(",\n");
+	let merged = merged.unwrap_or(transform::Root::Mapping(Mapping::new()));
+
+	let mut emitter = Emitter::new(options);
+	emitter.emit(&transform::prepare_root(merged, options)?)?;
+
+	options.finish(emitter.into_output(), None)
+}
+
+/// Read YAML files and deep merge them the same way as [`parse_merged`]
+pub fn parse_merged_files(paths: &[impl AsRef<Path>], options: &ParseOptions) -> Result<String> {
+	let docs = paths
+		.iter()
+		.map(fs::read_to_string)
+		.collect::<std::io::Result<Vec<_>>>()?;
+	let docs: Vec<&str> = docs.iter().map(String::as_str).collect();
+
+	parse_merged(&docs, options)
+}
+
+/// Compare two YAML documents, returning a Lua patch table describing the changes
+///
+/// The patch has three tables keyed by paths in the [`KeyPath`] syntax: `set` with
+/// new values of changed entries, `removed` listing paths of removed entries and
+/// `added` with values of new entries. Mappings are compared entry by entry, other
+/// values including sequences are replaced as a whole when anything in them differs
+///
+/// Both documents are prepared with the options like in [`parse_with_options`]. Keys
+/// of the patch are paths, so it's emitted only with the formatting options like
+/// the output mode, [`Indent`], quotes, trailing commas and the [`Dialect`]
+///
+/// ```rust
+/// use yaml2lua::{diff, ParseOptions};
+///
+/// let old = "server: { port: 8080, debug: true }";
+/// let new = "server: { port: 8081 }\nbeta: [a]";
+///
+/// assert_eq!(
+/// 	diff(old, new, &ParseOptions::new()).unwrap(),
+/// 	r#"{
+/// 	["set"] = {
+/// 		["server.port"] = 8081,
+/// 	},
+/// 	["removed"] = {
+/// 		"server.debug",
+/// 	},
+/// 	["added"] = {
+/// 		["beta"] = {
+/// 			"a",
+/// 		},
+/// 	},
+/// }"#
+/// );
+/// ```
+pub fn diff(old_yaml: &str, new_yaml: &str, options: &ParseOptions) -> Result<String> {
+	let old = transform::prepare(transform::load_with(old_yaml, options)?, options)?;
+	let new = transform::prepare(transform::load_with(new_yaml, options)?, options)?;
+
+	let options = ParseOptions {
+		output_mode: options.output_mode.clone(),
+		header: options.header.clone(),
+		dialect: options.dialect,
+		indent: options.indent,
+		omit_trailing_commas: options.omit_trailing_commas,
+		quote_style: options.quote_style,
+		compact: options.compact,
+		base_depth: options.base_depth,
+		final_newline: options.final_newline,
+		trailing_semicolon: options.trailing_semicolon,
+		post_process: options.post_process.clone(),
+		..ParseOptions::default()
+	};
+
+	let mut emitter = Emitter::new(&options);
+	emitter.emit_root(&diff::Patch::between(&old, &new).into_mapping())?;
+
+	options.finish(emitter.into_output(), None)
+}
+
+/// Convert a Lua literal back into YAML, like a table generated by [`parse`] and edited by hand
+///
+/// The literal can be prefixed with `return` or an assignment like `local t =`, other
+/// statements and expressions like function calls aren't supported. Tables with keys
+/// from 1 to their length become sequences and other tables become mappings with
+/// entries in the order they're written in, so empty tables become empty mappings.
+/// Comments are dropped and `nil` values are left out of tables like in Lua
+///
+/// ```rust
+/// use yaml2lua::unparse;
+///
+/// let yaml = unparse("return { name = 'lobby', players = { \"alice\", \"bob\" }, max = 4 }").unwrap();
+///
+/// assert_eq!(yaml, "name: lobby\nplayers:\n- alice\n- bob\nmax: 4\n");
+/// ```
+pub fn unparse(lua: &str) -> Result<String> {
+	Ok(serde_yaml::to_string(&unparse::to_value(lua)?)?)
+}
+
+/// Convert any serializable value into a Lua table, without writing it as YAML first
+///
+/// Output is the same as parsing the value serialized into YAML. Structs and maps
+/// become tables with entries in their serialization order, enum variants with
+/// data are tagged values like `!Variant` in YAML and unit variants are strings.
+/// The value has to serialize into a map unless there's a [`root_key`](ParseOptions::root_key)
+///
+/// ```rust
+/// #[derive(serde::Serialize)]
+/// struct Lobby {
+/// 	name: &'static str,
+/// 	players: Vec<&'static str>,
+/// }
+///
+/// let lobby = Lobby { name: "lobby", players: vec!["alice", "bob"] };
+///
+/// assert_eq!(
+/// 	yaml2lua::to_string(&lobby).unwrap(),
+/// 	yaml2lua::parse("name: lobby\nplayers: [alice, bob]").unwrap()
+/// );
+/// ```
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+	to_string_with_options(value, &ParseOptions::default())
+}
+
+/// Convert any serializable value into a Lua table like [`to_string`], using the provided options
+///
+/// Options that need the YAML source, like source comments and [`KeyStyle::MirrorSource`],
+/// write what they write for YAML without comments and quoted keys
+pub fn to_string_with_options<T: Serialize + ?Sized>(
+	value: &T,
+	options: &ParseOptions,
+) -> Result<String> {
+	let root = transform::prepare_root(transform::serialize_root(value, options)?, options)?;
+	let mut emitter = Emitter::new(options);
+
+	emitter.emit(&root)?;
+
+	options.finish(emitter.into_output(), None)
+}
 
-	lua
+/// Convert an already parsed YAML value into a Lua table, without writing it as YAML first
+///
+/// Output is the same as parsing the value written as YAML, like [`to_string`]
+/// for values that aren't [`Value`]s. Mappings are converted without copying them
+/// unless options transform the document, like [`ParseOptions::embedded_yaml`]
+///
+/// ```rust
+/// use serde_yaml::Value;
+///
+/// let mut value: Value = serde_yaml::from_str("name: lobby\nplayers: 2").unwrap();
+/// value["players"] = Value::from(4);
+///
+/// assert_eq!(
+/// 	yaml2lua::parse_value(&value).unwrap(),
+/// 	yaml2lua::parse("name: lobby\nplayers: 4").unwrap()
+/// );
+/// ```
+pub fn parse_value(value: &Value) -> Result<String> {
+	parse_value_with_options(value, &ParseOptions::default())
 }
 
-fn get_indent(depth: usize) -> String {
-	let mut indent = String::new();
+/// Convert an already parsed YAML value into a Lua table like [`parse_value`], using the provided options
+///
+/// Options that need the YAML source behave like they do for [`to_string_with_options`]
+pub fn parse_value_with_options(value: &Value, options: &ParseOptions) -> Result<String> {
+	let mut emitter = Emitter::new(options);
 
-	for _ in 0..depth {
-		indent.push('\t');
+	match value {
+		Value::Mapping(mapping) if !transform::changes_root(options) => {
+			transform::check(mapping, options)?;
+			emitter.emit_root(mapping)?;
+		}
+		value => {
+			let root = transform::value_root(value.clone(), options)?;
+			emitter.emit(&transform::prepare_root(root, options)?)?;
+		}
 	}
 
-	indent
+	options.finish(emitter.into_output(), None)
 }
 
-fn escape_string(string: &str) -> String {
-	let mut chars = string.chars();
+/// Parse only the part of YAML string selected by the key path
+///
+/// Path uses the same syntax as [`KeyPath`], e.g. `a.b[2].c` where `[2]` is
+/// a zero-based sequence index. Selected value can be a table or a scalar
+///
+/// ```rust
+/// use yaml2lua::parse_path;
+///
+/// let yaml = r#"
+/// features:
+///   experimental:
+///     flags: [a, b]
+/// "#;
+///
+/// assert_eq!(parse_path(yaml, "features.experimental.flags[1]").unwrap(), r#""b""#);
+/// ```
+pub fn parse_path(yaml: &str, path: &str) -> Result<String> {
+	let options = ParseOptions::default();
+	let path: KeyPath = path.parse()?;
+
+	let root = Value::Mapping(transform::prepare(
+		transform::load_with(yaml, &options)?,
+		&options,
+	)?);
+	let value = path.select(&root)?;
 
-	while let Some(char) = chars.next() {
-		if char == '\\' {
-			if let Some(next_char) = chars.next() {
-				match next_char {
-					'n' | 't' | 'r' | '\\' | '"' => {}
-					_ => {
-						return string.escape_default().to_string();
-					}
-				}
-			} else {
-				return string.escape_default().to_string();
-			}
-		} else {
-			match char {
-				'\n' | '\t' | '\r' | '"' => {
-					return string.escape_default().to_string();
-				}
-				_ => {}
+	let mut emitter = Emitter::new(&options).at(path);
+	emitter.emit_value(value)?;
+
+	Ok(emitter.into_output())
+}
+
+/// Compute a SHA-256 digest of the YAML document's structure for cache invalidation
+///
+/// The digest covers keys, values, their types and order, but not formatting
+/// of the source like comments, quoting or indentation. Canonical encoding is
+/// versioned and only changes in a new major version of this crate:
+///
+/// - null is `n`, booleans are `t` and `f`
+/// - integers are `i` followed by their decimal digits and `;`
+/// - floats are `d` followed by the 8 big-endian bytes of the IEEE 754 double
+/// - strings are `s` followed by the length and UTF-8 bytes
+/// - sequences are `[` followed by the length and the elements
+/// - mappings are `{` followed by the number of entries and keys with values in document order
+/// - tagged values are `!` followed by the tag encoded like a string and the value
+///
+/// Lengths are 64-bit big-endian integers and the encoding starts with `yaml2lua-digest-v1`
+/// followed by a zero byte
+///
+/// ```rust
+/// use yaml2lua::digest;
+///
+/// assert_eq!(digest("a: 1 # comment").unwrap(), digest("{ \"a\": 1 }").unwrap());
+/// assert_ne!(digest("a: 1").unwrap(), digest("a: 2").unwrap());
+/// ```
+pub fn digest(yaml: &str) -> Result<[u8; 32]> {
+	Ok(digest::digest(&transform::load_root(
+		yaml,
+		&ParseOptions::default(),
+	)?))
+}
+
+/// Parse YAML string into a Lua table like [`parse_with_options`], also returning its [`digest`]
+///
+/// Digest only depends on the [`root_key`](ParseOptions::root_key) option that puts sequence
+/// and scalar roots under a key, so include the other options in cache keys if they can change
+pub fn parse_with_digest(yaml: &str, options: &ParseOptions) -> Result<(String, [u8; 32])> {
+	let root = transform::load_root(yaml, options)?;
+
+	// Strings forced by the options aren't part of the document's structure
+	let digest = if options.force_string_paths.is_empty() {
+		digest::digest(&root)
+	} else {
+		let options = ParseOptions {
+			root_key: options.root_key.clone(),
+			..ParseOptions::default()
+		};
+
+		digest::digest(&transform::load_root(yaml, &options)?)
+	};
+
+	let mut emitter = Emitter::new(options).with_source(yaml);
+	emitter.emit(&transform::prepare_root(root, options)?)?;
+
+	Ok((options.finish(emitter.into_output(), None)?, digest))
+}
+
+/// Parse every top-level entry of YAML string into a separate `return ...` module,
+/// returning pairs of keys and module code in document order
+///
+/// Keys have to be strings usable as file names on every platform, which means
+/// no path separators, control characters or any of `<>:"|?*`, see [`split_to_dir`]
+///
+/// Tagged roots can't be split, because the tag has no module to apply to
+///
+/// ```rust
+/// use yaml2lua::{parse_split, ParseOptions};
+///
+/// let modules = parse_split("weapons: [sword]\nmaps: {}", &ParseOptions::new()).unwrap();
+///
+/// assert_eq!(modules[0], ("weapons".into(), "return {\n\t\"sword\",\n}".into()));
+/// assert_eq!(modules[1], ("maps".into(), "return {\n}".into()));
+/// ```
+pub fn parse_split(yaml: &str, options: &ParseOptions) -> Result<Vec<(String, String)>> {
+	let options = options.clone().output_mode(OutputMode::Module);
+	let transform::Root::Mapping(root) = transform::load_root(yaml, &options)? else {
+		return Err(Error::Options(String::from(
+			"splitting into modules requires an untagged root",
+		)));
+	};
+	let root = transform::prepare(root, &options)?;
+
+	root.iter()
+		.map(|(key, value)| {
+			let name = module_name(key)?;
+			let mut emitter = Emitter::new(&options)
+				.with_source(yaml)
+				.at(KeyPath::from(vec![PathSegment::from_key(key)]));
+
+			match value {
+				Value::Mapping(mapping) => emitter.emit_root(mapping)?,
+				value => emitter.emit_value(value)?,
 			}
-		}
+
+			Ok((name, options.finish(emitter.into_output(), None)?))
+		})
+		.collect()
+}
+
+fn module_name(key: &Value) -> Result<String> {
+	let invalid = |message: &str| Error::ModuleName {
+		key: match key {
+			Value::String(s) => format!("\"{}\"", s),
+			key => format!("of type {}", emit::type_name(key)),
+		},
+		message: message.to_owned(),
+	};
+
+	let Value::String(name) = key else {
+		return Err(invalid("only string keys can be module names"));
+	};
+
+	if name.is_empty() || name == "." || name == ".." {
+		return Err(invalid("name is empty or refers to a directory"));
+	}
+
+	if name.chars().any(|c| {
+		c.is_control() || matches!(c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*')
+	}) {
+		return Err(invalid(
+			"name contains characters that aren't allowed in file names",
+		));
 	}
 
-	string.to_owned()
+	Ok(name.clone())
+}
+
+/// Parse YAML string into a Lua table, also returning statistics about the conversion
+///
+/// ```rust
+/// use yaml2lua::{parse_with_stats, ParseOptions};
+///
+/// let (lua, stats) = parse_with_stats("a: [1, 2]", &ParseOptions::new()).unwrap();
+///
+/// assert_eq!(stats.integers, 2);
+/// assert_eq!(stats.tables, 2);
+/// assert_eq!(stats.output_bytes, lua.len());
+/// ```
+pub fn parse_with_stats(yaml: &str, options: &ParseOptions) -> Result<(String, Stats)> {
+	let root = transform::prepare_root(transform::load_root(yaml, options)?, options)?;
+	let mut emitter = Emitter::new(options).with_source(yaml).with_stats();
+
+	emitter.emit(&root)?;
+
+	let (lua, _, stats) = emitter.into_parts();
+	let lua = options.finish(lua, None)?;
+
+	Ok((lua, stats))
 }
 
 #[cfg(test)]
 mod test {
-	#[test]
-	fn all_values() {
-		use crate::parse;
-
-		let yaml = r#"
+	const ALL_VALUES: &str = r#"
 string: str
 int: 420
 float: 4.2
@@ -201,6 +878,10 @@ array:
 object:
   key: value"#;
 
+	#[test]
+	fn all_values() {
+		use crate::parse;
+
 		let lua = r#"{
 	["string"] = "str",
 	["int"] = 420,
@@ -220,7 +901,7 @@ object:
 	},
 }"#;
 
-		assert_eq!(parse(yaml).unwrap(), lua);
+		assert_eq!(parse(ALL_VALUES).unwrap(), lua);
 	}
 
 	#[test]
@@ -271,4 +952,4266 @@ object:
 
 		assert_eq!(parse(yaml).unwrap(), lua);
 	}
+
+	#[test]
+	fn numeric_string_keys() {
+		use crate::{parse_with_options, NumericKeys, ParseOptions};
+
+		let yaml = r#"
+"1": foo
+"2": bar
+"3":
+  "1": nested
+"01": zero
+"-1": sign
+"1.5": float"#;
+
+		let lua = r#"{
+	[1] = "foo",
+	[2] = "bar",
+	[3] = {
+		[1] = "nested",
+	},
+	["01"] = "zero",
+	["-1"] = "sign",
+	["1.5"] = "float",
+}"#;
+
+		let options = ParseOptions::new().numeric_keys(NumericKeys::default());
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		let options = ParseOptions::new().numeric_keys(NumericKeys {
+			floats: true,
+			leading_zeros: false,
+			signs: true,
+			whitespace: false,
+		});
+
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.contains("\t[-1] = \"sign\""));
+		assert!(lua.contains("\t[1.5] = \"float\""));
+	}
+
+	#[test]
+	fn numeric_key_collision() {
+		use crate::{parse_with_options, Error, NumericKeys, ParseOptions};
+
+		let options = ParseOptions::new().numeric_keys(NumericKeys::default());
+
+		assert!(matches!(
+			parse_with_options("1: a\n\"1\": b", &options),
+			Err(Error::KeyCollision { .. })
+		));
+	}
+
+	#[test]
+	fn float_precision() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let yaml = r#"
+x: 12.300000190734863
+even: 0.125
+odd: 0.135
+carry: 9.996
+whole: 2.5
+int: 123456
+big: 1e300
+nan: .nan
+1.23456: key"#;
+
+		let lua = r#"{
+	["x"] = 12.3,
+	["even"] = 0.12,
+	["odd"] = 0.14,
+	["carry"] = 10.0,
+	["whole"] = 2.5,
+	["int"] = 123456,
+	["big"] = 1e300,
+	["nan"] = 0/0,
+	[1.23] = "key",
+}"#;
+
+		let options = ParseOptions::new().float_precision(2);
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn diagnostics() {
+		use crate::{parse_with_diagnostics, DiagnosticKind, Error, ParseOptions, Severity};
+
+		let yaml = r#"
+a:
+  ? [1, 2]
+  : skipped
+  b: 18446744073709551615
+c: !Tag 1"#;
+
+		let (lua, diagnostics) = parse_with_diagnostics(yaml, &ParseOptions::new()).unwrap();
+
+		let lua_expected = r#"{
+	["a"] = {
+		["b"] = 18446744073709551615,
+	},
+	["c"] = {
+		["Tag"] = 1,
+	},
+}"#;
+
+		assert_eq!(lua, lua_expected);
+
+		let found: Vec<_> = diagnostics
+			.iter()
+			.map(|d| (d.severity, d.kind, d.path.to_string()))
+			.collect();
+
+		assert_eq!(
+			found,
+			vec![
+				(
+					Severity::Warning,
+					DiagnosticKind::UnsupportedKey,
+					String::from("a.?")
+				),
+				(
+					Severity::Warning,
+					DiagnosticKind::LargeInteger,
+					String::from("a.b")
+				),
+				(
+					Severity::Warning,
+					DiagnosticKind::UnknownTag,
+					String::from("c")
+				),
+			]
+		);
+
+		let options = ParseOptions::new().warnings_as_errors(true);
+
+		assert!(matches!(
+			parse_with_diagnostics(yaml, &options),
+			Err(Error::UnsupportedKey { key, .. }) if key == "sequence"
+		));
+	}
+
+	#[test]
+	fn unsupported_keys() {
+		use crate::{parse_with_options, Error, ErrorKind, Location, ParseOptions};
+
+		let options = ParseOptions::new().warnings_as_errors(true);
+		let yaml = "a:\n  b: 1\n  .nan: 2";
+		let err = parse_with_options(yaml, &options).unwrap_err();
+
+		assert_eq!(err.kind(), ErrorKind::UnsupportedKey);
+		assert!(matches!(&err, Error::UnsupportedKey { key, .. } if key == ".nan"));
+		assert_eq!(
+			err.locate(yaml),
+			Some(Location {
+				line: 3,
+				column: 3,
+				index: 12
+			})
+		);
+
+		// Lenient conversions skip the entry
+		assert_eq!(
+			parse_with_options(yaml, &ParseOptions::new()).unwrap(),
+			"{\n\t[\"a\"] = {\n\t\t[\"b\"] = 1,\n\t},\n}"
+		);
+	}
+
+	#[test]
+	fn stats() {
+		use crate::{parse_with_stats, ParseOptions, Stats};
+
+		let (lua, stats) = parse_with_stats(ALL_VALUES, &ParseOptions::new()).unwrap();
+
+		assert_eq!(
+			stats,
+			Stats {
+				strings: 4,
+				integers: 2,
+				floats: 1,
+				bools: 2,
+				nils: 1,
+				tables: 4,
+				tagged_values: 0,
+				max_depth: 3,
+				string_bytes: 15,
+				elided_entries: 0,
+				output_bytes: lua.len(),
+			}
+		);
+
+		let json = serde_json::to_value(&stats).unwrap();
+
+		assert_eq!(json["max_depth"], 3);
+	}
+
+	#[test]
+	fn schema_coercion() {
+		use crate::{
+			parse_with_diagnostics, DiagnosticKind, Error, ParseOptions, ScalarType, Schema,
+		};
+
+		let yaml = r#"
+retries: "3"
+servers:
+  - port: "8080"
+  - port: "eighty"
+nested:
+  deep:
+    debug: "true"
+version: 1.5"#;
+
+		let schema = Schema::new()
+			.field("retries", ScalarType::Int)
+			.unwrap()
+			.field("servers[*].port", ScalarType::Int)
+			.unwrap()
+			.field("**.debug", ScalarType::Bool)
+			.unwrap()
+			.field("version", ScalarType::String)
+			.unwrap();
+
+		let lua = r#"{
+	["retries"] = 3,
+	["servers"] = {
+		{
+			["port"] = 8080,
+		},
+		{
+			["port"] = "eighty",
+		},
+	},
+	["nested"] = {
+		["deep"] = {
+			["debug"] = true,
+		},
+	},
+	["version"] = "1.5",
+}"#;
+
+		let (output, diagnostics) =
+			parse_with_diagnostics(yaml, &ParseOptions::new().schema(schema)).unwrap();
+
+		assert_eq!(output, lua);
+		assert_eq!(diagnostics.len(), 1);
+		assert_eq!(diagnostics[0].kind, DiagnosticKind::Coercion);
+		assert_eq!(diagnostics[0].path.to_string(), "servers[1].port");
+
+		assert!(matches!(
+			Schema::new().field("servers[x]", ScalarType::Int),
+			Err(Error::InvalidPath { .. })
+		));
+	}
+
+	#[test]
+	fn key_style() {
+		use crate::{parse_with_options, KeyStyle, ParseOptions};
+
+		let yaml = r#"
+max-retries: 1
+HTTPServer: 2
+nested:
+  some_key: 3"#;
+
+		let lua = r#"{
+	["maxRetries"] = 1,
+	["httpServer"] = 2,
+	["nested"] = {
+		["someKey"] = 3,
+	},
+}"#;
+
+		let options = ParseOptions::new().key_style(KeyStyle::CamelCase);
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+	}
+
+	#[test]
+	fn key_style_collision() {
+		use crate::{
+			parse_with_diagnostics, DiagnosticKind, DuplicateKeys, Error, KeyStyle, NumericKeys,
+			ParseOptions,
+		};
+
+		let yaml = "max-retries: 1\nmax_retries: 2";
+		let options = ParseOptions::new().key_style(KeyStyle::SnakeCase);
+
+		let err = parse_with_diagnostics(yaml, &options).unwrap_err();
+
+		assert!(matches!(err, Error::KeyCollision { .. }));
+		assert_eq!(
+			err.to_string(),
+			r#"keys "max-retries" and "max_retries" both resolve to ["max_retries"]"#
+		);
+
+		let options = options.duplicate_keys(DuplicateKeys::Warn);
+		let (_, diagnostics) = parse_with_diagnostics(yaml, &options).unwrap();
+
+		assert_eq!(diagnostics[0].kind, DiagnosticKind::KeyCollision);
+
+		let options = ParseOptions::new().numeric_keys(NumericKeys::default());
+
+		assert!(matches!(
+			parse_with_diagnostics("a:\n  1: x\n  \"1\": y", &options),
+			Err(Error::KeyCollision { path, .. }) if path.to_string() == "a"
+		));
+
+		for (yaml, message) in [
+			("1: a\n1.0: b", "keys 1 and 1.0 both resolve to [1]"),
+			("1.0: a\n1: b", "keys 1.0 and 1 both resolve to [1]"),
+		] {
+			let err = parse_with_diagnostics(yaml, &ParseOptions::new()).unwrap_err();
+
+			assert_eq!(err.to_string(), message);
+		}
+	}
+
+	#[test]
+	fn sanitized_identifiers() {
+		use crate::{parse_with_diagnostics, Error, KeyStyle, ParseOptions};
+
+		let yaml = r#"
+some key: 1
+2d: 2
+end: 3
+valid_key: 4
+5: 5
+true: 6"#;
+
+		let lua = r#"{
+	some_key = 1,
+	_2d = 2,
+	end_ = 3,
+	valid_key = 4,
+	[5] = 5,
+	[true] = 6,
+	__original_keys = {
+		some_key = "some key",
+		_2d = "2d",
+		end_ = "end",
+	},
+}"#;
+
+		let options = ParseOptions::new()
+			.key_style(KeyStyle::SanitizedIdentifiers)
+			.original_keys_table(true);
+
+		let (output, diagnostics) = parse_with_diagnostics(yaml, &options).unwrap();
+
+		assert_eq!(output, lua);
+		assert_eq!(diagnostics.len(), 3);
+
+		assert!(matches!(
+			parse_with_diagnostics("a b: 1\na-b: 2", &options),
+			Err(Error::KeyCollision { .. })
+		));
+	}
+
+	#[test]
+	fn flatten() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let yaml = r#"
+server:
+  host: x
+  ports: [80, 443]
+  tags: {}
+deep:
+  a:
+    b:
+      c: true"#;
+
+		let lua = r#"{
+	["server.host"] = "x",
+	["server.ports.1"] = 80,
+	["server.ports.2"] = 443,
+	["server.tags"] = {
+	},
+	["deep.a.b.c"] = true,
+}"#;
+
+		let options = ParseOptions::new().flatten(".");
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		assert!(matches!(
+			parse_with_options("a:\n  b.c: 1", &options),
+			Err(Error::Transform { path, .. }) if path.to_string() == r#"a["b.c"]"#
+		));
+	}
+
+	#[test]
+	fn expand_dotted_keys() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let yaml = r#"
+server.host: x
+server.port: 80
+server:
+  tls.enabled: true
+a\.b: escaped"#;
+
+		let lua = r#"{
+	["server"] = {
+		["host"] = "x",
+		["port"] = 80,
+		["tls"] = {
+			["enabled"] = true,
+		},
+	},
+	["a.b"] = "escaped",
+}"#;
+
+		let options = ParseOptions::new().expand_dotted_keys(".");
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		let err = parse_with_options("a: 1\na.b: 2", &options).unwrap_err();
+
+		assert!(matches!(err, Error::Transform { .. }));
+		assert_eq!(err.to_string(), r#"key "a.b" conflicts with key "a""#);
+
+		for (yaml, message) in [
+			("a.b: 1\na: 2", r#"key "a" conflicts with key "a.b""#),
+			("a.b: 1\na: {b: 2}", r#"key "a" conflicts with key "a.b""#),
+			("a: {b: 1}\na.b: 2", r#"key "a.b" conflicts with key "a""#),
+			(
+				"x.a.b: 1\nx.a: 2",
+				r#"key "x.a" conflicts with key "x.a.b""#,
+			),
+		] {
+			assert_eq!(
+				parse_with_options(yaml, &options).unwrap_err().to_string(),
+				message
+			);
+		}
+	}
+
+	#[test]
+	fn parse_path() {
+		use crate::parse_path;
+
+		let yaml = r#"
+features:
+  experimental:
+    list:
+      - a
+      - x: 1
+  stable: true"#;
+
+		let lua = r#"{
+	["x"] = 1,
+}"#;
+
+		assert_eq!(
+			parse_path(yaml, "features.experimental.list[1]").unwrap(),
+			lua
+		);
+		assert_eq!(parse_path(yaml, "features.stable").unwrap(), "true");
+
+		assert_eq!(
+			parse_path(yaml, "features.beta").unwrap_err().to_string(),
+			"`beta` not found at `features`, available: experimental, stable"
+		);
+		assert_eq!(
+			parse_path(yaml, "features.experimental.list[5]")
+				.unwrap_err()
+				.to_string(),
+			"`[5]` not found at `features.experimental.list`, available: [0]..[1]"
+		);
+	}
+
+	#[test]
+	fn parse_merged() {
+		use crate::{parse_merged, Error, MergeConflicts, ParseOptions, SequenceMerge};
+
+		let base = r#"
+server:
+  host: localhost
+  port: 80
+  tls:
+    enabled: false
+hosts: [a, b]"#;
+
+		let production = r#"
+server:
+  host: example.com
+  tls:
+    enabled: true
+hosts: [c]"#;
+
+		let local = r#"
+server:
+  port: 8080
+debug: true"#;
+
+		let lua = r#"{
+	["server"] = {
+		["host"] = "example.com",
+		["port"] = 8080,
+		["tls"] = {
+			["enabled"] = true,
+		},
+	},
+	["hosts"] = {
+		"c",
+	},
+	["debug"] = true,
+}"#;
+
+		let docs = [base, production, local];
+
+		assert_eq!(parse_merged(&docs, &ParseOptions::new()).unwrap(), lua);
+
+		let options = ParseOptions::new().merge_sequences(SequenceMerge::Concat);
+		let lua = parse_merged(&docs, &options).unwrap();
+
+		assert!(lua.contains("\t[\"hosts\"] = {\n\t\t\"a\",\n\t\t\"b\",\n\t\t\"c\",\n\t},"));
+
+		let options = ParseOptions::new().merge_conflicts(MergeConflicts::Error);
+
+		assert!(matches!(
+			parse_merged(&[base, "server: down"], &options),
+			Err(Error::Transform { path, .. }) if path.to_string() == "server"
+		));
+	}
+
+	#[test]
+	fn chunked_output() {
+		use crate::{parse_with_options, Error, OutputMode, ParseOptions};
+
+		let yaml = r#"
+name: big
+items:
+  - a
+  - b
+  - nested: [1, 2, 3]
+small: [x]"#;
+
+		let lua = r#"local t = {}
+t["name"] = "big"
+t["items"] = {}
+t["items"][1] = "a"
+t["items"][2] = "b"
+t["items"][3] = {}
+t["items"][3]["nested"] = {}
+t["items"][3]["nested"][1] = 1
+t["items"][3]["nested"][2] = 2
+t["items"][3]["nested"][3] = 3
+t["small"] = {
+	"x",
+}
+return t"#;
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.chunk_threshold(2);
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		let options = options.output_mode(OutputMode::Assignment(String::from("Config")));
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.starts_with("Config = {}\nConfig[\"name\"] = \"big\"\n"));
+		assert!(lua.ends_with("Config[\"small\"] = {\n\t\"x\",\n}"));
+
+		let options = ParseOptions::new().chunk_threshold(2);
+
+		assert!(matches!(
+			parse_with_options(yaml, &options),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn dedupe_strings() {
+		use crate::{parse_with_options, Error, OutputMode, ParseOptions, StringDedup};
+
+		let yaml = r#"
+a: https://example.com
+b: [category, https://example.com, category]
+c:
+  d: category
+  e: short
+  f: short"#;
+
+		let lua = r#"local __s1 = "https://example.com"
+local __s2 = "category"
+
+return {
+	["a"] = __s1,
+	["b"] = {
+		__s2,
+		__s1,
+		__s2,
+	},
+	["c"] = {
+		["d"] = __s2,
+		["e"] = "short",
+		["f"] = "short",
+	},
+}"#;
+
+		let dedup = StringDedup {
+			min_count: 2,
+			min_len: 6,
+			max_locals: 10,
+		};
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.dedupe_strings(dedup);
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		let options = options.dedupe_strings(StringDedup {
+			max_locals: 1,
+			..dedup
+		});
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.starts_with("local __s1 = \"https://example.com\"\n\nreturn {"));
+
+		let options = ParseOptions::new().dedupe_strings(dedup);
+
+		assert!(matches!(
+			parse_with_options(yaml, &options),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn dedupe_subtrees() {
+		use crate::{parse_with_options, OutputMode, ParseOptions, SubtreeDedup};
+
+		let yaml = r#"
+a: { x: 1, y: 2 }
+b: [{ x: 1, y: 2 }, 3]
+c: { x: 1 }
+d: { x: 1 }"#;
+
+		let lua = r#"local __shared1 = {
+	["x"] = 1,
+	["y"] = 2,
+}
+
+return {
+	["a"] = __shared1,
+	["b"] = {
+		__shared1,
+		3,
+	},
+	["c"] = {
+		["x"] = 1,
+	},
+	["d"] = {
+		["x"] = 1,
+	},
+}"#;
+
+		let dedup = SubtreeDedup {
+			min_size: 2,
+			..SubtreeDedup::default()
+		};
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.dedupe_subtrees(dedup);
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		let options = options.dedupe_subtrees(SubtreeDedup {
+			copy: true,
+			..dedup
+		});
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		let state = mlua::Lua::new();
+		let data: mlua::Table = state.load(&lua).eval().unwrap();
+		let a: mlua::Table = data.get("a").unwrap();
+		let b: mlua::Table = data.get("b").unwrap();
+		let b: mlua::Table = b.get(1).unwrap();
+
+		assert_eq!(a.get::<i64>("y").unwrap(), 2);
+		assert_eq!(b.get::<i64>("y").unwrap(), 2);
+		assert_ne!(a.to_pointer(), b.to_pointer());
+	}
+
+	#[test]
+	fn fold_single_key_maps() {
+		use crate::{
+			parse_with_diagnostics, parse_with_options, DiagnosticKind, DuplicateKeys, Error,
+			ParseOptions,
+		};
+
+		let yaml = r#"
+steps:
+  - first: 1
+  - second: { a: 2 }
+mixed: [{ a: 1 }, 2]"#;
+
+		let lua = r#"{
+	["steps"] = {
+		["first"] = 1,
+		["second"] = {
+			["a"] = 2,
+		},
+	},
+	["mixed"] = {
+		{
+			["a"] = 1,
+		},
+		2,
+	},
+}"#;
+
+		let options = ParseOptions::new().fold_single_key_maps(true);
+		let (output, diagnostics) = parse_with_diagnostics(yaml, &options).unwrap();
+
+		assert_eq!(output, lua);
+		assert_eq!(diagnostics.len(), 1);
+		assert_eq!(diagnostics[0].kind, DiagnosticKind::UnfoldedSequence);
+		assert_eq!(diagnostics[0].path.to_string(), "mixed");
+
+		let yaml = "steps: [a: 1, a: 2]";
+
+		assert!(matches!(
+			parse_with_options(yaml, &options),
+			Err(Error::KeyCollision { .. })
+		));
+
+		let options = options.duplicate_keys(DuplicateKeys::Warn);
+
+		assert!(parse_with_options(yaml, &options).is_ok());
+	}
+
+	#[test]
+	fn base_depth() {
+		use crate::{parse_with_options, OutputMode, ParseOptions};
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.base_depth(1);
+		let lua = parse_with_options("a: 1\nb: [x]", &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"\treturn {\n\t\t[\"a\"] = 1,\n\t\t[\"b\"] = {\n\t\t\t\"x\",\n\t\t},\n\t}"
+		);
+
+		let options = options.output_mode(OutputMode::Assignment(String::from("local t")));
+		let lua = parse_with_options("a: 1", &options).unwrap();
+		let chunk = format!(
+			"local function get()\n{}\n\treturn t\nend\n\nreturn get()",
+			lua
+		);
+
+		assert_eq!(
+			chunk,
+			"local function get()\n\tlocal t = {\n\t\t[\"a\"] = 1,\n\t}\n\treturn t\nend\n\nreturn get()"
+		);
+
+		let state = mlua::Lua::new();
+		let table: mlua::Table = state.load(&chunk).eval().unwrap();
+
+		assert_eq!(table.get::<i64>("a").unwrap(), 1);
+	}
+
+	#[test]
+	fn key_type_collisions() {
+		use crate::{parse_with_diagnostics, DiagnosticKind, KeyComparison, ParseOptions};
+
+		let yaml = r#"
+1: a
+"1": b
+2.0: c
+"2": d
+table:
+  true: e
+  "true": f"#;
+
+		let messages = |comparison| {
+			let options = ParseOptions::new().key_type_collisions(comparison);
+			let (_, diagnostics) = parse_with_diagnostics(yaml, &options).unwrap();
+
+			diagnostics
+				.into_iter()
+				.filter(|d| d.kind == DiagnosticKind::KeyTypeCollision)
+				.map(|d| d.to_string())
+				.collect::<Vec<_>>()
+		};
+
+		assert!(messages(KeyComparison::None).is_empty());
+		assert_eq!(
+			messages(KeyComparison::String),
+			[
+				"keys 1 and \"1\" are equal when compared as strings",
+				"keys true and \"true\" are equal when compared as strings at `table`",
+			]
+		);
+		assert_eq!(
+			messages(KeyComparison::Tostring),
+			[
+				"keys 1 and \"1\" are equal when compared with tostring",
+				"keys 2.0 and \"2\" are equal when compared with tostring",
+				"keys true and \"true\" are equal when compared with tostring at `table`",
+			]
+		);
+	}
+
+	#[test]
+	fn sequence_length_field() {
+		use crate::{parse_with_options, OutputMode, ParseOptions};
+
+		let options = ParseOptions::new().sequence_length_field("n");
+		let lua = parse_with_options("a: [x, null, z]", &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"{\n\t[\"a\"] = {\n\t\t[\"n\"] = 3,\n\t\t\"x\",\n\t\tnil,\n\t\t\"z\",\n\t},\n}"
+		);
+
+		let options = options.output_mode(OutputMode::Module).chunk_threshold(1);
+		let lua = parse_with_options("a: [x, null, z]", &options).unwrap();
+
+		let state = mlua::Lua::new();
+		let table: mlua::Table = state.load(&lua).eval().unwrap();
+		let a: mlua::Table = table.get("a").unwrap();
+
+		assert_eq!(a.get::<i64>("n").unwrap(), 3);
+		assert_eq!(a.get::<String>(3).unwrap(), "z");
+		assert!(lua.find(" = 3\n").unwrap() < lua.find("\"x\"").unwrap());
+	}
+
+	#[test]
+	fn table_layout() {
+		use crate::{parse_with_options, Error, KeyStyle, ParseOptions, TableLayout};
+
+		let yaml = "list: [a, b]\nmy key: 1";
+		let options = ParseOptions::new()
+			.sequence_length_field("n")
+			.key_style(KeyStyle::SanitizedIdentifiers)
+			.original_keys_table(true);
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			"{\n\tlist = {\n\t\t[\"n\"] = 2,\n\t\t\"a\",\n\t\t\"b\",\n\t},\n\tmy_key = 1,\n\t__original_keys = {\n\t\tmy_key = \"my key\",\n\t},\n}"
+		);
+
+		let interleaved = options.clone().table_layout(TableLayout::Interleaved);
+
+		assert_eq!(
+			parse_with_options(yaml, &interleaved).unwrap(),
+			"{\n\tlist = {\n\t\t[\"n\"] = 2,\n\t\t\"a\",\n\t\t\"b\",\n\t},\n\tmy_key = 1,\n\t__original_keys = {\n\t\tmy_key = \"my key\",\n\t},\n}"
+		);
+
+		let err = parse_with_options("my key: 1\n__original_keys: 2", &options).unwrap_err();
+		assert!(matches!(err, Error::KeyCollision { .. }), "{}", err);
+	}
+
+	#[test]
+	fn mirror_source_keys() {
+		use crate::{parse_with_options, KeyStyle, ParseOptions};
+
+		let yaml = r#"
+bare: 1
+"quoted": 2
+'single': 3
+10: 4
+"20": 5
+not valid: 6
+end: 7
+list:
+  - { inner: a, "other": b }
+"#;
+
+		let options = ParseOptions::new().key_style(KeyStyle::MirrorSource);
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			r#"{
+	bare = 1,
+	["quoted"] = 2,
+	["single"] = 3,
+	[10] = 4,
+	["20"] = 5,
+	["not valid"] = 6,
+	["end"] = 7,
+	list = {
+		{
+			inner = "a",
+			["other"] = "b",
+		},
+	},
+}"#
+		);
+
+		let yaml = "a: &x { plain: 1, \"quoted\": 2, nested: &y [{ deep: 3 }] }\nb: *x\nc: [*y]";
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			r#"{
+	a = {
+		plain = 1,
+		["quoted"] = 2,
+		nested = {
+			{
+				deep = 3,
+			},
+		},
+	},
+	b = {
+		plain = 1,
+		["quoted"] = 2,
+		nested = {
+			{
+				deep = 3,
+			},
+		},
+	},
+	c = {
+		{
+			{
+				deep = 3,
+			},
+		},
+	},
+}"#
+		);
+	}
+
+	#[test]
+	fn parse_partial() {
+		use crate::{parse_partial, EmbeddedYaml, Error, KeyStyle, ParseOptions};
+
+		let yaml = "ok: 1\nbroken: !yaml \"a: [1\"\nnested:\n  fine: !yaml \"b: 2\"\n  clash: { a-b: 1, a_b: 2 }\nlast: true";
+		let options = ParseOptions::new()
+			.embedded_yaml(EmbeddedYaml::default())
+			.key_style(KeyStyle::SnakeCase)
+			.error_placeholder("{}");
+
+		let result = parse_partial(yaml, &options).unwrap();
+
+		assert!(!result.is_complete());
+		assert!(matches!(result.errors[0], Error::EmbeddedYaml { .. }));
+		assert!(matches!(result.errors[1], Error::KeyCollision { .. }));
+
+		let lines: Vec<&str> = result.lua.lines().collect();
+
+		assert_eq!(lines[1], "\t[\"ok\"] = 1,");
+		assert!(lines[2].starts_with("\t[\"broken\"] = {}, -- ERROR: embedded YAML at `broken`"));
+		assert_eq!(lines[4], "\t\t[\"fine\"] = {");
+		assert!(lines[7].starts_with("\t\t[\"clash\"] = {}, -- ERROR: keys"));
+		assert_eq!(lines[9], "\t[\"last\"] = true,");
+
+		assert!(parse_partial("a: [1", &options).is_err());
+		assert!(parse_partial("a: 1", &options)
+			.unwrap()
+			.into_result()
+			.is_ok());
+	}
+
+	#[test]
+	fn source_comments() {
+		use crate::{parse_with_options, Comments, ParseOptions, SourceComments};
+
+		let yaml = r#"# server settings
+max_players: 20
+
+world:
+  name: !Name lobby
+
+  spawn: [0, 64, 0]
+tags:
+  - pvp
+"#;
+
+		let options = ParseOptions::new()
+			.comments(Comments::Tags)
+			.source_comments(SourceComments {
+				file: Some(String::from("config/server.yaml")),
+				max_depth: 2,
+			});
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			r#"{
+	["max_players"] = 20, -- config/server.yaml:2
+	["world"] = {
+		["name"] = {
+			["Name"] = "lobby",
+		}, -- !Name config/server.yaml:5
+		["spawn"] = {
+			0,
+			64,
+			0,
+		}, -- config/server.yaml:7
+	}, -- config/server.yaml:4
+	["tags"] = {
+		"pvp", -- config/server.yaml:9
+	}, -- config/server.yaml:8
+}"#
+		);
+
+		let options = ParseOptions::new().source_comments(SourceComments::default());
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.contains("\t\t},\n\t}, -- line 4\n"));
+	}
+
+	#[test]
+	fn wrapped_scalars() {
+		use crate::{
+			parse_with_diagnostics, DiagnosticKind, Dialect, FidelityMode, ParseOptions,
+			WrapperFields,
+		};
+
+		let options = ParseOptions::new().fidelity(FidelityMode::Wrapped(WrapperFields {
+			type_field: String::from("kind"),
+			value_field: String::from("raw"),
+		}));
+
+		let wrapped = |yaml: &str, options: &ParseOptions| {
+			let (lua, diagnostics) = parse_with_diagnostics(yaml, options).unwrap();
+			let kinds: Vec<DiagnosticKind> = diagnostics.iter().map(|d| d.kind).collect();
+
+			(lua, kinds)
+		};
+
+		let (lua, kinds) = wrapped("a: 18446744073709551615\nb: -1", &options);
+		assert!(lua.contains("\t\tkind = \"u64\",\n\t\traw = \"18446744073709551615\",\n"));
+		assert!(lua.contains("[\"b\"] = -1,"));
+		assert_eq!(kinds, [DiagnosticKind::WrappedScalar]);
+
+		let luau = options.clone().dialect(Dialect::Luau);
+		let (lua, kinds) = wrapped("a: 9007199254740993\nb: 9007199254740992", &luau);
+		assert!(lua.contains("\t\tkind = \"i64\",\n\t\traw = \"9007199254740993\",\n"));
+		assert!(lua.contains("[\"b\"] = 9007199254740992,"));
+		assert_eq!(kinds, [DiagnosticKind::WrappedScalar]);
+
+		let (lua, kinds) = wrapped("a: !!binary |\n  aGVs\n  bG8=\nb: aGVsbG8=", &options);
+		assert!(lua.contains("\t\tkind = \"binary\",\n\t\traw = \"aGVsbG8=\",\n"));
+		assert!(lua.contains("[\"b\"] = \"aGVsbG8=\","));
+		assert_eq!(kinds, [DiagnosticKind::WrappedScalar]);
+
+		let yaml = "a: !!timestamp 2001-12-14t21:59:43.123456789Z";
+		let (lua, kinds) = wrapped(yaml, &options);
+		assert!(lua.contains(
+			"\t\tkind = \"timestamp\",\n\t\traw = \"2001-12-14t21:59:43.123456789Z\",\n"
+		));
+		assert_eq!(kinds, [DiagnosticKind::WrappedScalar]);
+
+		let (lua, kinds) = wrapped(yaml, &ParseOptions::new());
+		assert!(lua.contains("[\"a\"] = \"2001-12-14t21:59:43.123456789Z\","));
+		assert!(kinds.is_empty());
+
+		let (lua, kinds) = wrapped("a: 2001-12-14\nb: \"2001-12-14\"", &options);
+		assert!(lua.contains("\t\tkind = \"timestamp\",\n\t\traw = \"2001-12-14\",\n"));
+		assert!(lua.contains("[\"b\"] = \"2001-12-14\","));
+		assert_eq!(kinds, [DiagnosticKind::WrappedScalar]);
+	}
+
+	#[test]
+	fn concurrent_conversions() {
+		use crate::{
+			parse_with_options, Converter, KeyStyle, ParseOptions, ProgressInterval, StringDedup,
+			SubtreeDedup,
+		};
+		use std::{ops::ControlFlow, sync::Arc, thread};
+
+		let options = Arc::new(
+			ParseOptions::roblox()
+				.key_style(KeyStyle::SanitizedIdentifiers)
+				.dedupe_strings(StringDedup::default())
+				.dedupe_subtrees(SubtreeDedup {
+					min_size: 1,
+					..Default::default()
+				})
+				.on_progress(ProgressInterval::Nodes(1), |_| ControlFlow::Continue(()))
+				.post_process(|lua, _| Ok::<_, std::io::Error>(format!("-- generated\n{}", lua))),
+		);
+
+		let fixtures: Vec<String> = (0..8)
+			.map(|i| {
+				format!(
+					"{}\nextra {}: {{ a: [x, x, x], b: [x, x, x] }}",
+					ALL_VALUES, i
+				)
+			})
+			.collect();
+
+		let expected: Vec<String> = fixtures
+			.iter()
+			.map(|yaml| parse_with_options(yaml, &options).unwrap())
+			.collect();
+
+		thread::scope(|scope| {
+			for i in 0..16 {
+				let (options, fixtures, expected) = (Arc::clone(&options), &fixtures, &expected);
+
+				scope.spawn(move || {
+					let mut converter = Converter::new(ParseOptions::clone(&options));
+
+					for round in 0..20 {
+						let n = (i + round) % fixtures.len();
+
+						assert_eq!(
+							parse_with_options(&fixtures[n], &options).unwrap(),
+							expected[n]
+						);
+						assert_eq!(converter.convert(&fixtures[n]).unwrap(), expected[n]);
+					}
+				});
+			}
+		});
+	}
+
+	#[test]
+	fn number_keys() {
+		use crate::{
+			parse_with_diagnostics, parse_with_options, DiagnosticKind, Error, ParseOptions,
+		};
+
+		let key = |yaml: &str| {
+			let lua = parse_with_options(yaml, &ParseOptions::new()).unwrap();
+			lua.lines().nth(1).unwrap_or_default().trim().to_owned()
+		};
+
+		assert_eq!(key("1: a"), "[1] = \"a\",");
+		assert_eq!(key("1.0: a"), "[1.0] = \"a\",");
+		assert_eq!(key("1e3: a"), "[1000.0] = \"a\",");
+		assert_eq!(key("-0.0: a"), "[-0.0] = \"a\",");
+		assert_eq!(key("0.1: a"), "[0.1] = \"a\",");
+
+		let (lua, diagnostics) =
+			parse_with_diagnostics(".inf: a\nb: c", &ParseOptions::new()).unwrap();
+		assert_eq!(lua, "{\n\t[\"b\"] = \"c\",\n}");
+		assert_eq!(diagnostics[0].kind, DiagnosticKind::UnsupportedKey);
+		assert!(
+			parse_with_options(".nan: a", &ParseOptions::new().warnings_as_errors(true)).is_err()
+		);
+
+		let err = parse_with_options("1: a\n1.0: b", &ParseOptions::new()).unwrap_err();
+		assert!(matches!(err, Error::KeyCollision { .. }), "{}", err);
+	}
+
+	#[test]
+	fn visit_counts_nodes() {
+		use crate::{visit, DocumentVisitor, KeyPath, VisitError};
+		use serde_yaml::Value;
+
+		#[derive(Default)]
+		struct Counter {
+			mappings: usize,
+			sequences: usize,
+			scalars: usize,
+			tags: Vec<String>,
+			depth: usize,
+		}
+
+		impl DocumentVisitor for Counter {
+			fn enter_mapping(&mut self, _: &KeyPath) -> Result<(), VisitError> {
+				self.mappings += 1;
+				self.depth += 1;
+				Ok(())
+			}
+
+			fn exit_mapping(&mut self, _: &KeyPath) -> Result<(), VisitError> {
+				self.depth -= 1;
+				Ok(())
+			}
+
+			fn enter_sequence(&mut self, _: &KeyPath) -> Result<(), VisitError> {
+				self.sequences += 1;
+				self.depth += 1;
+				Ok(())
+			}
+
+			fn exit_sequence(&mut self, _: &KeyPath) -> Result<(), VisitError> {
+				self.depth -= 1;
+				Ok(())
+			}
+
+			fn tagged(&mut self, path: &KeyPath, tag: &str) -> Result<(), VisitError> {
+				self.tags.push(format!("{} {}", path, tag));
+				Ok(())
+			}
+
+			fn scalar(&mut self, _: &KeyPath, _: &Value) -> Result<(), VisitError> {
+				self.scalars += 1;
+				Ok(())
+			}
+		}
+
+		let mut counter = Counter::default();
+		visit(ALL_VALUES, &mut counter).unwrap();
+
+		assert_eq!(counter.depth, 0);
+		assert!(counter.scalars > 5);
+
+		let mut counter = Counter::default();
+		visit("a: { b: [1, !Vec [2, 3]] }\nc: null", &mut counter).unwrap();
+
+		assert_eq!(
+			(counter.mappings, counter.sequences, counter.scalars),
+			(2, 2, 4)
+		);
+		assert_eq!(counter.tags, ["a.b[1] !Vec"]);
+	}
+
+	#[test]
+	fn visit_aborts() {
+		use crate::{visit, DocumentVisitor, Error, KeyPath, VisitError};
+		use serde_yaml::Value;
+
+		struct Limit(Vec<String>);
+
+		impl DocumentVisitor for Limit {
+			fn scalar(&mut self, path: &KeyPath, value: &Value) -> Result<(), VisitError> {
+				if value.as_i64().is_some_and(|n| n > 10) {
+					return Err(format!("{} is over the limit", value.as_i64().unwrap()).into());
+				}
+
+				self.0.push(path.to_string());
+				Ok(())
+			}
+		}
+
+		let yaml = "a: 1\nlist:\n  - 2\n  - 50\n  - 3\nb: 4";
+		let mut limit = Limit(Vec::new());
+		let err = visit(yaml, &mut limit).unwrap_err();
+
+		assert_eq!(limit.0, ["a", "list[0]"]);
+		assert!(matches!(&err, Error::Visit { path, .. } if path.to_string() == "list[1]"));
+		assert_eq!(
+			err.to_string(),
+			"visitor failed: 50 is over the limit at `list[1]`"
+		);
+		assert!(err.render(yaml).contains("4 |   - 50"));
+	}
+
+	#[test]
+	fn parse_stream() {
+		use crate::{parse_documents, parse_stream, ParseOptions, StreamStyle};
+
+		let yaml = "name: server\nports: [80, 443]\n...\n%TAG !e! tag:example.com,2024:\n---\nfields:\n  port: !e!int integer\n";
+		let options = ParseOptions::new();
+
+		let docs = parse_documents(yaml, &options).unwrap();
+		assert_eq!(docs.len(), 2);
+		assert!(docs[1].contains("[\"int\"] = \"integer\""));
+
+		let state = mlua::Lua::new();
+
+		let lua = parse_stream(yaml, &options, StreamStyle::MultipleValues).unwrap();
+		let (cfg, schema): (mlua::Table, mlua::Table) = state.load(&lua).eval().unwrap();
+
+		assert_eq!(cfg.get::<String>("name").unwrap(), "server");
+		assert_eq!(
+			cfg.get::<mlua::Table>("ports")
+				.unwrap()
+				.get::<i64>(2)
+				.unwrap(),
+			443
+		);
+		assert!(schema.contains_key("fields").unwrap());
+
+		let lua = parse_stream(yaml, &options, StreamStyle::Table).unwrap();
+		let docs: mlua::Table = state.load(&lua).eval().unwrap();
+
+		assert_eq!(docs.raw_len(), 2);
+		assert_eq!(
+			docs.get::<mlua::Table>(1)
+				.unwrap()
+				.get::<String>("name")
+				.unwrap(),
+			"server"
+		);
+
+		let many = vec!["a: 1"; 201].join("\n---\n");
+		assert!(parse_stream(&many, &options, StreamStyle::MultipleValues).is_err());
+		assert!(parse_stream(&many, &options, StreamStyle::Table).is_ok());
+		assert!(parse_stream("a: 1\n---\nb: [", &options, StreamStyle::Table).is_err());
+	}
+
+	#[test]
+	fn parse_chunks() {
+		use crate::{parse, parse_chunks, ParseOptions};
+
+		let yaml = "text: \"ąść\\n\\\"quoted\\\"\"\nlist: [1, 2, 3]\nnested: { a: { b: c } }";
+		let lua = parse(yaml).unwrap();
+
+		for size in [1, 5, 13, 64, 1 << 20] {
+			let chunks: Vec<String> = parse_chunks(yaml, &ParseOptions::new())
+				.unwrap()
+				.target_size(size)
+				.collect();
+
+			assert_eq!(chunks.concat(), lua);
+
+			for chunk in &chunks[..chunks.len() - 1] {
+				assert!(chunk.len() >= size && chunk.ends_with('\n'), "{:?}", chunk);
+			}
+		}
+	}
+
+	#[cfg(feature = "testing")]
+	#[test]
+	fn canonical() {
+		use crate::{
+			parse_with_options, testing::assert_lua_equivalent, Comments, OutputMode, ParseOptions,
+		};
+		use std::panic;
+
+		let yaml = "b: [x, null, 1.5]\n2: true\na: { \"c d\": 1 }\n1: false";
+
+		let lua = parse_with_options(yaml, &ParseOptions::canonical()).unwrap();
+
+		assert_eq!(
+			lua,
+			r#"{
+	[1] = false,
+	[2] = true,
+	["a"] = {
+		["c d"] = 1,
+	},
+	["b"] = {
+		"x",
+		nil,
+		1.5,
+	},
+}"#
+		);
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Assignment(String::from("local config")))
+			.comments(Comments::Types);
+		let production = parse_with_options(yaml, &options).unwrap();
+
+		assert_lua_equivalent(&lua, &production);
+		assert_lua_equivalent(
+			&lua,
+			"return { a = { ['c d'] = 1 }, b = { [3] = 15e-1, [1] = 'x' }, [2.0] = true, false } -- end",
+		);
+
+		let changed = parse_with_options(&yaml.replace("1.5", "2.5"), &options).unwrap();
+		let panic = panic::catch_unwind(|| assert_lua_equivalent(&lua, &changed)).unwrap_err();
+
+		assert!(panic
+			.downcast_ref::<String>()
+			.unwrap()
+			.starts_with("Lua values differ at `[\"b\"][3]`: 1.5 != 2.5"));
+	}
+
+	#[test]
+	fn force_string_paths() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let yaml = "version: 1.10\ncode: 0o017\nsize: &size 1e3\ncopy: *size\nother: 1.10";
+
+		let lua = parse_with_options(yaml, &ParseOptions::new()).unwrap();
+
+		assert!(lua.contains(r#"["version"] = 1.1,"#));
+		assert!(lua.contains(r#"["code"] = 15,"#));
+
+		let options = ParseOptions::new()
+			.force_string_paths(["version", "code", "copy"])
+			.unwrap();
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.contains(r#"["version"] = "1.10","#));
+		assert!(lua.contains(r#"["code"] = "0o017","#));
+		assert!(lua.contains(r#"["size"] = 1000.0,"#));
+		assert!(lua.contains(r#"["copy"] = "1000.0","#));
+		assert!(lua.contains(r#"["other"] = 1.1,"#));
+
+		assert!(ParseOptions::new().force_string_paths(["a["]).is_err());
+	}
+
+	#[test]
+	fn preview() {
+		use crate::{
+			parse_with_diagnostics, parse_with_stats, DiagnosticKind, ParseOptions, Preview,
+		};
+
+		let mut yaml = String::from("deep: { a: { b: { c: { d: 1 } } } }\nwide:\n");
+
+		for i in 0..50 {
+			yaml.push_str(&format!("  - {}\n", i));
+		}
+
+		let options = ParseOptions::new()
+			.output_mode(crate::OutputMode::Module)
+			.preview(Preview {
+				max_render_depth: 3,
+				max_entries_per_table: 10,
+			});
+
+		let (lua, stats) = parse_with_stats(&yaml, &options).unwrap();
+
+		assert!(lua.contains("[\"b\"] = { --[[ … 1 entry ]] },"));
+		assert!(lua.contains("\t\t9,\n\t\t-- … 40 more entries\n\t},"));
+		assert_eq!(stats.elided_entries, 41);
+
+		let state = mlua::Lua::new();
+		let table: mlua::Table = state.load(&lua).eval().unwrap();
+		let wide: mlua::Table = table.get("wide").unwrap();
+
+		assert_eq!(wide.raw_len(), 10);
+
+		let (_, diagnostics) = parse_with_diagnostics(&yaml, &options).unwrap();
+
+		assert_eq!(diagnostics.len(), 2);
+		assert!(diagnostics.iter().all(|d| d.kind == DiagnosticKind::Elided));
+		assert_eq!(diagnostics[0].path.to_string(), "deep.a.b");
+	}
+
+	#[test]
+	fn converter() {
+		use crate::{parse_with_options, Converter, OutputMode, ParseOptions};
+
+		fn assert_send<T: Send>(_: &T) {}
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.chunk_threshold(1);
+		let mut converter = Converter::new(options.clone());
+
+		assert_send(&converter);
+
+		for yaml in [ALL_VALUES, "a: [1, 2]", "", ALL_VALUES] {
+			assert_eq!(
+				converter.convert(yaml).unwrap(),
+				parse_with_options(yaml, &options).unwrap()
+			);
+		}
+
+		assert!(converter.convert("a: [").is_err());
+		assert_eq!(
+			converter.convert_to_string("b: true").unwrap(),
+			parse_with_options("b: true", &options).unwrap()
+		);
+	}
+
+	#[test]
+	fn parse_reader() {
+		use crate::{parse, parse_bytes, parse_reader, parse_reader_to, Error};
+		use std::io::Cursor;
+
+		for yaml in [ALL_VALUES, "", "a: !Tag [1, 2]", "!Config {a: 1}"] {
+			let mut lua = Vec::new();
+
+			parse_reader_to(Cursor::new(yaml), &mut lua).unwrap();
+
+			assert_eq!(
+				parse_reader(Cursor::new(yaml)).unwrap(),
+				parse(yaml).unwrap()
+			);
+			assert_eq!(String::from_utf8(lua).unwrap(), parse(yaml).unwrap());
+		}
+
+		let invalid = b"a: \xff\xfe";
+
+		assert!(matches!(
+			parse_reader(Cursor::new(invalid)),
+			Err(Error::Encoding(message)) if message == "invalid UTF-8 at byte 3"
+		));
+		assert_eq!(
+			parse_reader(Cursor::new(invalid)).unwrap_err().to_string(),
+			parse_bytes(invalid).unwrap_err().to_string()
+		);
+		assert_eq!(
+			parse_reader(Cursor::new(b"\xEF\xBB\xBFa: 1")).unwrap(),
+			parse("a: 1").unwrap()
+		);
+	}
+
+	#[test]
+	fn parse_bytes() {
+		use crate::{parse, parse_bytes, Error};
+
+		let plain = parse(ALL_VALUES).unwrap();
+
+		let utf8_bom = [&[0xEF, 0xBB, 0xBF], ALL_VALUES.as_bytes()].concat();
+		let utf16_le: Vec<u8> = [0xFEFF]
+			.into_iter()
+			.chain(ALL_VALUES.encode_utf16())
+			.flat_map(u16::to_le_bytes)
+			.collect();
+		let utf16_be: Vec<u8> = [0xFEFF]
+			.into_iter()
+			.chain("ключ: значение".encode_utf16())
+			.flat_map(u16::to_be_bytes)
+			.collect();
+
+		assert_eq!(parse_bytes(&utf8_bom).unwrap(), plain);
+		assert_eq!(parse_bytes(&utf16_le).unwrap(), plain);
+		assert_eq!(
+			parse_bytes(&utf16_be).unwrap(),
+			parse("ключ: значение").unwrap()
+		);
+
+		assert!(matches!(
+			parse_bytes(&utf16_le[..utf16_le.len() - 1]),
+			Err(Error::Encoding(_))
+		));
+		assert!(matches!(
+			parse_bytes(b"a: \xC3\x28"),
+			Err(Error::Encoding(_))
+		));
+	}
+
+	fn temp_dir(name: &str) -> std::path::PathBuf {
+		let dir = std::env::temp_dir().join(format!("yaml2lua-{}-{}", name, std::process::id()));
+
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(dir.join("input/nested")).unwrap();
+
+		std::fs::write(dir.join("input/a.yaml"), ALL_VALUES).unwrap();
+		std::fs::write(dir.join("input/nested/b.yml"), "b: 1").unwrap();
+		std::fs::write(dir.join("input/nested/broken.yaml"), "b: [").unwrap();
+		std::fs::write(dir.join("input/ignored.txt"), "c: 1").unwrap();
+
+		dir
+	}
+
+	#[test]
+	fn convert_dir() {
+		use crate::{convert_dir, parse, ParseOptions};
+
+		let dir = temp_dir("convert-dir");
+		let report =
+			convert_dir(dir.join("input"), dir.join("output"), &ParseOptions::new()).unwrap();
+
+		let outputs: Vec<_> = report.converted.iter().map(|file| &file.output).collect();
+
+		assert_eq!(
+			outputs,
+			[dir.join("output/a.lua"), dir.join("output/nested/b.lua")]
+				.iter()
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(report.failed.len(), 1);
+		assert_eq!(report.failed[0].input, dir.join("input/nested/broken.yaml"));
+		assert_eq!(
+			std::fs::read_to_string(dir.join("output/a.lua")).unwrap(),
+			parse(ALL_VALUES).unwrap()
+		);
+
+		assert!(convert_dir(
+			dir.join("missing"),
+			dir.join("output"),
+			&ParseOptions::new()
+		)
+		.is_err());
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[cfg(feature = "tokio")]
+	#[tokio::test]
+	async fn convert_dir_async() {
+		use crate::{
+			convert_dir, parse,
+			tokio::{convert_dir_async, parse_file_async},
+			Error, ParseOptions,
+		};
+
+		let dir = temp_dir("convert-dir-async");
+		let options = ParseOptions::new();
+
+		let report = convert_dir_async(dir.join("input"), dir.join("async"), &options)
+			.await
+			.unwrap();
+		let sync_report = convert_dir(dir.join("input"), dir.join("sync"), &options).unwrap();
+
+		assert_eq!(report.converted.len(), sync_report.converted.len());
+		assert_eq!(report.failed.len(), 1);
+
+		for (file, sync_file) in report.converted.iter().zip(&sync_report.converted) {
+			assert_eq!(
+				std::fs::read_to_string(&file.output).unwrap(),
+				std::fs::read_to_string(&sync_file.output).unwrap()
+			);
+		}
+
+		assert_eq!(
+			parse_file_async(dir.join("input/a.yaml")).await.unwrap(),
+			parse(ALL_VALUES).unwrap()
+		);
+		assert!(matches!(
+			parse_file_async(dir.join("input/missing.yaml")).await,
+			Err(Error::Io(_))
+		));
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[cfg(feature = "bytecode")]
+	#[test]
+	fn parse_to_bytecode() {
+		use crate::{
+			parse_to_bytecode, parse_with_options, Dialect, Error, OutputMode, ParseOptions,
+		};
+		use std::convert::Infallible;
+
+		let options = ParseOptions::new();
+		let bytecode = parse_to_bytecode(ALL_VALUES, &options).unwrap();
+
+		let module =
+			parse_with_options(ALL_VALUES, &options.clone().output_mode(OutputMode::Module));
+		let lua = mlua::Lua::new();
+
+		let from_bytecode: mlua::Table = lua.load(&bytecode).eval().unwrap();
+		let from_source: mlua::Table = lua.load(module.unwrap()).eval().unwrap();
+
+		lua.globals().set("a", from_bytecode).unwrap();
+		lua.globals().set("b", from_source).unwrap();
+
+		let equal: bool = lua
+			.load(
+				r#"
+local function equal(a, b)
+	if type(a) ~= "table" or type(b) ~= "table" then
+		return a == b and math.type(a) == math.type(b)
+	end
+
+	for k, v in pairs(a) do
+		if not equal(v, b[k]) then
+			return false
+		end
+	end
+
+	for k in pairs(b) do
+		if a[k] == nil then
+			return false
+		end
+	end
+
+	return true
+end
+
+return equal(a, b)"#,
+			)
+			.eval()
+			.unwrap();
+
+		assert!(equal);
+
+		let options = options.post_process(|lua, _| Ok::<_, Infallible>(lua.replace("= 1", "= =")));
+		let err = parse_to_bytecode("a: 1\nb: 2", &options).unwrap_err();
+
+		assert!(matches!(err, Error::Bytecode { line: Some(2), .. }));
+		assert!(err.to_string().ends_with("in `[\"a\"] = =,`"));
+
+		for dialect in [Dialect::Lua51, Dialect::Luau] {
+			assert!(matches!(
+				parse_to_bytecode("a: 1", &ParseOptions::new().dialect(dialect)),
+				Err(Error::Options(_))
+			));
+		}
+	}
+
+	#[cfg(feature = "watch")]
+	#[test]
+	fn watch_dir() {
+		use crate::{watch_dir, ParseOptions, WatchEvent};
+		use std::{ops::ControlFlow, sync::mpsc, thread, time::Duration};
+
+		let dir = temp_dir("watch");
+		let (input, output) = (dir.join("input"), dir.join("output"));
+		let (sender, receiver) = mpsc::channel();
+
+		let watcher = thread::spawn({
+			let (input, output) = (input.clone(), output.clone());
+
+			move || {
+				watch_dir(input, output, &ParseOptions::new(), |event| {
+					let removed = matches!(event, WatchEvent::Removed { .. });
+					sender.send(event).unwrap();
+
+					if removed {
+						ControlFlow::Break(())
+					} else {
+						ControlFlow::Continue(())
+					}
+				})
+			}
+		});
+
+		let next = || receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+
+		let initial: Vec<WatchEvent> = (0..3).map(|_| next()).collect();
+
+		assert_eq!(
+			initial
+				.iter()
+				.filter(|event| matches!(event, WatchEvent::Converted(_)))
+				.count(),
+			2
+		);
+
+		std::fs::write(input.join("c.yaml"), "c: 1").unwrap();
+
+		match next() {
+			WatchEvent::Converted(file) => assert_eq!(
+				std::fs::read_to_string(file.output).unwrap(),
+				"{\n\t[\"c\"] = 1,\n}"
+			),
+			event => panic!("unexpected event {:?}", event),
+		}
+
+		std::fs::remove_dir_all(input.join("nested")).unwrap();
+
+		assert!(matches!(next(), WatchEvent::Removed { .. }));
+		assert!(!output.join("nested").exists());
+
+		watcher.join().unwrap().unwrap();
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn comments() {
+		use crate::{parse_with_options, Comments, OutputMode, ParseOptions};
+
+		let yaml = r#"
+pos: !Vector3 [1, 2, 3]
+name: "-- not a comment"
+count: 2
+ratio: 0.5
+list: [true, null]
+"#;
+
+		let lua = r#"{
+	["pos"] = {
+		["Vector3"] = {
+			1,
+			2,
+			3,
+		},
+	}, -- !Vector3
+	["name"] = "-- not a comment",
+	["count"] = 2,
+	["ratio"] = 0.5,
+	["list"] = {
+		true,
+		nil,
+	},
+}"#;
+
+		let options = ParseOptions::new().comments(Comments::Tags);
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		let lua = r#"{
+	["pos"] = {
+		["Vector3"] = {
+			1, -- integer
+			2, -- integer
+			3, -- integer
+		},
+	}, -- !Vector3
+	["name"] = "-- not a comment", -- string
+	["count"] = 2, -- integer
+	["ratio"] = 0.5, -- float
+	["list"] = {
+		true, -- boolean
+		nil, -- nil
+	},
+}"#;
+
+		let options = ParseOptions::new().comments(Comments::Types);
+
+		assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+
+		let options = options.output_mode(OutputMode::Module).chunk_threshold(1);
+		let lua = parse_with_options("a: [1, 2]", &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"local t = {}\nt[\"a\"] = {}\nt[\"a\"][1] = 1 -- integer\nt[\"a\"][2] = 2 -- integer\nreturn t"
+		);
+	}
+
+	#[test]
+	fn fmt_escape_lua_string() {
+		use crate::fmt::{escape_lua_string, QuoteStyle};
+
+		let escape = |s| escape_lua_string(s, QuoteStyle::Double);
+
+		assert_eq!(escape("plain"), "plain");
+		assert_eq!(escape("a\"b\\c\nd\re\tf"), "a\\\"b\\\\c\\nd\\re\\tf");
+		assert_eq!(escape("\u{1b}[0m\0"), "\\027[0m\\000");
+		assert_eq!(escape("\u{7f}1"), "\\1271");
+		assert_eq!(escape("\u{85}"), "\\194\\133");
+		assert_eq!(escape("zażółć 🦀 'x'"), "zażółć 🦀 'x'");
+		assert_eq!(escape("already\\nescaped\\\""), "already\\nescaped\\\"");
+		assert_eq!(escape("mixed\\n\n"), "mixed\\\\n\\n");
+		assert_eq!(escape("bad\\q"), "bad\\\\q");
+
+		let escape = |s| escape_lua_string(s, QuoteStyle::Single);
+
+		assert_eq!(escape("it's \"ok\""), "it\\'s \"ok\"");
+		assert_eq!(escape("it\\'s"), "it\\'s");
+	}
+
+	#[test]
+	fn fmt_format_lua_key() {
+		use crate::fmt::{format_lua_key, LuaKey};
+		use std::borrow::Cow;
+
+		assert_eq!(format_lua_key(&LuaKey::from("a b")), r#"["a b"]"#);
+		assert_eq!(format_lua_key(&LuaKey::from("a\"\n")), r#"["a\"\n"]"#);
+		assert_eq!(
+			format_lua_key(&LuaKey::Identifier(Cow::Borrowed("name"))),
+			"name"
+		);
+		assert_eq!(
+			format_lua_key(&LuaKey::Identifier(Cow::Borrowed("end"))),
+			r#"["end"]"#
+		);
+		assert_eq!(format_lua_key(&LuaKey::Number(1.into())), "[1]");
+		assert_eq!(format_lua_key(&LuaKey::Number((-1.5).into())), "[-1.5]");
+		assert_eq!(format_lua_key(&LuaKey::Bool(true)), "[true]");
+	}
+
+	#[test]
+	fn fmt_is_lua_identifier() {
+		use crate::fmt::is_lua_identifier;
+
+		for ident in ["a", "_", "_1", "snake_case", "CamelCase", "goto_", "End"] {
+			assert!(is_lua_identifier(ident), "{}", ident);
+		}
+
+		for ident in ["", "1a", "a-b", "a b", "end", "nil", "goto", "ключ", "a.b"] {
+			assert!(!is_lua_identifier(ident), "{}", ident);
+		}
+	}
+
+	#[test]
+	fn tag_directives() {
+		use crate::{parse_with_diagnostics, ParseOptions};
+
+		let yaml = r#"
+%TAG !e! tag:example.com,2024:
+%TAG !m! !my-
+---
+primary: !local 1
+secondary: !e!vector [1, 2]
+local_prefix: !m!thing x
+verbatim: !<tag:example.com,2024:color> red
+nested:
+  - !e!point { x: !e!coord 1 }
+core: !!str 5
+"#;
+
+		let lua = r#"{
+	["primary"] = {
+		["local"] = 1,
+	},
+	["secondary"] = {
+		["vector"] = {
+			1,
+			2,
+		},
+	},
+	["local_prefix"] = {
+		["my-thing"] = "x",
+	},
+	["verbatim"] = {
+		["color"] = "red",
+	},
+	["nested"] = {
+		{
+			["point"] = {
+				["x"] = {
+					["coord"] = 1,
+				},
+			},
+		},
+	},
+	["core"] = "5",
+}"#;
+
+		let (output, diagnostics) = parse_with_diagnostics(yaml, &ParseOptions::new()).unwrap();
+
+		assert_eq!(output, lua);
+		assert_eq!(
+			diagnostics[1].message,
+			"tag `!<tag:example.com,2024:vector>` was converted into a wrapper table"
+		);
+	}
+
+	#[test]
+	fn presets() {
+		use crate::{
+			parse_with_diagnostics, parse_with_options, DiagnosticKind, Error, ParseOptions,
+		};
+
+		let yaml = r#"
+name: Sword
+damage: 9007199254740993
+tags: [melee, rare]
+"#;
+
+		let table = r#"{
+	["name"] = "Sword",
+	["damage"] = 9007199254740993,
+	["tags"] = {
+		"melee",
+		"rare",
+	},
+}"#;
+
+		let frozen = r#"table.freeze({
+	["name"] = "Sword",
+	["damage"] = 9007199254740993,
+	["tags"] = table.freeze({
+		"melee",
+		"rare",
+	}),
+})"#;
+
+		let presets = [
+			(ParseOptions::minimal(), table.to_owned(), false),
+			(
+				ParseOptions::lua51(),
+				table.replace("9007199254740993", "\"9007199254740993\""),
+				false,
+			),
+			(ParseOptions::strict(), table.to_owned(), false),
+			(ParseOptions::luau(), table.to_owned(), true),
+			(ParseOptions::roblox(), format!("return {}", table), true),
+			(ParseOptions::luau().freeze(true), frozen.to_owned(), true),
+		];
+
+		for (options, lua, warns) in presets {
+			let (output, diagnostics) = parse_with_diagnostics(yaml, &options).unwrap();
+
+			assert_eq!(output, lua);
+			assert_eq!(
+				diagnostics
+					.iter()
+					.any(|d| d.kind == DiagnosticKind::LargeInteger),
+				warns
+			);
+		}
+
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::strict().dialect(crate::Dialect::Luau)),
+			Err(Error::Strict(_))
+		));
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::new().freeze(true)),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn render_error() {
+		use crate::{parse, parse_with_options, KeyStyle, ParseOptions};
+
+		let yaml = "a: 1\nb: 2\nc: [1, 2\nd: 3\ne: 4";
+		let err = parse(yaml).unwrap_err();
+
+		let rendered = r#"  |
+2 | b: 2
+3 | c: [1, 2
+4 | d: 3
+  |  ^
+5 | e: 4"#;
+
+		assert!(err.render(yaml).starts_with("error: "));
+		assert!(err.render(yaml).ends_with(rendered), "{}", err.render(yaml));
+
+		let yaml = "server:\n  max_retries: 1\n  maxRetries: 2\n";
+		let options = ParseOptions::new().key_style(KeyStyle::SnakeCase);
+		let err = parse_with_options(yaml, &options).unwrap_err();
+
+		let rendered = r#"error: keys "max_retries" and "maxRetries" both resolve to ["max_retries"] at `server`
+  --> line 1, column 1
+  |
+1 | server:
+  | ^
+2 |   max_retries: 1"#;
+
+		assert_eq!(err.render(yaml), rendered);
+
+		let err = parse_with_options("a: 1", &ParseOptions::new().flatten("")).unwrap_err();
+
+		assert_eq!(err.render("a: 1"), format!("error: {}", err));
+
+		// Unterminated flow collections fail at the end of the input
+		for yaml in ["a: [\nb", "a: {\nb: 1\n"] {
+			let err = parse(yaml).unwrap_err();
+			let location = err.location().unwrap();
+
+			assert_eq!(location.line, 3);
+			assert!(err
+				.render(yaml)
+				.contains(&format!("--> line 3, column {}", location.column)));
+			assert!(err.render(yaml).ends_with("3 | \n  | ^"));
+		}
+	}
+
+	#[test]
+	fn digest() {
+		use crate::{digest, parse_with_digest, parse_with_options, KeyStyle, ParseOptions};
+
+		let reformatted = r#"
+# Same document written differently
+{ "string": "str", "int": 420, "float": 4.2, "bool": true, "nil": ~,
+  "array": [string, 12345, false, { k: v }], "object": { key: value } }
+"#;
+
+		assert_eq!(digest(ALL_VALUES).unwrap(), digest(reformatted).unwrap());
+
+		let options = ParseOptions::new().key_style(KeyStyle::PascalCase);
+		let (lua, hash) = parse_with_digest(ALL_VALUES, &options).unwrap();
+
+		assert_eq!(lua, parse_with_options(ALL_VALUES, &options).unwrap());
+		assert_eq!(hash, digest(ALL_VALUES).unwrap());
+
+		for changed in [
+			ALL_VALUES.replace("420", "421"),
+			ALL_VALUES.replace("420", "420.0"),
+			ALL_VALUES.replace("420", "\"420\""),
+			ALL_VALUES.replace("key: value", "value: key"),
+			ALL_VALUES.replace("int: 420\nfloat: 4.2", "float: 4.2\nint: 420"),
+		] {
+			assert_ne!(digest(&changed).unwrap(), digest(ALL_VALUES).unwrap());
+		}
+
+		// Known value guards the canonical encoding against accidental changes
+		let hex: String = digest("a: [1, 2.5, x, null]")
+			.unwrap()
+			.iter()
+			.map(|b| format!("{:02x}", b))
+			.collect();
+
+		assert_eq!(
+			hex,
+			"0afd3592f9c21711211c3f0ea8abc59aa319c0e825036deb27fc5be2de371775"
+		);
+	}
+
+	#[test]
+	fn parse_split() {
+		use crate::{parse_split, split_to_dir, Error, OutputMode, ParseOptions};
+
+		let yaml = r#"
+weapons:
+  sword: { damage: 10 }
+maps: [forest]
+version: 3
+"#;
+
+		let options = ParseOptions::new().output_mode(OutputMode::Assignment(String::from("x")));
+		let modules = parse_split(yaml, &options).unwrap();
+
+		assert_eq!(
+			modules,
+			[
+				(
+					String::from("weapons"),
+					String::from("return {\n\t[\"sword\"] = {\n\t\t[\"damage\"] = 10,\n\t},\n}")
+				),
+				(
+					String::from("maps"),
+					String::from("return {\n\t\"forest\",\n}")
+				),
+				(String::from("version"), String::from("return 3")),
+			]
+		);
+
+		for yaml in ["../up: 1", "a/b: 1", "1: x", "\"\": x", "[1]"] {
+			assert!(
+				matches!(
+					parse_split(yaml, &ParseOptions::new()),
+					Err(Error::ModuleName { .. } | Error::Yaml(_))
+				),
+				"{}",
+				yaml
+			);
+		}
+
+		let dir = std::env::temp_dir().join(format!("yaml2lua-split-{}", std::process::id()));
+		let paths = split_to_dir(yaml, dir.join("modules"), &options).unwrap();
+
+		assert_eq!(paths.len(), 3);
+		assert_eq!(
+			std::fs::read_to_string(dir.join("modules/maps.lua")).unwrap(),
+			modules[1].1
+		);
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn validator() {
+		use crate::{parse_with_options, Error, OutputMode, ParseOptions};
+		use mlua::{Lua, Table, Value};
+
+		let yaml = r#"
+name: sword
+"max damage": 10
+tags: [sharp, 2]
+drops:
+  - { item: gem, chance: 0.5 }
+  - { item: gold }
+"#;
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.validator(true);
+
+		let lua = Lua::new();
+		let module: Table = lua
+			.load(parse_with_options(yaml, &options).unwrap())
+			.eval()
+			.unwrap();
+
+		let validate = |code: &str| -> (bool, Option<String>, Option<String>) {
+			lua.globals().set("data", module.clone()).unwrap();
+
+			lua.load(format!(
+				"local value = {}
+return data.validate(value)",
+				code
+			))
+			.eval()
+			.unwrap()
+		};
+
+		assert_eq!(module.get::<String>("name").unwrap(), "sword");
+		assert_eq!(validate("data"), (true, None, None));
+		assert_eq!(
+			validate(
+				r#"{ name = "axe", ["max damage"] = 1, tags = {}, drops = { { item = "x" } } }"#
+			),
+			(true, None, None)
+		);
+
+		let error = |path: &str, message: &str| (false, Some(path.into()), Some(message.into()));
+
+		assert_eq!(
+			validate(r#"{ ["max damage"] = 1, tags = {}, drops = {} }"#),
+			error("name", "missing required value")
+		);
+		assert_eq!(
+			validate(r#"{ name = "axe", ["max damage"] = "1", tags = {}, drops = {} }"#),
+			error("[\"max damage\"]", "expected number, got string")
+		);
+		assert_eq!(
+			validate(r#"{ name = "axe", ["max damage"] = 1, tags = { "a", true }, drops = {} }"#),
+			error("tags[2]", "expected number|string, got boolean")
+		);
+		assert_eq!(
+			validate(
+				r#"{ name = "axe", ["max damage"] = 1, tags = {}, drops = { { chance = 1 } } }"#
+			),
+			error("drops[1].item", "missing required value")
+		);
+		assert_eq!(
+			validate(
+				r#"{ name = "axe", ["max damage"] = 1, tags = {}, drops = { { item = "x", chance = "1" } } }"#
+			),
+			error("drops[1].chance", "expected number, got string")
+		);
+		assert!(matches!(
+			lua.load("return data.validate(5)").eval::<Value>(),
+			Ok(Value::Boolean(false))
+		));
+
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::new().validator(true)),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn annotations() {
+		use crate::{parse_with_options, Error, OutputMode, ParseOptions};
+
+		let yaml = r#"
+name: sword
+drops:
+  - { item: gem, chance: 0.5 }
+  - { item: gold, bonus: { kind: fire } }
+tags: [a, 1]
+"max damage": ~
+"#;
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Assignment(String::from("Sword")))
+			.annotations("Config");
+
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert_eq!(
+			lua.split(" = {").next().unwrap(),
+			"---@class Config
+---@field name string
+---@field drops Config.drops[]
+---@field tags (number|string)[]
+---@field [\"max damage\"] any
+
+---@class Config.drops
+---@field item string
+---@field chance number|nil
+---@field bonus Config.drops.bonus|nil
+
+---@class Config.drops.bonus
+---@field kind string
+
+---@type Config
+Sword"
+		);
+
+		let module = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.annotations("Config");
+
+		let lua = mlua::Lua::new();
+		let table: mlua::Table = lua
+			.load(parse_with_options(yaml, &module).unwrap())
+			.eval()
+			.unwrap();
+
+		assert_eq!(table.get::<String>("name").unwrap(), "sword");
+
+		for options in [
+			ParseOptions::new().annotations("Config"),
+			module.annotations("not valid"),
+		] {
+			assert!(matches!(
+				parse_with_options(yaml, &options),
+				Err(Error::Options(_))
+			));
+		}
+	}
+
+	#[test]
+	fn diff() {
+		use crate::{diff, KeyStyle, Nulls, ParseOptions, StringOverflow};
+
+		let old = r#"
+list: [1, 2]
+nested: { a: 1 }
+"dotted.key": x
+same: { b: [1] }
+"#;
+		let new = r#"
+list: [1, 2, 3]
+nested: 5
+"dotted.key": y
+same: { b: [1] }
+"#;
+
+		let lua = mlua::Lua::new();
+		let patch: mlua::Table = lua
+			.load(diff(old, new, &ParseOptions::new()).unwrap())
+			.eval()
+			.unwrap();
+
+		let set: mlua::Table = patch.get("set").unwrap();
+
+		assert_eq!(set.get::<mlua::Table>("list").unwrap().len().unwrap(), 3);
+		assert_eq!(set.get::<i64>("nested").unwrap(), 5);
+		assert_eq!(set.get::<String>("[\"dotted.key\"]").unwrap(), "y");
+		assert_eq!(set.len().unwrap(), 0);
+		assert_eq!(set.pairs::<String, mlua::Value>().count(), 3);
+
+		assert_eq!(
+			diff(old, old, &ParseOptions::new()).unwrap(),
+			"{\n\t[\"set\"] = {\n\t},\n\t[\"removed\"] = {\n\t},\n\t[\"added\"] = {\n\t},\n}"
+		);
+
+		let options = ParseOptions::new()
+			.key_style(KeyStyle::SnakeCase)
+			.bare_keys(true)
+			.sort_keys(true)
+			.final_newline(true)
+			.post_process(|lua, _| Ok::<_, std::convert::Infallible>(format!("return {}", lua)));
+
+		assert_eq!(
+			diff("a: 1", "a: 2", &options).unwrap(),
+			"return {\n\t[\"set\"] = {\n\t\t[\"a\"] = 2,\n\t},\n\t[\"removed\"] = {\n\t},\n\t[\"added\"] = {\n\t},\n}\n"
+		);
+
+		// Length fields would be read as removed paths
+		let options = ParseOptions::new().sequence_length_field("n");
+		assert!(!diff("a: 1\nb: 2", "{}", &options)
+			.unwrap()
+			.contains("[\"n\"]"));
+
+		let options =
+			ParseOptions::new().max_string_len(3, StringOverflow::Truncate(String::new()));
+		assert!(diff("long.key: 1", "{}", &options)
+			.unwrap()
+			.contains("[\"removed\"] = {\n\t\t\"[\\\"long.key\\\"]\","));
+
+		let options = ParseOptions::new().rename_keys(str::to_uppercase);
+		assert!(diff("a: 1", "a: 2", &options)
+			.unwrap()
+			.starts_with("{\n\t[\"set\"] = {\n\t\t[\"a\"] = 2,"));
+
+		// Null values are changes too, so they're kept in the patch
+		assert_eq!(
+			diff("a: 1", "a: null\nb: ~", &ParseOptions::new().nulls(Nulls::Skip)).unwrap(),
+			"{\n\t[\"set\"] = {\n\t\t[\"a\"] = nil,\n\t},\n\t[\"removed\"] = {\n\t},\n\t[\"added\"] = {\n\t\t[\"b\"] = nil,\n\t},\n}"
+		);
+	}
+
+	#[test]
+	fn progress() {
+		use crate::{parse_with_options, Error, ParseOptions, ProgressInterval};
+		use std::{
+			ops::ControlFlow,
+			sync::{Arc, Mutex},
+		};
+
+		let calls = Arc::new(Mutex::new(Vec::new()));
+
+		let options = ParseOptions::new().on_progress(ProgressInterval::Bytes(20), {
+			let calls = calls.clone();
+
+			move |progress| {
+				calls.lock().unwrap().push((
+					progress.nodes,
+					progress.bytes,
+					progress.path.to_string(),
+				));
+				ControlFlow::Continue(())
+			}
+		});
+
+		let lua = parse_with_options(ALL_VALUES, &options).unwrap();
+		let calls = calls.lock().unwrap();
+
+		assert_eq!(lua, crate::parse(ALL_VALUES).unwrap());
+		assert!(calls.len() > 3);
+		assert!(calls.windows(2).all(|w| w[1].1 >= w[0].1 + 20));
+		assert_eq!(calls[0].2, "int");
+
+		let cancel =
+			ParseOptions::new().on_progress(ProgressInterval::Nodes(1), |progress| match progress
+				.path
+				.to_string()
+				.as_str()
+			{
+				"array[1]" => ControlFlow::Break(()),
+				_ => ControlFlow::Continue(()),
+			});
+
+		assert!(matches!(
+			parse_with_options(ALL_VALUES, &cancel),
+			Err(Error::Cancelled)
+		));
+	}
+
+	#[test]
+	fn post_process() {
+		use crate::{convert_dir, parse_with_options, Converter, Error, ParseOptions};
+
+		let options = ParseOptions::new()
+			.post_process(|lua, _| Ok::<_, Error>(lua.replace("\t", "  ")))
+			.post_process(|lua, path| {
+				let name = path.and_then(|p| p.file_name()).unwrap_or_default();
+				Ok::<_, Error>(format!("-- {}\n{}", name.to_string_lossy(), lua))
+			});
+
+		let expected = "-- \n{\n  [\"a\"] = 1,\n}";
+
+		assert_eq!(parse_with_options("a: 1", &options).unwrap(), expected);
+		assert_eq!(
+			Converter::new(options.clone()).convert("a: 1").unwrap(),
+			expected
+		);
+
+		let dir = temp_dir("post-process");
+		let report = convert_dir(dir.join("input"), dir.join("output"), &options).unwrap();
+
+		assert_eq!(
+			std::fs::read_to_string(dir.join("output/nested/b.lua")).unwrap(),
+			"-- b.yml\n{\n  [\"b\"] = 1,\n}"
+		);
+		assert_eq!(report.converted.len(), 2);
+
+		let failing = options.post_process(|_, _| Err("formatter crashed"));
+		let report = convert_dir(dir.join("input"), dir.join("output"), &failing).unwrap();
+
+		assert!(matches!(
+			&report.failed[0].error,
+			Error::PostProcess { path: Some(path), .. } if path.ends_with("a.yaml")
+		));
+		assert_eq!(
+			report.failed[0].error.to_string(),
+			format!(
+				"post-processing failed: formatter crashed for {}",
+				dir.join("input/a.yaml").display()
+			)
+		);
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn max_string_len() {
+		use crate::{
+			parse_with_diagnostics, parse_with_options, DiagnosticKind, Error, ParseOptions,
+			StringOverflow,
+		};
+
+		let yaml = "ééééé: short\nlong: [\"ééééééé\"]";
+
+		let truncate =
+			ParseOptions::new().max_string_len(5, StringOverflow::Truncate(String::from("..")));
+		let (lua, diagnostics) = parse_with_diagnostics(yaml, &truncate).unwrap();
+
+		assert_eq!(
+			lua,
+			"{\n\t[\"ééééé\"] = \"short\",\n\t[\"long\"] = {\n\t\t\"ééé..\",\n\t},\n}"
+		);
+		assert_eq!(diagnostics.len(), 1);
+		assert_eq!(diagnostics[0].kind, DiagnosticKind::TruncatedString);
+		assert_eq!(diagnostics[0].path.to_string(), "long[0]");
+
+		let (lua, _) = parse_with_diagnostics(
+			"abcdefgh: 1",
+			&ParseOptions::new().max_string_len(4, StringOverflow::Truncate(String::new())),
+		)
+		.unwrap();
+
+		assert_eq!(lua, "{\n\t[\"abcd\"] = 1,\n}");
+
+		let long_suffix =
+			ParseOptions::new().max_string_len(2, StringOverflow::Truncate(String::from("...")));
+
+		assert!(matches!(
+			parse_with_options("a: abcdef", &long_suffix),
+			Err(Error::Options(message)) if message.contains("longer than the maximum string length 2")
+		));
+
+		let error = ParseOptions::new().max_string_len(4, StringOverflow::Error);
+
+		match parse_with_options(yaml, &error) {
+			Err(Error::StringTooLong { path, length, max }) => {
+				assert_eq!((path.to_string().as_str(), length, max), ("ééééé", 5, 4));
+			}
+			result => panic!("unexpected result {:?}", result),
+		}
+	}
+
+	#[test]
+	fn index_modules() {
+		use crate::{convert_dir, Error, ModuleStyle, ParseOptions};
+
+		let dir = temp_dir("index-modules");
+		let (input, output) = (dir.join("input"), dir.join("output"));
+
+		std::fs::write(input.join("my-file.yaml"), "c: 1").unwrap();
+		std::fs::write(input.join("my_file.yml"), "c: 2").unwrap();
+
+		let options = ParseOptions::new().index_modules(ModuleStyle::Roblox);
+		let report = convert_dir(&input, &output, &options).unwrap();
+
+		assert_eq!(report.indexes.len(), 2);
+		assert_eq!(
+			std::fs::read_to_string(output.join("init.lua")).unwrap(),
+			"return {\n\ta = require(script.a),\n\tmy_file = require(script[\"my-file\"]),\n\tnested = require(script.nested),\n}"
+		);
+		assert!(report
+			.failed
+			.iter()
+			.any(|file| matches!(file.error, Error::ModuleName { .. })
+				&& file.input.ends_with("my_file.yml")));
+
+		let options = ParseOptions::new()
+			.output_mode(crate::OutputMode::Module)
+			.index_modules(ModuleStyle::Path(String::new()));
+
+		convert_dir(&input, &output, &options).unwrap();
+
+		let lua = mlua::Lua::new();
+		lua.globals()
+			.get::<mlua::Table>("package")
+			.unwrap()
+			.set(
+				"path",
+				format!("{0}/?.lua;{0}/?/init.lua", output.display()),
+			)
+			.unwrap();
+
+		let b: i64 = lua
+			.load(format!(
+				"return dofile({:?}).nested.b.b",
+				output.join("init.lua").display().to_string()
+			))
+			.eval()
+			.unwrap();
+
+		assert_eq!(b, 1);
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn embedded_yaml() {
+		use crate::{parse_with_options, EmbeddedYaml, Error, ParseOptions};
+
+		let yaml = r#"
+snippet: !yaml |
+  name: card
+  inner: !yaml "[1, !yaml 'x: true']"
+"#;
+
+		let options = ParseOptions::new().embedded_yaml(EmbeddedYaml::default());
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			parse_with_options(
+				"snippet: { name: card, inner: [1, { x: true }] }",
+				&ParseOptions::new()
+			)
+			.unwrap()
+		);
+
+		let shallow = ParseOptions::new().embedded_yaml(EmbeddedYaml {
+			tag: String::from("!yaml"),
+			max_depth: 2,
+		});
+
+		assert!(matches!(
+			parse_with_options(yaml, &shallow),
+			Err(Error::Transform { path, .. }) if path.to_string() == "snippet.inner[1]"
+		));
+		assert!(matches!(
+			parse_with_options("a: !yaml [1]", &options),
+			Err(Error::Transform { .. })
+		));
+
+		let error = parse_with_options("a:\n  b: !yaml \"[1\"", &options).unwrap_err();
+
+		assert!(matches!(error, Error::EmbeddedYaml { .. }));
+		assert!(error
+			.to_string()
+			.starts_with("embedded YAML at `a.b` is invalid: "));
+	}
+
+	#[test]
+	fn tagged_root() {
+		use crate::{
+			digest, parse, parse_merged, parse_split, parse_with_digest, parse_with_options,
+			OutputMode, ParseOptions,
+		};
+
+		assert_eq!(
+			parse("!Config\nname: lobby\nsize: 4").unwrap(),
+			"{\n\t[\"Config\"] = {\n\t\t[\"name\"] = \"lobby\",\n\t\t[\"size\"] = 4,\n\t},\n}"
+		);
+
+		let module = ParseOptions::new().output_mode(OutputMode::Module);
+
+		assert_eq!(
+			parse_with_options("!Color red", &module).unwrap(),
+			"return {\n\t[\"Color\"] = \"red\",\n}"
+		);
+		assert!(parse("[1, 2]").is_err());
+
+		let (lua, hash) = parse_with_digest("!Root {a: 1}", &module).unwrap();
+
+		assert_eq!(lua, parse_with_options("!Root {a: 1}", &module).unwrap());
+		assert_eq!(hash, digest("!Root {a: 1}").unwrap());
+		assert_ne!(hash, digest("{a: 1}").unwrap());
+
+		let options = ParseOptions::new();
+
+		assert_eq!(
+			parse_merged(&["!T [1]"], &options).unwrap(),
+			parse("!T [1]").unwrap()
+		);
+		assert_eq!(
+			parse_merged(&["a: 1", "!T {b: 2}", "c: 3"], &options).unwrap(),
+			parse("!T {a: 1, b: 2, c: 3}").unwrap()
+		);
+
+		let validator = module.clone().validator(true);
+
+		for error in [
+			parse_merged(&["!T {a: 1}"], &validator).unwrap_err(),
+			parse_with_digest("!T {a: 1}", &validator).unwrap_err(),
+			parse_split("!T {a: 1}", &options).unwrap_err(),
+		] {
+			assert!(error.to_string().ends_with("requires an untagged root"));
+		}
+	}
+
+	#[test]
+	fn unknown_tags() {
+		use crate::{
+			parse_with_options, Error, ParseOptions, TagHandler, TagReplacement, UnknownTags,
+		};
+		use serde_yaml::Value;
+
+		let yaml = "!Config\nitems: [1, !Item 2, [!Item 3]]\nname: !Name lobby";
+
+		let error = parse_with_options(
+			yaml,
+			&ParseOptions::new().on_unknown_tag(UnknownTags::Error),
+		)
+		.unwrap_err();
+
+		assert!(matches!(&error, Error::UnknownTags(tags) if tags.len() == 4));
+		assert_eq!(
+			error.to_string(),
+			"unknown tags: `!Config` at the root, `!Item` at `items[1]`, `!Item` at `items[2][0]`, `!Name` at `name`"
+		);
+
+		let strip = ParseOptions::new().on_unknown_tag(UnknownTags::Strip);
+
+		assert_eq!(
+			parse_with_options(yaml, &strip).unwrap(),
+			parse_with_options("items: [1, 2, [3]]\nname: lobby", &ParseOptions::new()).unwrap()
+		);
+
+		let custom = ParseOptions::new().on_unknown_tag(UnknownTags::Custom(TagHandler::new(
+			|tag, value| {
+				Ok::<_, std::fmt::Error>(match tag {
+					"Item" => Value::from(value.as_i64().unwrap_or_default() * 10).into(),
+					"Name" => format!("Name({:?})", value.as_str().unwrap_or_default()).into(),
+					_ => TagReplacement::Value(value.clone()),
+				})
+			},
+		)));
+
+		assert_eq!(
+			parse_with_options(yaml, &custom).unwrap(),
+			"{\n\t[\"items\"] = {\n\t\t1,\n\t\t20,\n\t\t{\n\t\t\t30,\n\t\t},\n\t},\n\t[\"name\"] = Name(\"lobby\"),\n}"
+		);
+
+		let failing =
+			ParseOptions::new().on_unknown_tag(UnknownTags::Custom(TagHandler::new(|_, _| {
+				Err::<Value, _>("unsupported")
+			})));
+
+		assert!(matches!(
+			parse_with_options("a: !Tag 1", &failing),
+			Err(Error::TagHandler { path, tag, .. }) if path.to_string() == "a" && tag == "!Tag"
+		));
+	}
+
+	#[test]
+	fn golden_files() {
+		use crate::{parse_with_options, KeyStyle, ParseOptions, FORMAT_VERSIONS};
+
+		let golden = [(
+			1,
+			include_str!("../tests/golden/v1.yaml"),
+			include_str!("../tests/golden/v1.lua"),
+		)];
+
+		assert_eq!(
+			golden.map(|(version, ..)| version).as_slice(),
+			FORMAT_VERSIONS
+		);
+
+		for (version, yaml, lua) in golden {
+			let options = ParseOptions::new().format_version(version);
+			assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+		}
+
+		let (_, yaml, lua) = golden[golden.len() - 1];
+		assert_eq!(parse_with_options(yaml, &ParseOptions::new()).unwrap(), lua);
+
+		let pinned = ParseOptions::new().format_version(1);
+
+		assert_eq!(
+			parse_with_options("a: 1", &pinned.clone().compact(true))
+				.unwrap_err()
+				.to_string(),
+			"invalid options: compact changes the formatting pinned by format version 1"
+		);
+		assert!(parse_with_options("a: 1", &pinned.clone().bare_keys(true)).is_err());
+		assert!(parse_with_options("a: 1", &pinned.key_style(KeyStyle::PascalCase)).is_ok());
+	}
+
+	#[test]
+	fn scalars_per_line() {
+		use crate::{parse_with_options, ParseOptions};
+
+		let options = ParseOptions::new().scalars_per_line(16);
+		let numbers: Vec<String> = (1..=1000).map(|i| i.to_string()).collect();
+		let yaml = format!("data: [{}]", numbers.join(", "));
+
+		let mut lua = String::from("{\n\t[\"data\"] = {\n");
+
+		for line in numbers.chunks(16) {
+			lua.push_str(&format!("\t\t{},\n", line.join(", ")));
+		}
+
+		lua.push_str("\t},\n}");
+
+		let output = parse_with_options(&yaml, &options).unwrap();
+
+		assert_eq!(output, lua);
+		assert_eq!(output.lines().count(), 63 + 4);
+		assert!(output.contains("\t\t993, 994, 995, 996, 997, 998, 999, 1000,\n"));
+
+		let mixed = options.clone().sequence_length_field("n");
+
+		assert_eq!(
+			parse_with_options("a: [x, 'y', null, true]\nb: [1, [2]]", &mixed).unwrap(),
+			r#"{
+	["a"] = {
+		["n"] = 4,
+		"x", "y", nil, true,
+	},
+	["b"] = {
+		["n"] = 2,
+		1,
+		{
+			["n"] = 1,
+			2,
+		},
+	},
+}"#
+		);
+	}
+
+	#[test]
+	fn deterministic_order() {
+		use crate::{
+			diff, parse_merged, parse_split, parse_with_options, OutputMode, ParseOptions,
+			StringDedup, SubtreeDedup,
+		};
+
+		let yaml = "zeta: { x: shared text, y: [1, 2] }\nalpha: { x: shared text, y: [1, 2] }\nmid: shared text\n7: seven\ntrue: yes";
+		let layer = "omega: 1\nalpha: { z: 3 }\nbeta: 2";
+
+		let options = ParseOptions::new()
+			.dedupe_strings(StringDedup::default())
+			.dedupe_subtrees(SubtreeDedup::default())
+			.output_mode(OutputMode::Module);
+
+		let convert = || {
+			(
+				parse_with_options(yaml, &options).unwrap(),
+				parse_merged(&[yaml, layer], &ParseOptions::new()).unwrap(),
+				diff(yaml, layer, &ParseOptions::new()).unwrap(),
+				parse_split(layer, &ParseOptions::new()).unwrap(),
+				parse_with_options(yaml, &ParseOptions::new().sort_keys(true)).unwrap(),
+			)
+		};
+
+		let first = convert();
+
+		for _ in 0..20 {
+			assert_eq!(convert(), first);
+		}
+
+		let keys = |lua: &str| -> Vec<String> {
+			lua.lines()
+				.filter(|line| line.starts_with("\t[") && !line.starts_with("\t\t"))
+				.map(|line| line[1..line.find(" =").unwrap()].to_owned())
+				.collect()
+		};
+
+		assert_eq!(
+			keys(&first.1),
+			[
+				r#"["zeta"]"#,
+				r#"["alpha"]"#,
+				r#"["mid"]"#,
+				"[7]",
+				"[true]",
+				r#"["omega"]"#,
+				r#"["beta"]"#
+			]
+		);
+		assert_eq!(
+			keys(&first.4),
+			["[7]", r#"["alpha"]"#, r#"["mid"]"#, r#"["zeta"]"#, "[true]"]
+		);
+	}
+
+	#[test]
+	fn incremental_conversion() {
+		use crate::{parse_with_options, Converter, KeyStyle, OutputMode, ParseOptions};
+
+		// Xorshift keeps the test reproducible without a dependency
+		let mut state = 0x2545_f491_4f6c_dd1d_u64;
+		let mut random = |max: u64| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state % max
+		};
+
+		let mut entries: Vec<String> = (0..40).map(|i| format!("key {}: {}", i, i)).collect();
+
+		let options = [
+			ParseOptions::new(),
+			ParseOptions::new()
+				.sort_keys(true)
+				.key_style(KeyStyle::SanitizedIdentifiers)
+				.original_keys_table(true)
+				.sequence_length_field("n"),
+			ParseOptions::new()
+				.output_mode(OutputMode::Module)
+				.validator(true),
+		];
+
+		for options in options {
+			let mut converter = Converter::new(options.clone());
+
+			for _ in 0..50 {
+				for _ in 0..random(4) {
+					let i = random(entries.len() as u64) as usize;
+
+					entries[i] = match random(4) {
+						0 => format!("key {}: {}", i, random(100)),
+						1 => format!("key {}: [{}, {}]", i, random(10), random(10)),
+						2 => format!("key {}: {{ nested: {{ value: {} }} }}", i, random(10)),
+						_ => format!("renamed {}: text {}", random(100), random(10)),
+					};
+				}
+
+				let yaml = entries.join("\n");
+
+				match parse_with_options(&yaml, &options) {
+					Ok(lua) => assert_eq!(converter.convert_incremental(&yaml).unwrap(), lua),
+					Err(_) => assert!(converter.convert_incremental(&yaml).is_err()),
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn incremental_source_changes() {
+		use crate::{
+			parse_with_options, Binary, Converter, FidelityMode, KeyStyle, ParseOptions, Timestamps,
+		};
+
+		// Only tags or quoting change, so the loaded values are the same
+		let cases = [
+			(
+				ParseOptions::new().fidelity(FidelityMode::Wrapped(Default::default())),
+				"a: aGk=",
+				"a: !!binary aGk=",
+			),
+			(
+				ParseOptions::new().binary(Binary::String),
+				"a: aGVsbG8=",
+				"a: !!binary aGVsbG8=",
+			),
+			(
+				ParseOptions::new().timestamps(Timestamps::Epoch),
+				"a: !!str 2001-12-14",
+				"a: 2001-12-14",
+			),
+			(
+				ParseOptions::new().key_style(KeyStyle::MirrorSource),
+				"a: 1",
+				"\"a\": 1",
+			),
+		];
+
+		for (options, before, after) in cases {
+			let mut converter = Converter::new(options.clone());
+
+			converter.convert_incremental(before).unwrap();
+
+			assert_eq!(
+				converter.convert_incremental(after).unwrap(),
+				parse_with_options(after, &options).unwrap()
+			);
+		}
+	}
+
+	#[test]
+	fn constant_references() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let options = ParseOptions::new()
+			.constant_references([("modes[*]", "Game.Mode."), ("**.difficulty", "")])
+			.unwrap();
+
+		assert_eq!(
+			parse_with_options(
+				"modes: [CAPTURE_THE_FLAG, KING]\nlevel: { difficulty: HARD, name: HARD }",
+				&options
+			)
+			.unwrap(),
+			r#"{
+	["modes"] = {
+		Game.Mode.CAPTURE_THE_FLAG,
+		Game.Mode.KING,
+	},
+	["level"] = {
+		["difficulty"] = HARD,
+		["name"] = "HARD",
+	},
+}"#
+		);
+
+		for invalid in ["modes: [king of the hill]", "modes: [end]", "modes: ['']"] {
+			assert!(matches!(
+				parse_with_options(invalid, &options),
+				Err(Error::Transform { path, .. }) if path.to_string() == "modes[0]"
+			));
+		}
+
+		assert_eq!(
+			parse_with_options("difficulty: 1", &options).unwrap(),
+			"{\n\t[\"difficulty\"] = 1,\n}"
+		);
+
+		for prefix in ["Game", "Game..", "1.", "Game:"] {
+			assert!(matches!(
+				ParseOptions::new().constant_references([("a", prefix)]),
+				Err(Error::Options(_))
+			));
+		}
+	}
+
+	#[test]
+	fn pathological_inputs() {
+		use crate::{parse, parse_documents, parse_partial, Error, ParseOptions};
+
+		let mut bomb = String::from("a: &a [x, x, x, x, x, x, x, x, x]\n");
+
+		for (previous, name) in ('a'..'i').zip('b'..='i') {
+			let aliases = vec![format!("*{}", previous); 9].join(", ");
+			bomb.push_str(&format!("{}: &{} [{}]\n", name, name, aliases));
+		}
+
+		let too_deep = [
+			format!("a: {}{}", "[".repeat(100_000), "]".repeat(100_000)),
+			format!("a: {}", "{b: ".repeat(100_000)),
+			format!("a: {}", "[".repeat(200)),
+			format!("{}a", "- ".repeat(10_000)),
+			String::from("a: &x { b: *x }"),
+			bomb,
+		];
+
+		for yaml in &too_deep {
+			assert!(matches!(parse(yaml), Err(Error::Yaml(_))));
+			assert!(parse_documents(yaml, &ParseOptions::new()).is_err());
+			assert!(parse_partial(yaml, &ParseOptions::new()).is_err());
+		}
+
+		let corpus = [
+			format!("a: '{}'", "[".repeat(1_000_000)),
+			format!("a: {}", "!t ".repeat(1000)),
+			format!("a: {}1{}", "[".repeat(128), "]".repeat(128)),
+			String::from("a: !<> 1"),
+			String::from("a: ! 1"),
+			String::from("a: !!<x> 1"),
+			String::from("a: !e!x 1"),
+			String::from("%TAG ! !\n--- !x 1"),
+			String::from("!a"),
+			String::from("? [a, b]\n: 1"),
+			String::from("a: !!binary ab="),
+			String::from("a: \"\\ud800\""),
+			String::from("\u{feff}a: \u{0}"),
+			String::from("a: *x"),
+		];
+
+		for yaml in &corpus {
+			let _ = parse(yaml);
+			let _ = parse_documents(yaml, &ParseOptions::new());
+			let _ = parse_partial(yaml, &ParseOptions::new());
+		}
+	}
+
+	#[test]
+	fn tag_wrappers() {
+		use crate::{parse_with_options, Error, ParseOptions, TagWrapper};
+
+		let yaml = "a: !Outer { b: !Inner [1], c: 2 }";
+
+		let fields = |inline_mappings| TagWrapper::Fields {
+			tag_field: String::from("__tag"),
+			value_field: String::from("value"),
+			inline_mappings,
+		};
+
+		let expected = [
+			(
+				TagWrapper::default(),
+				r#"{
+	["a"] = {
+		["Outer"] = {
+			["b"] = {
+				["Inner"] = {
+					1,
+				},
+			},
+			["c"] = 2,
+		},
+	},
+}"#,
+			),
+			(
+				TagWrapper::Key(String::from("__tag_$tag")),
+				r#"{
+	["a"] = {
+		["__tag_Outer"] = {
+			["b"] = {
+				["__tag_Inner"] = {
+					1,
+				},
+			},
+			["c"] = 2,
+		},
+	},
+}"#,
+			),
+			(
+				fields(false),
+				r#"{
+	["a"] = {
+		["__tag"] = "Outer",
+		["value"] = {
+			["b"] = {
+				["__tag"] = "Inner",
+				["value"] = {
+					1,
+				},
+			},
+			["c"] = 2,
+		},
+	},
+}"#,
+			),
+			(
+				fields(true),
+				r#"{
+	["a"] = {
+		["__tag"] = "Outer",
+		["b"] = {
+			["__tag"] = "Inner",
+			["value"] = {
+				1,
+			},
+		},
+		["c"] = 2,
+	},
+}"#,
+			),
+		];
+
+		for (wrapper, lua) in expected {
+			let options = ParseOptions::new().tag_wrapper(wrapper);
+			assert_eq!(parse_with_options(yaml, &options).unwrap(), lua);
+		}
+
+		let options = ParseOptions::new().tag_wrapper(fields(true));
+
+		assert!(matches!(
+			parse_with_options("a: !T { b: { __tag: 1 }, __tag: 2 }", &options),
+			Err(Error::KeyCollision { path, .. }) if path.to_string() == "a"
+		));
+
+		let options = ParseOptions::new().tag_wrapper(TagWrapper::Fields {
+			tag_field: String::from("value"),
+			value_field: String::from("value"),
+			inline_mappings: false,
+		});
+
+		assert!(matches!(
+			parse_with_options("a: !T 1", &options),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn root_mapping() {
+		use crate::parse;
+
+		assert_eq!(
+			parse("b: 1\na: 2\nc: 3").unwrap(),
+			"{\n\t[\"b\"] = 1,\n\t[\"a\"] = 2,\n\t[\"c\"] = 3,\n}"
+		);
+
+		assert!(parse("a: 1\na: 2")
+			.unwrap_err()
+			.to_string()
+			.starts_with("duplicate entry with key \"a\""));
+
+		assert_eq!(
+			parse("[1]").unwrap_err().to_string(),
+			"invalid type: sequence, expected a map"
+		);
+	}
+
+	#[test]
+	fn columnar_records() {
+		use crate::{
+			parse_with_diagnostics, parse_with_options, ColumnarRecords, DiagnosticKind, Error,
+			ParseOptions, SubtreeDedup,
+		};
+
+		let rows: Vec<String> = (1..=500)
+			.map(|i| format!("  - {{ id: {}, name: Monster {}, hp: {} }}", i, i, i * 10))
+			.collect();
+		let yaml = format!("monsters:\n{}", rows.join("\n"));
+
+		let options = ParseOptions::new().columnar_records(ColumnarRecords::default());
+		let columnar = parse_with_options(&yaml, &options).unwrap();
+		let plain = parse_with_options(&yaml, &ParseOptions::new()).unwrap();
+
+		assert!(columnar.starts_with(
+			"{\n\t[\"monsters\"] = {\n\t\t[\"__fields\"] = { \"id\", \"name\", \"hp\" },\n\t\t{ 1, \"Monster 1\", 10 },\n"
+		));
+		assert!(columnar.len() * 2 < plain.len());
+
+		let (lua, diagnostics) = parse_with_diagnostics(
+			"a: [{ x: 1, y: 2 }, { y: 4, x: 3 }, { x: 5 }, 6, { x: 7, y: 8, z: 9 }]",
+			&options,
+		)
+		.unwrap();
+
+		assert_eq!(
+			lua,
+			r#"{
+	["a"] = {
+		["__fields"] = { "x", "y" },
+		{ 1, 2 },
+		{ 3, 4 },
+		{
+			["x"] = 5,
+		},
+		6,
+		{
+			["x"] = 7,
+			["y"] = 8,
+			["z"] = 9,
+		},
+	},
+}"#
+		);
+		assert_eq!(
+			diagnostics
+				.iter()
+				.filter(|d| d.kind == DiagnosticKind::IrregularRecord)
+				.map(|d| d.path.to_string())
+				.collect::<Vec<_>>(),
+			["a[2]", "a[3]", "a[4]"]
+		);
+
+		assert_eq!(
+			parse_with_options("a: [{ x: 1 }, { y: 2 }]", &options).unwrap(),
+			"{\n\t[\"a\"] = {\n\t\t{\n\t\t\t[\"x\"] = 1,\n\t\t},\n\t\t{\n\t\t\t[\"y\"] = 2,\n\t\t},\n\t},\n}"
+		);
+
+		assert!(matches!(
+			parse_with_options(
+				"a: 1",
+				&options.clone().dedupe_subtrees(SubtreeDedup::default())
+			),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn self_test() {
+		use crate::{self_test, Error, OutputMode, ParseOptions, UnknownTags, FORMAT_VERSIONS};
+
+		let report = self_test(&ParseOptions::new()).unwrap();
+
+		assert_eq!(report.format_version, *FORMAT_VERSIONS.last().unwrap());
+		assert!(report.changed_options.is_empty());
+		assert_eq!(report.failed().count(), 0);
+		assert_eq!(report, self_test(&ParseOptions::new()).unwrap());
+
+		let nils = report
+			.cases
+			.iter()
+			.find(|case| case.name == "nils")
+			.unwrap();
+		assert_eq!(
+			nils.output.as_deref(),
+			Ok("{\n\t[\"value\"] = nil,\n\t[\"sequence\"] = {\n\t\t1,\n\t\tnil,\n\t\t3,\n\t},\n\t[\"table\"] = {\n\t\t[\"a\"] = nil,\n\t},\n}")
+		);
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.on_unknown_tag(UnknownTags::Error);
+		let changed = self_test(&options).unwrap();
+
+		assert_eq!(changed.changed_options, ["output_mode", "on_unknown_tag"]);
+		assert_eq!(
+			changed.failed().map(|case| case.name).collect::<Vec<_>>(),
+			["tags"]
+		);
+		assert_ne!(changed.cases, report.cases);
+
+		assert!(matches!(
+			self_test(&ParseOptions::new().format_version(0)),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn root_key() {
+		use crate::{
+			digest, parse_merged, parse_with_digest, parse_with_options, KeyStyle, ParseOptions,
+			SequenceMerge,
+		};
+
+		let yaml =
+			"%TAG !e! tag:example.com,2024:\n---\n- name: sword\n  id: 01\n- !e!shield { id: 2 }";
+
+		let options = ParseOptions::new()
+			.root_key("items")
+			.force_string_paths(["items[*].id"])
+			.unwrap();
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			r#"{
+	["items"] = {
+		{
+			["name"] = "sword",
+			["id"] = "01",
+		},
+		{
+			["shield"] = {
+				["id"] = "2",
+			},
+		},
+	},
+}"#
+		);
+
+		let (lua, hash) = parse_with_digest(yaml, &options).unwrap();
+		let root_key = |key| parse_with_digest(yaml, &ParseOptions::new().root_key(key));
+
+		assert_eq!(lua, parse_with_options(yaml, &options).unwrap());
+		assert_eq!(hash, root_key("items").unwrap().1);
+		assert_ne!(hash, root_key("other").unwrap().1);
+		assert_eq!(
+			parse_with_digest("[1]", &ParseOptions::new().root_key("items"))
+				.unwrap()
+				.1,
+			digest("items: [1]").unwrap()
+		);
+
+		let options = ParseOptions::new()
+			.root_key("all items")
+			.key_style(KeyStyle::SanitizedIdentifiers);
+
+		assert_eq!(
+			parse_with_options("\"quoted\"", &options).unwrap(),
+			"{\n\tall_items = \"quoted\",\n}"
+		);
+		assert_eq!(
+			parse_merged(
+				&["[1]", "[2]"],
+				&options.clone().merge_sequences(SequenceMerge::Concat)
+			)
+			.unwrap(),
+			"{\n\tall_items = {\n\t\t1,\n\t\t2,\n\t},\n}"
+		);
+		assert_eq!(parse_with_options("", &options).unwrap(), "{\n}");
+		assert_eq!(
+			parse_with_options("a: 1", &options).unwrap(),
+			"{\n\ta = 1,\n}"
+		);
+	}
+
+	#[test]
+	fn error_locations() {
+		use crate::{parse, parse_with_options, ErrorKind, Location, ParseOptions, StringOverflow};
+
+		let yaml = "name: lobby\nplayers:\n  - alice\n  - [bob, carol\nmax: 4\n";
+		let err = parse(yaml).unwrap_err();
+
+		assert_eq!(err.kind(), ErrorKind::Yaml);
+		assert_eq!(err.path(), None);
+		assert_eq!(
+			err.location(),
+			Some(Location {
+				line: 5,
+				column: 4,
+				index: 50,
+			})
+		);
+		assert_eq!(err.locate(yaml), err.location());
+
+		// Columns count characters and indices count bytes
+		let yaml = "t: x\np: { ü: 1, name: alice }\n";
+		let options = ParseOptions::new().max_string_len(4, StringOverflow::Error);
+		let err = parse_with_options(yaml, &options).unwrap_err();
+
+		assert_eq!(err.kind(), ErrorKind::StringTooLong);
+		assert_eq!(err.path().unwrap().to_string(), "p.name");
+		assert_eq!(err.location(), None);
+		assert_eq!(
+			err.locate(yaml),
+			Some(Location {
+				line: 2,
+				column: 12,
+				index: 17,
+			})
+		);
+	}
+
+	#[test]
+	fn output_endings() {
+		use crate::{
+			convert_dir, parse_documents, parse_split, parse_stream, parse_with_options, Converter,
+			Error, ModuleStyle, OutputMode, ParseOptions, StreamStyle,
+		};
+
+		let ended = |options: ParseOptions| options.final_newline(true).trailing_semicolon(true);
+		let module = ended(ParseOptions::new().output_mode(OutputMode::Module));
+
+		assert_eq!(
+			parse_with_options("a: 1", &module).unwrap(),
+			"return {\n\t[\"a\"] = 1,\n};\n"
+		);
+		assert_eq!(
+			parse_with_options("a: 1", &module.clone().chunk_threshold(0)).unwrap(),
+			"local t = {}\nt[\"a\"] = 1\nreturn t;\n"
+		);
+		assert_eq!(
+			parse_with_options(
+				"a: 1",
+				&ended(ParseOptions::new().output_mode(OutputMode::Assignment("t".into())))
+					.chunk_threshold(0)
+			)
+			.unwrap(),
+			"t = {}\nt[\"a\"] = 1;\n"
+		);
+		assert_eq!(
+			parse_with_options("a: 1", &ParseOptions::new().final_newline(true)).unwrap(),
+			"{\n\t[\"a\"] = 1,\n}\n"
+		);
+		assert!(matches!(
+			parse_with_options("a: 1", &ended(ParseOptions::new())),
+			Err(Error::Options(_))
+		));
+
+		// Multi-document outputs end once per module, not once per document
+		assert_eq!(
+			parse_stream(
+				"a: 1\n---\nb: 2",
+				&ended(ParseOptions::new()),
+				StreamStyle::MultipleValues
+			)
+			.unwrap(),
+			"return {\n\t[\"a\"] = 1,\n}, {\n\t[\"b\"] = 2,\n};\n"
+		);
+		assert_eq!(
+			parse_documents("a: 1\n---\nb: 2", &ParseOptions::new().final_newline(true)).unwrap(),
+			["{\n\t[\"a\"] = 1,\n}\n", "{\n\t[\"b\"] = 2,\n}\n"]
+		);
+		assert_eq!(
+			parse_split("a: 1", &ended(ParseOptions::new())).unwrap(),
+			[("a".into(), "return 1;\n".into())]
+		);
+
+		// Hooks run on the ended output
+		let mut converter = Converter::new(
+			module
+				.clone()
+				.post_process(|lua, _| Ok::<_, Error>(format!("-- Generated\n{}", lua))),
+		);
+		assert_eq!(
+			converter.convert("a: 1").unwrap(),
+			"-- Generated\nreturn {\n\t[\"a\"] = 1,\n};\n"
+		);
+
+		let dir = temp_dir("output-endings");
+		let (input, output) = (dir.join("input"), dir.join("output"));
+		let options = module.index_modules(ModuleStyle::Roblox);
+
+		convert_dir(&input, &output, &options).unwrap();
+
+		for file in ["a.lua", "init.lua", "nested/init.lua"] {
+			let lua = std::fs::read_to_string(output.join(file)).unwrap();
+			assert!(lua.ends_with("};\n"), "{}: {:?}", file, lua);
+		}
+	}
+
+	#[test]
+	fn output_style() {
+		use crate::{
+			fmt::QuoteStyle, parse_with_options, Converter, Indent, OutputMode, ParseOptions,
+		};
+
+		let options = ParseOptions::new()
+			.indent(Indent::Spaces(2))
+			.trailing_commas(false)
+			.quote_style(QuoteStyle::Single);
+
+		assert_eq!(
+			parse_with_options("a: [1, {}]\n\"it's\": !Color red", &options).unwrap(),
+			"{\n  ['a'] = {\n    1,\n    {\n    }\n  },\n  ['it\\'s'] = {\n    ['Color'] = 'red'\n  }\n}"
+		);
+		assert_eq!(
+			parse_with_options("a: [1, 2, 3]", &options.clone().scalars_per_line(2)).unwrap(),
+			"{\n  ['a'] = {\n    1, 2,\n    3\n  }\n}"
+		);
+		assert_eq!(
+			parse_with_options(
+				"a b: { c: 1 }",
+				&options
+					.clone()
+					.output_mode(OutputMode::Module)
+					.chunk_threshold(0)
+			)
+			.unwrap(),
+			"local t = {}\nt['a b'] = {}\nt['a b']['c'] = 1\nreturn t"
+		);
+
+		// Generated code and cached entries follow the style too, and the output still runs
+		let options = options
+			.output_mode(OutputMode::Module)
+			.comments(crate::Comments::Types)
+			.validator(true);
+		let lua = parse_with_options(ALL_VALUES, &options).unwrap();
+
+		assert!(!lua.contains('\t'));
+		let lines: Vec<&str> = lua.lines().collect();
+		assert!(
+			!lines.windows(2).any(|lines| {
+				let entry = lines[0].split(" -- ").next().unwrap_or_default();
+				entry.ends_with(',') && lines[1].trim_start().starts_with('}')
+			}),
+			"{}",
+			lua
+		);
+		mlua::Lua::new().load(&lua).exec().unwrap();
+
+		let options = options.validator(false);
+		let mut converter = Converter::new(options.clone());
+
+		converter.convert_incremental("a: [1]\nb: 1").unwrap();
+		converter.convert_incremental("b: 2\na: [1]").unwrap();
+
+		assert_eq!(
+			converter.convert_incremental("b: 2\na: [1]").unwrap(),
+			parse_with_options("b: 2\na: [1]", &options).unwrap()
+		);
+	}
+
+	#[test]
+	fn unparse() {
+		use crate::{parse_with_options, unparse, Error, OutputMode, ParseOptions};
+
+		let yaml = "name: lobby\nmax: 4\nratio: 0.5\nenabled: true\nmodes:\n- ffa\n- teams\nmaps:\n  \"1\": arena\n  two words:\n    size: [1, 2]\n";
+		let lua =
+			parse_with_options(yaml, &ParseOptions::new().output_mode(OutputMode::Module)).unwrap();
+
+		let value = |yaml: &str| serde_yaml::from_str::<serde_yaml::Value>(yaml).unwrap();
+		assert_eq!(value(&unparse(&lua).unwrap()), value(yaml));
+
+		// Hand edits keep their order and Lua semantics
+		assert_eq!(
+			unparse("{ z = 1, [3] = 'c', [1] = 'a', z = 2, gone = nil } -- edited").unwrap(),
+			"z: 2\n3: c\n1: a\n"
+		);
+		assert_eq!(unparse("{ [2] = 'b', [1] = 'a' }").unwrap(), "- a\n- b\n");
+		assert_eq!(unparse("{}").unwrap(), "{}\n");
+
+		assert!(matches!(unparse("{ a = f() }"), Err(Error::Lua(_))));
+		assert_eq!(
+			unparse("{ a = { \"\\255\" } }").unwrap_err().to_string(),
+			"Lua can't be converted: string at `a[1]` isn't valid UTF-8"
+		);
+	}
+
+	#[test]
+	fn multiple_documents() {
+		use crate::{parse, parse_documents, parse_with_options, Error, ErrorKind, ParseOptions};
+
+		// Like `helm template` output with an empty template
+		let yaml = "---\n# Source: a.yaml\nkind: A\n---\n# Source: empty.yaml\n---\nkind: B\n";
+		let err = parse(yaml).unwrap_err();
+
+		assert!(matches!(err, Error::MultipleDocuments { documents: 3 }));
+		assert_eq!(err.kind(), ErrorKind::MultipleDocuments);
+		assert!(err.to_string().contains("parse_documents"));
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::new().root_key("items")),
+			Err(Error::MultipleDocuments { documents: 3 })
+		));
+
+		assert_eq!(
+			parse_documents(yaml, &ParseOptions::new()).unwrap(),
+			[
+				"{\n\t[\"kind\"] = \"A\",\n}",
+				"{\n}",
+				"{\n\t[\"kind\"] = \"B\",\n}"
+			]
+		);
+
+		// Syntax errors are still reported as they are
+		assert!(matches!(parse("a: [\n---\nb: 1"), Err(Error::Yaml(_))));
+	}
+
+	#[test]
+	fn parse_to_writer() {
+		use crate::{
+			parse_to_writer_with_options, parse_with_options, Indent, OutputMode, ParseOptions,
+		};
+
+		// Writer counting the bytes passed to it in the largest write
+		#[derive(Default)]
+		struct Sink {
+			output: Vec<u8>,
+			largest_write: usize,
+		}
+
+		impl std::io::Write for Sink {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				self.largest_write = self.largest_write.max(buf.len());
+				self.output.extend_from_slice(buf);
+				Ok(buf.len())
+			}
+
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let yaml: String = (0..5_000)
+			.map(|i| {
+				format!(
+					"key{}:\n  text: \"ünïcode {}\"\n  list: [{}, {}]\n",
+					i,
+					i,
+					i,
+					i + 1
+				)
+			})
+			.collect();
+
+		for options in [
+			ParseOptions::new(),
+			ParseOptions::new()
+				.output_mode(OutputMode::Module)
+				.base_depth(2)
+				.indent(Indent::Spaces(3))
+				.trailing_commas(false)
+				.final_newline(true),
+			ParseOptions::new()
+				.output_mode(OutputMode::Assignment("t".into()))
+				.chunk_threshold(1000)
+				.trailing_semicolon(true),
+		] {
+			let mut sink = Sink::default();
+			parse_to_writer_with_options(&yaml, &options, &mut sink).unwrap();
+
+			let expected = parse_with_options(&yaml, &options).unwrap();
+
+			assert!(sink.largest_write < expected.len() / 4);
+			assert_eq!(String::from_utf8(sink.output).unwrap(), expected);
+		}
+	}
+
+	#[cfg(feature = "mlua")]
+	#[test]
+	fn to_lua_table() {
+		use crate::{parse, to_lua_table};
+
+		let yaml = format!(
+			"{}\ntagged: !Color [1, 0, 0]\nholes: [1, null, 3]\n1: one\ntrue: yes\n[a]: skipped\nbig: 18446744073709551615\nmerged:\n  <<: {{ a: 1 }}\n  b: 2",
+			ALL_VALUES
+		);
+
+		let lua = mlua::Lua::new();
+		let table = to_lua_table(&lua, &yaml).unwrap();
+		let loaded: mlua::Table = lua.load(parse(&yaml).unwrap()).eval().unwrap();
+
+		let equal: mlua::Function = lua
+			.load(
+				"local function equal(a, b)
+					if type(a) ~= 'table' or type(b) ~= 'table' then
+						return a == b and math.type(a) == math.type(b)
+					end
+					for k, v in pairs(a) do
+						if not equal(v, b[k]) then return false end
+					end
+					for k in pairs(b) do
+						if a[k] == nil then return false end
+					end
+					return true
+				end
+				return equal",
+			)
+			.eval()
+			.unwrap();
+
+		assert!(equal.call::<bool>((table.clone(), loaded)).unwrap());
+		assert_eq!(
+			table
+				.get::<mlua::Table>("holes")
+				.unwrap()
+				.get::<i64>(3)
+				.unwrap(),
+			3
+		);
+
+		let inf = to_lua_table(&lua, "a: .inf").unwrap();
+		assert_eq!(inf.get::<f64>("a").unwrap(), f64::INFINITY);
+
+		assert!(to_lua_table(&lua, "a: [").is_err());
+	}
+
+	#[test]
+	fn tag_handlers() {
+		use crate::{parse_with_options, Error, ParseOptions, UnknownTags};
+
+		let vector = |value: &serde_yaml::Value| {
+			let numbers = value.as_sequence().ok_or("expected a sequence")?;
+			let numbers: Vec<String> = numbers
+				.iter()
+				.map(|n| n.as_f64().unwrap_or(0.0).to_string())
+				.collect();
+
+			Ok::<_, &str>(format!("Vector3.new({})", numbers.join(", ")))
+		};
+
+		let options = ParseOptions::new()
+			.with_tag("Vector3", |_| Ok::<_, &str>(String::from("replaced")))
+			.with_tag("Vector3", vector)
+			.with_tag("Size", |value| Ok::<_, &str>(value.clone()));
+
+		assert_eq!(
+			parse_with_options("a: !Vector3 [1, 2, 3]\nb: !Size 4\nc: !Other 5", &options).unwrap(),
+			"{\n\t[\"a\"] = Vector3.new(1, 2, 3),\n\t[\"b\"] = 4,\n\t[\"c\"] = {\n\t\t[\"Other\"] = 5,\n\t},\n}"
+		);
+
+		// Handled tags aren't unknown
+		let options = options.on_unknown_tag(UnknownTags::Error);
+
+		assert!(parse_with_options("a: !Vector3 [1, 2, 3]", &options).is_ok());
+		assert!(matches!(
+			parse_with_options("a: !Other 1", &options),
+			Err(Error::UnknownTags(_))
+		));
+
+		let err = parse_with_options("p: { a: !Vector3 1 }", &options).unwrap_err();
+
+		assert!(matches!(&err, Error::TagHandler { tag, .. } if tag == "!Vector3"));
+		assert_eq!(err.path().unwrap().to_string(), "p.a");
+	}
+
+	#[test]
+	#[cfg(feature = "roblox")]
+	fn roblox_datatypes() {
+		use crate::{parse_with_options, Error, ParseOptions};
+
+		let yaml = r##"
+position: !Vector3 { x: 1, Y: 2.5, z: -3 }
+offset: !Vector2 [4, 5]
+color: !Color3 [1, 0, 0]
+hex: !Color3 "#FF8000"
+size: !UDim2 { x: [0, 10], y: { scale: 1, offset: 0 } }
+padding: !UDim [0.5, 8]
+pivot: !CFrame [0, 5, 0]
+range: !NumberRange 2
+material: !Enum Enum.Material.Wood
+"##;
+
+		assert_eq!(
+			parse_with_options(yaml, &ParseOptions::roblox()).unwrap(),
+			"return {\n\t[\"position\"] = Vector3.new(1, 2.5, -3),\n\t[\"offset\"] = Vector2.new(4, 5),\n\t[\"color\"] = Color3.new(1, 0, 0),\n\t[\"hex\"] = Color3.fromHex(\"#FF8000\"),\n\t[\"size\"] = UDim2.new(0, 10, 1, 0),\n\t[\"padding\"] = UDim.new(0.5, 8),\n\t[\"pivot\"] = CFrame.new(0, 5, 0),\n\t[\"range\"] = NumberRange.new(2),\n\t[\"material\"] = Enum.Material.Wood,\n}"
+		);
+
+		for (yaml, message) in [
+			(
+				"a: !Vector3 [1, 2]",
+				"expected 3 numbers or a mapping with x, y and z, found 2",
+			),
+			(
+				"a: !Vector3 { x: 1, y: 2, w: 3 }",
+				"unexpected field `w`, expected x, y and z",
+			),
+			("a: !Color3 [1, red, 0]", "2: expected a number"),
+			(
+				"a: !CFrame [1, 2, 3, 4]",
+				"expected 3, 7 or 12 numbers, found 4",
+			),
+			(
+				"a: !Enum Wood",
+				"`Wood` isn't an enum item like Material.Wood",
+			),
+		] {
+			let err = parse_with_options(yaml, &ParseOptions::roblox()).unwrap_err();
+
+			assert!(
+				matches!(&err, Error::TagHandler { source, .. } if source.to_string() == message),
+				"{}: {}",
+				yaml,
+				err
+			);
+		}
+	}
+
+	#[test]
+	fn serialize() {
+		use crate::{parse_with_options, to_string, to_string_with_options, Error, ParseOptions};
+		use serde::Serialize;
+		use std::collections::BTreeMap;
+
+		#[derive(Serialize)]
+		enum Shape {
+			Circle(f64),
+			Square,
+		}
+
+		#[derive(Serialize)]
+		struct Part {
+			name: String,
+			shape: Shape,
+			tags: Vec<&'static str>,
+			parent: Option<u32>,
+			attributes: BTreeMap<u32, bool>,
+		}
+
+		let parts = [
+			Part {
+				name: String::from("wheel \"front\""),
+				shape: Shape::Circle(0.5),
+				tags: vec!["round"],
+				parent: None,
+				attributes: BTreeMap::from([(2, true), (1, false)]),
+			},
+			Part {
+				name: String::from("crate"),
+				shape: Shape::Square,
+				tags: Vec::new(),
+				parent: Some(1),
+				attributes: BTreeMap::new(),
+			},
+		];
+
+		let yaml = r#"
+name: "wheel \"front\""
+shape: !Circle 0.5
+tags: [round]
+parent: null
+attributes: { 1: false, 2: true }
+"#;
+
+		let options = ParseOptions::roblox().sort_keys(true);
+
+		assert_eq!(to_string(&parts[0]).unwrap(), crate::parse(yaml).unwrap());
+		assert_eq!(
+			to_string_with_options(&parts[0], &options).unwrap(),
+			parse_with_options(yaml, &options).unwrap()
+		);
+
+		// Sequences need a root key like YAML documents
+		assert!(matches!(to_string(&parts), Err(Error::Yaml(_))));
+
+		let options = ParseOptions::new().root_key("parts");
+
+		assert_eq!(
+			to_string_with_options(&parts, &options).unwrap(),
+			"{\n\t[\"parts\"] = {\n\t\t{\n\t\t\t[\"name\"] = \"wheel \\\"front\\\"\",\n\t\t\t[\"shape\"] = {\n\t\t\t\t[\"Circle\"] = 0.5,\n\t\t\t},\n\t\t\t[\"tags\"] = {\n\t\t\t\t\"round\",\n\t\t\t},\n\t\t\t[\"parent\"] = nil,\n\t\t\t[\"attributes\"] = {\n\t\t\t\t[1] = false,\n\t\t\t\t[2] = true,\n\t\t\t},\n\t\t},\n\t\t{\n\t\t\t[\"name\"] = \"crate\",\n\t\t\t[\"shape\"] = \"Square\",\n\t\t\t[\"tags\"] = {\n\t\t\t},\n\t\t\t[\"parent\"] = 1,\n\t\t\t[\"attributes\"] = {\n\t\t\t},\n\t\t},\n\t},\n}"
+		);
+	}
+
+	#[test]
+	fn preserve_anchors() {
+		use crate::{parse_with_options, Error, OutputMode, ParseOptions, SubtreeDedup};
+
+		let yaml = r#"
+defaults: &defaults
+  stats: &stats [1, 2]
+  hp: 10
+boss: *defaults
+waves: [*stats, *stats]
+name: &name lobby
+title: *name
+"#;
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.preserve_anchors(true);
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		// Nested anchors are declared first
+		assert!(lua.starts_with("local __anchor_stats = {"));
+		assert!(lua.contains("local __anchor_defaults = {\n\t[\"stats\"] = __anchor_stats,"));
+
+		let state = mlua::Lua::new();
+		let data: mlua::Table = state.load(&lua).eval().unwrap();
+		let defaults: mlua::Table = data.get("defaults").unwrap();
+		let waves: mlua::Table = data.get("waves").unwrap();
+
+		assert_eq!(defaults.get::<i64>("hp").unwrap(), 10);
+		assert_eq!(data.get::<String>("title").unwrap(), "lobby");
+
+		// Aliases are the same table as their anchor
+		assert_eq!(defaults, data.get::<mlua::Table>("boss").unwrap());
+		assert_eq!(
+			waves.get::<mlua::Table>(1).unwrap(),
+			defaults.get::<mlua::Table>("stats").unwrap()
+		);
+		assert_eq!(
+			waves.get::<mlua::Table>(2).unwrap(),
+			defaults.get::<mlua::Table>("stats").unwrap()
+		);
+
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::new().preserve_anchors(true)),
+			Err(Error::Options(_))
+		));
+		assert!(matches!(
+			parse_with_options(yaml, &options.dedupe_subtrees(SubtreeDedup::default())),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn header() {
+		use crate::{parse_stream, parse_with_options, OutputMode, ParseOptions, StreamStyle};
+
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Module)
+			.preserve_anchors(true)
+			.header("Generated file\n\nDo not edit");
+
+		assert_eq!(
+			parse_with_options("a: &a [1]\nb: *a", &options).unwrap(),
+			"-- Generated file\n--\n-- Do not edit\nlocal __anchor_a = {\n\t1,\n}\n\nreturn {\n\t[\"a\"] = __anchor_a,\n\t[\"b\"] = __anchor_a,\n}"
+		);
+
+		// Streams have one header
+		let options = options.preserve_anchors(false);
+
+		assert_eq!(
+			parse_stream("a: 1\n---\nb: 2", &options, StreamStyle::MultipleValues).unwrap(),
+			"-- Generated file\n--\n-- Do not edit\nreturn {\n\t[\"a\"] = 1,\n}, {\n\t[\"b\"] = 2,\n}"
+		);
+	}
+
+	#[test]
+	fn bare_keys() {
+		use crate::{parse_with_options, KeyStyle, OutputMode, ParseOptions};
+
+		let yaml = "max-players: 4\nfor: 1\n\"2d\": !Tag 2\nnested: { a b: 3, c: 4 }";
+		let options = ParseOptions::new()
+			.key_style(KeyStyle::SnakeCase)
+			.bare_keys(true);
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			"{\n\tmax_players = 4,\n\t[\"for\"] = 1,\n\t[\"2d\"] = {\n\t\tTag = 2,\n\t},\n\tnested = {\n\t\ta_b = 3,\n\t\tc = 4,\n\t},\n}"
+		);
+
+		// Chunked assignments index bare keys with dots
+		let options = ParseOptions::new()
+			.output_mode(OutputMode::Assignment(String::from("config")))
+			.chunk_threshold(1)
+			.bare_keys(true);
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.contains("config.nested.c = 4"));
+		assert!(lua.contains("config.nested[\"a b\"] = 3"));
+		assert!(lua.contains("config[\"for\"] = 1"));
+
+		let state = mlua::Lua::new();
+		state.load(&lua).exec().unwrap();
+		let config: mlua::Table = state.globals().get("config").unwrap();
+
+		assert_eq!(config.get::<i64>("max-players").unwrap(), 4);
+	}
+
+	#[test]
+	fn luau_types() {
+		use crate::{parse_with_options, Error, LuauTypes, OutputMode, ParseOptions};
+
+		let yaml = r#"
+name: sword
+drops:
+  - { item: gem, chance: 0.5 }
+  - { item: gold, bonus: { kind: fire } }
+tags: [a, 1]
+ids: { 1: a, 2: b }
+"max damage": ~
+"#;
+
+		let types = LuauTypes {
+			name: String::from("Config"),
+			export: true,
+		};
+		let lua =
+			parse_with_options(yaml, &ParseOptions::roblox().luau_types(types.clone())).unwrap();
+
+		assert_eq!(
+			lua.split(" = {\n\t[\"name\"]").next().unwrap(),
+			"export type Config = {
+	name: string,
+	drops: { {
+		item: string,
+		chance: number?,
+		bonus: {
+			kind: string,
+		}?,
+	} },
+	tags: { number | string },
+	ids: {
+		[number]: string,
+	},
+	[\"max damage\"]: any,
+}
+
+local data: Config"
+		);
+		assert!(lua.ends_with("}\n\nreturn data"));
+
+		for options in [
+			ParseOptions::new().output_mode(OutputMode::Module),
+			ParseOptions::luau(),
+			ParseOptions::roblox().chunk_threshold(1),
+		] {
+			assert!(matches!(
+				parse_with_options(yaml, &options.luau_types(types.clone())),
+				Err(Error::Options(_))
+			));
+		}
+
+		let invalid = LuauTypes {
+			name: String::from("my.Config"),
+			export: false,
+		};
+
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::roblox().luau_types(invalid)),
+			Err(Error::Options(message)) if message.contains("my.Config")
+		));
+	}
+
+	#[test]
+	fn compact() {
+		use crate::{parse_with_options, Comments, Error, OutputMode, ParseOptions, StringDedup};
+
+		let yaml = "a: 1\nb: [x, y]\nc: { d: x, e: [] }";
+		let options = ParseOptions::new().compact(true);
+
+		assert_eq!(
+			parse_with_options(yaml, &options).unwrap(),
+			"{[\"a\"]=1,[\"b\"]={\"x\",\"y\"},[\"c\"]={[\"d\"]=\"x\",[\"e\"]={}}}"
+		);
+
+		// Statements are separated by spaces and comments can't end the line
+		let options = options
+			.output_mode(OutputMode::Module)
+			.dedupe_strings(StringDedup {
+				min_count: 2,
+				min_len: 1,
+				max_locals: 10,
+			})
+			.comments(Comments::Types);
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"local __s1=\"x\" return {[\"a\"]=1,--[[ integer ]][\"b\"]={__s1,--[[ string ]]\"y\"--[[ string ]]},[\"c\"]={[\"d\"]=__s1,--[[ string ]][\"e\"]={}}}"
+		);
+
+		let state = mlua::Lua::new();
+		let table: mlua::Table = state.load(&lua).eval().unwrap();
+		let b: mlua::Table = table.get("b").unwrap();
+
+		assert_eq!(b.get::<String>(2).unwrap(), "y");
+
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::new().compact(true).chunk_threshold(1)),
+			Err(Error::Options(message)) if message.contains("chunked output")
+		));
+		assert!(matches!(
+			parse_with_options(yaml, &ParseOptions::new().compact(true).header("generated")),
+			Err(Error::Options(message)) if message.contains("a header")
+		));
+	}
+
+	#[test]
+	fn number_formatting() {
+		use crate::{
+			parse_partial, parse_with_options, Error, LargeIntegers, NonFiniteFloats, ParseOptions,
+		};
+
+		let yaml =
+			"inf: .inf\nneg: -.inf\nnan: .nan\nbig: 18446744073709551615\nsafe: 9007199254740993";
+
+		let state = mlua::Lua::new();
+		let lua = parse_with_options(yaml, &ParseOptions::new()).unwrap();
+		let table: mlua::Table = state.load(&lua).eval().unwrap();
+
+		assert_eq!(table.get::<f64>("inf").unwrap(), f64::INFINITY);
+		assert_eq!(table.get::<f64>("neg").unwrap(), f64::NEG_INFINITY);
+		assert!(table.get::<f64>("nan").unwrap().is_nan());
+
+		// Integers over 2^53 only lose precision without native integers
+		let options = ParseOptions::lua51().large_integers(LargeIntegers::String);
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.contains("[\"big\"] = \"18446744073709551615\","));
+		assert!(lua.contains("[\"safe\"] = \"9007199254740993\","));
+
+		let options = ParseOptions::new().large_integers(LargeIntegers::Float);
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.contains("[\"big\"] = 1.8446744073709552e19,"));
+		assert!(lua.contains("[\"safe\"] = 9007199254740993,"));
+
+		let options = ParseOptions::new()
+			.non_finite_floats(NonFiniteFloats::Error)
+			.large_integers(LargeIntegers::Error);
+
+		assert!(matches!(
+			parse_with_options(yaml, &options),
+			Err(Error::Number { path, message }) if path.to_string() == "inf" && message == ".inf isn't a finite number"
+		));
+
+		let result = parse_partial(yaml, &options).unwrap();
+
+		assert_eq!(result.errors.len(), 4);
+		assert!(result.lua.contains("[\"safe\"] = 9007199254740993,"));
+	}
+
+	#[test]
+	fn max_depth() {
+		use crate::{parse_partial, parse_with_options, EmbeddedYaml, Error, ParseOptions};
+
+		let options = ParseOptions::new()
+			.embedded_yaml(EmbeddedYaml::default())
+			.max_depth(3);
+		let yaml = "a: { b: !yaml \"[[1], 2]\" }";
+
+		let err = parse_with_options(yaml, &options).unwrap_err();
+
+		assert!(matches!(&err, Error::TooDeep { max: 3, .. }));
+		assert_eq!(
+			err.to_string(),
+			"tables are nested deeper than the maximum of 3 at `a.b[0]`"
+		);
+
+		// Each embedded document is only limited by the YAML parser on its own
+		let nested = (0..100).fold(String::from("1"), |yaml, _| format!("[{}]", yaml));
+		let yaml = format!("a: !yaml \"{}\"", nested);
+		let options = options.max_depth(64);
+
+		assert!(matches!(
+			parse_with_options(&yaml, &options),
+			Err(Error::TooDeep { max: 64, .. })
+		));
+		assert!(parse_partial(&yaml, &options).is_err());
+	}
+
+	#[test]
+	fn parse_value() {
+		use crate::{
+			parse_value_with_options, parse_with_options, visit_value, DocumentVisitor,
+			EmbeddedYaml, KeyPath, ParseOptions, VisitError,
+		};
+		use serde_yaml::Value;
+
+		let yaml = "a: !Tag { x: 1 }\nb: [1, !yaml \"c: 2\"]";
+		let value: Value = serde_yaml::from_str(yaml).unwrap();
+
+		for options in [
+			ParseOptions::new(),
+			ParseOptions::new().embedded_yaml(EmbeddedYaml::default()),
+		] {
+			assert_eq!(
+				parse_value_with_options(&value, &options).unwrap(),
+				parse_with_options(yaml, &options).unwrap()
+			);
+		}
+
+		let options = ParseOptions::new().root_key("items");
+
+		assert_eq!(
+			parse_value_with_options(&Value::from(vec![1, 2]), &options).unwrap(),
+			parse_with_options("[1, 2]", &options).unwrap()
+		);
+
+		#[derive(Default)]
+		struct Paths(Vec<String>);
+
+		impl DocumentVisitor for Paths {
+			fn scalar(&mut self, path: &KeyPath, _: &Value) -> Result<(), VisitError> {
+				self.0.push(path.to_string());
+				Ok(())
+			}
+		}
+
+		let mut paths = Paths::default();
+		visit_value(&value["b"], &mut paths).unwrap();
+
+		assert_eq!(paths.0, ["[0]", "[1]"]);
+	}
+
+	#[test]
+	fn rename_keys() {
+		use crate::{parse_with_options, Error, KeyStyle, ParseOptions};
+
+		let options = ParseOptions::new()
+			.rename_keys(|key| key.trim_start_matches("x-").to_owned())
+			.key_style(KeyStyle::CamelCase);
+
+		assert_eq!(
+			parse_with_options("x-max-retries: 3\nname: a", &options).unwrap(),
+			"{\n\t[\"maxRetries\"] = 3,\n\t[\"name\"] = \"a\",\n}"
+		);
+
+		let err = parse_with_options("x-name: a\nname: b", &options).unwrap_err();
+
+		assert!(matches!(err, Error::KeyCollision { .. }));
+	}
+
+	#[test]
+	fn binary() {
+		use crate::{parse_with_options, Binary, Error, ParseOptions};
+
+		let yaml = "a: !!binary |\n  AP8i\n  XA==\nb: AP8iXA==";
+		let lua = |binary| parse_with_options(yaml, &ParseOptions::luau().binary(binary)).unwrap();
+
+		assert_eq!(
+			lua(Binary::String),
+			"{\n\t[\"a\"] = \"\\000\\255\\\"\\\\\",\n\t[\"b\"] = \"AP8iXA==\",\n}"
+		);
+		assert!(lua(Binary::Bytes).contains("[\"a\"] = {0, 255, 34, 92},"));
+		assert!(
+			lua(Binary::Buffer).contains("[\"a\"] = buffer.fromstring(\"\\000\\255\\\"\\\\\"),")
+		);
+
+		let options = ParseOptions::new().binary(Binary::Bytes);
+
+		assert!(matches!(
+			parse_with_options("a: !!binary a===", &options),
+			Err(Error::Transform { .. })
+		));
+		assert!(matches!(
+			parse_with_options("a: !!binary aGk=", &options.binary(Binary::Buffer)),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn timestamps() {
+		use crate::{parse_with_options, Error, ParseOptions, Timestamps};
+
+		let yaml = "a: 2001-12-14 21:59:43.10 -5\nb: !!timestamp 2024-06-01\nc: \"2024-06-01\"\nd: 2023-02-29";
+		let lua = |timestamps| {
+			parse_with_options(yaml, &ParseOptions::luau().timestamps(timestamps)).unwrap()
+		};
+
+		assert_eq!(
+			lua(Timestamps::Epoch),
+			"{\n\t[\"a\"] = 1008385183.1,\n\t[\"b\"] = 1717200000,\n\t[\"c\"] = \"2024-06-01\",\n\t[\"d\"] = \"2023-02-29\",\n}"
+		);
+		assert!(lua(Timestamps::OsTime).contains(
+			"[\"a\"] = os.time({ year = 2001, month = 12, day = 15, hour = 2, min = 59, sec = 43 }),"
+		));
+		assert!(lua(Timestamps::DateTime)
+			.contains("[\"a\"] = DateTime.fromIsoDate(\"2001-12-15T02:59:43.1Z\"),"));
+
+		let options = ParseOptions::new().timestamps(Timestamps::Epoch);
+
+		assert!(matches!(
+			parse_with_options("a: !!timestamp soon", &options),
+			Err(Error::Transform { .. })
+		));
+		assert!(matches!(
+			parse_with_options("a: 2024-06-01", &options.timestamps(Timestamps::DateTime)),
+			Err(Error::Options(_))
+		));
+	}
+
+	#[test]
+	fn long_strings() {
+		use crate::{parse_to_writer_with_options, parse_with_options, ParseOptions};
+
+		let yaml = "a: |\n  one\n  two\nb: \"x]]\\ny\"\nc: \"a\\r\\nb\"\nd:\n  e: \"]=]\\n[[\"";
+		let options = ParseOptions::new().long_strings(true).base_depth(1);
+
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert_eq!(
+			lua,
+			"\t{\n\t\t[\"a\"] = [[\none\ntwo\n]],\n\t\t[\"b\"] = [=[\nx]]\ny]=],\n\t\t[\"c\"] = \"a\\r\\nb\",\n\t\t[\"d\"] = {\n\t\t\t[\"e\"] = [==[\n]=]\n[[]==],\n\t\t},\n\t}"
+		);
+
+		let mut written = Vec::new();
+		parse_to_writer_with_options(yaml, &options, &mut written).unwrap();
+
+		assert_eq!(String::from_utf8(written).unwrap(), lua);
+
+		let state = mlua::Lua::new();
+		let table: mlua::Table = state.load(format!("return {}", lua)).eval().unwrap();
+		let nested: mlua::Table = table.get("d").unwrap();
+
+		assert_eq!(table.get::<String>("a").unwrap(), "one\ntwo\n");
+		assert_eq!(table.get::<String>("b").unwrap(), "x]]\ny");
+		assert_eq!(nested.get::<String>("e").unwrap(), "]=]\n[[");
+	}
+
+	#[test]
+	fn nulls() {
+		use crate::{parse_with_options, Nulls, ParseOptions};
+
+		let yaml = "a: ~\nb: [1, null, 3]\nc:\n  d: null\n  e: 1";
+
+		assert_eq!(
+			parse_with_options(yaml, &ParseOptions::new().nulls(Nulls::Skip)).unwrap(),
+			"{\n\t[\"b\"] = {\n\t\t1,\n\t\tnil,\n\t\t3,\n\t},\n\t[\"c\"] = {\n\t\t[\"e\"] = 1,\n\t},\n}"
+		);
+
+		let options = ParseOptions::new().nulls(Nulls::Sentinel(String::from("json.null")));
+		let lua = parse_with_options(yaml, &options).unwrap();
+
+		assert!(lua.contains("[\"a\"] = json.null,"));
+		assert!(lua.contains("\t\tjson.null,\n\t\t3,"));
+		assert!(lua.contains("[\"d\"] = json.null,"));
+	}
+
+	#[test]
+	fn lua_value() {
+		use crate::{parse, LuaValue};
+
+		let yaml = "name: \"lobby\\n\"\nmax: 4\nratio: 1.0\nlist: [true, -.inf, {b: .inf}]\n";
+		let value: LuaValue = yaml.parse().unwrap();
+
+		assert_eq!(String::from(value.clone()), parse(yaml).unwrap());
+		assert_eq!(
+			LuaValue::try_from(&serde_yaml::from_str::<serde_yaml::Value>(yaml).unwrap()).unwrap(),
+			value
+		);
+
+		// Nil entries aren't kept and keys counting from 1 are written like sequences
+		assert_eq!(
+			String::from("a: ~\nb: {1: c}".parse::<LuaValue>().unwrap()),
+			"{\n\t[\"b\"] = {\n\t\t\"c\",\n\t},\n}"
+		);
+		assert_eq!("5".parse::<LuaValue>().unwrap(), LuaValue::Integer(5));
+		assert_eq!("~".parse::<LuaValue>().unwrap(), LuaValue::Nil);
+		assert_eq!(
+			"[a, [2]]".parse::<LuaValue>().unwrap(),
+			LuaValue::Table(vec![
+				(LuaValue::Integer(1), LuaValue::from("a")),
+				(
+					LuaValue::Integer(2),
+					LuaValue::Table(vec![(LuaValue::Integer(1), LuaValue::Integer(2))])
+				),
+			])
+		);
+		assert!("a: [".parse::<LuaValue>().is_err());
+	}
 }
@@ -0,0 +1,536 @@
+//! Parser of Lua literals, used to compare generated Lua and to convert it back into YAML
+
+use std::{cmp::Ordering, collections::BTreeMap, fmt};
+
+/// Value of a parsed literal
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Lua {
+	Nil,
+	Bool(bool),
+	Integer(i64),
+	Float(f64),
+	String(Vec<u8>),
+	/// Entries in the order of their first assignment
+	Table(Vec<(TableKey, Lua)>),
+}
+
+/// Table key normalized the way Lua does, integral floats are stored as integers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum TableKey {
+	Bool(bool),
+	Integer(i64),
+	Float(FloatKey),
+	String(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FloatKey(pub(crate) f64);
+
+impl Eq for FloatKey {}
+
+impl PartialOrd for FloatKey {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for FloatKey {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.0.total_cmp(&other.0)
+	}
+}
+
+impl fmt::Display for TableKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TableKey::Bool(b) => write!(f, "[{}]", b),
+			TableKey::Integer(i) => write!(f, "[{}]", i),
+			TableKey::Float(float) => write!(f, "[{:?}]", float.0),
+			TableKey::String(s) => write!(f, "[{:?}]", String::from_utf8_lossy(s)),
+		}
+	}
+}
+
+impl fmt::Display for Lua {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Lua::Nil => write!(f, "nil"),
+			Lua::Bool(b) => write!(f, "{}", b),
+			Lua::Integer(i) => write!(f, "{}", i),
+			Lua::Float(float) => write!(f, "{:?}", float),
+			Lua::String(s) => write!(f, "{:?}", String::from_utf8_lossy(s)),
+			Lua::Table(table) => write!(f, "table with {} entries", table.len()),
+		}
+	}
+}
+
+pub(crate) struct Parser<'a> {
+	source: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Parser<'a> {
+	pub(crate) fn new(source: &'a str) -> Self {
+		Self {
+			source: source.as_bytes(),
+			position: 0,
+		}
+	}
+
+	/// Parse a chunk consisting of a single, optionally returned or assigned, value
+	pub(crate) fn chunk(&mut self) -> Result<Lua, String> {
+		self.skip_trivia()?;
+
+		if self.keyword("return") {
+			self.skip_trivia()?;
+		} else if self
+			.peek()
+			.is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+		{
+			let start = self.position;
+
+			self.keyword("local");
+			self.skip_trivia()?;
+
+			while self
+				.peek()
+				.is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_' || c == b'.')
+			{
+				self.position += 1;
+			}
+
+			self.skip_trivia()?;
+
+			if !self.eat(b'=') || self.peek() == Some(b'=') {
+				self.position = start;
+			}
+
+			self.skip_trivia()?;
+		}
+
+		let value = self.value()?;
+		self.skip_trivia()?;
+		self.eat(b';');
+		self.skip_trivia()?;
+
+		match self.peek() {
+			None => Ok(value),
+			Some(_) => Err(self.error("expected the end of the chunk")),
+		}
+	}
+
+	fn value(&mut self) -> Result<Lua, String> {
+		let value = match self.peek() {
+			Some(b'{') => self.table()?,
+			Some(b'"' | b'\'') => Lua::String(self.string()?),
+			Some(b'[') => Lua::String(self.long_string()?),
+			// Non-finite floats as the emitter writes them
+			_ if self.keyword("0/0") => Lua::Float(f64::NAN),
+			_ if self.keyword("math.huge") => Lua::Float(f64::INFINITY),
+			Some(b'-' | b'.' | b'0'..=b'9') => self.number()?,
+			_ if self.keyword("nil") => Lua::Nil,
+			_ if self.keyword("true") => Lua::Bool(true),
+			_ if self.keyword("false") => Lua::Bool(false),
+			_ => return Err(self.error("expected a literal")),
+		};
+
+		Ok(value)
+	}
+
+	fn table(&mut self) -> Result<Lua, String> {
+		self.expect(b'{')?;
+
+		// Later assignments replace the value but keep the position of the first one
+		let mut table: Vec<(TableKey, Option<Lua>)> = Vec::new();
+		let mut positions: BTreeMap<TableKey, usize> = BTreeMap::new();
+		let mut index = 0;
+
+		loop {
+			self.skip_trivia()?;
+
+			if self.eat(b'}') {
+				break;
+			}
+
+			let key = match self.peek() {
+				Some(b'[') if !matches!(self.source.get(self.position + 1), Some(b'[' | b'=')) => {
+					self.position += 1;
+					self.skip_trivia()?;
+
+					let key = self.value()?;
+
+					self.skip_trivia()?;
+					self.expect(b']')?;
+					self.skip_trivia()?;
+					self.expect(b'=')?;
+
+					Some(key)
+				}
+				Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+					let start = self.position;
+					let name = self.name();
+
+					self.skip_trivia()?;
+
+					if self.peek() == Some(b'=')
+						&& self.source.get(self.position + 1) != Some(&b'=')
+					{
+						self.position += 1;
+						Some(Lua::String(name.into_bytes()))
+					} else {
+						self.position = start;
+						None
+					}
+				}
+				_ => None,
+			};
+
+			self.skip_trivia()?;
+
+			let value = self.value()?;
+
+			let key = match key {
+				Some(key) => key,
+				None => {
+					index += 1;
+					Lua::Integer(index)
+				}
+			};
+
+			let key = match key {
+				Lua::Nil => return Err(self.error("table key can't be nil")),
+				Lua::Bool(b) => TableKey::Bool(b),
+				Lua::Integer(i) => TableKey::Integer(i),
+				Lua::Float(f) if f.is_nan() => return Err(self.error("table key can't be NaN")),
+				Lua::Float(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+					TableKey::Integer(f as i64)
+				}
+				Lua::Float(f) => TableKey::Float(FloatKey(f)),
+				Lua::String(s) => TableKey::String(s),
+				Lua::Table(_) => return Err(self.error("tables as keys aren't supported")),
+			};
+
+			// Assigning nil removes the entry
+			let value = match value {
+				Lua::Nil => None,
+				value => Some(value),
+			};
+
+			match positions.get(&key) {
+				Some(&position) => table[position].1 = value,
+				None => {
+					positions.insert(key.clone(), table.len());
+					table.push((key, value));
+				}
+			}
+
+			self.skip_trivia()?;
+
+			if !self.eat(b',') && !self.eat(b';') {
+				self.skip_trivia()?;
+				self.expect(b'}')?;
+				break;
+			}
+		}
+
+		Ok(Lua::Table(
+			table
+				.into_iter()
+				.filter_map(|(key, value)| Some((key, value?)))
+				.collect(),
+		))
+	}
+
+	fn number(&mut self) -> Result<Lua, String> {
+		let negative = self.eat(b'-');
+		self.skip_trivia()?;
+
+		if negative && self.keyword("math.huge") {
+			return Ok(Lua::Float(f64::NEG_INFINITY));
+		}
+
+		let start = self.position;
+
+		if self.source[start..].starts_with(b"0x") || self.source[start..].starts_with(b"0X") {
+			self.position += 2;
+
+			while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+				self.position += 1;
+			}
+
+			let digits = std::str::from_utf8(&self.source[start + 2..self.position]).unwrap_or("");
+			let int = u64::from_str_radix(digits, 16)
+				.map_err(|_| self.error("invalid hexadecimal number"))? as i64;
+
+			return Ok(Lua::Integer(if negative {
+				int.wrapping_neg()
+			} else {
+				int
+			}));
+		}
+
+		let mut float = false;
+
+		while let Some(c) = self.peek() {
+			match c {
+				b'0'..=b'9' => {}
+				b'.' => float = true,
+				b'e' | b'E' => {
+					float = true;
+
+					if matches!(self.source.get(self.position + 1), Some(b'+' | b'-')) {
+						self.position += 1;
+					}
+				}
+				_ => break,
+			}
+
+			self.position += 1;
+		}
+
+		let text = std::str::from_utf8(&self.source[start..self.position]).unwrap_or("");
+		let text = if negative {
+			format!("-{}", text)
+		} else {
+			text.to_owned()
+		};
+
+		if !float {
+			if let Ok(int) = text.parse() {
+				return Ok(Lua::Integer(int));
+			}
+		}
+
+		text.parse()
+			.map(Lua::Float)
+			.map_err(|_| self.error("invalid number"))
+	}
+
+	fn string(&mut self) -> Result<Vec<u8>, String> {
+		let quote = self.source[self.position];
+		self.position += 1;
+
+		let mut string = Vec::new();
+
+		loop {
+			let Some(c) = self.peek() else {
+				return Err(self.error("unfinished string"));
+			};
+
+			self.position += 1;
+
+			match c {
+				c if c == quote => return Ok(string),
+				b'\n' => return Err(self.error("unfinished string")),
+				b'\\' => {
+					let Some(escape) = self.peek() else {
+						return Err(self.error("unfinished string"));
+					};
+
+					self.position += 1;
+
+					match escape {
+						b'n' => string.push(b'\n'),
+						b't' => string.push(b'\t'),
+						b'r' => string.push(b'\r'),
+						b'a' => string.push(0x07),
+						b'b' => string.push(0x08),
+						b'f' => string.push(0x0c),
+						b'v' => string.push(0x0b),
+						b'\n' => string.push(b'\n'),
+						b'0'..=b'9' => {
+							let start = self.position - 1;
+
+							while self.position - start < 3
+								&& self.peek().is_some_and(|c| c.is_ascii_digit())
+							{
+								self.position += 1;
+							}
+
+							let digits = std::str::from_utf8(&self.source[start..self.position])
+								.unwrap_or("");
+							let byte = digits
+								.parse::<u8>()
+								.map_err(|_| self.error("decimal escape is too large"))?;
+
+							string.push(byte);
+						}
+						b'x' => {
+							let digits = self
+								.source
+								.get(self.position..self.position + 2)
+								.and_then(|digits| std::str::from_utf8(digits).ok())
+								.and_then(|digits| u8::from_str_radix(digits, 16).ok())
+								.ok_or_else(|| self.error("invalid hexadecimal escape"))?;
+
+							self.position += 2;
+							string.push(digits);
+						}
+						b'z' => {
+							while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+								self.position += 1;
+							}
+						}
+						b'u' => {
+							let end = self.source[self.position..]
+								.iter()
+								.position(|&c| c == b'}')
+								.ok_or_else(|| self.error("unfinished unicode escape"))?;
+
+							let digits = std::str::from_utf8(
+								&self.source[self.position + 1..self.position + end],
+							)
+							.unwrap_or("");
+							let char = u32::from_str_radix(digits, 16)
+								.ok()
+								.and_then(char::from_u32)
+								.ok_or_else(|| self.error("invalid unicode escape"))?;
+
+							self.position += end + 1;
+							string.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes());
+						}
+						c => string.push(c),
+					}
+				}
+				c => string.push(c),
+			}
+		}
+	}
+
+	/// Parse a `[[...]]` or `[==[...]==]` string
+	fn long_string(&mut self) -> Result<Vec<u8>, String> {
+		let level = self
+			.long_bracket()
+			.ok_or_else(|| self.error("expected a literal"))?;
+		let close = format!("]{}]", "=".repeat(level));
+
+		let end = self.source[self.position..]
+			.windows(close.len())
+			.position(|window| window == close.as_bytes())
+			.ok_or_else(|| self.error("unfinished long string"))?;
+
+		let mut string = &self.source[self.position..self.position + end];
+
+		// Newline directly after the opening bracket is skipped
+		if let Some(rest) = string
+			.strip_prefix(b"\r\n")
+			.or_else(|| string.strip_prefix(b"\n"))
+		{
+			string = rest;
+		}
+
+		self.position += end + close.len();
+
+		Ok(string.to_vec())
+	}
+
+	/// Consume an opening long bracket and return its level
+	fn long_bracket(&mut self) -> Option<usize> {
+		let rest = self.source.get(self.position..)?.strip_prefix(b"[")?;
+		let level = rest.iter().take_while(|&&c| c == b'=').count();
+
+		if rest.get(level) != Some(&b'[') {
+			return None;
+		}
+
+		self.position += level + 2;
+
+		Some(level)
+	}
+
+	fn skip_trivia(&mut self) -> Result<(), String> {
+		loop {
+			while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+				self.position += 1;
+			}
+
+			if !self.source[self.position..].starts_with(b"--") {
+				return Ok(());
+			}
+
+			self.position += 2;
+
+			if self.peek() == Some(b'[') && self.long_string_comment()? {
+				continue;
+			}
+
+			while self.peek().is_some_and(|c| c != b'\n') {
+				self.position += 1;
+			}
+		}
+	}
+
+	/// Skip the rest of a `--[[ ... ]]` comment, if the comment is one
+	fn long_string_comment(&mut self) -> Result<bool, String> {
+		let start = self.position;
+
+		if self.long_bracket().is_none() {
+			return Ok(false);
+		}
+
+		self.position = start;
+		self.long_string()?;
+
+		Ok(true)
+	}
+
+	fn name(&mut self) -> String {
+		let start = self.position;
+
+		while self
+			.peek()
+			.is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_')
+		{
+			self.position += 1;
+		}
+
+		String::from_utf8_lossy(&self.source[start..self.position]).into_owned()
+	}
+
+	/// Consume the keyword if it's next and isn't a prefix of a longer name
+	fn keyword(&mut self, keyword: &str) -> bool {
+		let end = self.position + keyword.len();
+
+		if !self.source[self.position..].starts_with(keyword.as_bytes())
+			|| self
+				.source
+				.get(end)
+				.is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_')
+		{
+			return false;
+		}
+
+		self.position = end;
+
+		true
+	}
+
+	fn peek(&self) -> Option<u8> {
+		self.source.get(self.position).copied()
+	}
+
+	fn eat(&mut self, c: u8) -> bool {
+		let matches = self.peek() == Some(c);
+
+		if matches {
+			self.position += 1;
+		}
+
+		matches
+	}
+
+	fn expect(&mut self, c: u8) -> Result<(), String> {
+		match self.eat(c) {
+			true => Ok(()),
+			false => Err(self.error(&format!("expected `{}`", c as char))),
+		}
+	}
+
+	fn error(&self, message: &str) -> String {
+		let consumed = &self.source[..self.position];
+		let line = consumed.iter().filter(|&&c| c == b'\n').count() + 1;
+		let column = consumed.iter().rev().take_while(|&&c| c != b'\n').count() + 1;
+
+		format!("{} at line {}, column {}", message, line, column)
+	}
+}
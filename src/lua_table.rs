@@ -0,0 +1,94 @@
+use crate::{
+	emit::tag_name,
+	transform::{self, Root},
+	ParseOptions,
+};
+use mlua::{IntoLua, Lua, Table, Value as LuaValue};
+use serde_yaml::{Mapping, Number, Value};
+
+/// Parse YAML string into a table of the Lua state, without generating and loading Lua code
+///
+/// Table has the same contents as loading the output of [`parse`](crate::parse): keys
+/// other than strings, numbers and booleans are skipped, `null` values are `nil` and
+/// tagged values are in wrapper tables like `{ Tag = value }`. Floats keep their exact
+/// value, including infinities and NaN which have no literal in generated code
+///
+/// Requires the `mlua` feature, which uses `mlua` with a vendored Lua 5.4 like
+/// the `bytecode` feature, so the state has to be a Lua 5.4 state
+///
+/// ```rust
+/// let lua = mlua::Lua::new();
+/// let table = yaml2lua::to_lua_table(&lua, "name: lobby\nplayers: [alice, bob]").unwrap();
+///
+/// assert_eq!(table.get::<String>("name").unwrap(), "lobby");
+/// assert_eq!(table.get::<mlua::Table>("players").unwrap().raw_len(), 2);
+/// ```
+pub fn to_lua_table(lua: &Lua, yaml: &str) -> mlua::Result<Table> {
+	let options = ParseOptions::default();
+	let root = transform::load_root(yaml, &options)
+		.and_then(|root| transform::prepare_root(root, &options))
+		.map_err(mlua::Error::external)?;
+
+	match root {
+		Root::Mapping(mapping) => mapping_table(lua, &mapping),
+		Root::Tagged(value) => match to_lua(lua, &value)? {
+			LuaValue::Table(table) => Ok(table),
+			_ => unreachable!("tagged values are wrapped in tables"),
+		},
+	}
+}
+
+fn to_lua(lua: &Lua, value: &Value) -> mlua::Result<LuaValue> {
+	match value {
+		Value::Null => Ok(LuaValue::Nil),
+		Value::Bool(b) => Ok(LuaValue::Boolean(*b)),
+		Value::Number(n) => Ok(number(n)),
+		Value::String(s) => s.as_str().into_lua(lua),
+		Value::Sequence(sequence) => {
+			let table = lua.create_table_with_capacity(sequence.len(), 0)?;
+
+			// Null elements leave holes like `nil` in a table constructor
+			for (i, value) in sequence.iter().enumerate() {
+				table.raw_set(i + 1, to_lua(lua, value)?)?;
+			}
+
+			Ok(LuaValue::Table(table))
+		}
+		Value::Mapping(mapping) => mapping_table(lua, mapping).map(LuaValue::Table),
+		Value::Tagged(tagged) => {
+			let table = lua.create_table_with_capacity(0, 1)?;
+			table.raw_set(
+				tag_name(&tagged.tag.to_string()),
+				to_lua(lua, &tagged.value)?,
+			)?;
+
+			Ok(LuaValue::Table(table))
+		}
+	}
+}
+
+fn mapping_table(lua: &Lua, mapping: &Mapping) -> mlua::Result<Table> {
+	let table = lua.create_table_with_capacity(0, mapping.len())?;
+
+	for (key, value) in mapping {
+		let key = match key {
+			Value::String(s) => s.as_str().into_lua(lua)?,
+			Value::Number(n) if n.as_f64().is_some_and(|f| !f.is_finite()) => continue,
+			Value::Number(n) => number(n),
+			Value::Bool(b) => LuaValue::Boolean(*b),
+			_ => continue,
+		};
+
+		table.raw_set(key, to_lua(lua, value)?)?;
+	}
+
+	Ok(table)
+}
+
+/// Integers too large for Lua integers become floats, like their literals do
+fn number(number: &Number) -> LuaValue {
+	match number.as_i64() {
+		Some(i) => LuaValue::Integer(i),
+		None => LuaValue::Number(number.as_f64().unwrap_or(f64::NAN)),
+	}
+}
@@ -0,0 +1,395 @@
+//! Command line interface converting YAML files into Lua, built with the `cli` feature
+
+use std::{
+	env, fs,
+	io::{self, Read, Write},
+	ops::ControlFlow,
+	path::{Path, PathBuf},
+	process::ExitCode,
+};
+use yaml2lua::{
+	convert_dir, parse_with_diagnostics, split_to_dir, watch_dir, Dialect, Indent, OutputMode,
+	ParseOptions, Severity, WatchEvent,
+};
+
+const USAGE: &str = "\
+Usage: yaml2lua [OPTIONS] [INPUT]...
+
+Converts YAML files into Lua tables. INPUT is a YAML file or a directory converted
+recursively, standard input is read without it or when it's `-`. Multiple files
+and directories are converted into the output directory
+
+Options:
+  -o, --output <PATH>    Lua file or directory, standard output without it or when it's `-`
+      --indent <INDENT>  Indent with `tabs` or the given number of spaces [default: tabs]
+      --module           Write a requirable module like `return { ... }`
+      --assign <NAME>    Write an assignment like `NAME = { ... }`
+      --dialect <NAME>   Target `lua51`, `lua54` or `luau` [default: lua54]
+      --strict           Fail on lossy conversions instead of warning about them
+      --sort-keys        Sort keys of tables
+      --compact          Write the output on a single line
+      --split            Write every top-level key into `<key>.lua` in the output directory
+      --watch            Keep converting the input directory as its files change
+      --bytecode         Write compiled Lua chunks into `.luac` files, requires the `bytecode` feature
+  -h, --help             Print this help
+  -V, --version          Print the version";
+
+/// Command line arguments
+#[derive(Default)]
+struct Args {
+	inputs: Vec<PathBuf>,
+	output: Option<PathBuf>,
+	options: ParseOptions,
+	split: bool,
+	watch: bool,
+	bytecode: bool,
+}
+
+enum Command {
+	Convert(Box<Args>),
+	Help,
+	Version,
+}
+
+fn main() -> ExitCode {
+	let args = match parse_args(env::args().skip(1)) {
+		Ok(Command::Convert(args)) => args,
+		Ok(Command::Help) => {
+			println!("{}", USAGE);
+			return ExitCode::SUCCESS;
+		}
+		Ok(Command::Version) => {
+			println!("yaml2lua {}", env!("CARGO_PKG_VERSION"));
+			return ExitCode::SUCCESS;
+		}
+		Err(message) => {
+			eprintln!("error: {}\n\nFor more information, try --help", message);
+			return ExitCode::from(2);
+		}
+	};
+
+	match run(*args) {
+		Ok(true) => ExitCode::SUCCESS,
+		Ok(false) => ExitCode::FAILURE,
+		Err(message) => {
+			eprintln!("error: {}", message);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+	let mut args = args.into_iter();
+	let mut parsed = Args::default();
+	let mut options = ParseOptions::new().final_newline(true);
+	let mut positional = false;
+
+	while let Some(arg) = args.next() {
+		if positional || arg == "-" || !arg.starts_with('-') {
+			parsed.inputs.push(PathBuf::from(arg));
+			continue;
+		}
+
+		// Values can be written after the flag or after `=`
+		let (flag, inline) = match arg.split_once('=') {
+			Some((flag, value)) if flag.starts_with("--") => {
+				(flag.to_owned(), Some(value.to_owned()))
+			}
+			_ => (arg, None),
+		};
+
+		let mut value = |name: &str| {
+			inline
+				.clone()
+				.or_else(|| args.next())
+				.ok_or_else(|| format!("{} requires a value", name))
+		};
+
+		match flag.as_str() {
+			"--" => positional = true,
+			"-h" | "--help" => return Ok(Command::Help),
+			"-V" | "--version" => return Ok(Command::Version),
+			"-o" | "--output" => parsed.output = Some(PathBuf::from(value(&flag)?)),
+			"--indent" => {
+				let indent = match value(&flag)?.as_str() {
+					"tabs" => Indent::Tabs,
+					spaces => spaces.parse().map(Indent::Spaces).map_err(|_| {
+						format!(
+							"indent must be `tabs` or a number of spaces, found `{}`",
+							spaces
+						)
+					})?,
+				};
+
+				options = options.indent(indent);
+			}
+			"--module" => options = options.output_mode(OutputMode::Module),
+			"--assign" => options = options.output_mode(OutputMode::Assignment(value(&flag)?)),
+			"--dialect" => {
+				let dialect = match value(&flag)?.to_lowercase().as_str() {
+					"lua51" | "luajit" => Dialect::Lua51,
+					"lua54" => Dialect::Lua54,
+					"luau" => Dialect::Luau,
+					dialect => return Err(format!("unknown dialect `{}`", dialect)),
+				};
+
+				options = options.dialect(dialect);
+			}
+			"--strict" => options = options.warnings_as_errors(true),
+			"--sort-keys" => options = options.sort_keys(true),
+			"--compact" => options = options.compact(true),
+			"--split" => parsed.split = true,
+			"--watch" => parsed.watch = true,
+			"--bytecode" if cfg!(feature = "bytecode") => parsed.bytecode = true,
+			"--bytecode" => return Err(String::from("--bytecode requires the `bytecode` feature")),
+			flag => return Err(format!("unknown option `{}`", flag)),
+		}
+	}
+
+	parsed.options = options;
+
+	Ok(Command::Convert(Box::new(parsed)))
+}
+
+/// Convert the inputs, returning whether every file was converted
+fn run(args: Args) -> Result<bool, String> {
+	let output = args
+		.output
+		.as_deref()
+		.filter(|path| *path != Path::new("-"));
+	let directory = |mode: &str| {
+		output.ok_or_else(|| format!("{} requires an output directory set with --output", mode))
+	};
+
+	if args.watch {
+		let [input] = args.inputs.as_slice() else {
+			return Err(String::from("--watch requires a single input directory"));
+		};
+
+		if args.split || args.bytecode {
+			return Err(String::from(
+				"--watch can't be used with --split or --bytecode",
+			));
+		}
+
+		return watch(input, directory("--watch")?, &args.options).map(|()| true);
+	}
+
+	match args.inputs.as_slice() {
+		[] => convert_single(None, output, &args),
+		[input] if input == Path::new("-") => convert_single(None, output, &args),
+		[input] if args.split => {
+			let yaml = read(Some(input))?;
+			split_to_dir(&yaml, directory("--split")?, &args.options)
+				.map_err(|err| format!("{}: {}", input.display(), err))?;
+
+			Ok(true)
+		}
+		[input] if input.is_dir() => convert_directory(input, directory("directory input")?, &args),
+		[input] => convert_single(Some(input), output, &args),
+		inputs => {
+			let dir = directory("converting multiple inputs")?;
+			let mut converted = true;
+
+			for input in inputs {
+				converted &= match input.is_dir() {
+					true => convert_directory(input, dir, &args)?,
+					false => {
+						let name = Path::new(input.file_name().unwrap_or_default());
+						convert_file(input, &target(dir, name, &args), &args)
+					}
+				};
+			}
+
+			Ok(converted)
+		}
+	}
+}
+
+/// Convert a file or standard input into a file or standard output
+fn convert_single(
+	input: Option<&Path>,
+	output: Option<&Path>,
+	args: &Args,
+) -> Result<bool, String> {
+	let yaml = read(input)?;
+	let name = input.map_or_else(
+		|| String::from("<stdin>"),
+		|path| path.display().to_string(),
+	);
+	let lua = convert(&yaml, &name, args).map_err(|err| format!("{}: {}", name, err))?;
+
+	match output {
+		Some(output) => fs::write(output, lua),
+		None => io::stdout().write_all(&lua),
+	}
+	.map_err(|err| format!("can't write the output: {}", err))?;
+
+	Ok(true)
+}
+
+/// Convert every YAML file in the directory into the output directory, reporting failed files
+fn convert_directory(input: &Path, output: &Path, args: &Args) -> Result<bool, String> {
+	let read_error = |err: String| format!("can't read {}: {}", input.display(), err);
+
+	if args.bytecode {
+		let mut converted = true;
+
+		for file in yaml_files(input).map_err(|err| read_error(err.to_string()))? {
+			let relative = file.strip_prefix(input).unwrap_or(&file);
+			converted &= convert_file(&file, &target(output, relative, args), args);
+		}
+
+		return Ok(converted);
+	}
+
+	let report =
+		convert_dir(input, output, &args.options).map_err(|err| read_error(err.to_string()))?;
+
+	for failed in &report.failed {
+		eprintln!("error: {}: {}", failed.input.display(), failed.error);
+	}
+
+	Ok(report.failed.is_empty())
+}
+
+/// Convert one of multiple files, printing the error if it fails
+fn convert_file(input: &Path, output: &Path, args: &Args) -> bool {
+	let result = read(Some(input)).and_then(|yaml| {
+		let lua = convert(&yaml, &input.display().to_string(), args)
+			.map_err(|err| format!("{}: {}", input.display(), err))?;
+
+		if let Some(parent) = output.parent() {
+			fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+		}
+
+		fs::write(output, lua).map_err(|err| format!("can't write {}: {}", output.display(), err))
+	});
+
+	match result {
+		Ok(()) => true,
+		Err(message) => {
+			eprintln!("error: {}", message);
+			false
+		}
+	}
+}
+
+/// Convert YAML into Lua source or bytecode, printing warnings about lossy conversions
+fn convert(yaml: &str, name: &str, args: &Args) -> yaml2lua::Result<Vec<u8>> {
+	#[cfg(feature = "bytecode")]
+	if args.bytecode {
+		return yaml2lua::parse_to_bytecode(yaml, &args.options);
+	}
+
+	let (lua, diagnostics) = parse_with_diagnostics(yaml, &args.options)?;
+
+	for diagnostic in diagnostics
+		.iter()
+		.filter(|diagnostic| diagnostic.severity == Severity::Warning)
+	{
+		eprintln!("warning: {}: {}", name, diagnostic);
+	}
+
+	Ok(lua.into_bytes())
+}
+
+/// Convert the directory and keep converting it as it changes, printing every change
+fn watch(input: &Path, output: &Path, options: &ParseOptions) -> Result<(), String> {
+	watch_dir(input, output, options, |event| {
+		match event {
+			WatchEvent::Converted(file) => {
+				println!(
+					"converted {} -> {}",
+					file.input.display(),
+					file.output.display()
+				)
+			}
+			WatchEvent::Failed(file) => {
+				eprintln!("error: {}: {}", file.input.display(), file.error)
+			}
+			WatchEvent::Removed { output, .. } => println!("removed {}", output.display()),
+		}
+
+		ControlFlow::Continue(())
+	})
+	.map_err(|err| format!("can't watch {}: {}", input.display(), err))
+}
+
+/// Read YAML from the file, or standard input without one
+fn read(input: Option<&Path>) -> Result<String, String> {
+	let mut yaml = String::new();
+
+	match input {
+		Some(input) => fs::File::open(input).and_then(|mut file| file.read_to_string(&mut yaml)),
+		None => io::stdin().read_to_string(&mut yaml),
+	}
+	.map_err(|err| match input {
+		Some(input) => format!("can't read {}: {}", input.display(), err),
+		None => format!("can't read standard input: {}", err),
+	})?;
+
+	Ok(yaml)
+}
+
+/// Path of the output of a YAML file at the relative path inside the output directory
+fn target(output: &Path, relative: &Path, args: &Args) -> PathBuf {
+	output
+		.join(relative)
+		.with_extension(if args.bytecode { "luac" } else { "lua" })
+}
+
+/// Find YAML files inside the directory and its subdirectories, sorted by their path
+fn yaml_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+
+		if path.is_dir() {
+			files.extend(yaml_files(&path)?);
+		} else if path
+			.extension()
+			.is_some_and(|extension| extension == "yaml" || extension == "yml")
+		{
+			files.push(path);
+		}
+	}
+
+	files.sort();
+
+	Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+	#[test]
+	fn parse_args() {
+		use crate::{parse_args, Command};
+		use std::path::PathBuf;
+
+		let args = |args: &str| parse_args(args.split_whitespace().map(String::from));
+
+		let Ok(Command::Convert(parsed)) = args("a.yaml --indent=2 -o out.lua --module -- -b.yaml")
+		else {
+			panic!("arguments weren't parsed");
+		};
+
+		assert_eq!(
+			parsed.inputs,
+			vec![PathBuf::from("a.yaml"), PathBuf::from("-b.yaml")]
+		);
+		assert_eq!(parsed.output, Some(PathBuf::from("out.lua")));
+		assert_eq!(
+			yaml2lua::parse_with_options("a: 1", &parsed.options).unwrap(),
+			"return {\n  [\"a\"] = 1,\n}\n"
+		);
+
+		assert!(matches!(args("a.yaml --help"), Ok(Command::Help)));
+		assert_eq!(
+			args("--indent four").err().unwrap(),
+			"indent must be `tabs` or a number of spaces, found `four`"
+		);
+		assert_eq!(args("-o").err().unwrap(), "-o requires a value");
+	}
+}
@@ -0,0 +1,1844 @@
+use crate::{
+	fmt::{is_lua_identifier, QuoteStyle},
+	progress::ProgressHook,
+	Error, KeyStyle, PathPattern, Progress, ProgressInterval, Schema, StringDedup, SubtreeDedup,
+	TagHandler, TagReplacement, TagWrapper, UnknownTags,
+};
+use serde_yaml::Value;
+use std::{error, fmt, ops::ControlFlow, path::Path, sync::Arc};
+
+/// Error returned by a post-processing hook or tag handler
+pub(crate) type HookError = Box<dyn error::Error + Send + Sync>;
+
+type PostProcess = dyn Fn(String, Option<&Path>) -> Result<String, HookError> + Send + Sync;
+
+/// Post-processing hook stored in the options
+#[derive(Clone)]
+pub(crate) struct PostProcessHook(Arc<PostProcess>);
+
+// Sharing options between threads is part of the public API, so adding hooks
+// that aren't `Send + Sync` has to fail to compile
+const _: () = {
+	const fn assert_send_sync<T: Send + Sync>() {}
+	assert_send_sync::<ParseOptions>();
+};
+
+impl fmt::Debug for PostProcessHook {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("PostProcessHook")
+	}
+}
+
+type RenameKey = dyn Fn(&str) -> String + Send + Sync;
+
+/// Function renaming string keys stored in the options
+#[derive(Clone)]
+pub(crate) struct RenameKeys(pub(crate) Arc<RenameKey>);
+
+impl fmt::Debug for RenameKeys {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("RenameKeys")
+	}
+}
+
+/// Options controlling how YAML is converted into a Lua table
+///
+/// Options are `Send + Sync` and every conversion keeps its state to itself,
+/// nothing is stored in globals or thread-locals. One `Arc<ParseOptions>`
+/// can be shared by any number of threads converting documents at once
+///
+/// ```rust
+/// use yaml2lua::{parse_with_options, NumericKeys, ParseOptions};
+///
+/// let options = ParseOptions::new().numeric_keys(NumericKeys::default());
+///
+/// assert_eq!(
+/// 	parse_with_options(r#""1": abc"#, &options).unwrap(),
+/// 	"{\n\t[1] = \"abc\",\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+	pub(crate) numeric_keys: Option<NumericKeys>,
+	pub(crate) float_precision: Option<u8>,
+	pub(crate) strict: bool,
+	pub(crate) schema: Option<Schema>,
+	pub(crate) key_style: KeyStyle,
+	pub(crate) rename_keys: Option<RenameKeys>,
+	pub(crate) bare_keys: bool,
+	pub(crate) duplicate_keys: DuplicateKeys,
+	pub(crate) key_type_collisions: KeyComparison,
+	pub(crate) sort_keys: bool,
+	pub(crate) force_string_paths: Vec<PathPattern>,
+	pub(crate) constant_references: Vec<(PathPattern, String)>,
+	pub(crate) original_keys_table: bool,
+	pub(crate) fold_single_key_maps: bool,
+	pub(crate) sequence_length_field: Option<String>,
+	pub(crate) table_layout: TableLayout,
+	pub(crate) scalars_per_line: Option<usize>,
+	pub(crate) flatten: Option<String>,
+	pub(crate) expand_dotted_keys: Option<String>,
+	pub(crate) merge_sequences: SequenceMerge,
+	pub(crate) merge_conflicts: MergeConflicts,
+	pub(crate) output_mode: OutputMode,
+	pub(crate) header: Option<String>,
+	pub(crate) base_depth: usize,
+	pub(crate) chunk_threshold: Option<usize>,
+	pub(crate) dedupe_strings: Option<StringDedup>,
+	pub(crate) dedupe_subtrees: Option<SubtreeDedup>,
+	pub(crate) preserve_anchors: bool,
+	pub(crate) comments: Comments,
+	pub(crate) dialect: Dialect,
+	pub(crate) freeze: bool,
+	pub(crate) validator: bool,
+	pub(crate) annotations: Option<String>,
+	pub(crate) luau_types: Option<LuauTypes>,
+	pub(crate) progress: Option<ProgressHook>,
+	pub(crate) post_process: Vec<PostProcessHook>,
+	pub(crate) max_string_len: Option<(usize, StringOverflow)>,
+	pub(crate) preview: Option<Preview>,
+	pub(crate) error_placeholder: Option<String>,
+	pub(crate) source_comments: Option<SourceComments>,
+	pub(crate) columnar_records: Option<ColumnarRecords>,
+	pub(crate) fidelity: FidelityMode,
+	pub(crate) non_finite_floats: NonFiniteFloats,
+	pub(crate) large_integers: LargeIntegers,
+	pub(crate) binary: Binary,
+	pub(crate) timestamps: Timestamps,
+	pub(crate) nulls: Nulls,
+	pub(crate) index_modules: Option<ModuleStyle>,
+	pub(crate) embedded_yaml: Option<EmbeddedYaml>,
+	pub(crate) max_depth: Option<usize>,
+	pub(crate) unknown_tags: UnknownTags,
+	pub(crate) tag_handlers: Vec<(String, TagHandler)>,
+	pub(crate) tag_wrapper: TagWrapper,
+	pub(crate) format_version: Option<u32>,
+	pub(crate) root_key: Option<String>,
+	pub(crate) final_newline: bool,
+	pub(crate) trailing_semicolon: bool,
+	pub(crate) indent: Indent,
+	pub(crate) omit_trailing_commas: bool,
+	pub(crate) compact: bool,
+	pub(crate) quote_style: QuoteStyle,
+	pub(crate) long_strings: bool,
+}
+
+impl ParseOptions {
+	/// Create options matching the behavior of [`parse`](crate::parse)
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Preset for Roblox: Luau dialect with a requirable `return { ... }` module,
+	/// also writing [`roblox_datatypes`](Self::roblox_datatypes) with the `roblox` feature
+	pub fn roblox() -> Self {
+		let options = Self::luau().output_mode(OutputMode::Module);
+
+		#[cfg(feature = "roblox")]
+		let options = options.roblox_datatypes();
+
+		options
+	}
+
+	/// Preset for Luau, which also allows freezing tables with [`freeze`](Self::freeze)
+	pub fn luau() -> Self {
+		Self::new().dialect(Dialect::Luau)
+	}
+
+	/// Preset for Lua 5.1 and LuaJIT, where numbers are doubles. Integers that would
+	/// lose precision as doubles are written as strings, see [`LargeIntegers::String`]
+	pub fn lua51() -> Self {
+		Self::new()
+			.dialect(Dialect::Lua51)
+			.large_integers(LargeIntegers::String)
+	}
+
+	/// Preset failing on every lossy conversion instead of reporting a warning,
+	/// including merge conflicts and colliding keys
+	pub fn strict() -> Self {
+		Self::new()
+			.warnings_as_errors(true)
+			.duplicate_keys(DuplicateKeys::Error)
+			.merge_conflicts(MergeConflicts::Error)
+	}
+
+	/// Preset generating a plain table constructor without any extras, same as [`new`](Self::new)
+	pub fn minimal() -> Self {
+		Self::new()
+	}
+
+	/// Preset for snapshot tests generating a canonical form of the document,
+	/// a plain table constructor with sorted keys regardless of their order in YAML
+	///
+	/// Outputs can be compared ignoring formatting with `testing::assert_lua_equivalent`,
+	/// which requires the `testing` feature
+	pub fn canonical() -> Self {
+		Self::new().sort_keys(true)
+	}
+
+	/// Choose which Lua implementation the output targets
+	pub fn dialect(mut self, dialect: Dialect) -> Self {
+		self.dialect = dialect;
+		self
+	}
+
+	/// Choose how scalars that Lua can't represent exactly are written, see [`FidelityMode`]
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, FidelityMode, ParseOptions};
+	///
+	/// let options = ParseOptions::new().fidelity(FidelityMode::Wrapped(Default::default()));
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: 18446744073709551615", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = {\n\t\t__type = \"u64\",\n\t\tvalue = \"18446744073709551615\",\n\t},\n}"
+	/// );
+	/// ```
+	pub fn fidelity(mut self, fidelity: FidelityMode) -> Self {
+		self.fidelity = fidelity;
+		self
+	}
+
+	/// Choose how infinite and NaN floats are written, as expressions by default
+	/// since Lua has no literals for them
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: [.inf, -.inf, .nan]", &ParseOptions::new()).unwrap(),
+	/// 	"{\n\t[\"a\"] = {\n\t\tmath.huge,\n\t\t-math.huge,\n\t\t0/0,\n\t},\n}"
+	/// );
+	/// ```
+	pub fn non_finite_floats(mut self, non_finite_floats: NonFiniteFloats) -> Self {
+		self.non_finite_floats = non_finite_floats;
+		self
+	}
+
+	/// Choose how integers the target dialect can't represent exactly are written,
+	/// which are integers above the largest signed 64-bit integer and
+	/// integers over 2^53 for dialects without integers
+	///
+	/// Integers wrapped by [`FidelityMode::Wrapped`] are still written as wrapper tables
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, LargeIntegers, ParseOptions};
+	///
+	/// let options = ParseOptions::luau().large_integers(LargeIntegers::String);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("id: 9007199254740993", &options).unwrap(),
+	/// 	"{\n\t[\"id\"] = \"9007199254740993\",\n}"
+	/// );
+	/// ```
+	pub fn large_integers(mut self, large_integers: LargeIntegers) -> Self {
+		self.large_integers = large_integers;
+		self
+	}
+
+	/// Choose how strings tagged with `!!binary` are written, they're kept as their
+	/// base64 text by default. Decoded values that aren't valid base64 fail with
+	/// [`Error::Transform`](crate::Error::Transform)
+	///
+	/// Tags are read from the YAML source, so values converted without it, like with
+	/// [`parse_value`](crate::parse_value), are written as they are
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, Binary, ParseOptions};
+	///
+	/// let options = ParseOptions::new().binary(Binary::Bytes);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("icon: !!binary aGk=", &options).unwrap(),
+	/// 	"{\n\t[\"icon\"] = {104, 105},\n}"
+	/// );
+	/// ```
+	pub fn binary(mut self, binary: Binary) -> Self {
+		self.binary = binary;
+		self
+	}
+
+	/// Choose how timestamps are written, which are strings tagged with `!!timestamp`
+	/// and unquoted strings like `2024-06-01` or `2024-06-01T12:00:00Z`. Timestamps are
+	/// kept as strings by default, timestamps without a time zone are in UTC. Tagged
+	/// values that aren't timestamps fail with [`Error::Transform`](crate::Error::Transform)
+	///
+	/// Timestamps are found in the YAML source, so values converted without it, like with
+	/// [`parse_value`](crate::parse_value), are written as they are
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions, Timestamps};
+	///
+	/// let options = ParseOptions::new().timestamps(Timestamps::Epoch);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("at: 2024-06-01T14:00:00+02:00\nname: \"2024-06-01\"", &options).unwrap(),
+	/// 	"{\n\t[\"at\"] = 1717243200,\n\t[\"name\"] = \"2024-06-01\",\n}"
+	/// );
+	/// ```
+	pub fn timestamps(mut self, timestamps: Timestamps) -> Self {
+		self.timestamps = timestamps;
+		self
+	}
+
+	/// Choose how `null` values are written, `nil` by default. Entries assigned `nil`
+	/// aren't stored in Lua tables, so null values are lost once the table is loaded
+	/// unless they're written as a sentinel
+	///
+	/// Null elements of sequences are still written with [`Nulls::Skip`], so the
+	/// indices of the elements after them don't change. Patches of [`diff`](crate::diff)
+	/// write null values as `nil`, so changes to null aren't left out
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, Nulls, ParseOptions};
+	///
+	/// let options = ParseOptions::new().nulls(Nulls::Sentinel(String::from("json.null")));
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: ~\nb: [1, null]", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = json.null,\n\t[\"b\"] = {\n\t\t1,\n\t\tjson.null,\n\t},\n}"
+	/// );
+	/// ```
+	pub fn nulls(mut self, nulls: Nulls) -> Self {
+		self.nulls = nulls;
+		self
+	}
+
+	/// Wrap every table in `table.freeze(...)` making it read-only,
+	/// requires [`Dialect::Luau`] and can't be used with chunked output
+	pub fn freeze(mut self, freeze: bool) -> Self {
+		self.freeze = freeze;
+		self
+	}
+
+	/// Generate a `validate(value)` function checking that other values have the same
+	/// shape as the document, available as `data.validate` through the metatable
+	/// of the returned table
+	///
+	/// Values are checked recursively for presence and type of every field, returning
+	/// `true` or `false` with the path and a message like `expected string, got number`.
+	/// Fields missing from some mappings of a sequence are optional and elements are
+	/// checked against the union of the types observed in the sequence.
+	///
+	/// Requires [`OutputMode::Module`] and can't be used with chunked output or freezing
+	pub fn validator(mut self, validator: bool) -> Self {
+		self.validator = validator;
+		self
+	}
+
+	/// Emit lua-language-server `---@class` and `---@field` annotations describing
+	/// the table, with the root class named `class` and nested mappings named after
+	/// their path like `Config.weapons`
+	///
+	/// ```lua
+	/// ---@class Config
+	/// ---@field name string
+	/// ---@field drops Config.drops[]
+	///
+	/// ---@class Config.drops
+	/// ---@field item string
+	/// ---@field chance number|nil
+	///
+	/// ---@type Config
+	/// local data = {
+	/// ```
+	///
+	/// Annotations are plain comments, so the output still loads in any Lua.
+	/// Requires [`OutputMode::Module`] or [`OutputMode::Assignment`]
+	pub fn annotations(mut self, class: impl Into<String>) -> Self {
+		self.annotations = Some(class.into());
+		self
+	}
+
+	/// Declare a Luau type describing the table and annotate the table with it,
+	/// inferred from the values like [`annotations`](Self::annotations)
+	///
+	/// ```lua
+	/// export type Config = {
+	/// 	name: string,
+	/// 	drops: { {
+	/// 		item: string,
+	/// 		chance: number?,
+	/// 	} },
+	/// }
+	///
+	/// local data: Config = {
+	/// ```
+	///
+	/// Fields missing from some elements of a sequence and `null` values are optional,
+	/// keys other than strings are described by an indexer like `[number]: string`.
+	/// Requires the Luau dialect and [`OutputMode::Module`], and can't be used with chunked output
+	pub fn luau_types(mut self, types: LuauTypes) -> Self {
+		self.luau_types = Some(types);
+		self
+	}
+
+	/// Call the callback periodically while the table is written, returning
+	/// [`ControlFlow::Break`] stops the conversion with [`Error::Cancelled`](crate::Error::Cancelled)
+	///
+	/// ```rust
+	/// use std::ops::ControlFlow;
+	/// use yaml2lua::{parse_with_options, Error, ParseOptions, ProgressInterval};
+	///
+	/// let options = ParseOptions::new().on_progress(ProgressInterval::Nodes(2), |progress| {
+	/// 	if progress.nodes > 3 {
+	/// 		ControlFlow::Break(())
+	/// 	} else {
+	/// 		ControlFlow::Continue(())
+	/// 	}
+	/// });
+	///
+	/// assert!(matches!(
+	/// 	parse_with_options("a: [1, 2, 3, 4]", &options),
+	/// 	Err(Error::Cancelled)
+	/// ));
+	/// ```
+	pub fn on_progress(
+		mut self,
+		interval: ProgressInterval,
+		callback: impl Fn(&Progress) -> ControlFlow<()> + Send + Sync + 'static,
+	) -> Self {
+		self.progress = Some(ProgressHook {
+			interval,
+			callback: Arc::new(callback),
+		});
+		self
+	}
+
+	/// Rewrite the finished Lua before it's returned or written, like adding a license header
+	///
+	/// The hook also receives the path of the YAML file when converting files with
+	/// [`convert_dir`](crate::convert_dir) and similar functions. Hooks added
+	/// by calling this multiple times run in the order they were added, failing
+	/// hooks make the conversion return [`Error::PostProcess`](crate::Error::PostProcess)
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().post_process(|lua, _| {
+	/// 	Ok::<_, std::fmt::Error>(format!("-- Generated, do not edit\n{}", lua))
+	/// });
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: 1", &options).unwrap(),
+	/// 	"-- Generated, do not edit\n{\n\t[\"a\"] = 1,\n}"
+	/// );
+	/// ```
+	pub fn post_process<E: Into<HookError>>(
+		mut self,
+		hook: impl Fn(String, Option<&Path>) -> Result<String, E> + Send + Sync + 'static,
+	) -> Self {
+		self.post_process
+			.push(PostProcessHook(Arc::new(move |lua, path| {
+				hook(lua, path).map_err(Into::into)
+			})));
+		self
+	}
+
+	/// Choose what kind of Lua code is generated around the table
+	pub fn output_mode(mut self, output_mode: OutputMode) -> Self {
+		self.output_mode = output_mode;
+		self
+	}
+
+	/// Start the output with a comment, like a note that the file is generated.
+	/// Every line of the text becomes a `--` comment line before any other code
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, OutputMode, ParseOptions};
+	///
+	/// let options = ParseOptions::new()
+	/// 	.output_mode(OutputMode::Module)
+	/// 	.header("Generated from config.yaml\nDo not edit");
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: 1", &options).unwrap(),
+	/// 	"-- Generated from config.yaml\n-- Do not edit\nreturn {\n\t[\"a\"] = 1,\n}"
+	/// );
+	/// ```
+	pub fn header(mut self, header: impl Into<String>) -> Self {
+		self.header = Some(header.into());
+		self
+	}
+
+	/// Indent every line of the output by the given number of tabs,
+	/// as if it was nested that deep in surrounding Lua code
+	pub fn base_depth(mut self, base_depth: usize) -> Self {
+		self.base_depth = base_depth;
+		self
+	}
+
+	/// Indent nested entries with tabs or the given number of spaces per level, tabs by default
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, Indent, ParseOptions};
+	///
+	/// let options = ParseOptions::new().indent(Indent::Spaces(2));
+	///
+	/// assert_eq!(parse_with_options("a: [1]", &options).unwrap(), "{\n  [\"a\"] = {\n    1,\n  },\n}");
+	/// ```
+	pub fn indent(mut self, indent: Indent) -> Self {
+		self.indent = indent;
+		self
+	}
+
+	/// Write a comma after the last entry of tables, enabled by default
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().trailing_commas(false);
+	///
+	/// assert_eq!(parse_with_options("a: 1\nb: 2", &options).unwrap(), "{\n\t[\"a\"] = 1,\n\t[\"b\"] = 2\n}");
+	/// ```
+	pub fn trailing_commas(mut self, trailing_commas: bool) -> Self {
+		self.omit_trailing_commas = !trailing_commas;
+		self
+	}
+
+	/// Write the output on a single line without indentation and with as few spaces as possible,
+	/// for tables embedded where size matters more than readability
+	///
+	/// Statements like local declarations are separated by spaces, comments written
+	/// for entries become block comments and the last entries of tables don't end with
+	/// a comma. Annotations, chunked output and a [`header`](Self::header), which
+	/// takes lines of its own, can't be used
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().compact(true);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: 1\nb: [x, y]", &options).unwrap(),
+	/// 	"{[\"a\"]=1,[\"b\"]={\"x\",\"y\"}}"
+	/// );
+	/// ```
+	pub fn compact(mut self, compact: bool) -> Self {
+		self.compact = compact;
+		self
+	}
+
+	/// Delimit strings and string keys with double or single quotes, double by default
+	///
+	/// ```rust
+	/// use yaml2lua::{fmt::QuoteStyle, parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().quote_style(QuoteStyle::Single);
+	///
+	/// assert_eq!(parse_with_options("a: it's", &options).unwrap(), "{\n\t['a'] = 'it\\'s',\n}");
+	/// ```
+	pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+		self.quote_style = quote_style;
+		self
+	}
+
+	/// Write strings spanning multiple lines as long strings like `[[...]]`, keeping
+	/// their lines as they are instead of escaping the newlines
+	///
+	/// Brackets get as many `=` as needed so the string can't close them, like `[=[...]=]`
+	/// for strings containing `]]`. Strings with control characters other than tabs keep
+	/// their escapes, since Lua reads `\r\n` in long strings as `\n`. Compact output can't be used
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().long_strings(true);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("text: |\n  first\n  second", &options).unwrap(),
+	/// 	"{\n\t[\"text\"] = [[\nfirst\nsecond]],\n}"
+	/// );
+	/// ```
+	pub fn long_strings(mut self, long_strings: bool) -> Self {
+		self.long_strings = long_strings;
+		self
+	}
+
+	/// End the output with a newline, which many editors and linters expect from files
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().final_newline(true);
+	///
+	/// assert_eq!(parse_with_options("a: 1", &options).unwrap(), "{\n\t[\"a\"] = 1,\n}\n");
+	/// ```
+	pub fn final_newline(mut self, final_newline: bool) -> Self {
+		self.final_newline = final_newline;
+		self
+	}
+
+	/// End the last statement of the output with a semicolon, before the final newline
+	///
+	/// Requires [`OutputMode::Module`] or [`OutputMode::Assignment`], since a table
+	/// constructor followed by a semicolon isn't a valid expression
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, OutputMode, ParseOptions};
+	///
+	/// let options = ParseOptions::new()
+	/// 	.output_mode(OutputMode::Assignment(String::from("Config")))
+	/// 	.trailing_semicolon(true);
+	///
+	/// assert_eq!(parse_with_options("a: 1", &options).unwrap(), "Config = {\n\t[\"a\"] = 1,\n};");
+	/// ```
+	pub fn trailing_semicolon(mut self, trailing_semicolon: bool) -> Self {
+		self.trailing_semicolon = trailing_semicolon;
+		self
+	}
+
+	/// Split tables with more entries than the threshold into separate assignments
+	///
+	/// Instead of one huge table constructor, which hits constant and recursion
+	/// limits in some Lua implementations, the table is built statement by statement:
+	///
+	/// ```lua
+	/// local t = {}
+	/// t["items"] = {}
+	/// t["items"][1] = "a"
+	/// return t
+	/// ```
+	///
+	/// Nested tables are split recursively in the same order as the constructor form.
+	/// Requires [`OutputMode::Module`] or [`OutputMode::Assignment`]
+	pub fn chunk_threshold(mut self, threshold: usize) -> Self {
+		self.chunk_threshold = Some(threshold);
+		self
+	}
+
+	/// Hoist repeated string values into `local __s1 = "..."` declarations
+	/// placed before the table and reference them instead of repeating the literals
+	///
+	/// Requires [`OutputMode::Module`] or [`OutputMode::Assignment`], the number
+	/// of hoisted strings is capped to stay within Lua's limit of 200 locals
+	pub fn dedupe_strings(mut self, dedup: StringDedup) -> Self {
+		self.dedupe_strings = Some(dedup);
+		self
+	}
+
+	/// Hoist tables that occur more than once into `local __shared1 = { ... }`
+	/// declarations placed before the table and reference them where they occur
+	///
+	/// Occurrences reference the same table unless [`SubtreeDedup::copy`] is set,
+	/// which deep copies the declared table at every use instead. Requires
+	/// [`OutputMode::Module`] or [`OutputMode::Assignment`]
+	pub fn dedupe_subtrees(mut self, dedup: SubtreeDedup) -> Self {
+		self.dedupe_subtrees = Some(dedup);
+		self
+	}
+
+	/// Hoist anchored tables that aliases refer to into `local __anchor_name = { ... }`
+	/// declarations placed before the table and reference them at the anchor and its aliases,
+	/// so they're the same table in Lua like they're the same node in YAML
+	///
+	/// Aliases merged with `<<` and occurrences written differently, like ones
+	/// coerced by a schema at their path, are written in full. Requires
+	/// [`OutputMode::Module`] or [`OutputMode::Assignment`] and can't be
+	/// used with [`dedupe_subtrees`](Self::dedupe_subtrees)
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, OutputMode, ParseOptions};
+	///
+	/// let yaml = "base: &base { hp: 10 }\nboss: *base";
+	/// let options = ParseOptions::new().output_mode(OutputMode::Module).preserve_anchors(true);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options(yaml, &options).unwrap(),
+	/// 	"local __anchor_base = {\n\t[\"hp\"] = 10,\n}\n\nreturn {\n\t[\"base\"] = __anchor_base,\n\t[\"boss\"] = __anchor_base,\n}"
+	/// );
+	/// ```
+	pub fn preserve_anchors(mut self, preserve: bool) -> Self {
+		self.preserve_anchors = preserve;
+		self
+	}
+
+	/// Append a trailing comment describing the original value to entries,
+	/// like `["pos"] = { ... }, -- !Vector3`
+	pub fn comments(mut self, comments: Comments) -> Self {
+		self.comments = comments;
+		self
+	}
+
+	/// Convert string keys that look like numbers into numeric keys
+	pub fn numeric_keys(mut self, numeric_keys: NumericKeys) -> Self {
+		self.numeric_keys = Some(numeric_keys);
+		self
+	}
+
+	/// Rewrite string keys using the given style
+	pub fn key_style(mut self, key_style: KeyStyle) -> Self {
+		self.key_style = key_style;
+		self
+	}
+
+	/// Rename string keys with the function, for conventions the [`key_style`](Self::key_style)
+	/// doesn't cover. The style is applied to the renamed keys, and keys renamed
+	/// to the same key are reported like other colliding keys
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().rename_keys(|key| key.replace("colour", "color"));
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("text-colour: red", &options).unwrap(),
+	/// 	"{\n\t[\"text-color\"] = \"red\",\n}"
+	/// );
+	/// ```
+	pub fn rename_keys(mut self, rename: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+		self.rename_keys = Some(RenameKeys(Arc::new(rename)));
+		self
+	}
+
+	/// Write string keys that are valid Lua identifiers bare like `name = value`
+	/// instead of `["name"] = value`, other keys stay bracketed
+	///
+	/// Keys rewritten by the [`key_style`](Self::key_style) are checked after rewriting
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().bare_keys(true);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("name: a\nmax-players: 4\nend: 1", &options).unwrap(),
+	/// 	"{\n\tname = \"a\",\n\t[\"max-players\"] = 4,\n\t[\"end\"] = 1,\n}"
+	/// );
+	/// ```
+	pub fn bare_keys(mut self, bare_keys: bool) -> Self {
+		self.bare_keys = bare_keys;
+		self
+	}
+
+	/// Add `__original_keys` table to every table with keys changed
+	/// by [`KeyStyle::SanitizedIdentifiers`], mapping them to the original ones
+	pub fn original_keys_table(mut self, original_keys_table: bool) -> Self {
+		self.original_keys_table = original_keys_table;
+		self
+	}
+
+	/// Fold sequences of single-key mappings like `[{ first: 1 }, { second: 2 }]`
+	/// into one table `{ ["first"] = 1, ["second"] = 2 }`, keeping the order of the entries
+	///
+	/// Keys repeated across the elements are handled by [`duplicate_keys`](Self::duplicate_keys).
+	/// Sequences mixing single-key mappings with other values aren't folded and get
+	/// an info diagnostic. Paths of folded entries skip the index, like `list.first`
+	pub fn fold_single_key_maps(mut self, fold_single_key_maps: bool) -> Self {
+		self.fold_single_key_maps = fold_single_key_maps;
+		self
+	}
+
+	/// Add the number of elements to every sequence under the given key as its
+	/// first entry, like `{ ["n"] = 3, "a", nil, "c" }` in the `table.pack` convention
+	///
+	/// Null elements are counted too, so the length is right even when `#` isn't
+	pub fn sequence_length_field(mut self, field: impl Into<String>) -> Self {
+		self.sequence_length_field = Some(field.into());
+		self
+	}
+
+	/// Order of positional and keyed entries in generated tables
+	pub fn table_layout(mut self, table_layout: TableLayout) -> Self {
+		self.table_layout = table_layout;
+		self
+	}
+
+	/// Write up to the given number of elements per line in sequences of scalars,
+	/// like long arrays of numbers, instead of one element per line
+	///
+	/// Sequences with tables or tagged values, and sequences whose elements get
+	/// comments, are still written one element per line. Chunked output always
+	/// writes one element per line
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().scalars_per_line(2);
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: [1, 2, 3]", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = {\n\t\t1, 2,\n\t\t3,\n\t},\n}"
+	/// );
+	/// ```
+	pub fn scalars_per_line(mut self, per_line: usize) -> Self {
+		self.scalars_per_line = Some(per_line);
+		self
+	}
+
+	/// What to do when rewritten keys of a table collide with each other
+	pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+		self.duplicate_keys = duplicate_keys;
+		self
+	}
+
+	/// Emit scalars at paths matching any of the patterns as strings, whatever type
+	/// YAML resolves them into, so `version: 1.10` stays `"1.10"` instead of `1.1`
+	///
+	/// Strings use the text of the scalar as written, values without their own text
+	/// like aliases use canonical formatting. Patterns use the [`PathPattern`] syntax
+	/// and are matched against the document before structural transformations
+	pub fn force_string_paths<S: AsRef<str>>(
+		mut self,
+		patterns: impl IntoIterator<Item = S>,
+	) -> crate::Result<Self> {
+		for pattern in patterns {
+			self.force_string_paths.push(pattern.as_ref().parse()?);
+		}
+
+		Ok(self)
+	}
+
+	/// Emit strings at paths matching the patterns as unquoted references to constants
+	/// defined elsewhere, with the prefix of the first matching pattern applied
+	///
+	/// This writes values from the document into the output as Lua code, so only use it
+	/// for values that are meant to be constants. Strings that aren't valid identifiers
+	/// fail with [`Error::Transform`]. Prefixes have to be empty or identifiers joined
+	/// and followed by `.`, patterns use the [`PathPattern`] syntax and are matched
+	/// against the document after structural transformations
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new()
+	/// 	.constant_references([("difficulty", "Difficulty.")])
+	/// 	.unwrap();
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("difficulty: HARD", &options).unwrap(),
+	/// 	"{\n\t[\"difficulty\"] = Difficulty.HARD,\n}"
+	/// );
+	/// assert!(parse_with_options("difficulty: very hard", &options).is_err());
+	/// ```
+	pub fn constant_references<S: AsRef<str>, P: Into<String>>(
+		mut self,
+		references: impl IntoIterator<Item = (S, P)>,
+	) -> crate::Result<Self> {
+		for (pattern, prefix) in references {
+			let prefix = prefix.into();
+
+			let valid = prefix.is_empty()
+				|| prefix
+					.strip_suffix('.')
+					.is_some_and(|path| path.split('.').all(is_lua_identifier));
+
+			if !valid {
+				return Err(Error::Options(format!(
+					"`{}` can't prefix constant references, prefixes are identifiers followed by `.`",
+					prefix
+				)));
+			}
+
+			self.constant_references
+				.push((pattern.as_ref().parse()?, prefix));
+		}
+
+		Ok(self)
+	}
+
+	/// Sort entries of every table by their key instead of keeping the order
+	/// from YAML, numbers come first followed by strings and booleans
+	pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+		self.sort_keys = sort_keys;
+		self
+	}
+
+	/// Report a warning when keys of different types in one table are equal once
+	/// compared the way the consumer might, like `1` and `"1"` compared as strings
+	///
+	/// Both keys are still emitted, Lua treats them as different keys
+	pub fn key_type_collisions(mut self, comparison: KeyComparison) -> Self {
+		self.key_type_collisions = comparison;
+		self
+	}
+
+	/// Flatten nested tables into a single table with keys joined by the separator
+	///
+	/// `server: { ports: [80] }` becomes `["server.ports.1"] = 80` with `.` separator,
+	/// sequence elements use 1-based indices like Lua arrays. Empty tables are kept
+	/// as `{}` values at their flattened keys, keys containing the separator are errors
+	pub fn flatten(mut self, separator: impl Into<String>) -> Self {
+		self.flatten = Some(separator.into());
+		self
+	}
+
+	/// Split string keys on the separator into nested tables, the inverse of [`flatten`](Self::flatten)
+	///
+	/// `server.host: x` becomes `["server"] = { ["host"] = "x" }` with `.` separator,
+	/// tables sharing a prefix are merged. Keys that are both a value and a prefix
+	/// of another key (`a: 1` and `a.b: 2`) are errors. To keep the separator
+	/// in a key, escape it with a backslash like `a\.b`
+	pub fn expand_dotted_keys(mut self, separator: impl Into<String>) -> Self {
+		self.expand_dotted_keys = Some(separator.into());
+		self
+	}
+
+	/// How sequences are merged by [`parse_merged`](crate::parse_merged)
+	pub fn merge_sequences(mut self, merge_sequences: SequenceMerge) -> Self {
+		self.merge_sequences = merge_sequences;
+		self
+	}
+
+	/// How type conflicts are handled by [`parse_merged`](crate::parse_merged)
+	pub fn merge_conflicts(mut self, merge_conflicts: MergeConflicts) -> Self {
+		self.merge_conflicts = merge_conflicts;
+		self
+	}
+
+	/// Round floats to the given number of decimal places
+	///
+	/// Rounding uses round-half-even on the shortest decimal representation
+	/// of the float, trailing zeros are trimmed and whole results keep `.0`
+	/// so they stay floats. Applies to both values and numeric keys,
+	/// integers and non-finite floats are left untouched
+	pub fn float_precision(mut self, precision: u8) -> Self {
+		self.float_precision = Some(precision);
+		self
+	}
+
+	/// Coerce scalars into types declared by the schema, values that can't
+	/// be coerced are reported as warnings and left as they are
+	pub fn schema(mut self, schema: Schema) -> Self {
+		self.schema = Some(schema);
+		self
+	}
+
+	/// Limit length of string values and keys to the given number of characters,
+	/// measured before escaping. Strings of any length are kept by default
+	///
+	/// Truncated strings are reported with [`DiagnosticKind::TruncatedString`](crate::DiagnosticKind::TruncatedString).
+	/// Suffixes of [`StringOverflow::Truncate`] longer than the limit fail with
+	/// [`Error::Options`](crate::Error::Options), since no truncated string could fit
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions, StringOverflow};
+	///
+	/// let options = ParseOptions::new().max_string_len(8, StringOverflow::Truncate(String::from("…")));
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: aGVsbG8gd29ybGQ=", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = \"aGVsbG8…\",\n}"
+	/// );
+	/// ```
+	pub fn max_string_len(mut self, max: usize, overflow: StringOverflow) -> Self {
+		self.max_string_len = Some((max, overflow));
+		self
+	}
+
+	/// Annotate entries with comments pointing at the line they come from in the source,
+	/// like `["max_players"] = 20, -- config/server.yaml:14`
+	///
+	/// Only entries with a line in the source get a comment, entries created by
+	/// transformations or merged from other documents don't. Files converted by
+	/// [`convert_dir`](crate::convert_dir) use their path when no file name is set
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions, SourceComments};
+	///
+	/// let options = ParseOptions::new().source_comments(SourceComments {
+	/// 	file: Some(String::from("server.yaml")),
+	/// 	..Default::default()
+	/// });
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: 1\nb: 2", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = 1, -- server.yaml:1\n\t[\"b\"] = 2, -- server.yaml:2\n}"
+	/// );
+	/// ```
+	pub fn source_comments(mut self, source_comments: SourceComments) -> Self {
+		self.source_comments = Some(source_comments);
+		self
+	}
+
+	/// Write sequences of mappings with the same keys as a header with the keys
+	/// and one positional table of values per mapping, instead of repeating the keys
+	///
+	/// Elements that aren't mappings with exactly the keys of the first mapping are
+	/// written as usual and reported with [`DiagnosticKind::IrregularRecord`](crate::DiagnosticKind::IrregularRecord).
+	/// Can't be used with chunked output, subtree deduplication, previews, validators or annotations
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ColumnarRecords, ParseOptions};
+	///
+	/// let options = ParseOptions::new().columnar_records(ColumnarRecords::default());
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: [{ id: 1, hp: 10 }, { id: 2, hp: 20 }]", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = {\n\t\t[\"__fields\"] = { \"id\", \"hp\" },\n\t\t{ 1, 10 },\n\t\t{ 2, 20 },\n\t},\n}"
+	/// );
+	/// ```
+	pub fn columnar_records(mut self, columnar: ColumnarRecords) -> Self {
+		self.columnar_records = Some(columnar);
+		self
+	}
+
+	/// Lua expression written in place of values that fail to convert
+	/// with [`parse_partial`](crate::parse_partial), `nil` by default
+	pub fn error_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+		self.error_placeholder = Some(placeholder.into());
+		self
+	}
+
+	/// Render a shortened preview of the document, leaving out tables nested too
+	/// deep and entries over the limit with comments like `-- … 412 more entries`
+	///
+	/// Output is still valid Lua. Every left out part is reported as an info diagnostic
+	/// with [`DiagnosticKind::Elided`](crate::DiagnosticKind::Elided) and counted in
+	/// [`Stats::elided_entries`](crate::Stats::elided_entries). Can't be used with chunked output
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions, Preview};
+	///
+	/// let options = ParseOptions::new().preview(Preview {
+	/// 	max_render_depth: 1,
+	/// 	max_entries_per_table: 1,
+	/// });
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: { b: 1 }\nc: 2", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = { --[[ … 1 entry ]] },\n\t-- … 1 more entry\n}"
+	/// );
+	/// ```
+	pub fn preview(mut self, preview: Preview) -> Self {
+		self.preview = Some(preview);
+		self
+	}
+
+	/// Write an `init.lua` index into every directory converted by
+	/// [`convert_dir`](crate::convert_dir), requiring its modules and the indexes
+	/// of its subdirectories so the whole tree is reachable from the root one
+	///
+	/// ```lua
+	/// return {
+	/// 	maps = require(script.maps),
+	/// 	weapons = require(script.weapons),
+	/// }
+	/// ```
+	///
+	/// Keys are file stems and directory names sanitized into identifiers, names that
+	/// collide after sanitizing or are called `init` are reported as failed files
+	pub fn index_modules(mut self, style: ModuleStyle) -> Self {
+		self.index_modules = Some(style);
+		self
+	}
+
+	/// Parse strings tagged like `payload: !yaml "a: 1"` as YAML and emit
+	/// the parsed value in their place, see [`EmbeddedYaml`]
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, EmbeddedYaml, ParseOptions};
+	///
+	/// let options = ParseOptions::new().embedded_yaml(EmbeddedYaml::default());
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("payload: !yaml \"[1, 2]\"", &options).unwrap(),
+	/// 	"{\n\t[\"payload\"] = {\n\t\t1,\n\t\t2,\n\t},\n}"
+	/// );
+	/// ```
+	pub fn embedded_yaml(mut self, embedded: EmbeddedYaml) -> Self {
+		self.embedded_yaml = Some(embedded);
+		self
+	}
+
+	/// Fail with [`Error::TooDeep`](crate::Error::TooDeep) if tables are nested deeper
+	/// than the given depth, the root table has depth of 1
+	///
+	/// Documents are already limited to 128 levels by the YAML parser, but embedded YAML
+	/// and serialized values can be nested deeper. The depth is checked before the document
+	/// is transformed or written, without recursing into the tables, so inputs from
+	/// untrusted sources can't overflow the stack
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, Error, ParseOptions};
+	///
+	/// let options = ParseOptions::new().max_depth(2);
+	///
+	/// assert!(parse_with_options("a: [1]", &options).is_ok());
+	/// assert!(matches!(
+	/// 	parse_with_options("a: [[1]]", &options),
+	/// 	Err(Error::TooDeep { max: 2, .. })
+	/// ));
+	/// ```
+	pub fn max_depth(mut self, max_depth: usize) -> Self {
+		self.max_depth = Some(max_depth);
+		self
+	}
+
+	/// Choose what happens to tagged values that aren't handled by any other option,
+	/// at any depth including the document root
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions, TagHandler, UnknownTags};
+	///
+	/// let options = ParseOptions::new().on_unknown_tag(UnknownTags::Custom(TagHandler::new(
+	/// 	|tag, value| Ok::<_, std::fmt::Error>(format!("{}.new({})", tag, value.as_u64().unwrap_or(0))),
+	/// )));
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("id: !Id 7", &options).unwrap(),
+	/// 	"{\n\t[\"id\"] = Id.new(7),\n}"
+	/// );
+	/// ```
+	pub fn on_unknown_tag(mut self, policy: UnknownTags) -> Self {
+		self.unknown_tags = policy;
+		self
+	}
+
+	/// Replace values with the tag by the result of the handler, like writing
+	/// `!Vector3 [1, 2, 3]` as a constructor call instead of a wrapper table
+	///
+	/// Tag is the name without the leading `!`, like in [`TagHandler`]. Handling
+	/// the same tag again replaces its handler, tags without a handler are handled
+	/// as set by [`on_unknown_tag`](Self::on_unknown_tag)
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().with_tag("Vector3", |value| {
+	/// 	let numbers: Vec<String> = value
+	/// 		.as_sequence()
+	/// 		.ok_or("expected a sequence")?
+	/// 		.iter()
+	/// 		.map(|n| n.as_f64().unwrap_or(0.0).to_string())
+	/// 		.collect();
+	///
+	/// 	Ok::<_, &str>(format!("Vector3.new({})", numbers.join(", ")))
+	/// });
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("spawn: !Vector3 [1, 2.5, 3]", &options).unwrap(),
+	/// 	"{\n\t[\"spawn\"] = Vector3.new(1, 2.5, 3),\n}"
+	/// );
+	/// ```
+	pub fn with_tag<R: Into<TagReplacement>, E: Into<HookError>>(
+		mut self,
+		tag: impl Into<String>,
+		handler: impl Fn(&Value) -> Result<R, E> + Send + Sync + 'static,
+	) -> Self {
+		let tag = tag.into();
+		let handler = TagHandler::new(move |_, value| handler(value));
+
+		match self.tag_handlers.iter_mut().find(|(name, _)| *name == tag) {
+			Some((_, existing)) => *existing = handler,
+			None => self.tag_handlers.push((tag, handler)),
+		}
+
+		self
+	}
+
+	/// Write values tagged with Roblox datatypes as their constructors, like
+	/// `Color3.new(1, 0, 0)` for `!Color3 [1, 0, 0]`
+	///
+	/// Handled tags are `Vector2`, `Vector3`, `Color3`, `UDim`, `UDim2`, `CFrame`,
+	/// `NumberRange` and `Enum`. Numbers are written in constructor order as a sequence or
+	/// as a mapping with their field names like `{x: 1, y: 2, z: 3}`, colors can also be
+	/// hex strings and enum items are written like `Material.Wood`. Values that don't fit
+	/// the datatype fail with [`Error::TagHandler`](crate::Error::TagHandler), handlers
+	/// registered later with [`with_tag`](Self::with_tag) replace these
+	///
+	/// Requires the `roblox` feature
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().roblox_datatypes();
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("size: !UDim2 [0, 10, 1, 0]\nmaterial: !Enum Material.Wood", &options).unwrap(),
+	/// 	"{\n\t[\"size\"] = UDim2.new(0, 10, 1, 0),\n\t[\"material\"] = Enum.Material.Wood,\n}"
+	/// );
+	/// ```
+	#[cfg(feature = "roblox")]
+	pub fn roblox_datatypes(self) -> Self {
+		crate::roblox::DATATYPES
+			.iter()
+			.fold(self, |options, (tag, datatype)| {
+				options.with_tag(*tag, datatype)
+			})
+	}
+
+	/// Set the format of wrapper tables written for tagged values by [`UnknownTags::Wrapper`]
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions, TagWrapper};
+	///
+	/// let options = ParseOptions::new().tag_wrapper(TagWrapper::Key(String::from("__tag_$tag")));
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("a: !Id 7", &options).unwrap(),
+	/// 	"{\n\t[\"a\"] = {\n\t\t[\"__tag_Id\"] = 7,\n\t},\n}"
+	/// );
+	/// ```
+	pub fn tag_wrapper(mut self, wrapper: TagWrapper) -> Self {
+		self.tag_wrapper = wrapper;
+		self
+	}
+
+	/// Pin the output formatting to one of the [`FORMAT_VERSIONS`](crate::FORMAT_VERSIONS),
+	/// so output committed to a repository doesn't change when the crate is updated
+	///
+	/// Unpinned options use the latest version. Options that change the output
+	/// on purpose, like [`KeyStyle`] or [`Comments`], work with every version.
+	/// Unsupported versions and options changing the formatting the version
+	/// specifies, like [`compact`](Self::compact), [`Indent`], quotes, bare keys,
+	/// table layouts and trailing commas, fail with [`Error::Options`](crate::Error::Options)
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().format_version(1);
+	///
+	/// assert_eq!(parse_with_options("a: 1.0", &options).unwrap(), "{\n\t[\"a\"] = 1.0,\n}");
+	/// assert!(parse_with_options("a: 1", &ParseOptions::new().format_version(0)).is_err());
+	/// assert!(parse_with_options("a: 1", &options.compact(true)).is_err());
+	/// ```
+	pub fn format_version(mut self, version: u32) -> Self {
+		self.format_version = Some(version);
+		self
+	}
+
+	/// Put documents with a sequence or a scalar at the root under the key, so
+	/// the output is always a table with keys like `{ ["items"] = { ... } }`
+	///
+	/// The key is written like any other key and paths in other options and in
+	/// diagnostics start with it. Mappings are already tables with keys, so mapping
+	/// roots are written as they are. Tagged roots and empty documents aren't put under the key
+	///
+	/// ```rust
+	/// use yaml2lua::{parse_with_options, ParseOptions};
+	///
+	/// let options = ParseOptions::new().root_key("items");
+	///
+	/// assert_eq!(
+	/// 	parse_with_options("- a\n- b", &options).unwrap(),
+	/// 	"{\n\t[\"items\"] = {\n\t\t\"a\",\n\t\t\"b\",\n\t},\n}"
+	/// );
+	/// assert_eq!(parse_with_options("a: 1", &options).unwrap(), "{\n\t[\"a\"] = 1,\n}");
+	/// ```
+	pub fn root_key(mut self, key: impl Into<String>) -> Self {
+		self.root_key = Some(key.into());
+		self
+	}
+
+	/// Fail on any warning diagnostic instead of converting leniently, entries with
+	/// keys that can't be table keys fail with [`Error::UnsupportedKey`](crate::Error::UnsupportedKey)
+	/// and other warnings with [`Error::Strict`](crate::Error::Strict). This is the strict
+	/// mode that the [`strict`](Self::strict) preset turns on with the other lossy conversions
+	pub fn warnings_as_errors(mut self, strict: bool) -> Self {
+		self.strict = strict;
+		self
+	}
+
+	/// Get names of the builder methods of options that aren't set to their defaults
+	pub(crate) fn changed(&self) -> Vec<&'static str> {
+		// Destructured so new options can't be forgotten here
+		let ParseOptions {
+			numeric_keys,
+			float_precision,
+			strict,
+			schema,
+			key_style,
+			rename_keys,
+			bare_keys,
+			duplicate_keys,
+			key_type_collisions,
+			sort_keys,
+			force_string_paths,
+			constant_references,
+			original_keys_table,
+			fold_single_key_maps,
+			sequence_length_field,
+			table_layout,
+			scalars_per_line,
+			flatten,
+			expand_dotted_keys,
+			merge_sequences,
+			merge_conflicts,
+			output_mode,
+			header,
+			base_depth,
+			chunk_threshold,
+			dedupe_strings,
+			dedupe_subtrees,
+			preserve_anchors,
+			comments,
+			dialect,
+			freeze,
+			validator,
+			annotations,
+			luau_types,
+			progress,
+			post_process,
+			max_string_len,
+			preview,
+			error_placeholder,
+			source_comments,
+			columnar_records,
+			fidelity,
+			non_finite_floats,
+			large_integers,
+			binary,
+			timestamps,
+			nulls,
+			index_modules,
+			embedded_yaml,
+			max_depth,
+			unknown_tags,
+			tag_handlers,
+			tag_wrapper,
+			format_version,
+			root_key,
+			final_newline,
+			trailing_semicolon,
+			indent,
+			omit_trailing_commas,
+			compact,
+			quote_style,
+			long_strings,
+		} = self;
+
+		[
+			("numeric_keys", numeric_keys.is_some()),
+			("float_precision", float_precision.is_some()),
+			("warnings_as_errors", *strict),
+			("schema", schema.is_some()),
+			("key_style", *key_style != KeyStyle::default()),
+			("rename_keys", rename_keys.is_some()),
+			("bare_keys", *bare_keys),
+			(
+				"duplicate_keys",
+				*duplicate_keys != DuplicateKeys::default(),
+			),
+			(
+				"key_type_collisions",
+				*key_type_collisions != KeyComparison::default(),
+			),
+			("sort_keys", *sort_keys),
+			("force_string_paths", !force_string_paths.is_empty()),
+			("constant_references", !constant_references.is_empty()),
+			("original_keys_table", *original_keys_table),
+			("fold_single_key_maps", *fold_single_key_maps),
+			("sequence_length_field", sequence_length_field.is_some()),
+			("table_layout", *table_layout != TableLayout::default()),
+			("scalars_per_line", scalars_per_line.is_some()),
+			("flatten", flatten.is_some()),
+			("expand_dotted_keys", expand_dotted_keys.is_some()),
+			(
+				"merge_sequences",
+				*merge_sequences != SequenceMerge::default(),
+			),
+			(
+				"merge_conflicts",
+				*merge_conflicts != MergeConflicts::default(),
+			),
+			("output_mode", *output_mode != OutputMode::default()),
+			("header", header.is_some()),
+			("base_depth", *base_depth != 0),
+			("chunk_threshold", chunk_threshold.is_some()),
+			("dedupe_strings", dedupe_strings.is_some()),
+			("dedupe_subtrees", dedupe_subtrees.is_some()),
+			("preserve_anchors", *preserve_anchors),
+			("comments", *comments != Comments::default()),
+			("dialect", *dialect != Dialect::default()),
+			("freeze", *freeze),
+			("validator", *validator),
+			("annotations", annotations.is_some()),
+			("luau_types", luau_types.is_some()),
+			("on_progress", progress.is_some()),
+			("post_process", !post_process.is_empty()),
+			("max_string_len", max_string_len.is_some()),
+			("preview", preview.is_some()),
+			("error_placeholder", error_placeholder.is_some()),
+			("source_comments", source_comments.is_some()),
+			("columnar_records", columnar_records.is_some()),
+			("fidelity", *fidelity != FidelityMode::default()),
+			(
+				"non_finite_floats",
+				*non_finite_floats != NonFiniteFloats::default(),
+			),
+			(
+				"large_integers",
+				*large_integers != LargeIntegers::default(),
+			),
+			("binary", *binary != Binary::default()),
+			("timestamps", *timestamps != Timestamps::default()),
+			("nulls", *nulls != Nulls::default()),
+			("index_modules", index_modules.is_some()),
+			("embedded_yaml", embedded_yaml.is_some()),
+			("max_depth", max_depth.is_some()),
+			(
+				"on_unknown_tag",
+				!matches!(unknown_tags, UnknownTags::Wrapper),
+			),
+			("with_tag", !tag_handlers.is_empty()),
+			("tag_wrapper", *tag_wrapper != TagWrapper::default()),
+			("format_version", format_version.is_some()),
+			("root_key", root_key.is_some()),
+			("final_newline", *final_newline),
+			("trailing_semicolon", *trailing_semicolon),
+			("indent", *indent != Indent::default()),
+			("trailing_commas", *omit_trailing_commas),
+			("compact", *compact),
+			("quote_style", *quote_style != QuoteStyle::default()),
+			("long_strings", *long_strings),
+		]
+		.into_iter()
+		.filter_map(|(name, changed)| changed.then_some(name))
+		.collect()
+	}
+
+	/// Start the output with the [`header`](Self::header) comment
+	pub(crate) fn begin_output(&self, lua: &mut String) {
+		let Some(header) = &self.header else {
+			return;
+		};
+
+		for line in header.lines() {
+			lua.push_str("--");
+
+			if !line.is_empty() {
+				lua.push(' ');
+				lua.push_str(line);
+			}
+
+			lua.push('\n');
+		}
+	}
+
+	/// End the output as set by [`final_newline`](Self::final_newline) and
+	/// [`trailing_semicolon`](Self::trailing_semicolon)
+	pub(crate) fn end_output(&self, lua: &mut String) {
+		if self.trailing_semicolon && !lua.ends_with(';') {
+			lua.push(';');
+		}
+
+		if self.final_newline && !lua.ends_with('\n') {
+			lua.push('\n');
+		}
+	}
+
+	/// End the finished output of the YAML file at the path and run post-processing hooks on it,
+	/// so hooks see the final code
+	pub(crate) fn finish(&self, mut lua: String, path: Option<&Path>) -> crate::Result<String> {
+		self.end_output(&mut lua);
+
+		for PostProcessHook(hook) in &self.post_process {
+			lua = hook(lua, path).map_err(|source| Error::PostProcess {
+				path: path.map(Path::to_owned),
+				source,
+			})?;
+		}
+
+		Ok(lua)
+	}
+}
+
+/// Indentation of nested lines, see [`ParseOptions::indent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Indent {
+	/// One tab per level
+	#[default]
+	Tabs,
+	/// The given number of spaces per level
+	Spaces(usize),
+}
+
+impl Indent {
+	/// Write the indentation of the given nesting depth into the output
+	pub(crate) fn write(&self, output: &mut String, depth: usize) {
+		match self {
+			Indent::Tabs => output.extend(std::iter::repeat_n('\t', depth)),
+			Indent::Spaces(spaces) => output.extend(std::iter::repeat_n(' ', spaces * depth)),
+		}
+	}
+}
+
+/// What kind of Lua code is generated around the table
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputMode {
+	/// Table constructor expression `{ ... }`
+	#[default]
+	Table,
+	/// Requirable module `return { ... }`
+	Module,
+	/// Assignment to the given variable or field `name = { ... }`
+	Assignment(String),
+}
+
+/// How [`parse_stream`](crate::parse_stream) returns the documents of a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamStyle {
+	/// Multiple return values `return { ... }, { ... }`, for `local a, b = require(...)`
+	#[default]
+	MultipleValues,
+	/// Sequence of the documents `return { { ... }, { ... } }`
+	Table,
+}
+
+/// Lua implementation targeted by the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+	/// Lua 5.1 and LuaJIT, numbers are doubles
+	Lua51,
+	/// Lua 5.3 and 5.4 with 64-bit integers
+	#[default]
+	Lua54,
+	/// Luau used by Roblox, numbers are doubles
+	Luau,
+}
+
+impl Dialect {
+	pub(crate) fn has_integers(&self) -> bool {
+		*self == Dialect::Lua54
+	}
+
+	pub(crate) fn name(&self) -> &'static str {
+		match self {
+			Dialect::Lua51 => "Lua 5.1",
+			Dialect::Lua54 => "Lua 5.4",
+			Dialect::Luau => "Luau",
+		}
+	}
+}
+
+/// Which entries get a trailing comment, see [`ParseOptions::comments`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Comments {
+	/// No comments
+	#[default]
+	None,
+	/// Tagged values get their original tag like `-- !Tag`
+	Tags,
+	/// Like [`Comments::Tags`], also scalars get their Lua type like `-- string` or `-- integer`
+	Types,
+}
+
+/// Limits of a shortened preview, see [`ParseOptions::preview`]
+///
+/// Unlike hard limits like [`ParseOptions::max_string_len`] nothing fails,
+/// everything over the limits is left out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preview {
+	/// Deepest table nesting rendered, the root table has depth of 1
+	pub max_render_depth: usize,
+	/// Maximum number of entries rendered in one table
+	pub max_entries_per_table: usize,
+}
+
+impl Default for Preview {
+	fn default() -> Self {
+		Self {
+			max_render_depth: 3,
+			max_entries_per_table: 20,
+		}
+	}
+}
+
+/// How scalars that can't be represented exactly by a native Lua value are written
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum FidelityMode {
+	/// Write them as the closest Lua value, reporting lost precision with
+	/// [`DiagnosticKind::LargeInteger`](crate::DiagnosticKind::LargeInteger)
+	#[default]
+	Native,
+	/// Write them as tables with their type and text, like `{ __type = "u64", value = "18446744073709551615" }`
+	///
+	/// Wrapped values are reported with [`DiagnosticKind::WrappedScalar`](crate::DiagnosticKind::WrappedScalar).
+	/// Types of wrapped values are:
+	///
+	/// - `u64` for integers above the largest signed 64-bit integer
+	/// - `i64` for integers that don't fit into the doubles of Lua 5.1 and Luau exactly
+	/// - `binary` for `!!binary` strings, the value is their base64 text without whitespace
+	/// - `timestamp` for `!!timestamp` strings and plain scalars written like timestamps,
+	///   the value is their text
+	Wrapped(WrapperFields),
+}
+
+/// Names of the fields of tables written by [`FidelityMode::Wrapped`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrapperFields {
+	pub type_field: String,
+	pub value_field: String,
+}
+
+impl Default for WrapperFields {
+	fn default() -> Self {
+		Self {
+			type_field: String::from("__type"),
+			value_field: String::from("value"),
+		}
+	}
+}
+
+/// How infinite and NaN floats are written, see [`ParseOptions::non_finite_floats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFiniteFloats {
+	/// Write them as `math.huge`, `-math.huge` and `0/0`
+	#[default]
+	Expressions,
+	/// Fail with [`Error::Number`](crate::Error::Number)
+	Error,
+}
+
+/// How integers the target dialect can't represent exactly are written,
+/// see [`ParseOptions::large_integers`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LargeIntegers {
+	/// Write them as integer literals, reporting lost precision with
+	/// [`DiagnosticKind::LargeInteger`](crate::DiagnosticKind::LargeInteger)
+	#[default]
+	Native,
+	/// Write them as strings of their digits
+	String,
+	/// Write them as the closest float
+	Float,
+	/// Fail with [`Error::Number`](crate::Error::Number)
+	Error,
+}
+
+/// How strings tagged with `!!binary` are written, see [`ParseOptions::binary`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Binary {
+	/// Keep them as strings of their base64 text, wrapped by [`FidelityMode::Wrapped`]
+	#[default]
+	Base64,
+	/// Write the decoded bytes as a string, bytes that aren't printable ASCII are
+	/// written as decimal escapes like `\255`
+	String,
+	/// Write the decoded bytes as a table of numbers like `{104, 105}`
+	Bytes,
+	/// Write the decoded bytes as a Luau buffer like `buffer.fromstring("hi")`, requires [`Dialect::Luau`]
+	Buffer,
+}
+
+/// How timestamps are written, see [`ParseOptions::timestamps`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timestamps {
+	/// Keep them as strings of their text, tagged ones are wrapped by [`FidelityMode::Wrapped`]
+	#[default]
+	String,
+	/// Write them as seconds since the Unix epoch, seconds with a fraction are floats
+	Epoch,
+	/// Write them as calls like `os.time({ year = 2024, month = 6, day = 1, hour = 12, min = 0, sec = 0 })`
+	/// with the fields in UTC. `os.time` reads the fields as local time and has no fractions
+	/// of seconds, so [`Timestamps::Epoch`] is exact where the local time zone isn't UTC
+	OsTime,
+	/// Write them as Roblox `DateTime.fromIsoDate("2024-06-01T12:00:00Z")` calls
+	/// with the time in UTC, requires [`Dialect::Luau`]
+	DateTime,
+}
+
+/// How `null` values are written, see [`ParseOptions::nulls`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Nulls {
+	/// Write them as `nil`
+	#[default]
+	Nil,
+	/// Leave out entries of mappings with null values, elements of sequences are written as `nil`
+	Skip,
+	/// Write them as the Lua expression, like `json.null` or `Enum.None`
+	Sentinel(String),
+}
+
+/// Options of comments pointing at the source, see [`ParseOptions::source_comments`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceComments {
+	/// Name of the source file written before the line number, only the line is written without it
+	pub file: Option<String>,
+	/// Deepest entries annotated, top-level entries have depth of 1
+	pub max_depth: usize,
+}
+
+impl Default for SourceComments {
+	fn default() -> Self {
+		Self {
+			file: None,
+			max_depth: 1,
+		}
+	}
+}
+
+/// Options of the Luau type declaration, see [`ParseOptions::luau_types`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuauTypes {
+	/// Name of the declared type
+	pub name: String,
+	/// Declare the type with `export type` so modules requiring the output can use it
+	pub export: bool,
+}
+
+/// Options of sequences written as records, see [`ParseOptions::columnar_records`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnarRecords {
+	/// Key of the header listing the keys of the records
+	pub fields_key: String,
+	/// Minimum number of elements with the keys of the header for a sequence to be written as records
+	pub min_rows: usize,
+}
+
+impl Default for ColumnarRecords {
+	fn default() -> Self {
+		Self {
+			fields_key: String::from("__fields"),
+			min_rows: 2,
+		}
+	}
+}
+
+/// Options for parsing embedded YAML strings, see [`ParseOptions::embedded_yaml`]
+///
+/// Embedded documents are parsed like the outer one and can contain embedded YAML
+/// themselves. Tagged values that aren't strings fail with [`Error::Transform`](crate::Error::Transform)
+/// and strings that aren't valid YAML fail with [`Error::EmbeddedYaml`](crate::Error::EmbeddedYaml)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedYaml {
+	/// Tag marking embedded YAML strings, with or without the leading `!`
+	pub tag: String,
+	/// Maximum number of embedded documents nested inside each other
+	pub max_depth: usize,
+}
+
+impl Default for EmbeddedYaml {
+	fn default() -> Self {
+		Self {
+			tag: String::from("yaml"),
+			max_depth: 8,
+		}
+	}
+}
+
+/// How index modules written with [`ParseOptions::index_modules`] require other modules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleStyle {
+	/// Roblox instances relative to the index, `require(script.weapons)`
+	Roblox,
+	/// Dotted module paths starting with the given root, like
+	/// `require("config.nested.weapons")` for root `config`, an empty root is left out
+	Path(String),
+}
+
+/// What happens to strings longer than [`ParseOptions::max_string_len`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringOverflow {
+	/// Fail with [`Error::StringTooLong`](crate::Error::StringTooLong)
+	Error,
+	/// Cut the string so that it ends with the suffix and fits into the limit including it
+	Truncate(String),
+}
+
+/// Order of entries in generated tables, see [`ParseOptions::table_layout`]
+///
+/// Keyed entries never use array indices taken by positional ones, so the layout
+/// only changes how the table reads, not what it contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableLayout {
+	/// Positional entries come first, followed by keyed entries including the ones
+	/// added by options. [`ParseOptions::sequence_length_field`] always comes first
+	#[default]
+	PositionalFirst,
+	/// Entries keep the order they have in the document, added entries come first
+	/// except for `__original_keys` which always comes last
+	Interleaved,
+}
+
+/// How a sequence is merged with a sequence from an earlier document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SequenceMerge {
+	/// Later sequence replaces the earlier one
+	#[default]
+	Replace,
+	/// Elements of the later sequence are appended to the earlier one
+	Concat,
+}
+
+/// What happens when a key is a table in one merged document and something else in another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeConflicts {
+	/// Later value replaces the earlier one like any other scalar
+	#[default]
+	Override,
+	/// Fail with [`Error::Transform`](crate::Error::Transform)
+	Error,
+}
+
+/// What happens when multiple keys of the same table resolve into one Lua key,
+/// which can only happen when keys are rewritten by options like [`KeyStyle`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+	/// Fail with [`Error::KeyCollision`](crate::Error::KeyCollision)
+	#[default]
+	Error,
+	/// Report a warning and emit both entries, in Lua the last one wins
+	Warn,
+}
+
+/// How keys of different types are compared to find keys that may be confused,
+/// see [`ParseOptions::key_type_collisions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyComparison {
+	/// Keys of different types are never compared
+	#[default]
+	None,
+	/// Compare keys as they're written, `1` and `"1"` are equal but `1.0` and `"1"` aren't
+	String,
+	/// Compare the results of Lua's `tostring`, where `1.0` and `"1"` are equal
+	/// since Lua stores integral float keys as integers
+	Tostring,
+}
+
+/// Controls which string keys are converted into numeric keys
+///
+/// By default only plain integers like `"1"` or `"42"` are converted,
+/// everything else is conservatively left as a string key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumericKeys {
+	/// Convert floats like `"1.5"`
+	pub floats: bool,
+	/// Convert numbers with leading zeros like `"01"`
+	pub leading_zeros: bool,
+	/// Convert numbers with an explicit sign like `"-1"` or `"+1"`
+	pub signs: bool,
+	/// Convert numbers surrounded by whitespace like `" 1 "`
+	pub whitespace: bool,
+}
+
+impl NumericKeys {
+	pub(crate) fn parse(&self, string: &str) -> Option<serde_yaml::Number> {
+		let string = if self.whitespace {
+			string.trim()
+		} else {
+			string
+		};
+
+		let digits = match string.strip_prefix(['-', '+']) {
+			Some(digits) if self.signs => digits,
+			Some(_) => return None,
+			None => string,
+		};
+
+		let (int, fract) = match digits.split_once('.') {
+			Some((int, fract)) if self.floats => (int, Some(fract)),
+			Some(_) => return None,
+			None => (digits, None),
+		};
+
+		let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+		if !is_digits(int) || !fract.is_none_or(is_digits) {
+			return None;
+		}
+
+		if !self.leading_zeros && int.len() > 1 && int.starts_with('0') {
+			return None;
+		}
+
+		let string = string.strip_prefix('+').unwrap_or(string);
+
+		if fract.is_some() {
+			string.parse::<f64>().ok().map(Into::into)
+		} else {
+			string.parse::<i64>().ok().map(Into::into)
+		}
+	}
+}
@@ -0,0 +1,30 @@
+use crate::{Diagnostic, Error, Result};
+
+/// Output of [`parse_partial`](crate::parse_partial) along with the errors of values
+/// that were replaced by the placeholder
+///
+/// Output is only complete when there are no errors, use [`PartialResult::into_result`]
+/// to treat any of them as a failure of the whole conversion
+#[derive(Debug)]
+#[must_use = "errors of the conversion are lost if the result isn't checked"]
+pub struct PartialResult {
+	pub lua: String,
+	/// Errors in document order, each one replaced a value in the output
+	pub errors: Vec<Error>,
+	pub diagnostics: Vec<Diagnostic>,
+}
+
+impl PartialResult {
+	/// Check whether every value was converted
+	pub fn is_complete(&self) -> bool {
+		self.errors.is_empty()
+	}
+
+	/// Get the output if every value was converted, otherwise the first error
+	pub fn into_result(self) -> Result<String> {
+		match self.errors.into_iter().next() {
+			Some(error) => Err(error),
+			None => Ok(self.lua),
+		}
+	}
+}
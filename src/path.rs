@@ -0,0 +1,313 @@
+use crate::{Error, Result};
+use serde_yaml::Value;
+use std::{fmt, result, str::FromStr};
+
+/// Location of a value inside of a YAML document
+///
+/// Displayed as `a.b[2].c` where `[2]` is a zero-based sequence index,
+/// keys that contain `.`, `[`, `]` or `"` are displayed as `a["b.c"]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyPath {
+	segments: Vec<PathSegment>,
+}
+
+/// Single step of a [`KeyPath`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+	/// Mapping key, numbers and booleans are stored in their text form
+	Key(String),
+	/// Zero-based sequence index
+	Index(usize),
+}
+
+/// Pattern matching [`KeyPath`]s, using the same syntax with wildcards added
+///
+/// - `*` matches any single key, e.g. `servers.*.port`
+/// - `[*]` matches any sequence index, e.g. `servers[*].port`
+/// - `**` matches any number of segments, e.g. `**.port`
+///
+/// Quoted keys like `["*"]` are always matched literally
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PathPattern {
+	segments: Vec<PatternSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PatternSegment {
+	Key(String),
+	Index(usize),
+	AnyKey,
+	AnyIndex,
+	AnyDepth,
+}
+
+impl KeyPath {
+	/// Create empty path pointing at the document root
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Get all segments of the path
+	pub fn segments(&self) -> &[PathSegment] {
+		&self.segments
+	}
+
+	/// Check whether the path points at the document root
+	pub fn is_root(&self) -> bool {
+		self.segments.is_empty()
+	}
+
+	pub(crate) fn push(&mut self, segment: PathSegment) {
+		self.segments.push(segment);
+	}
+
+	pub(crate) fn pop(&mut self) {
+		self.segments.pop();
+	}
+
+	pub(crate) fn truncate(&mut self, len: usize) {
+		self.segments.truncate(len);
+	}
+}
+
+impl FromStr for KeyPath {
+	type Err = Error;
+
+	fn from_str(path: &str) -> Result<Self> {
+		let invalid = |message: String| Error::InvalidPath {
+			path: path.to_owned(),
+			message,
+		};
+
+		let segments = parse_segments(path)
+			.map_err(invalid)?
+			.into_iter()
+			.map(|segment| match segment {
+				PatternSegment::Key(key) => Ok(PathSegment::Key(key)),
+				PatternSegment::Index(index) => Ok(PathSegment::Index(index)),
+				_ => Err(invalid(String::from(
+					"wildcards are only allowed in patterns",
+				))),
+			})
+			.collect::<Result<_>>()?;
+
+		Ok(Self { segments })
+	}
+}
+
+impl KeyPath {
+	/// Find the value this path points at
+	pub(crate) fn select<'v>(&self, root: &'v Value) -> Result<&'v Value> {
+		let mut value = root;
+
+		for (i, segment) in self.segments.iter().enumerate() {
+			let found = match (segment, value) {
+				(PathSegment::Key(_), Value::Mapping(mapping)) => mapping
+					.iter()
+					.find(|(k, _)| PathSegment::from_key(k) == *segment)
+					.map(|(_, v)| v),
+				(PathSegment::Index(index), Value::Sequence(sequence)) => sequence.get(*index),
+				_ => None,
+			};
+
+			value = match found {
+				Some(found) => found,
+				None => {
+					let available = match value {
+						Value::Mapping(mapping) => mapping
+							.keys()
+							.map(|k| KeyPath::from(vec![PathSegment::from_key(k)]).to_string())
+							.collect(),
+						Value::Sequence(sequence) if !sequence.is_empty() => {
+							vec![format!("[0]..[{}]", sequence.len() - 1)]
+						}
+						_ => Vec::new(),
+					};
+
+					return Err(Error::PathNotFound {
+						path: KeyPath::from(self.segments[..i].to_vec()),
+						segment: KeyPath::from(vec![segment.clone()]).to_string(),
+						available,
+					});
+				}
+			};
+		}
+
+		Ok(value)
+	}
+}
+
+impl From<Vec<PathSegment>> for KeyPath {
+	fn from(segments: Vec<PathSegment>) -> Self {
+		Self { segments }
+	}
+}
+
+impl PathSegment {
+	pub(crate) fn from_key(key: &Value) -> Self {
+		match key {
+			Value::String(s) => PathSegment::Key(s.clone()),
+			Value::Number(n) => PathSegment::Key(n.to_string()),
+			Value::Bool(b) => PathSegment::Key(b.to_string()),
+			Value::Null => PathSegment::Key(String::from("null")),
+			_ => PathSegment::Key(String::from("?")),
+		}
+	}
+}
+
+impl PathPattern {
+	/// Check whether the pattern matches the given path
+	pub fn matches(&self, path: &KeyPath) -> bool {
+		fn matches(pattern: &[PatternSegment], path: &[PathSegment]) -> bool {
+			match (pattern.first(), path.first()) {
+				(None, None) => true,
+				(Some(PatternSegment::AnyDepth), _) => {
+					matches(&pattern[1..], path)
+						|| (!path.is_empty() && matches(pattern, &path[1..]))
+				}
+				(Some(pattern_segment), Some(path_segment)) => {
+					let matched = match (pattern_segment, path_segment) {
+						(PatternSegment::Key(a), PathSegment::Key(b)) => a == b,
+						(PatternSegment::Index(a), PathSegment::Index(b)) => a == b,
+						(PatternSegment::AnyKey, PathSegment::Key(_)) => true,
+						(PatternSegment::AnyIndex, PathSegment::Index(_)) => true,
+						_ => false,
+					};
+
+					matched && matches(&pattern[1..], &path[1..])
+				}
+				_ => false,
+			}
+		}
+
+		matches(&self.segments, &path.segments)
+	}
+}
+
+impl FromStr for PathPattern {
+	type Err = Error;
+
+	fn from_str(path: &str) -> Result<Self> {
+		let segments = parse_segments(path).map_err(|message| Error::InvalidPath {
+			path: path.to_owned(),
+			message,
+		})?;
+
+		Ok(Self { segments })
+	}
+}
+
+/// Parse `a.b[2]["c.d"]` style paths into pattern segments
+fn parse_segments(path: &str) -> result::Result<Vec<PatternSegment>, String> {
+	let mut segments = Vec::new();
+	let mut chars = path.chars().peekable();
+	let mut expect_key = true;
+
+	if path.is_empty() {
+		return Err(String::from("path is empty"));
+	}
+
+	while let Some(&char) = chars.peek() {
+		match char {
+			'[' => {
+				chars.next();
+
+				if chars.peek() == Some(&'"') {
+					chars.next();
+
+					let mut key = String::new();
+
+					loop {
+						match chars.next() {
+							Some('\\') => match chars.next() {
+								Some(char) => key.push(char),
+								None => return Err(String::from("unterminated quoted key")),
+							},
+							Some('"') => break,
+							Some(char) => key.push(char),
+							None => return Err(String::from("unterminated quoted key")),
+						}
+					}
+
+					segments.push(PatternSegment::Key(key));
+				} else {
+					let mut index = String::new();
+
+					while let Some(char) = chars.next_if(|c| *c != ']') {
+						index.push(char);
+					}
+
+					segments.push(match index.as_str() {
+						"*" => PatternSegment::AnyIndex,
+						_ => PatternSegment::Index(
+							index
+								.parse()
+								.map_err(|_| format!("invalid sequence index `{}`", index))?,
+						),
+					});
+				}
+
+				if chars.next() != Some(']') {
+					return Err(String::from("missing closing `]`"));
+				}
+
+				expect_key = false;
+			}
+			'.' if !expect_key => {
+				chars.next();
+				expect_key = true;
+
+				if chars.peek().is_none_or(|c| *c == '.') {
+					return Err(String::from("empty key"));
+				}
+			}
+			_ if expect_key => {
+				let mut key = String::new();
+
+				while let Some(char) = chars.next_if(|c| !matches!(c, '.' | '[' | ']')) {
+					key.push(char);
+				}
+
+				if key.is_empty() {
+					return Err(format!("unexpected `{}`", char));
+				}
+
+				segments.push(match key.as_str() {
+					"*" => PatternSegment::AnyKey,
+					"**" => PatternSegment::AnyDepth,
+					_ => PatternSegment::Key(key),
+				});
+
+				expect_key = false;
+			}
+			_ => return Err(format!("unexpected `{}`", char)),
+		}
+	}
+
+	if expect_key {
+		return Err(String::from("path ends with `.`"));
+	}
+
+	Ok(segments)
+}
+
+impl fmt::Display for KeyPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, segment) in self.segments.iter().enumerate() {
+			match segment {
+				PathSegment::Key(key) if key.is_empty() || key.contains(['.', '[', ']', '"']) => {
+					write!(
+						f,
+						"[\"{}\"]",
+						key.replace('\\', "\\\\").replace('"', "\\\"")
+					)?
+				}
+				PathSegment::Key(key) if i == 0 => write!(f, "{}", key)?,
+				PathSegment::Key(key) => write!(f, ".{}", key)?,
+				PathSegment::Index(index) => write!(f, "[{}]", index)?,
+			}
+		}
+
+		Ok(())
+	}
+}
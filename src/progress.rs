@@ -0,0 +1,57 @@
+use crate::KeyPath;
+use std::{fmt, ops::ControlFlow, sync::Arc};
+
+/// Progress of a conversion passed to the callback set with
+/// [`ParseOptions::on_progress`](crate::ParseOptions::on_progress)
+#[derive(Debug)]
+pub struct Progress<'a> {
+	/// Number of values written so far
+	pub nodes: usize,
+	/// Length of the Lua written so far in bytes
+	pub bytes: usize,
+	/// Path of the value that is about to be written
+	pub path: &'a KeyPath,
+}
+
+/// How often the progress callback is called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressInterval {
+	/// Every given number of values
+	Nodes(usize),
+	/// Every time the output grows by the given number of bytes
+	Bytes(usize),
+}
+
+type Callback = dyn Fn(&Progress) -> ControlFlow<()> + Send + Sync;
+
+/// Progress callback stored in the options
+#[derive(Clone)]
+pub(crate) struct ProgressHook {
+	pub interval: ProgressInterval,
+	pub callback: Arc<Callback>,
+}
+
+impl fmt::Debug for ProgressHook {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ProgressHook")
+			.field("interval", &self.interval)
+			.finish_non_exhaustive()
+	}
+}
+
+impl ProgressInterval {
+	/// Get the node count or output length at which the callback is called next
+	pub(crate) fn next(&self, nodes: usize, bytes: usize) -> usize {
+		match *self {
+			ProgressInterval::Nodes(interval) => nodes + interval.max(1),
+			ProgressInterval::Bytes(interval) => bytes + interval.max(1),
+		}
+	}
+
+	pub(crate) fn is_due(&self, next: usize, nodes: usize, bytes: usize) -> bool {
+		match self {
+			ProgressInterval::Nodes(_) => nodes >= next,
+			ProgressInterval::Bytes(_) => bytes >= next,
+		}
+	}
+}
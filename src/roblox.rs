@@ -0,0 +1,189 @@
+use crate::fmt::is_lua_identifier;
+use serde_yaml::{Mapping, Value};
+
+type Datatype = fn(&Value) -> Result<String, String>;
+
+/// Roblox datatypes written as constructor expressions, by their tag
+pub(crate) const DATATYPES: &[(&str, Datatype)] = &[
+	("Vector2", vector2),
+	("Vector3", vector3),
+	("Color3", color3),
+	("UDim", udim),
+	("UDim2", udim2),
+	("CFrame", cframe),
+	("NumberRange", number_range),
+	("Enum", enum_item),
+];
+
+fn vector2(value: &Value) -> Result<String, String> {
+	call("Vector2.new", &components(value, &["x", "y"])?)
+}
+
+fn vector3(value: &Value) -> Result<String, String> {
+	call("Vector3.new", &components(value, &["x", "y", "z"])?)
+}
+
+/// Colors with components from 0 to 1 like `[1, 0.5, 0]`, or hex strings like `"#ff8000"`
+fn color3(value: &Value) -> Result<String, String> {
+	if let Value::String(hex) = value {
+		let digits = hex.strip_prefix('#').unwrap_or(hex);
+
+		if !matches!(digits.len(), 3 | 6) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+			return Err(format!("`{}` isn't a hex color like #ff8000", hex));
+		}
+
+		return Ok(format!("Color3.fromHex(\"#{}\")", digits));
+	}
+
+	call("Color3.new", &components(value, &["r", "g", "b"])?)
+}
+
+fn udim(value: &Value) -> Result<String, String> {
+	call("UDim.new", &components(value, &["scale", "offset"])?)
+}
+
+/// Four numbers like `UDim2.new`, or a mapping with `x` and `y` written like [`udim`]
+fn udim2(value: &Value) -> Result<String, String> {
+	let Value::Mapping(mapping) = value else {
+		return call(
+			"UDim2.new",
+			&numbers(value, 4, "x scale, x offset, y scale and y offset")?,
+		);
+	};
+
+	let mut axes = Vec::with_capacity(4);
+
+	for axis in ["x", "y"] {
+		let udim = field(mapping, axis).ok_or_else(|| format!("missing field `{}`", axis))?;
+		axes.extend(
+			components(udim, &["scale", "offset"]).map_err(|err| format!("{}: {}", axis, err))?,
+		);
+	}
+
+	call("UDim2.new", &axes)
+}
+
+/// Position alone, position with a quaternion or position with a rotation matrix,
+/// same as the overloads of `CFrame.new`
+fn cframe(value: &Value) -> Result<String, String> {
+	let Value::Sequence(sequence) = value else {
+		return call("CFrame.new", &components(value, &["x", "y", "z"])?);
+	};
+
+	if !matches!(sequence.len(), 3 | 7 | 12) {
+		return Err(format!(
+			"expected 3, 7 or 12 numbers, found {}",
+			sequence.len()
+		));
+	}
+
+	call("CFrame.new", &numbers(value, sequence.len(), "")?)
+}
+
+/// Single number for ranges of one value, or the minimum and maximum
+fn number_range(value: &Value) -> Result<String, String> {
+	match value {
+		Value::Number(_) => call("NumberRange.new", &[number(value)?]),
+		value => call("NumberRange.new", &components(value, &["min", "max"])?),
+	}
+}
+
+/// Enum items like `Material.Wood`, also accepted with the `Enum.` prefix
+fn enum_item(value: &Value) -> Result<String, String> {
+	let Value::String(item) = value else {
+		return Err(String::from("expected an enum item like Material.Wood"));
+	};
+
+	let path = item.strip_prefix("Enum.").unwrap_or(item);
+
+	match path.split_once('.') {
+		Some((name, item)) if is_lua_identifier(name) && is_lua_identifier(item) => {
+			Ok(format!("Enum.{}.{}", name, item))
+		}
+		_ => Err(format!("`{}` isn't an enum item like Material.Wood", item)),
+	}
+}
+
+/// Numbers of a datatype written as a sequence in constructor order,
+/// or as a mapping with the fields, which are matched ignoring case
+fn components(value: &Value, fields: &[&str]) -> Result<Vec<String>, String> {
+	let Value::Mapping(mapping) = value else {
+		return numbers(value, fields.len(), &list(fields));
+	};
+
+	if let Some((key, _)) = mapping.iter().find(|(key, _)| {
+		!key.as_str()
+			.is_some_and(|key| fields.iter().any(|f| f.eq_ignore_ascii_case(key)))
+	}) {
+		return Err(format!(
+			"unexpected field `{}`, expected {}",
+			key_name(key),
+			list(fields)
+		));
+	}
+
+	fields
+		.iter()
+		.map(|name| match field(mapping, name) {
+			Some(value) => number(value).map_err(|err| format!("{}: {}", name, err)),
+			None => Err(format!("missing field `{}`", name)),
+		})
+		.collect()
+}
+
+fn numbers(value: &Value, len: usize, names: &str) -> Result<Vec<String>, String> {
+	let expected = || match names.is_empty() {
+		true => format!("expected {} numbers", len),
+		false => format!("expected {} numbers or a mapping with {}", len, names),
+	};
+
+	match value {
+		Value::Sequence(sequence) if sequence.len() == len => sequence
+			.iter()
+			.enumerate()
+			.map(|(i, value)| number(value).map_err(|err| format!("{}: {}", i + 1, err)))
+			.collect(),
+		Value::Sequence(sequence) => Err(format!("{}, found {}", expected(), sequence.len())),
+		_ => Err(expected()),
+	}
+}
+
+fn number(value: &Value) -> Result<String, String> {
+	match value {
+		Value::Number(n) if n.as_f64().is_some_and(f64::is_finite) => Ok(n.to_string()),
+		Value::Number(n) => Err(format!("{} isn't a finite number", n)),
+		_ => Err(String::from("expected a number")),
+	}
+}
+
+fn field<'a>(mapping: &'a Mapping, name: &str) -> Option<&'a Value> {
+	mapping
+		.iter()
+		.find(|(key, _)| {
+			key.as_str()
+				.is_some_and(|key| key.eq_ignore_ascii_case(name))
+		})
+		.map(|(_, value)| value)
+}
+
+fn key_name(key: &Value) -> String {
+	match key {
+		Value::String(s) => s.clone(),
+		key => serde_yaml::to_string(key)
+			.map(|key| key.trim_end().to_owned())
+			.unwrap_or_default(),
+	}
+}
+
+/// List field names like `x, y and z`
+fn list(fields: &[&str]) -> String {
+	match fields.split_last() {
+		Some((last, [])) => last.to_string(),
+		Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+		None => String::new(),
+	}
+}
+
+fn call(constructor: &str, arguments: &[String]) -> Result<String, String> {
+	Ok(format!("{}({})", constructor, arguments.join(", ")))
+}
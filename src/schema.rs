@@ -0,0 +1,105 @@
+use crate::{KeyPath, PathPattern, Result};
+use serde_yaml::Value;
+
+/// Type that scalars matched by a [`Schema`] are coerced into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarType {
+	String,
+	Int,
+	Float,
+	Bool,
+}
+
+/// Path based rules coercing string scalars into other types
+///
+/// This is not a full schema validation, keys that aren't matched by any
+/// rule are left untouched. When multiple rules match, the first one wins
+///
+/// ```rust
+/// use yaml2lua::{parse_with_options, ParseOptions, ScalarType, Schema};
+///
+/// let schema = Schema::new()
+/// 	.field("retries", ScalarType::Int)?
+/// 	.field("servers[*].port", ScalarType::Int)?
+/// 	.field("**.debug", ScalarType::Bool)?;
+///
+/// let yaml = r#"
+/// retries: "3"
+/// servers:
+///   - port: "8080"
+/// debug: "true"
+/// "#;
+///
+/// let lua = parse_with_options(yaml, &ParseOptions::new().schema(schema))?;
+///
+/// assert!(lua.contains(r#"["retries"] = 3,"#));
+/// assert!(lua.contains(r#"["port"] = 8080,"#));
+/// assert!(lua.contains(r#"["debug"] = true,"#));
+/// # Ok::<(), yaml2lua::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+	fields: Vec<(PathPattern, ScalarType)>,
+}
+
+impl Schema {
+	/// Create empty schema
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Coerce values at paths matching the pattern into the given type
+	pub fn field(mut self, pattern: &str, ty: ScalarType) -> Result<Self> {
+		self.fields.push((pattern.parse()?, ty));
+		Ok(self)
+	}
+
+	pub(crate) fn get(&self, path: &KeyPath) -> Option<ScalarType> {
+		self.fields
+			.iter()
+			.find(|(pattern, _)| pattern.matches(path))
+			.map(|(_, ty)| *ty)
+	}
+}
+
+impl ScalarType {
+	/// Coerce the value, returning `None` if it already has this type
+	/// and `Err` with the value's type name if it can't be coerced
+	pub(crate) fn coerce(&self, value: &Value) -> std::result::Result<Option<Value>, ()> {
+		match (self, value) {
+			(ScalarType::String, Value::String(_)) => Ok(None),
+			(ScalarType::String, Value::Number(n)) => Ok(Some(Value::String(n.to_string()))),
+			(ScalarType::String, Value::Bool(b)) => Ok(Some(Value::String(b.to_string()))),
+			(ScalarType::Int, Value::Number(n)) if !n.is_f64() => Ok(None),
+			(ScalarType::Int, Value::String(s)) => s
+				.parse::<i64>()
+				.map(|i| Some(i.into()))
+				.or_else(|_| s.parse::<u64>().map(|u| Some(u.into())))
+				.map_err(|_| ()),
+			(ScalarType::Float, Value::Number(n)) if n.is_f64() => Ok(None),
+			(ScalarType::Float, Value::Number(n)) => Ok(n.as_f64().map(Into::into)),
+			(ScalarType::Float, Value::String(s)) => s
+				.parse::<f64>()
+				.ok()
+				.filter(|f| f.is_finite())
+				.map(|f| Some(f.into()))
+				.ok_or(()),
+			(ScalarType::Bool, Value::Bool(_)) => Ok(None),
+			(ScalarType::Bool, Value::String(s)) => match s.as_str() {
+				"true" => Ok(Some(Value::Bool(true))),
+				"false" => Ok(Some(Value::Bool(false))),
+				_ => Err(()),
+			},
+			_ => Err(()),
+		}
+	}
+
+	pub(crate) fn name(&self) -> &'static str {
+		match self {
+			ScalarType::String => "string",
+			ScalarType::Int => "integer",
+			ScalarType::Float => "float",
+			ScalarType::Bool => "bool",
+		}
+	}
+}
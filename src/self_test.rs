@@ -0,0 +1,95 @@
+use crate::{parse_with_options, Error, ParseOptions, Result, FORMAT_VERSIONS};
+
+/// Documents converted by [`self_test`], named after what they cover
+const CASES: &[(&str, &str)] = &[
+	(
+		"scalars",
+		"none: ~\nbool: true\nint: 42\nnegative: -7\nfloat: 1.5\nwhole: 2.0\nexponent: 1e20\nstring: text",
+	),
+	(
+		"escapes",
+		"quote: \"say \\\"hi\\\"\"\nbackslash: 'C:\\dir'\nlines: \"a\\nb\\r\\tc\"\ncontrol: \"\\x01\\x7f\"\nunicode: \"héllo ✓\"",
+	),
+	(
+		"keys",
+		"1: number\n1.5: float\ntrue: bool\nsnake_case: identifier\nwith space: string\nend: keyword",
+	),
+	(
+		"nesting",
+		"table: { a: { b: [1, [2, 3]] } }\nsequence: [{ a: 1 }, [], {}]\nmixed: [1, a, true]",
+	),
+	("nils", "value: ~\nsequence: [1, ~, 3]\ntable: { a: ~ }"),
+	(
+		"large numbers",
+		"max: 9223372036854775807\nmin: -9223372036854775808\nunsigned: 18446744073709551615\nprecise: 9007199254740993",
+	),
+	("tags", "scalar: !Color red\ntable: !Vector { x: 1, y: 2 }\nnested: !Outer [!Inner 1]"),
+	(
+		"anchors",
+		"base: &base { a: 1, b: 2 }\nalias: *base\nmerged: { <<: *base, b: 3 }",
+	),
+];
+
+/// Result of converting the built-in documents with [`self_test`]
+///
+/// Reports of the same options are equal as long as the output doesn't change,
+/// so one can be stored and compared with the report of the running version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+	/// Output format version of the options, the pinned one or the latest
+	pub format_version: u32,
+	/// Options that aren't set to their defaults, named after their builder methods
+	pub changed_options: Vec<&'static str>,
+	pub cases: Vec<SelfTestCase>,
+}
+
+/// One of the built-in documents converted by [`self_test`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestCase {
+	pub name: &'static str,
+	pub yaml: &'static str,
+	/// Output of the document or the message of the error converting it failed with
+	pub output: std::result::Result<String, String>,
+}
+
+impl SelfTestReport {
+	/// Get the cases that failed to convert
+	pub fn failed(&self) -> impl Iterator<Item = &SelfTestCase> {
+		self.cases.iter().filter(|case| case.output.is_err())
+	}
+}
+
+/// Convert a built-in set of documents covering every scalar type, string escapes,
+/// keys, nesting, nils, large numbers, tags and anchors with the options
+///
+/// Documents failing with the options are reported in their cases, only
+/// options that can't be used at all fail with [`Error::Options`]
+///
+/// ```rust
+/// use yaml2lua::{self_test, ParseOptions};
+///
+/// let report = self_test(&ParseOptions::new().sort_keys(true)).unwrap();
+///
+/// assert_eq!(report.changed_options, ["sort_keys"]);
+/// assert_eq!(report.failed().count(), 0);
+/// ```
+pub fn self_test(options: &ParseOptions) -> Result<SelfTestReport> {
+	let mut cases = Vec::with_capacity(CASES.len());
+
+	for &(name, yaml) in CASES {
+		let output = match parse_with_options(yaml, options) {
+			Err(err @ Error::Options(_)) => return Err(err),
+			output => output.map_err(|err| err.to_string()),
+		};
+
+		cases.push(SelfTestCase { name, yaml, output });
+	}
+
+	let latest = FORMAT_VERSIONS.last().copied().unwrap_or_default();
+
+	Ok(SelfTestReport {
+		format_version: options.format_version.unwrap_or(latest),
+		changed_options: options.changed(),
+		cases,
+	})
+}
@@ -0,0 +1,648 @@
+use crate::{timestamp::Timestamp, KeyPath, PathPattern, PathSegment};
+use serde_yaml::{value::TaggedValue, Mapping, Value};
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet},
+};
+use yaml_rust2::{
+	parser::{Event, Parser, Tag},
+	scanner::Marker,
+	scanner::{Scanner, TScalarStyle, Token, TokenType},
+};
+
+/// Prefix of tags from the YAML core schema like `!!str`, these are applied by the deserializer
+const CORE_SCHEMA_PREFIX: &str = "tag:yaml.org,2002:";
+
+/// Reattach tags the deserializer drops, which are tags using handles declared
+/// by `%TAG` directives and verbatim tags like `!<tag:example.com,2024:vector>`
+///
+/// Tags resolved into a global URI become verbatim tags, so `!e!vector` with
+/// `%TAG !e! tag:example.com,2024:` is attached as `!<tag:example.com,2024:vector>`.
+/// Roots put under a [`ParseOptions::root_key`](crate::ParseOptions::root_key) are found under the key
+pub(crate) fn resolve_tags(yaml: &str, root: &mut Mapping, root_key: Option<&str>) {
+	if !yaml.contains("%TAG") && !yaml.contains("!<") {
+		return;
+	}
+
+	let yaml = expand_tag_handles(yaml);
+	let mut tags = Vec::new();
+
+	let walked = walk(&yaml, |node| {
+		let tag = match node.event {
+			Event::Scalar(_, _, _, tag)
+			| Event::SequenceStart(_, tag)
+			| Event::MappingStart(_, tag) => tag.as_ref(),
+			_ => None,
+		};
+
+		if node.role != Role::Value || node.path.is_root() {
+			return;
+		}
+
+		if let Some(tag) = tag.and_then(resolve) {
+			let plain = match node.event {
+				Event::Scalar(text, TScalarStyle::Plain, ..) => Some(text.clone()),
+				_ => None,
+			};
+
+			tags.push((under(root_key, node.path), tag, plain));
+		}
+	});
+
+	// The deserializer already accepted the document, so anything unexpected
+	// here just means that no tags are reattached
+	if walked.is_none() {
+		return;
+	}
+
+	for (path, tag, plain) in tags {
+		if let Some(value) = select(root, &path) {
+			attach(value, tag, plain);
+		}
+	}
+}
+
+/// Replace plain scalars at paths matching the patterns that were resolved
+/// into numbers, booleans or null with strings of their text as written
+pub(crate) fn force_strings(
+	yaml: &str,
+	root: &mut Mapping,
+	patterns: &[PathPattern],
+	root_key: Option<&str>,
+) {
+	let mut texts = Vec::new();
+
+	walk(&expand_tag_handles(yaml), |node| {
+		if node.role != Role::Value || node.path.is_root() {
+			return;
+		}
+
+		if let Event::Scalar(text, TScalarStyle::Plain, ..) = node.event {
+			let path = under(root_key, node.path);
+
+			if patterns.iter().any(|pattern| pattern.matches(&path)) {
+				texts.push((path, text.clone()));
+			}
+		}
+	});
+
+	for (path, text) in texts {
+		if let Some(value @ (Value::Null | Value::Bool(_) | Value::Number(_))) = select(root, &path)
+		{
+			*value = Value::String(text);
+		}
+	}
+}
+
+/// Collect paths of entries with keys written as plain scalars, as opposed to quoted
+/// or block scalars. Nothing is collected if the document can't be parsed
+pub(crate) fn plain_keys(yaml: &str) -> HashSet<KeyPath> {
+	let mut keys = HashSet::new();
+
+	let walked = walk(&expand_tag_handles(yaml), |node| {
+		if let (Role::Key, Event::Scalar(_, TScalarStyle::Plain, ..)) = (node.role, node.event) {
+			keys.insert(node.path.clone());
+		}
+	});
+
+	if walked.is_none() {
+		keys.clear();
+	}
+
+	// Aliases repeat the keys of the anchored value under their own paths
+	for anchor in anchors(yaml) {
+		let depth = anchor.path.segments().len();
+		let inside: Vec<KeyPath> = keys
+			.iter()
+			.filter(|path| {
+				path.segments().len() > depth && path.segments().starts_with(anchor.path.segments())
+			})
+			.cloned()
+			.collect();
+
+		for alias in &anchor.aliases {
+			for path in &inside {
+				let mut aliased = alias.clone();
+
+				for segment in &path.segments()[depth..] {
+					aliased.push(segment.clone());
+				}
+
+				keys.insert(aliased);
+			}
+		}
+	}
+
+	keys
+}
+
+/// Collect one-based lines of entries at most `max_depth` deep, pointing at their keys if they have one
+pub(crate) fn lines(yaml: &str, max_depth: usize) -> HashMap<KeyPath, usize> {
+	let mut lines = HashMap::new();
+
+	walk(&expand_tag_handles(yaml), |node| {
+		let depth = node.path.segments().len();
+
+		if node.role != Role::Ignored && depth > 0 && depth <= max_depth {
+			lines.entry(node.path.clone()).or_insert(node.marker.line());
+		}
+	});
+
+	lines
+}
+
+/// Collect paths of scalars with tags of core schema types that the deserializer
+/// reads as plain strings, mapped to the name of the type like `binary`
+pub(crate) fn core_tags(yaml: &str) -> HashMap<KeyPath, &'static str> {
+	let mut tags = HashMap::new();
+
+	walk(&expand_tag_handles(yaml), |node| {
+		let Event::Scalar(_, _, _, Some(tag)) = node.event else {
+			return;
+		};
+
+		let name = format!("{}{}", tag.handle, tag.suffix);
+		let name = name.strip_prefix(CORE_SCHEMA_PREFIX).unwrap_or(&name);
+
+		let ty = match name {
+			"binary" | "!!binary" => "binary",
+			"timestamp" | "!!timestamp" => "timestamp",
+			_ => return,
+		};
+
+		if node.role == Role::Value && !node.path.is_root() {
+			tags.insert(node.path.clone(), ty);
+		}
+	});
+
+	tags
+}
+
+/// Collect paths of scalars tagged with `!!timestamp` and of plain scalars
+/// without a tag written like timestamps, as opposed to quoted strings
+pub(crate) fn timestamps(yaml: &str) -> HashSet<KeyPath> {
+	let mut timestamps = HashSet::new();
+
+	walk(&expand_tag_handles(yaml), |node| {
+		if node.role != Role::Value || node.path.is_root() {
+			return;
+		}
+
+		let timestamp = match node.event {
+			Event::Scalar(text, TScalarStyle::Plain, _, None) => Timestamp::parse(text).is_some(),
+			Event::Scalar(_, _, _, Some(tag)) => {
+				let name = format!("{}{}", tag.handle, tag.suffix);
+				let name = name.strip_prefix(CORE_SCHEMA_PREFIX).unwrap_or(&name);
+
+				matches!(name, "timestamp" | "!!timestamp")
+			}
+			_ => false,
+		};
+
+		if timestamp {
+			timestamps.insert(node.path.clone());
+		}
+	});
+
+	timestamps
+}
+
+/// Node with an anchor that aliases refer to
+pub(crate) struct Anchor {
+	/// Name of the anchor as written after `&`
+	pub name: String,
+	pub path: KeyPath,
+	/// Paths of the aliases referring to the anchor in document order
+	pub aliases: Vec<KeyPath>,
+}
+
+/// Collect anchored values with their aliases, in the order their nodes end so anchors
+/// nested in another anchored value come before it. Anchors without aliases, anchored
+/// keys and aliases used as keys are left out. Nothing is collected if the document can't be parsed
+pub(crate) fn anchors(yaml: &str) -> Vec<Anchor> {
+	if !yaml.contains('*') {
+		return Vec::new();
+	}
+
+	let yaml = expand_tag_handles(yaml);
+
+	// The parser numbers anchors from 1 in the order they're written, without their names
+	let names: Vec<String> = Scanner::new(yaml.chars())
+		.filter_map(|Token(_, token)| match token {
+			TokenType::Anchor(name) => Some(name),
+			_ => None,
+		})
+		.collect();
+
+	let mut anchors: HashMap<usize, (usize, KeyPath)> = HashMap::new();
+	let mut aliases: HashMap<usize, Vec<KeyPath>> = HashMap::new();
+	let mut position = 0;
+
+	let walked = walk(&yaml, |node| {
+		position += 1;
+
+		if node.role != Role::Value || node.path.is_root() {
+			return;
+		}
+
+		match *node.event {
+			Event::Scalar(_, _, id, _)
+			| Event::SequenceStart(id, _)
+			| Event::MappingStart(id, _)
+				if id > 0 =>
+			{
+				anchors.insert(id, (position, node.path.clone()));
+			}
+			Event::Alias(id) => aliases.entry(id).or_default().push(node.path.clone()),
+			_ => {}
+		}
+	});
+
+	if walked.is_none() {
+		return Vec::new();
+	}
+
+	let mut anchors: Vec<(usize, Anchor)> = anchors
+		.into_iter()
+		.filter_map(|(id, (position, path))| {
+			let anchor = Anchor {
+				name: names.get(id - 1)?.clone(),
+				path,
+				aliases: aliases.remove(&id)?,
+			};
+
+			Some((position, anchor))
+		})
+		.collect();
+
+	// Nodes end after the nodes inside of them and otherwise in the order they start
+	let contains = |outer: &KeyPath, inner: &KeyPath| {
+		inner.segments().len() > outer.segments().len()
+			&& inner.segments().starts_with(outer.segments())
+	};
+
+	anchors.sort_by(|(a_position, a), (b_position, b)| {
+		if contains(&a.path, &b.path) {
+			std::cmp::Ordering::Greater
+		} else if contains(&b.path, &a.path) {
+			std::cmp::Ordering::Less
+		} else {
+			a_position.cmp(b_position)
+		}
+	});
+
+	anchors.into_iter().map(|(_, anchor)| anchor).collect()
+}
+
+/// Split a stream into the source text of its documents, directives before a document
+/// belong to it. Returns `None` if the stream can't be parsed
+pub(crate) fn split_documents(yaml: &str) -> Option<Vec<&str>> {
+	let offset = |index: usize| {
+		yaml.char_indices()
+			.nth(index)
+			.map_or(yaml.len(), |(i, _)| i)
+	};
+
+	let mut parser = Parser::new_from_str(yaml);
+	let mut documents = Vec::new();
+	let mut start = 0;
+
+	loop {
+		match parser.next_token().ok()? {
+			(Event::DocumentEnd, marker) => {
+				let mut end = offset(marker.index());
+				documents.push(&yaml[start..end]);
+
+				// Explicit document end markers don't belong to the next document
+				if yaml[end..].starts_with("...") {
+					end = yaml[end..].find('\n').map_or(yaml.len(), |i| end + i + 1);
+				}
+
+				start = end;
+			}
+			(Event::StreamEnd, _) => return Some(documents),
+			_ => {}
+		}
+	}
+}
+
+/// Deepest nesting of values the deserializer accepts
+const MAX_DEPTH: usize = 128;
+
+/// Fail documents with flow collections nested deeper than the deserializer accepts
+/// without deserializing all of them, the parser gets quadratically slow on those
+///
+/// The error is the one the deserializer returns, only for the source up to the
+/// collection that's nested too deep
+pub(crate) fn check_depth(yaml: &str) -> Result<(), serde_yaml::Error> {
+	// Counting brackets overestimates the depth, so only scan documents that might be too deep
+	let mut depth = 0usize;
+	let mut max = 0;
+
+	for byte in yaml.bytes() {
+		match byte {
+			b'[' | b'{' => {
+				depth += 1;
+				max = max.max(depth);
+			}
+			b']' | b'}' => depth = depth.saturating_sub(1),
+			_ => {}
+		}
+	}
+
+	if max <= MAX_DEPTH {
+		return Ok(());
+	}
+
+	// Markers are char indices
+	let prefix = |index: usize| {
+		let end = yaml
+			.char_indices()
+			.nth(index)
+			.map_or(yaml.len(), |(i, _)| i);
+
+		serde_yaml::from_str::<Value>(&yaml[..end]).map(|_| ())
+	};
+
+	let mut scanner = Scanner::new(yaml.chars());
+	let mut depth = 0usize;
+
+	for Token(marker, token) in &mut scanner {
+		match token {
+			TokenType::FlowSequenceStart | TokenType::FlowMappingStart => depth += 1,
+			TokenType::FlowSequenceEnd | TokenType::FlowMappingEnd => {
+				depth = depth.saturating_sub(1)
+			}
+			_ => continue,
+		}
+
+		if depth > MAX_DEPTH {
+			return prefix(marker.index() + 1);
+		}
+	}
+
+	// Scanner has a nesting limit of its own, it can fail before reaching the collection
+	match scanner.get_error() {
+		Some(err) if err.info() == "recursion limit exceeded" => prefix(err.marker().index()),
+		_ => Ok(()),
+	}
+}
+
+/// Find the one-based line and column of the node at the path, pointing at its key if it has one
+pub(crate) fn locate(yaml: &str, path: &KeyPath) -> Option<(usize, usize)> {
+	let mut location = None;
+
+	walk(&expand_tag_handles(yaml), |node| {
+		if location.is_none() && node.role != Role::Ignored && node.path == path {
+			location = Some((node.marker.line(), node.marker.col() + 1));
+		}
+	});
+
+	location
+}
+
+/// Rewrite tags using handles declared by `%TAG` directives into verbatim tags
+/// and comment the directives out
+///
+/// The parser only keeps the last directive of a document, so handles are
+/// expanded here using the scanner, which reports the directives and tags as written
+fn expand_tag_handles(yaml: &str) -> Cow<'_, str> {
+	let source: Vec<char> = yaml.chars().collect();
+
+	let mut handles = HashMap::new();
+	let mut edits = Vec::new();
+
+	for Token(marker, token) in Scanner::new(yaml.chars()) {
+		match token {
+			TokenType::TagDirective(handle, prefix) => {
+				handles.insert(handle, prefix);
+				edits.push((marker.index(), 1, String::from("#")));
+			}
+			TokenType::Tag(handle, suffix) => {
+				if let Some(prefix) = handles.get(&handle) {
+					// Markers are char indices
+					let start = marker.index();
+					let len = source[start..]
+						.iter()
+						.take_while(|c| {
+							!c.is_whitespace() && !matches!(c, ',' | '[' | ']' | '{' | '}')
+						})
+						.count();
+
+					edits.push((start, len, format!("!<{}{}>", prefix, suffix)));
+				}
+			}
+			TokenType::DocumentEnd => break,
+			_ => {}
+		}
+	}
+
+	if edits.is_empty() {
+		return Cow::Borrowed(yaml);
+	}
+
+	let mut output = String::with_capacity(yaml.len());
+	let mut position = 0;
+
+	for (start, len, replacement) in edits {
+		output.extend(&source[position..start]);
+		output.push_str(&replacement);
+		position = start + len;
+	}
+
+	output.extend(&source[position..]);
+
+	Cow::Owned(output)
+}
+
+/// How a node visited by [`walk`] is used in the document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+	Value,
+	/// Scalar key of the entry at the path
+	Key,
+	/// Node inside of a complex key, which has no path
+	Ignored,
+}
+
+struct Node<'e> {
+	path: &'e KeyPath,
+	event: &'e Event,
+	marker: Marker,
+	role: Role,
+}
+
+/// Walk the first document calling the visitor for every node, keys are visited
+/// before their values with the same path. Returns `None` if the document can't be parsed
+fn walk(yaml: &str, visit: impl FnMut(Node)) -> Option<()> {
+	let mut walker = Walker {
+		parser: Parser::new_from_str(yaml),
+		visit,
+	};
+
+	loop {
+		match walker.next()?.0 {
+			Event::StreamStart => continue,
+			Event::DocumentStart => break,
+			_ => return None,
+		}
+	}
+
+	let (event, marker) = walker.next()?;
+	walker.node(event, marker, &mut KeyPath::new(), Role::Value)
+}
+
+struct Walker<'a, F> {
+	parser: Parser<std::str::Chars<'a>>,
+	visit: F,
+}
+
+impl<F: FnMut(Node)> Walker<'_, F> {
+	fn next(&mut self) -> Option<(Event, Marker)> {
+		self.parser.next_token().ok()
+	}
+
+	fn node(&mut self, event: Event, marker: Marker, path: &mut KeyPath, role: Role) -> Option<()> {
+		(self.visit)(Node {
+			path,
+			event: &event,
+			marker,
+			role,
+		});
+
+		// Only values have paths for their children
+		let role = match role {
+			Role::Key => Role::Ignored,
+			role => role,
+		};
+
+		match event {
+			Event::SequenceStart(..) => {
+				for i in 0.. {
+					let (event, marker) = self.next()?;
+
+					if event == Event::SequenceEnd {
+						break;
+					}
+
+					path.push(PathSegment::Index(i));
+					self.node(event, marker, path, role)?;
+					path.pop();
+				}
+			}
+			Event::MappingStart(..) => loop {
+				let (event, marker) = self.next()?;
+
+				if event == Event::MappingEnd {
+					break;
+				}
+
+				let key = match &event {
+					Event::Scalar(key, ..) if role == Role::Value => Some(key.clone()),
+					_ => None,
+				};
+
+				match key {
+					Some(key) => {
+						path.push(PathSegment::Key(key));
+						self.node(event, marker, path, Role::Key)?;
+
+						let (event, marker) = self.next()?;
+						self.node(event, marker, path, Role::Value)?;
+						path.pop();
+					}
+					None => {
+						self.node(event, marker, path, Role::Ignored)?;
+
+						let (event, marker) = self.next()?;
+						self.node(event, marker, path, Role::Ignored)?;
+					}
+				}
+			},
+			Event::Scalar(..) | Event::Alias(_) => {}
+			_ => return None,
+		}
+
+		Some(())
+	}
+}
+
+/// Get the tag the deserializer drops, local tags and core schema tags are already handled
+fn resolve(tag: &Tag) -> Option<String> {
+	if tag.handle == "!" || tag.handle == CORE_SCHEMA_PREFIX {
+		return None;
+	}
+
+	let tag = format!("{}{}", tag.handle, tag.suffix);
+
+	if tag.starts_with(CORE_SCHEMA_PREFIX) || tag == "!" {
+		None
+	} else if tag.starts_with('!') {
+		Some(tag)
+	} else {
+		Some(format!("!<{}>", tag))
+	}
+}
+
+/// Get the path of a node in a document with its root put under the key
+fn under(root_key: Option<&str>, path: &KeyPath) -> KeyPath {
+	let Some(key) = root_key else {
+		return path.clone();
+	};
+
+	let mut segments = vec![PathSegment::Key(key.to_owned())];
+	segments.extend_from_slice(path.segments());
+
+	KeyPath::from(segments)
+}
+
+fn select<'v>(root: &'v mut Mapping, path: &KeyPath) -> Option<&'v mut Value> {
+	let (first, rest) = path.segments().split_first()?;
+	let mut value = find_key(root, first)?;
+
+	for segment in rest {
+		value = descend(value, segment)?;
+	}
+
+	Some(value)
+}
+
+fn find_key<'v>(mapping: &'v mut Mapping, segment: &PathSegment) -> Option<&'v mut Value> {
+	mapping
+		.iter_mut()
+		.find(|(key, _)| PathSegment::from_key(key) == *segment)
+		.map(|(_, value)| value)
+}
+
+fn descend<'v>(value: &'v mut Value, segment: &PathSegment) -> Option<&'v mut Value> {
+	match (value, segment) {
+		(Value::Tagged(tagged), segment) => descend(&mut tagged.value, segment),
+		(Value::Mapping(mapping), PathSegment::Key(_)) => find_key(mapping, segment),
+		(Value::Sequence(sequence), PathSegment::Index(index)) => sequence.get_mut(*index),
+		_ => None,
+	}
+}
+
+/// Attach tag to the value, plain scalars that were turned into strings
+/// because of their unknown tag get their untagged type back
+fn attach(value: &mut Value, tag: String, plain: Option<String>) {
+	let tag = serde_yaml::value::Tag::new(tag);
+
+	if let (Value::String(_), Some(plain)) = (&value, plain) {
+		if let Ok(scalar @ (Value::Null | Value::Bool(_) | Value::Number(_))) =
+			serde_yaml::from_str(&plain)
+		{
+			*value = scalar;
+		}
+	}
+
+	match value {
+		Value::Tagged(tagged) => tagged.tag = tag,
+		value => {
+			*value = Value::Tagged(Box::new(TaggedValue {
+				tag,
+				value: std::mem::take(value),
+			}))
+		}
+	}
+}
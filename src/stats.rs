@@ -0,0 +1,25 @@
+use serde::Serialize;
+
+/// Metrics collected during a single conversion
+///
+/// Only values are counted, keys are not included in any of the counts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+	pub strings: usize,
+	pub integers: usize,
+	pub floats: usize,
+	pub bools: usize,
+	pub nils: usize,
+	/// Number of emitted tables including the root one
+	pub tables: usize,
+	/// Number of tagged values, their wrapper tables are not counted in `tables`
+	pub tagged_values: usize,
+	/// Deepest table nesting reached, the root table has depth of 1
+	pub max_depth: usize,
+	/// Total length of all string values in bytes before escaping
+	pub string_bytes: usize,
+	/// Entries left out by [`Preview`](crate::Preview), entries nested in them aren't counted
+	pub elided_entries: usize,
+	/// Length of the generated Lua in bytes
+	pub output_bytes: usize,
+}
@@ -0,0 +1,91 @@
+use crate::options::HookError;
+use serde_yaml::Value;
+use std::{fmt, sync::Arc};
+
+type Handler = dyn Fn(&str, &Value) -> Result<TagReplacement, HookError> + Send + Sync;
+
+/// Function replacing tagged values, receiving the tag name and the tagged value
+///
+/// Tag names are written without the leading `!`, tags resolved from `%TAG`
+/// directives are shortened to their last segment like wrapper table keys
+#[derive(Clone)]
+pub struct TagHandler(Arc<Handler>);
+
+impl TagHandler {
+	pub fn new<R: Into<TagReplacement>, E: Into<HookError>>(
+		handler: impl Fn(&str, &Value) -> Result<R, E> + Send + Sync + 'static,
+	) -> Self {
+		Self(Arc::new(move |tag, value| {
+			handler(tag, value).map(Into::into).map_err(Into::into)
+		}))
+	}
+
+	pub(crate) fn call(&self, tag: &str, value: &Value) -> Result<TagReplacement, HookError> {
+		(self.0)(tag, value)
+	}
+}
+
+impl fmt::Debug for TagHandler {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("TagHandler")
+	}
+}
+
+/// What a [`TagHandler`] replaces the tagged value with
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagReplacement {
+	/// Value converted like any other value in the document, it can't be tagged again
+	Value(Value),
+	/// Lua expression written into the output as it is, like `Color3.new(1, 0, 0)`
+	Lua(String),
+}
+
+impl From<Value> for TagReplacement {
+	fn from(value: Value) -> Self {
+		TagReplacement::Value(value)
+	}
+}
+
+impl From<String> for TagReplacement {
+	fn from(lua: String) -> Self {
+		TagReplacement::Lua(lua)
+	}
+}
+
+/// What happens to tagged values nothing else handles, see [`ParseOptions::on_unknown_tag`](crate::ParseOptions::on_unknown_tag)
+#[derive(Debug, Clone, Default)]
+pub enum UnknownTags {
+	/// Fail with [`Error::UnknownTags`](crate::Error::UnknownTags) listing every unknown tag in the document
+	Error,
+	/// Write the value without its tag
+	Strip,
+	/// Write the value in a wrapper table, its format is set with [`ParseOptions::tag_wrapper`](crate::ParseOptions::tag_wrapper)
+	#[default]
+	Wrapper,
+	/// Replace the value with the result of the handler
+	Custom(TagHandler),
+}
+
+/// Format of tables written by [`UnknownTags::Wrapper`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagWrapper {
+	/// Table with the value at its only key, which is the template with `$tag` replaced
+	/// by the tag name. Template `$tag` writes `{ ["Tag"] = value }`
+	Key(String),
+	/// Table with the tag name and the value in separate fields, like `{ ["__tag"] = "Tag", ["value"] = value }`
+	///
+	/// Entries of mappings are written beside the tag field instead of in the value field
+	/// when `inline_mappings` is set, failing with [`Error::KeyCollision`](crate::Error::KeyCollision)
+	/// if one of their keys is the tag field
+	Fields {
+		tag_field: String,
+		value_field: String,
+		inline_mappings: bool,
+	},
+}
+
+impl Default for TagWrapper {
+	fn default() -> Self {
+		TagWrapper::Key(String::from("$tag"))
+	}
+}
@@ -0,0 +1,86 @@
+//! Helpers for snapshot tests of generated Lua, enabled with the `testing` feature
+//!
+//! ```rust
+//! use yaml2lua::{parse_with_options, testing::assert_lua_equivalent, ParseOptions};
+//!
+//! let lua = parse_with_options("b: [1, 2]\na: x", &ParseOptions::canonical()).unwrap();
+//!
+//! assert_lua_equivalent(&lua, "{ a = 'x', b = { 1, [2] = 2 } }");
+//! ```
+
+use crate::lua::{Lua, Parser, TableKey};
+use std::collections::BTreeMap;
+
+/// Check that two Lua table constructors describe the same value, ignoring formatting,
+/// order of entries and whether keys are written as identifiers or strings
+///
+/// Both sides may be prefixed with `return` or an assignment like `local t =`,
+/// other statements and expressions other than literals aren't supported.
+/// Integers and floats are different values even if they're equal numerically
+///
+/// # Panics
+///
+/// When the values differ, with the path of the first difference,
+/// or when either side can't be parsed
+#[track_caller]
+pub fn assert_lua_equivalent(a: &str, b: &str) {
+	let parse = |lua: &str, side: &str| match Parser::new(lua).chunk() {
+		Ok(value) => value,
+		Err(err) => panic!("{} side can't be parsed: {}", side, err),
+	};
+
+	let (left, right) = (parse(a, "left"), parse(b, "right"));
+
+	if let Some(difference) = difference(&left, &right, &mut String::new()) {
+		panic!(
+			"Lua values differ {}\n\nleft:\n{}\n\nright:\n{}",
+			difference, a, b
+		);
+	}
+}
+
+/// Describe the first difference between the values, if there's one
+fn difference<'a>(left: &'a Lua, right: &'a Lua, path: &mut String) -> Option<String> {
+	let at = |path: &str| match path.is_empty() {
+		true => String::from("at the root"),
+		false => format!("at `{}`", path),
+	};
+
+	let (Lua::Table(left), Lua::Table(right)) = (left, right) else {
+		let equal = match (left, right) {
+			(Lua::Float(a), Lua::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+			(a, b) => a == b,
+		};
+
+		return (!equal).then(|| format!("{}: {} != {}", at(path), left, right));
+	};
+
+	// Entries are compared in key order, whatever order they were written in
+	let sorted = |table: &'a [(TableKey, Lua)]| -> BTreeMap<&'a TableKey, &'a Lua> {
+		table.iter().map(|(key, value)| (key, value)).collect()
+	};
+	let (left, right) = (sorted(left), sorted(right));
+
+	for key in left
+		.keys()
+		.chain(right.keys().filter(|k| !left.contains_key(*k)))
+	{
+		let len = path.len();
+		path.push_str(&key.to_string());
+
+		let difference = match (left.get(key), right.get(key)) {
+			(Some(a), Some(b)) => difference(a, b, path),
+			(Some(a), None) => Some(format!("{}: {} != nil", at(path), a)),
+			(None, Some(b)) => Some(format!("{}: nil != {}", at(path), b)),
+			(None, None) => None,
+		};
+
+		path.truncate(len);
+
+		if difference.is_some() {
+			return difference;
+		}
+	}
+
+	None
+}
@@ -0,0 +1,183 @@
+/// Timestamp in the format of the YAML timestamp type, like `2024-06-01`
+/// or `2024-06-01T12:00:00.5+02:00`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Timestamp {
+	pub year: i64,
+	pub month: u32,
+	pub day: u32,
+	pub hour: u32,
+	pub minute: u32,
+	pub second: u32,
+	/// Digits of the fraction of the second without the dot, trailing zeros are removed
+	pub fraction: String,
+	/// Offset from UTC in minutes, timestamps without a time zone are in UTC
+	pub offset: i64,
+}
+
+impl Timestamp {
+	/// Parse the text if it matches the YAML timestamp type, which is a date alone or a date
+	/// and a time separated by `T` or spaces, followed by an optional `Z` or `+hh:mm` zone
+	pub fn parse(text: &str) -> Option<Self> {
+		let mut rest = text;
+
+		let year = digits(&mut rest, 4, 4)?;
+		expect(&mut rest, '-')?;
+
+		// Dates alone have two-digit months and days, dates with a time can have one
+		let month = digits(&mut rest, 1, 2)?;
+		expect(&mut rest, '-')?;
+		let day = digits(&mut rest, 1, 2)?;
+
+		let mut timestamp = Timestamp {
+			year: i64::from(year),
+			month,
+			day,
+			hour: 0,
+			minute: 0,
+			second: 0,
+			fraction: String::new(),
+			offset: 0,
+		};
+
+		if rest.is_empty() {
+			return (text.len() == 10 && timestamp.is_valid()).then_some(timestamp);
+		}
+
+		rest = match rest.strip_prefix(['T', 't']) {
+			Some(rest) => rest,
+			None if rest.starts_with([' ', '\t']) => rest.trim_start_matches([' ', '\t']),
+			None => return None,
+		};
+
+		timestamp.hour = digits(&mut rest, 1, 2)?;
+		expect(&mut rest, ':')?;
+		timestamp.minute = digits(&mut rest, 2, 2)?;
+		expect(&mut rest, ':')?;
+		timestamp.second = digits(&mut rest, 2, 2)?;
+
+		if let Some(fraction) = rest.strip_prefix('.') {
+			let len = fraction
+				.find(|c: char| !c.is_ascii_digit())
+				.unwrap_or(fraction.len());
+
+			timestamp.fraction = fraction[..len].trim_end_matches('0').to_owned();
+			rest = &fraction[len..];
+		}
+
+		let zone = rest.trim_start_matches([' ', '\t']);
+
+		match zone.chars().next() {
+			None => {}
+			Some('Z') if zone.len() == 1 => {}
+			Some(sign @ ('+' | '-')) => {
+				let mut zone = &zone[1..];
+
+				let hours = digits(&mut zone, 1, 2)?;
+				let minutes = match zone.strip_prefix(':') {
+					Some(mut rest) => {
+						let minutes = digits(&mut rest, 2, 2)?;
+						zone = rest;
+						minutes
+					}
+					None => 0,
+				};
+
+				if !zone.is_empty() || minutes > 59 {
+					return None;
+				}
+
+				let offset = i64::from(hours * 60 + minutes);
+				timestamp.offset = if sign == '-' { -offset } else { offset };
+			}
+			_ => return None,
+		}
+
+		timestamp.is_valid().then_some(timestamp)
+	}
+
+	/// Get the whole seconds since the Unix epoch, rounded down
+	pub fn epoch(&self) -> i64 {
+		let days = days_from_civil(self.year, self.month, self.day);
+		let seconds = i64::from(self.hour * 3600 + self.minute * 60 + self.second);
+
+		days * 86400 + seconds - self.offset * 60
+	}
+
+	/// Get the same point in time in UTC
+	pub fn utc(&self) -> Self {
+		let epoch = self.epoch();
+		let (year, month, day) = civil_from_days(epoch.div_euclid(86400));
+		let seconds = epoch.rem_euclid(86400) as u32;
+
+		Timestamp {
+			year,
+			month,
+			day,
+			hour: seconds / 3600,
+			minute: seconds / 60 % 60,
+			second: seconds % 60,
+			fraction: self.fraction.clone(),
+			offset: 0,
+		}
+	}
+
+	fn is_valid(&self) -> bool {
+		let days = days_from_civil(self.year, self.month, self.day);
+
+		(1..=12).contains(&self.month)
+			&& civil_from_days(days) == (self.year, self.month, self.day)
+			&& self.hour < 24
+			&& self.minute < 60
+			&& self.second < 60
+	}
+}
+
+fn digits(rest: &mut &str, min: usize, max: usize) -> Option<u32> {
+	let len = rest
+		.find(|c: char| !c.is_ascii_digit())
+		.unwrap_or(rest.len());
+
+	if len < min || len > max {
+		return None;
+	}
+
+	let (digits, remaining) = rest.split_at(len);
+	*rest = remaining;
+
+	digits.parse().ok()
+}
+
+fn expect(rest: &mut &str, c: char) -> Option<()> {
+	*rest = rest.strip_prefix(c)?;
+	Some(())
+}
+
+/// Count the days since the Unix epoch of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((i64::from(month) + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	era * 146097 + day_of_era - 719468
+}
+
+/// Get the date of a day counted from the Unix epoch, the inverse of [`days_from_civil`]
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let days = days + 719468;
+	let era = days.div_euclid(146097);
+	let day_of_era = days - era * 146097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let shifted_month = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+	let month = if shifted_month < 10 {
+		shifted_month + 3
+	} else {
+		shifted_month - 9
+	} as u32;
+
+	(year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
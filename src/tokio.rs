@@ -0,0 +1,95 @@
+//! Async file conversion using tokio, enabled with the `tokio` feature
+//!
+//! File I/O uses [`tokio::fs`] and the conversion itself runs on the blocking
+//! thread pool, so converting large documents doesn't stall the runtime
+
+use crate::{
+	batch::{self, BatchReport},
+	Error, ParseOptions, Result,
+};
+use std::path::{Path, PathBuf};
+use tokio::{fs, task};
+
+/// Async version of [`parse_file`](crate::parse_file)
+pub async fn parse_file_async(path: impl AsRef<Path>) -> Result<String> {
+	let bytes = fs::read(path).await?;
+	let options = ParseOptions::default();
+
+	spawn_blocking(move || batch::convert_bytes(&bytes, &options, None)).await
+}
+
+/// Async version of [`convert_dir`](crate::convert_dir)
+pub async fn convert_dir_async(
+	input: impl AsRef<Path>,
+	output: impl AsRef<Path>,
+	options: &ParseOptions,
+) -> Result<BatchReport> {
+	let (input, output) = (input.as_ref(), output.as_ref());
+	let mut report = BatchReport::default();
+
+	for file in find_yaml_files(input).await? {
+		let target = batch::output_path(input, output, &file);
+		let result = convert_file(&file, &target, options).await;
+
+		report.push(file, target, result);
+	}
+
+	if let Some(style) = options.index_modules.clone() {
+		let (input, output) = (input.to_owned(), output.to_owned());
+		let options = options.clone();
+
+		report = spawn_blocking(move || {
+			batch::write_indexes(&input, &output, &mut report, &style, &options)?;
+			Ok(report)
+		})
+		.await?;
+	}
+
+	Ok(report)
+}
+
+async fn convert_file(file: &Path, target: &Path, options: &ParseOptions) -> Result<()> {
+	let bytes = fs::read(file).await?;
+	let (options, path) = (options.clone(), file.to_owned());
+
+	let lua = spawn_blocking(move || batch::convert_bytes(&bytes, &options, Some(&path))).await?;
+
+	if let Some(parent) = target.parent() {
+		fs::create_dir_all(parent).await?;
+	}
+
+	Ok(fs::write(target, lua).await?)
+}
+
+async fn spawn_blocking<T: Send + 'static>(
+	f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+	match task::spawn_blocking(f).await {
+		Ok(result) => result,
+		Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+		Err(err) => Err(Error::Io(std::io::Error::other(err))),
+	}
+}
+
+async fn find_yaml_files(dir: &Path) -> Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+	let mut dirs = vec![dir.to_owned()];
+
+	while let Some(dir) = dirs.pop() {
+		let mut entries = fs::read_dir(&dir).await?;
+
+		while let Some(entry) = entries.next_entry().await? {
+			let path = entry.path();
+
+			if entry.file_type().await?.is_dir() {
+				dirs.push(path);
+			} else if batch::is_yaml(&path) {
+				files.push(path);
+			}
+		}
+	}
+
+	files.sort();
+
+	Ok(files)
+}
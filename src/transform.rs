@@ -0,0 +1,772 @@
+use crate::{
+	emit, encoding, source, EmbeddedYaml, Error, KeyPath, MergeConflicts, ParseOptions,
+	PathPattern, PathSegment, Result, SequenceMerge,
+};
+use serde::{
+	de::{DeserializeOwned, MapAccess, Visitor},
+	Deserialize, Deserializer, Serialize,
+};
+use serde_yaml::{from_str, value::Tag, Mapping, Value};
+use std::{collections::HashMap, fmt, io::Read};
+
+/// Deserialize YAML document with a mapping at its root
+pub(crate) fn load(yaml: &str) -> Result<Mapping> {
+	source::check_depth(yaml)?;
+
+	let RootMapping(mut root) = deserialize(yaml)?;
+
+	source::resolve_tags(yaml, &mut root, None);
+
+	Ok(root)
+}
+
+/// Deserialize a single document, failing with [`Error::MultipleDocuments`] for streams
+fn deserialize<T: DeserializeOwned>(yaml: &str) -> Result<T> {
+	from_str(yaml).map_err(|err| match source::split_documents(yaml) {
+		Some(documents) if documents.len() > 1 => Error::MultipleDocuments {
+			documents: documents.len(),
+		},
+		_ => Error::Yaml(err),
+	})
+}
+
+/// Mapping at the root of a document, deserialized in document order
+struct RootMapping(Mapping);
+
+impl<'de> Deserialize<'de> for RootMapping {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+		struct RootVisitor;
+
+		impl<'de> Visitor<'de> for RootVisitor {
+			type Value = RootMapping;
+
+			fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				f.write_str("a map")
+			}
+
+			fn visit_map<A: MapAccess<'de>>(
+				self,
+				mut map: A,
+			) -> std::result::Result<Self::Value, A::Error> {
+				let mut mapping = Mapping::with_capacity(map.size_hint().unwrap_or(0));
+
+				while let Some((key, value)) = map.next_entry()? {
+					mapping.insert(key, value);
+				}
+
+				Ok(RootMapping(mapping))
+			}
+		}
+
+		deserializer.deserialize_map(RootVisitor)
+	}
+}
+
+/// Root of a document that can be emitted
+pub(crate) enum Root {
+	Mapping(Mapping),
+	/// Tagged value of any type, which is always a [`Value::Tagged`]
+	Tagged(Value),
+}
+
+impl Root {
+	/// Get the mapping at the root, inside of the tag if the root is tagged
+	pub(crate) fn mapping_mut(&mut self) -> Option<&mut Mapping> {
+		match self {
+			Root::Mapping(mapping) => Some(mapping),
+			Root::Tagged(Value::Tagged(tagged)) => tagged.value.as_mapping_mut(),
+			Root::Tagged(_) => None,
+		}
+	}
+}
+
+/// Deserialize YAML document like [`load_with`], also accepting a tagged value at the root
+pub(crate) fn load_root(yaml: &str, options: &ParseOptions) -> Result<Root> {
+	source::check_depth(yaml)?;
+
+	let mut tagged = match deserialize(yaml)? {
+		Value::Mapping(mut root) => {
+			source::resolve_tags(yaml, &mut root, None);
+			force_strings(yaml, &mut root, options, None);
+
+			return Ok(Root::Mapping(root));
+		}
+		Value::Tagged(tagged) => tagged,
+		value => {
+			let wrapped = options
+				.root_key
+				.as_deref()
+				.and_then(|key| put_under_key(yaml, value, key, options));
+
+			// Deserialized again to fail with the same error as other conversions
+			return match wrapped {
+				Some(root) => Ok(Root::Mapping(root)),
+				None => load_with(yaml, options).map(Root::Mapping),
+			};
+		}
+	};
+
+	// Paths of values inside of a tag are the same as without it
+	if let Value::Mapping(mapping) = &mut tagged.value {
+		source::resolve_tags(yaml, mapping, None);
+		force_strings(yaml, mapping, options, None);
+	}
+
+	Ok(Root::Tagged(Value::Tagged(tagged)))
+}
+
+/// Serialize a value into a root like [`load_root`], without a source to apply options
+/// that need it. Sequences and scalars are put under the root key if there's one
+pub(crate) fn serialize_root<T: Serialize + ?Sized>(
+	value: &T,
+	options: &ParseOptions,
+) -> Result<Root> {
+	value_root(serde_yaml::to_value(value)?, options)
+}
+
+/// Turn a value into a root like [`serialize_root`]
+pub(crate) fn value_root(value: Value, options: &ParseOptions) -> Result<Root> {
+	if let Value::Tagged(_) = value {
+		return Ok(Root::Tagged(value));
+	}
+
+	if let Some(key) = &options.root_key {
+		if !matches!(value, Value::Mapping(_) | Value::Null) {
+			let mut mapping = Mapping::new();
+			mapping.insert(Value::String(key.clone()), value);
+
+			return Ok(Root::Mapping(mapping));
+		}
+	}
+
+	// Deserialized to fail with the same error as documents without a mapping
+	let RootMapping(root) = serde_yaml::from_value(value)?;
+
+	Ok(Root::Mapping(root))
+}
+
+/// Check whether [`prepare`] changes the root with the options, roots it
+/// doesn't change are checked with [`check`] and emitted without copying them
+pub(crate) fn changes_root(options: &ParseOptions) -> bool {
+	options.embedded_yaml.is_some()
+		|| options.expand_dotted_keys.is_some()
+		|| options.flatten.is_some()
+}
+
+/// Check the root like [`prepare`] does, without changing it
+pub(crate) fn check(root: &Mapping, options: &ParseOptions) -> Result<()> {
+	match options.max_depth {
+		Some(max) => check_depth(root, max),
+		None => Ok(()),
+	}
+}
+
+/// Prepare the mapping at the root like [`prepare`], tagged scalars and sequences are left as they are
+pub(crate) fn prepare_root(mut root: Root, options: &ParseOptions) -> Result<Root> {
+	if let Some(mapping) = root.mapping_mut() {
+		*mapping = prepare(std::mem::take(mapping), options)?;
+	}
+
+	Ok(root)
+}
+
+/// Deserialize YAML document like [`load`], also applying options that need the source text
+pub(crate) fn load_with(yaml: &str, options: &ParseOptions) -> Result<Mapping> {
+	if let Some(key) = &options.root_key {
+		source::check_depth(yaml)?;
+
+		if let Some(root) = put_under_key(yaml, deserialize(yaml)?, key, options) {
+			return Ok(root);
+		}
+	}
+
+	let mut root = load(yaml)?;
+	force_strings(yaml, &mut root, options, None);
+
+	Ok(root)
+}
+
+/// Put a sequence or scalar root under the root key, as the only entry of the root mapping.
+/// Returns `None` for mappings, tagged values and empty documents
+fn put_under_key(yaml: &str, root: Value, key: &str, options: &ParseOptions) -> Option<Mapping> {
+	if matches!(root, Value::Mapping(_) | Value::Tagged(_) | Value::Null) {
+		return None;
+	}
+
+	let mut mapping = Mapping::new();
+	mapping.insert(Value::String(key.to_owned()), root);
+
+	source::resolve_tags(yaml, &mut mapping, Some(key));
+	force_strings(yaml, &mut mapping, options, Some(key));
+
+	Some(mapping)
+}
+
+/// Turn scalars at paths matched by [`ParseOptions::force_string_paths`] into strings,
+/// using their text from the source and canonical formatting for values without one
+pub(crate) fn force_strings(
+	yaml: &str,
+	root: &mut Mapping,
+	options: &ParseOptions,
+	root_key: Option<&str>,
+) {
+	fn stringify(value: &mut Value, path: &mut KeyPath, patterns: &[PathPattern]) {
+		match value {
+			Value::Null | Value::Bool(_) | Value::Number(_)
+				if patterns.iter().any(|pattern| pattern.matches(path)) =>
+			{
+				let text = match value {
+					Value::Bool(b) => b.to_string(),
+					Value::Number(n) => n.to_string(),
+					_ => String::from("null"),
+				};
+
+				*value = Value::String(text);
+			}
+			Value::Sequence(sequence) => {
+				for (i, value) in sequence.iter_mut().enumerate() {
+					path.push(PathSegment::Index(i));
+					stringify(value, path, patterns);
+					path.pop();
+				}
+			}
+			Value::Mapping(mapping) => {
+				for (key, value) in mapping.iter_mut() {
+					path.push(PathSegment::from_key(key));
+					stringify(value, path, patterns);
+					path.pop();
+				}
+			}
+			_ => {}
+		}
+	}
+
+	let patterns = &options.force_string_paths;
+
+	if patterns.is_empty() {
+		return;
+	}
+
+	source::force_strings(yaml, root, patterns, root_key);
+
+	// Aliases don't have text of their own
+	for (key, value) in root.iter_mut() {
+		let mut path = KeyPath::from(vec![PathSegment::from_key(key)]);
+		stringify(value, &mut path, patterns);
+	}
+}
+
+/// Deserialize YAML document read from the reader, same as [`load_root`]. The whole
+/// input is read first and decoded like [`parse_bytes`](crate::parse_bytes)
+pub(crate) fn load_reader(mut reader: impl Read, options: &ParseOptions) -> Result<Root> {
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes)?;
+
+	load_root(&encoding::decode(&bytes)?, options)
+}
+
+/// Errors of values replaced with null in a partial conversion, with their paths
+pub(crate) type Failures = Vec<(KeyPath, Error)>;
+
+/// Turn the deserialized root into the mapping that gets emitted,
+/// applying all structural transformations enabled in the options
+pub(crate) fn prepare(root: Mapping, options: &ParseOptions) -> Result<Mapping> {
+	prepare_with(root, options, &mut None)
+}
+
+/// Prepare the root like [`prepare`], replacing values that fail to transform with null
+/// instead of failing. Errors of transformations over the whole document aren't recovered
+pub(crate) fn prepare_partial(mut root: Root, options: &ParseOptions) -> Result<(Root, Failures)> {
+	let mut failures = Some(Vec::new());
+
+	if let Some(mapping) = root.mapping_mut() {
+		*mapping = prepare_with(std::mem::take(mapping), options, &mut failures)?;
+	}
+
+	Ok((root, failures.unwrap_or_default()))
+}
+
+fn prepare_with(
+	mut root: Mapping,
+	options: &ParseOptions,
+	failures: &mut Option<Failures>,
+) -> Result<Mapping> {
+	let separators = [&options.expand_dotted_keys, &options.flatten];
+
+	if separators
+		.iter()
+		.any(|s| s.as_ref().is_some_and(|s| s.is_empty()))
+	{
+		return Err(Error::Transform {
+			path: KeyPath::new(),
+			message: String::from("key separator can't be empty"),
+		});
+	}
+
+	if let Some(embedded) = &options.embedded_yaml {
+		for (key, value) in root.iter_mut() {
+			let mut path = KeyPath::new();
+			path.push(PathSegment::from_key(key));
+
+			embed(value, embedded, 0, &mut path, failures)?;
+		}
+	}
+
+	check(&root, options)?;
+
+	if let Some(separator) = &options.expand_dotted_keys {
+		root = expand(root, separator, &mut KeyPath::new())?;
+	}
+
+	if let Some(separator) = &options.flatten {
+		root = flatten(root, separator)?;
+	}
+
+	Ok(root)
+}
+
+/// Fail if tables are nested deeper than the maximum, the root table has depth of 1
+///
+/// Tables are visited with a stack of their remaining entries instead of recursing,
+/// so checking works for tables nested any deep
+fn check_depth(root: &Mapping, max: usize) -> Result<()> {
+	fn entries(value: &Value) -> Option<Vec<(PathSegment, &Value)>> {
+		match value {
+			Value::Sequence(sequence) => Some(
+				sequence
+					.iter()
+					.enumerate()
+					.map(|(i, value)| (PathSegment::Index(i), value))
+					.collect(),
+			),
+			Value::Mapping(mapping) => Some(
+				mapping
+					.iter()
+					.map(|(key, value)| (PathSegment::from_key(key), value))
+					.collect(),
+			),
+			Value::Tagged(tagged) => entries(&tagged.value),
+			_ => None,
+		}
+	}
+
+	let mut path = KeyPath::new();
+
+	if max == 0 {
+		return Err(Error::TooDeep { path, max });
+	}
+
+	let mut stack = vec![root
+		.iter()
+		.map(|(key, value)| (PathSegment::from_key(key), value))
+		.collect::<Vec<_>>()
+		.into_iter()];
+
+	while let Some(remaining) = stack.last_mut() {
+		let Some((segment, value)) = remaining.next() else {
+			stack.pop();
+			path.pop();
+			continue;
+		};
+
+		if let Some(entries) = entries(value) {
+			path.push(segment);
+
+			if stack.len() == max {
+				return Err(Error::TooDeep { path, max });
+			}
+
+			stack.push(entries.into_iter());
+		}
+	}
+
+	Ok(())
+}
+
+/// Replace strings tagged as embedded YAML with the parsed values, recursively
+fn embed(
+	value: &mut Value,
+	embedded: &EmbeddedYaml,
+	depth: usize,
+	path: &mut KeyPath,
+	failures: &mut Option<Failures>,
+) -> Result<()> {
+	match value {
+		Value::Tagged(tagged) if tagged.tag == Tag::new(&embedded.tag) => {
+			match parse_embedded(&tagged.value, &tagged.tag, embedded, depth, path, failures) {
+				Ok(parsed) => *value = parsed,
+				Err(error) => match failures {
+					Some(failures) => {
+						failures.push((path.clone(), error));
+						*value = Value::Null;
+					}
+					None => return Err(error),
+				},
+			}
+		}
+		Value::Tagged(tagged) => embed(&mut tagged.value, embedded, depth, path, failures)?,
+		Value::Sequence(sequence) => {
+			for (i, value) in sequence.iter_mut().enumerate() {
+				path.push(PathSegment::Index(i));
+				embed(value, embedded, depth, path, failures)?;
+				path.pop();
+			}
+		}
+		Value::Mapping(mapping) => {
+			for (key, value) in mapping.iter_mut() {
+				path.push(PathSegment::from_key(key));
+				embed(value, embedded, depth, path, failures)?;
+				path.pop();
+			}
+		}
+		_ => {}
+	}
+
+	Ok(())
+}
+
+/// Parse the embedded YAML string, embedding strings tagged inside it too
+fn parse_embedded(
+	value: &Value,
+	tag: &Tag,
+	embedded: &EmbeddedYaml,
+	depth: usize,
+	path: &mut KeyPath,
+	failures: &mut Option<Failures>,
+) -> Result<Value> {
+	let Value::String(yaml) = value else {
+		return Err(Error::Transform {
+			path: path.clone(),
+			message: format!(
+				"`{}` tag requires a string, found {}",
+				tag,
+				emit::type_name(value)
+			),
+		});
+	};
+
+	if depth >= embedded.max_depth {
+		return Err(Error::Transform {
+			path: path.clone(),
+			message: format!(
+				"embedded YAML is nested deeper than {} levels",
+				embedded.max_depth
+			),
+		});
+	}
+
+	let embedded_error = |error| Error::EmbeddedYaml {
+		path: path.clone(),
+		error,
+	};
+
+	source::check_depth(yaml).map_err(embedded_error)?;
+
+	let mut parsed: Value = from_str(yaml).map_err(embedded_error)?;
+
+	if let Value::Mapping(mapping) = &mut parsed {
+		source::resolve_tags(yaml, mapping, None);
+	}
+
+	embed(&mut parsed, embedded, depth + 1, path, failures)?;
+
+	Ok(parsed)
+}
+
+/// Flatten nested tables into a single table with keys joined by the separator,
+/// sequence elements use 1-based indices and empty tables are kept as leaves
+fn flatten(root: Mapping, separator: &str) -> Result<Mapping> {
+	fn walk(
+		value: Value,
+		prefix: &str,
+		path: &mut KeyPath,
+		separator: &str,
+		flat: &mut Mapping,
+	) -> Result<()> {
+		let entries: Vec<(String, Value)> = match value {
+			Value::Mapping(mapping) if !mapping.is_empty() => mapping
+				.into_iter()
+				.map(|(key, value)| (key_text(&key), value))
+				.collect(),
+			Value::Sequence(sequence) if !sequence.is_empty() => sequence
+				.into_iter()
+				.enumerate()
+				.map(|(i, value)| ((i + 1).to_string(), value))
+				.collect(),
+			value => {
+				flat.insert(Value::String(prefix.to_owned()), value);
+				return Ok(());
+			}
+		};
+
+		for (key, value) in entries {
+			path.push(PathSegment::Key(key.clone()));
+
+			if key.contains(separator) {
+				return Err(Error::Transform {
+					path: path.clone(),
+					message: format!("key \"{}\" contains flatten separator `{}`", key, separator),
+				});
+			}
+
+			let prefix = if prefix.is_empty() {
+				key
+			} else {
+				format!("{}{}{}", prefix, separator, key)
+			};
+
+			walk(value, &prefix, path, separator, flat)?;
+			path.pop();
+		}
+
+		Ok(())
+	}
+
+	let mut flat = Mapping::new();
+
+	if !root.is_empty() {
+		walk(
+			Value::Mapping(root),
+			"",
+			&mut KeyPath::new(),
+			separator,
+			&mut flat,
+		)?;
+	}
+
+	Ok(flat)
+}
+
+/// Deep merge the layer into the base, later layers override earlier ones
+pub(crate) fn merge(
+	base: &mut Mapping,
+	layer: Mapping,
+	options: &ParseOptions,
+	path: &mut KeyPath,
+) -> Result<()> {
+	for (key, value) in layer {
+		path.push(PathSegment::from_key(&key));
+
+		match (base.get_mut(&key), value) {
+			(Some(Value::Mapping(base)), Value::Mapping(layer)) => {
+				merge(base, layer, options, path)?;
+			}
+			(Some(Value::Sequence(base)), Value::Sequence(layer))
+				if options.merge_sequences == SequenceMerge::Concat =>
+			{
+				base.extend(layer);
+			}
+			(Some(existing), value) => {
+				let conflicts = type_name(existing) != type_name(&value);
+
+				if conflicts && options.merge_conflicts == MergeConflicts::Error {
+					return Err(Error::Transform {
+						path: path.clone(),
+						message: format!(
+							"can't merge {} with {}",
+							type_name(existing),
+							type_name(&value)
+						),
+					});
+				}
+
+				*existing = value;
+			}
+			(None, value) => {
+				base.insert(key, value);
+			}
+		}
+
+		path.pop();
+	}
+
+	Ok(())
+}
+
+/// Merge a document root into another like [`merge`]. Mappings inside of tags are merged
+/// too and keep the tag of the later root if it has one, tagged roots of other types
+/// replace the earlier root like values that aren't mappings
+pub(crate) fn merge_root(base: &mut Root, mut layer: Root, options: &ParseOptions) -> Result<()> {
+	let tagged = matches!(layer, Root::Tagged(_));
+
+	if let (Some(mapping), Some(layer_mapping)) = (base.mapping_mut(), layer.mapping_mut()) {
+		let mut merged = std::mem::take(mapping);
+		merge(
+			&mut merged,
+			std::mem::take(layer_mapping),
+			options,
+			&mut KeyPath::new(),
+		)?;
+
+		if !tagged {
+			*mapping = merged;
+			return Ok(());
+		}
+
+		*layer_mapping = merged;
+	} else {
+		let root_type = |root: &Root| match root {
+			Root::Tagged(Value::Tagged(tagged)) => type_name(&tagged.value),
+			_ => "mapping",
+		};
+		let (existing, value) = (root_type(base), root_type(&layer));
+
+		if existing != value && options.merge_conflicts == MergeConflicts::Error {
+			return Err(Error::Transform {
+				path: KeyPath::new(),
+				message: format!("can't merge {} with {}", existing, value),
+			});
+		}
+	}
+
+	*base = layer;
+
+	Ok(())
+}
+
+fn type_name(value: &Value) -> &'static str {
+	match value {
+		Value::Mapping(_) => "mapping",
+		Value::Sequence(_) => "sequence",
+		_ => "scalar",
+	}
+}
+
+/// Split string keys on the separator into nested tables, merging tables
+/// that share a prefix, `\` before the separator prevents the split
+fn expand(mapping: Mapping, separator: &str, path: &mut KeyPath) -> Result<Mapping> {
+	let mut expanded = Mapping::new();
+	// Source key that first created the entry at each expanded path, for conflict errors
+	let mut origins: HashMap<Vec<String>, String> = HashMap::new();
+
+	for (key, value) in mapping {
+		path.push(PathSegment::from_key(&key));
+		let value = expand_value(value, separator, path)?;
+		path.pop();
+
+		let source = key_text(&key);
+		let parts = match &key {
+			Value::String(s) => split_escaped(s, separator),
+			_ => vec![source.clone()],
+		};
+
+		let (last, parents) = parts
+			.split_last()
+			.expect("split always returns at least one part");
+		let key = match key {
+			Value::String(_) => Value::String(last.clone()),
+			key => key,
+		};
+
+		if let Err(prefix) = insert_expanded(&mut expanded, parents, key, value) {
+			let other = (1..=prefix.len())
+				.rev()
+				.find_map(|len| origins.get(&prefix[..len]))
+				.cloned()
+				.unwrap_or_else(|| prefix.join(separator));
+
+			return Err(Error::Transform {
+				path: path.clone(),
+				message: format!("key \"{}\" conflicts with key \"{}\"", source, other),
+			});
+		}
+
+		for len in 1..=parts.len() {
+			origins
+				.entry(parts[..len].to_vec())
+				.or_insert_with(|| source.clone());
+		}
+	}
+
+	Ok(expanded)
+}
+
+fn expand_value(value: Value, separator: &str, path: &mut KeyPath) -> Result<Value> {
+	Ok(match value {
+		Value::Mapping(mapping) => Value::Mapping(expand(mapping, separator, path)?),
+		Value::Sequence(sequence) => Value::Sequence(
+			sequence
+				.into_iter()
+				.enumerate()
+				.map(|(i, value)| {
+					path.push(PathSegment::Index(i));
+					let value = expand_value(value, separator, path);
+					path.pop();
+					value
+				})
+				.collect::<Result<_>>()?,
+		),
+		value => value,
+	})
+}
+
+/// Insert value under nested parent keys, merging with tables already there.
+/// Fails with the expanded path of the entry that's in the way
+fn insert_expanded(
+	mapping: &mut Mapping,
+	parents: &[String],
+	key: Value,
+	value: Value,
+) -> std::result::Result<(), Vec<String>> {
+	let mut table = mapping;
+
+	for (i, parent) in parents.iter().enumerate() {
+		let entry = table
+			.entry(Value::String(parent.clone()))
+			.or_insert_with(|| Value::Mapping(Mapping::new()));
+
+		table = match entry {
+			Value::Mapping(mapping) => mapping,
+			_ => return Err(parents[..=i].to_vec()),
+		};
+	}
+
+	let prefix = |key: &Value| parents.iter().cloned().chain([key_text(key)]).collect();
+
+	match (table.get_mut(&key), value) {
+		(None, value) => {
+			table.insert(key, value);
+		}
+		(Some(Value::Mapping(existing)), Value::Mapping(value)) => {
+			for (k, v) in value {
+				insert_expanded(existing, &[], k, v).map_err(|_| prefix(&key))?;
+			}
+		}
+		(Some(_), _) => return Err(prefix(&key)),
+	}
+
+	Ok(())
+}
+
+/// Split string on the separator unless it's preceded by `\`
+fn split_escaped(string: &str, separator: &str) -> Vec<String> {
+	let mut parts = vec![String::new()];
+	let mut rest = string;
+
+	while !rest.is_empty() {
+		if let Some(after) = rest
+			.strip_prefix('\\')
+			.and_then(|r| r.strip_prefix(separator))
+		{
+			parts.last_mut().unwrap().push_str(separator);
+			rest = after;
+		} else if let Some(after) = rest.strip_prefix(separator) {
+			parts.push(String::new());
+			rest = after;
+		} else {
+			let char = rest.chars().next().unwrap();
+			parts.last_mut().unwrap().push(char);
+			rest = &rest[char.len_utf8()..];
+		}
+	}
+
+	parts
+}
+
+/// Text form of a key used when joining keys into paths
+fn key_text(key: &Value) -> String {
+	match PathSegment::from_key(key) {
+		PathSegment::Key(key) => key,
+		PathSegment::Index(index) => index.to_string(),
+	}
+}
@@ -0,0 +1,89 @@
+use crate::{
+	lua::{Lua, Parser, TableKey},
+	Error, Result,
+};
+use serde_yaml::{Mapping, Number, Value};
+
+/// Parse a Lua literal into a YAML value
+pub(crate) fn to_value(lua: &str) -> Result<Value> {
+	let value = Parser::new(lua).chunk().map_err(Error::Lua)?;
+	convert(value, &mut String::new())
+}
+
+/// Convert the value, tables with keys from 1 to their length become sequences
+fn convert(value: Lua, path: &mut String) -> Result<Value> {
+	let table = match value {
+		Lua::Nil => return Ok(Value::Null),
+		Lua::Bool(b) => return Ok(Value::Bool(b)),
+		Lua::Integer(i) => return Ok(Value::Number(i.into())),
+		Lua::Float(f) => return Ok(Value::Number(f.into())),
+		Lua::String(s) => return string(s, path).map(Value::String),
+		Lua::Table(table) => table,
+	};
+
+	let len = table.len() as i64;
+	let is_sequence = !table.is_empty()
+		&& table
+			.iter()
+			.all(|(key, _)| matches!(key, TableKey::Integer(i) if (1..=len).contains(i)));
+
+	if is_sequence {
+		let mut elements: Vec<(i64, Lua)> = table
+			.into_iter()
+			.map(|(key, value)| match key {
+				TableKey::Integer(i) => (i, value),
+				_ => unreachable!("sequence keys are integers"),
+			})
+			.collect();
+
+		elements.sort_by_key(|(i, _)| *i);
+
+		return elements
+			.into_iter()
+			.map(|(i, value)| nested(path, &format!("[{}]", i), |path| convert(value, path)))
+			.collect::<Result<_>>()
+			.map(Value::Sequence);
+	}
+
+	let mut mapping = Mapping::with_capacity(table.len());
+
+	for (key, value) in table {
+		let (segment, key) = match key {
+			TableKey::Bool(b) => (format!("[{}]", b), Value::Bool(b)),
+			TableKey::Integer(i) => (format!("[{}]", i), Value::Number(i.into())),
+			TableKey::Float(f) => (format!("[{:?}]", f.0), Value::Number(Number::from(f.0))),
+			TableKey::String(s) => {
+				let key = string(s, path)?;
+				(format!(".{}", key), Value::String(key))
+			}
+		};
+
+		let value = nested(path, &segment, |path| convert(value, path))?;
+		mapping.insert(key, value);
+	}
+
+	Ok(Value::Mapping(mapping))
+}
+
+/// Run the conversion with the segment added to the path used in errors
+fn nested<T>(path: &mut String, segment: &str, f: impl FnOnce(&mut String) -> T) -> T {
+	let len = path.len();
+	path.push_str(segment);
+
+	let result = f(path);
+	path.truncate(len);
+
+	result
+}
+
+fn string(bytes: Vec<u8>, path: &str) -> Result<String> {
+	String::from_utf8(bytes).map_err(|_| {
+		Error::Lua(match path.is_empty() {
+			true => String::from("string at the root isn't valid UTF-8"),
+			false => format!(
+				"string at `{}` isn't valid UTF-8",
+				path.trim_start_matches('.')
+			),
+		})
+	})
+}
@@ -0,0 +1,205 @@
+use crate::{
+	emit::Emitter,
+	fmt::{write_lua_key, write_lua_string, LuaKey, QuoteStyle},
+	lua::{Lua, Parser, TableKey},
+	parse, Error, ParseOptions, Result,
+};
+use serde_yaml::{Number, Value};
+use std::{borrow::Cow, str::FromStr};
+
+/// Lua value a YAML document converts into, for code that works with the converted
+/// values instead of the generated Lua
+///
+/// Values are parsed from the Lua written with the default options, so they're
+/// exactly what loading the output of [`parse`] gives. Parse YAML with
+/// [`str::parse`], convert already parsed values with [`TryFrom`] and write
+/// values as Lua with the default formatting with [`String::from`]
+///
+/// ```rust
+/// use yaml2lua::LuaValue;
+///
+/// let value: LuaValue = "name: lobby\nmax: 4".parse().unwrap();
+///
+/// assert_eq!(
+/// 	value,
+/// 	LuaValue::Table(vec![
+/// 		(LuaValue::from("name"), LuaValue::from("lobby")),
+/// 		(LuaValue::from("max"), LuaValue::Integer(4)),
+/// 	])
+/// );
+/// assert_eq!(String::from(value), "{\n\t[\"name\"] = \"lobby\",\n\t[\"max\"] = 4,\n}");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum LuaValue {
+	Nil,
+	Bool(bool),
+	Integer(i64),
+	Float(f64),
+	String(String),
+	/// Entries in the order they're written, elements of sequences have keys from 1
+	Table(Vec<(LuaValue, LuaValue)>),
+}
+
+impl From<&str> for LuaValue {
+	fn from(string: &str) -> Self {
+		LuaValue::String(string.to_owned())
+	}
+}
+
+/// Convert a YAML document, mappings convert exactly like with [`parse`] and other
+/// roots like values selected by [`parse_path`](crate::parse_path), so scalars
+/// aren't tables. Fails with the errors of converting the document
+///
+/// ```rust
+/// use yaml2lua::LuaValue;
+///
+/// assert_eq!("[1, 2.5]".parse::<LuaValue>().unwrap(), LuaValue::Table(vec![
+/// 	(LuaValue::Integer(1), LuaValue::Integer(1)),
+/// 	(LuaValue::Integer(2), LuaValue::Float(2.5)),
+/// ]));
+/// assert_eq!("hello".parse::<LuaValue>().unwrap(), LuaValue::from("hello"));
+/// assert_eq!("~".parse::<LuaValue>().unwrap(), LuaValue::Nil);
+/// assert!("a: [".parse::<LuaValue>().is_err());
+/// ```
+impl FromStr for LuaValue {
+	type Err = Error;
+
+	fn from_str(yaml: &str) -> Result<Self> {
+		match serde_yaml::from_str::<Value>(yaml) {
+			Ok(Value::Mapping(_) | Value::Tagged(_)) | Err(_) => from_lua(&parse(yaml)?),
+			Ok(value) => LuaValue::try_from(&value),
+		}
+	}
+}
+
+/// Convert an already parsed YAML value, mappings convert like with
+/// [`parse_value`](crate::parse_value) and other values like with [`LuaValue::from_str`]
+///
+/// ```rust
+/// use yaml2lua::LuaValue;
+///
+/// let value = serde_yaml::Value::from(vec!["a"]);
+///
+/// assert_eq!(
+/// 	LuaValue::try_from(&value).unwrap(),
+/// 	LuaValue::Table(vec![(LuaValue::Integer(1), LuaValue::from("a"))])
+/// );
+/// ```
+impl TryFrom<&Value> for LuaValue {
+	type Error = Error;
+
+	fn try_from(value: &Value) -> Result<Self> {
+		if let Value::Mapping(_) | Value::Tagged(_) = value {
+			return from_lua(&crate::parse_value(value)?);
+		}
+
+		let options = ParseOptions::default();
+		let mut emitter = Emitter::new(&options);
+		emitter.emit_value(value)?;
+
+		from_lua(&emitter.into_output())
+	}
+}
+
+/// Write the value as Lua with the default formatting of [`parse`], tables whose
+/// keys count from 1 are written as sequences and entries with nil keys are left out
+///
+/// ```rust
+/// use yaml2lua::LuaValue;
+///
+/// let value = LuaValue::Table(vec![(LuaValue::Integer(1), LuaValue::Float(f64::INFINITY))]);
+///
+/// assert_eq!(String::from(value), "{\n\tmath.huge,\n}");
+/// ```
+impl From<LuaValue> for String {
+	fn from(value: LuaValue) -> Self {
+		let mut lua = String::new();
+		write(&mut lua, &value, 0);
+		lua
+	}
+}
+
+/// Parse the generated Lua into a value
+fn from_lua(lua: &str) -> Result<LuaValue> {
+	convert(Parser::new(lua).chunk().map_err(Error::Lua)?)
+}
+
+fn convert(value: Lua) -> Result<LuaValue> {
+	Ok(match value {
+		Lua::Nil => LuaValue::Nil,
+		Lua::Bool(b) => LuaValue::Bool(b),
+		Lua::Integer(i) => LuaValue::Integer(i),
+		Lua::Float(f) => LuaValue::Float(f),
+		Lua::String(s) => LuaValue::String(string(s)?),
+		Lua::Table(table) => LuaValue::Table(
+			table
+				.into_iter()
+				.map(|(key, value)| {
+					let key = match key {
+						TableKey::Bool(b) => LuaValue::Bool(b),
+						TableKey::Integer(i) => LuaValue::Integer(i),
+						TableKey::Float(f) => LuaValue::Float(f.0),
+						TableKey::String(s) => LuaValue::String(string(s)?),
+					};
+
+					Ok((key, convert(value)?))
+				})
+				.collect::<Result<_>>()?,
+		),
+	})
+}
+
+fn string(bytes: Vec<u8>) -> Result<String> {
+	String::from_utf8(bytes).map_err(|_| Error::Lua(String::from("string isn't valid UTF-8")))
+}
+
+fn write(lua: &mut String, value: &LuaValue, depth: usize) {
+	match value {
+		LuaValue::Nil => lua.push_str("nil"),
+		LuaValue::Bool(b) => lua.push_str(if *b { "true" } else { "false" }),
+		LuaValue::Integer(i) => lua.push_str(&i.to_string()),
+		LuaValue::Float(f) if f.is_nan() => lua.push_str("0/0"),
+		LuaValue::Float(f) if f.is_infinite() && *f > 0.0 => lua.push_str("math.huge"),
+		LuaValue::Float(f) if f.is_infinite() => lua.push_str("-math.huge"),
+		LuaValue::Float(f) => lua.push_str(&Number::from(*f).to_string()),
+		LuaValue::String(s) => {
+			lua.push('"');
+			write_lua_string(lua, s, QuoteStyle::Double);
+			lua.push('"');
+		}
+		LuaValue::Table(entries) => {
+			lua.push_str("{\n");
+
+			// Elements with keys counting from 1 are written without them, like sequences
+			let mut next = 1;
+
+			for (key, value) in entries {
+				let key = match key {
+					LuaValue::Integer(i) if *i == next => {
+						next += 1;
+						None
+					}
+					LuaValue::Bool(b) => Some(LuaKey::Bool(*b)),
+					LuaValue::Integer(i) => Some(LuaKey::Number((*i).into())),
+					LuaValue::Float(f) => Some(LuaKey::Number(Number::from(*f))),
+					LuaValue::String(s) => Some(LuaKey::String(Cow::Borrowed(s))),
+					// Lua tables can't have these keys
+					LuaValue::Nil | LuaValue::Table(_) => continue,
+				};
+
+				lua.extend(std::iter::repeat_n('\t', depth + 1));
+
+				if let Some(key) = key {
+					write_lua_key(lua, &key, QuoteStyle::Double);
+					lua.push_str(" = ");
+				}
+
+				write(lua, value, depth + 1);
+				lua.push_str(",\n");
+			}
+
+			lua.extend(std::iter::repeat_n('\t', depth));
+			lua.push('}');
+		}
+	}
+}
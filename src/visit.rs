@@ -0,0 +1,129 @@
+use crate::{transform, Error, KeyPath, PathSegment};
+use serde_yaml::Value;
+use std::error;
+
+/// Error returned by a [`DocumentVisitor`] to stop visiting the document
+pub type VisitError = Box<dyn error::Error + Send + Sync>;
+
+/// Callbacks called by [`visit`] for every node of the document in document order
+///
+/// Every callback gets the key path of the node and does nothing by default.
+/// Returning an error stops the walk and [`visit`] fails with [`Error::Visit`]
+/// pointing at the path of the node
+pub trait DocumentVisitor {
+	fn enter_mapping(&mut self, path: &KeyPath) -> Result<(), VisitError> {
+		let _ = path;
+		Ok(())
+	}
+
+	fn exit_mapping(&mut self, path: &KeyPath) -> Result<(), VisitError> {
+		let _ = path;
+		Ok(())
+	}
+
+	fn enter_sequence(&mut self, path: &KeyPath) -> Result<(), VisitError> {
+		let _ = path;
+		Ok(())
+	}
+
+	fn exit_sequence(&mut self, path: &KeyPath) -> Result<(), VisitError> {
+		let _ = path;
+		Ok(())
+	}
+
+	/// Called before the value of a tagged node is visited with the same path
+	fn tagged(&mut self, path: &KeyPath, tag: &str) -> Result<(), VisitError> {
+		let _ = (path, tag);
+		Ok(())
+	}
+
+	/// Called for strings, numbers, booleans and nulls
+	fn scalar(&mut self, path: &KeyPath, value: &Value) -> Result<(), VisitError> {
+		let _ = (path, value);
+		Ok(())
+	}
+}
+
+/// Walk YAML string calling the visitor for every node, the document is parsed
+/// the same way as by [`parse`](crate::parse)
+///
+/// ```rust
+/// use serde_yaml::Value;
+/// use yaml2lua::{visit, DocumentVisitor, KeyPath, VisitError};
+///
+/// #[derive(Default)]
+/// struct Assets(Vec<String>);
+///
+/// impl DocumentVisitor for Assets {
+/// 	fn scalar(&mut self, path: &KeyPath, value: &Value) -> Result<(), VisitError> {
+/// 		if let Value::String(s) = value {
+/// 			if s.ends_with(".png") {
+/// 				self.0.push(format!("{} = {}", path, s));
+/// 			}
+/// 		}
+///
+/// 		Ok(())
+/// 	}
+/// }
+///
+/// let mut assets = Assets::default();
+/// visit("icon: a.png\nitems: [{ icon: b.png }, 1]", &mut assets).unwrap();
+///
+/// assert_eq!(assets.0, ["icon = a.png", "items[0].icon = b.png"]);
+/// ```
+pub fn visit(yaml: &str, visitor: &mut impl DocumentVisitor) -> crate::Result<()> {
+	let root = Value::Mapping(transform::load(yaml)?);
+	let mut path = KeyPath::new();
+
+	walk(&root, &mut path, visitor)
+}
+
+/// Walk an already parsed YAML value calling the visitor for every node like [`visit`],
+/// paths start at the value
+pub fn visit_value(value: &Value, visitor: &mut impl DocumentVisitor) -> crate::Result<()> {
+	walk(value, &mut KeyPath::new(), visitor)
+}
+
+fn walk(
+	value: &Value,
+	path: &mut KeyPath,
+	visitor: &mut impl DocumentVisitor,
+) -> crate::Result<()> {
+	let failed = |path: &KeyPath| {
+		let path = path.clone();
+		move |source| Error::Visit { path, source }
+	};
+
+	match value {
+		Value::Mapping(mapping) => {
+			visitor.enter_mapping(path).map_err(failed(path))?;
+
+			for (key, value) in mapping {
+				path.push(PathSegment::from_key(key));
+				walk(value, path, visitor)?;
+				path.pop();
+			}
+
+			visitor.exit_mapping(path).map_err(failed(path))
+		}
+		Value::Sequence(sequence) => {
+			visitor.enter_sequence(path).map_err(failed(path))?;
+
+			for (i, value) in sequence.iter().enumerate() {
+				path.push(PathSegment::Index(i));
+				walk(value, path, visitor)?;
+				path.pop();
+			}
+
+			visitor.exit_sequence(path).map_err(failed(path))
+		}
+		Value::Tagged(tagged) => {
+			visitor
+				.tagged(path, &tagged.tag.to_string())
+				.map_err(failed(path))?;
+
+			walk(&tagged.value, path, visitor)
+		}
+		scalar => visitor.scalar(path, scalar).map_err(failed(path)),
+	}
+}
@@ -0,0 +1,190 @@
+use crate::{
+	batch::{self, ConvertedFile, FailedFile},
+	convert_dir, Error, ParseOptions, Result,
+};
+use notify::{RecursiveMode, Watcher};
+use std::{
+	collections::BTreeSet,
+	fs, io,
+	ops::ControlFlow,
+	path::{Path, PathBuf},
+	sync::mpsc,
+	time::Duration,
+};
+
+/// How long the watched directory has to stay quiet before changes are converted,
+/// so editors writing temporary files and renaming them trigger a single conversion
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Change made to the output directory by [`watch_dir`]
+#[derive(Debug)]
+pub enum WatchEvent {
+	/// YAML file was converted after it was created or changed
+	Converted(ConvertedFile),
+	/// YAML file couldn't be converted or its output couldn't be removed
+	Failed(FailedFile),
+	/// Output was removed because its YAML file or directory disappeared
+	Removed { input: PathBuf, output: PathBuf },
+}
+
+/// Convert the input directory like [`convert_dir`] and keep converting files
+/// as they change, calling the callback for every change made to the output
+///
+/// Every file is converted once on startup. Later, changed YAML files are
+/// converted again and outputs of removed files and directories are deleted.
+/// Changes are collected until the directory is quiet for 100 ms, so one save
+/// triggers one conversion
+///
+/// Blocks until the callback returns [`ControlFlow::Break`]. Errors of individual
+/// files are reported to the callback, only failing to start watching returns an error
+///
+/// Requires the `watch` feature
+pub fn watch_dir(
+	input: impl AsRef<Path>,
+	output: impl AsRef<Path>,
+	options: &ParseOptions,
+	mut callback: impl FnMut(WatchEvent) -> ControlFlow<()>,
+) -> Result<()> {
+	// Events use canonical paths on some platforms
+	let input = input.as_ref().canonicalize()?;
+	let output = output.as_ref();
+
+	let (sender, receiver) = mpsc::channel();
+	let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+
+	watcher
+		.watch(&input, RecursiveMode::Recursive)
+		.map_err(watch_error)?;
+
+	let mut emit = |events: Vec<WatchEvent>| events.into_iter().try_for_each(&mut callback);
+
+	if emit(convert_all(&input, output, options)?).is_break() {
+		return Ok(());
+	}
+
+	while let Ok(event) = receiver.recv() {
+		let mut paths = BTreeSet::new();
+		let mut rescan = false;
+
+		let mut collect = |event: notify::Result<notify::Event>| match event {
+			// Reading files while converting them reports access events
+			Ok(event) if event.kind.is_access() => {}
+			Ok(event) => paths.extend(event.paths),
+			// Events may have been missed, so convert everything again
+			Err(_) => rescan = true,
+		};
+
+		collect(event);
+
+		while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+			collect(event);
+		}
+
+		let mut events = Vec::new();
+
+		if rescan {
+			match convert_all(&input, output, options) {
+				Ok(converted) => events.extend(converted),
+				Err(error) => events.push(WatchEvent::Failed(FailedFile {
+					input: input.clone(),
+					error,
+				})),
+			}
+		}
+
+		for path in paths {
+			update(&input, output, &path, options, &mut events);
+		}
+
+		if emit(events).is_break() {
+			return Ok(());
+		}
+	}
+
+	Ok(())
+}
+
+fn convert_all(input: &Path, output: &Path, options: &ParseOptions) -> Result<Vec<WatchEvent>> {
+	let report = convert_dir(input, output, options)?;
+
+	Ok(report
+		.converted
+		.into_iter()
+		.map(WatchEvent::Converted)
+		.chain(report.failed.into_iter().map(WatchEvent::Failed))
+		.collect())
+}
+
+/// Bring the output of the changed path up to date
+fn update(
+	input: &Path,
+	output: &Path,
+	path: &Path,
+	options: &ParseOptions,
+	events: &mut Vec<WatchEvent>,
+) {
+	let Ok(relative) = path.strip_prefix(input) else {
+		return;
+	};
+
+	let failed = |error| {
+		WatchEvent::Failed(FailedFile {
+			input: path.to_owned(),
+			error,
+		})
+	};
+
+	if path.is_dir() {
+		// Directories moved into the input directory don't report their files
+		match convert_all(path, &output.join(relative), options) {
+			Ok(converted) => events.extend(converted),
+			Err(error) => events.push(failed(error)),
+		}
+
+		return;
+	}
+
+	if batch::is_yaml(path) {
+		let target = batch::output_path(input, output, path);
+
+		if path.is_file() {
+			events.push(match batch::convert_file(path, &target, options) {
+				Ok(()) => WatchEvent::Converted(ConvertedFile {
+					input: path.to_owned(),
+					output: target,
+				}),
+				Err(error) => failed(error),
+			});
+		} else if target.is_file() {
+			events.push(match fs::remove_file(&target) {
+				Ok(()) => WatchEvent::Removed {
+					input: path.to_owned(),
+					output: target,
+				},
+				Err(error) => failed(error.into()),
+			});
+		}
+
+		return;
+	}
+
+	let target = output.join(relative);
+
+	// Removed directories take their converted files with them
+	if !path.exists() && !relative.as_os_str().is_empty() && target.is_dir() {
+		events.push(match fs::remove_dir_all(&target) {
+			Ok(()) => WatchEvent::Removed {
+				input: path.to_owned(),
+				output: target,
+			},
+			Err(error) => failed(error.into()),
+		});
+	}
+}
+
+fn watch_error(error: notify::Error) -> Error {
+	match error.kind {
+		notify::ErrorKind::Io(error) => Error::Io(error),
+		_ => Error::Io(io::Error::other(error)),
+	}
+}
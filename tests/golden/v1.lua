@@ -0,0 +1,90 @@
+{
+	["strings"] = {
+		["plain"] = "hello",
+		["empty"] = "",
+		["quotes"] = "single ' and double \"",
+		["backslash"] = "C:\\path\\to",
+		["escapes"] = "tab\tnewline\ncarriage\rbell\007null\000delete\127",
+		["unicode"] = "héllo wörld ✓",
+		["emoji"] = "🎮",
+		["literal"] = "line one\nline two\n",
+		["folded"] = "folded text\n",
+		["brackets"] = "]] and ]=]",
+	},
+	["integers"] = {
+		["zero"] = 0,
+		["positive"] = 42,
+		["negative"] = -17,
+		["max"] = 9223372036854775807,
+		["min"] = -9223372036854775808,
+		["hex"] = 31,
+		["octal"] = 15,
+	},
+	["floats"] = {
+		["simple"] = 4.2,
+		["whole"] = 2.0,
+		["negative"] = -0.5,
+		["small"] = 1e-6,
+		["large"] = 1e20,
+		["exponent"] = 6.02e23,
+		["negative zero"] = -0.0,
+	},
+	["booleans"] = {
+		true,
+		false,
+	},
+	["nulls"] = {
+		nil,
+		nil,
+	},
+	["sequences"] = {
+		["empty"] = {
+		},
+		["nested"] = {
+			{
+				1,
+				2,
+			},
+			{
+			},
+			{
+				{
+					3,
+				},
+			},
+		},
+		["mixed"] = {
+			1,
+			"two",
+			3.0,
+			true,
+			nil,
+		},
+	},
+	["mappings"] = {
+		["empty"] = {
+		},
+		["nested"] = {
+			["deeper"] = {
+				["deepest"] = "value",
+			},
+		},
+	},
+	["keys"] = {
+		["identifier"] = 1,
+		["with space"] = 2,
+		["end"] = 3,
+		["1"] = 4,
+		[1] = 5,
+		[2.5] = 6,
+		[true] = 7,
+		[""] = 8,
+		["_private"] = 9,
+		["ünicode"] = 10,
+	},
+	["tagged"] = {
+		["Custom"] = {
+			["value"] = 1,
+		},
+	},
+}
\ No newline at end of file